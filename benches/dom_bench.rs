@@ -42,6 +42,74 @@ fn bench_full_hackernews(c: &mut Criterion) {
     });
 }
 
+// Benchmark: wide item specs, matched in one walk per item
+fn bench_wide_item_extraction(c: &mut Criterion) {
+    let html = include_str!("../examples/hn.html");
+    let spec_json = r#"[{
+        "$": "tr.athing",
+        "rank": ".rank",
+        "title": "span.titleline > a",
+        "url": "span.titleline > a | attr:href",
+        "site": "span.titleline > span.sitebit | trim",
+        "site_href": "span.sitebit a | attr:href",
+        "vote": ".votelinks a | attr:href",
+        "vote_id": ".votelinks a | attr:id",
+        "title_cell": "td.title:last-child",
+        "rank_cell": "td.title",
+        "missing": ".does-not-exist"
+    }]"#;
+    let spec: Spec = serde_json::from_str(spec_json).unwrap();
+    let dom = html2json::Dom::parse(html).unwrap();
+
+    c.bench_function("wide_item_extraction", |b| {
+        b.iter(|| dom.extract(&spec).unwrap())
+    });
+}
+
+// Benchmark: many fields over large item subtrees (synthetic listing)
+fn bench_wide_deep_item_extraction(c: &mut Criterion) {
+    let mut html = String::from("<div class=\"list\">");
+    for i in 0..1000 {
+        html.push_str("<div class=\"card\">");
+        for _ in 0..30 {
+            html.push_str("<div class=\"filler\"><span>filler</span></div>");
+        }
+        for f in 0..12 {
+            html.push_str(&format!("<span class=\"f{f}\">{i}-{f}</span>"));
+        }
+        html.push_str("</div>");
+    }
+    html.push_str("</div>");
+
+    let mut spec = serde_json::Map::new();
+    spec.insert("$".into(), ".card".into());
+    for f in 0..12 {
+        spec.insert(format!("f{f}"), format!("span.f{f}").into());
+    }
+    let spec: Spec = serde_json::from_value(serde_json::json!([spec])).unwrap();
+    let dom = html2json::Dom::parse(&html).unwrap();
+
+    c.bench_function("wide_deep_item_extraction", |b| {
+        b.iter(|| dom.extract(&spec).unwrap())
+    });
+}
+
+#[cfg(feature = "parallel")]
+fn bench_parallel_array_extraction(c: &mut Criterion) {
+    let html = include_str!("../examples/hn.html");
+    let spec_json = r#"[{"$": "tr.athing", "id": "$ | attr:id", "title": ".titleline a", "score": "+ .subtext .score"}]"#;
+    let spec: Spec = serde_json::from_str(spec_json).unwrap();
+    let dom = html2json::Dom::parse(html).unwrap();
+
+    let mut group = c.benchmark_group("parallel_array_extraction");
+    group.bench_function("sequential", |b| b.iter(|| dom.extract(&spec).unwrap()));
+    group.bench_function("parallel", |b| b.iter(|| dom.extract_par(&spec).unwrap()));
+    group.finish();
+}
+
+#[cfg(not(feature = "parallel"))]
+fn bench_parallel_array_extraction(_c: &mut Criterion) {}
+
 fn bench_dom_parse(c: &mut Criterion) {
     let html = include_str!("../examples/hn.html");
 
@@ -50,21 +118,65 @@ fn bench_dom_parse(c: &mut Criterion) {
     });
 }
 
-fn bench_query_selector(c: &mut Criterion) {
+// Benchmark: a service that parses its spec on every request versus one that
+// keeps it in a SpecSet
+fn bench_spec_set(c: &mut Criterion) {
+    let html = r#"<article><h1>Title</h1><span class="author">Ann</span>
+        <ul><li><a href="/a">A</a></li><li><a href="/b">B</a></li></ul></article>"#;
+    let spec_json = r#"{"title": "h1", "author": ".author", "published": "time | attr:datetime",
+        "links": [{"$": "li a", "text": "$ | trim | lower", "href": "$ | attr:href"}]}"#;
+    let specs = html2json::SpecSet::builder()
+        .spec_json("page", spec_json)
+        .unwrap()
+        .build();
+
+    c.bench_function("per_request_spec_parse", |b| {
+        b.iter(|| {
+            let spec: Spec = serde_json::from_str(spec_json).unwrap();
+            extract(html, &spec).unwrap()
+        })
+    });
+    c.bench_function("spec_set", |b| {
+        b.iter(|| specs.extractor("page").unwrap().extract(html).unwrap())
+    });
+}
+
+fn bench_select_one(c: &mut Criterion) {
     let html = include_str!("../examples/hn.html");
     let dom = html2json::Dom::parse(html).unwrap();
 
-    c.bench_function("query_selector", |b| {
-        b.iter(|| dom.query_selector("title").unwrap())
+    c.bench_function("select_one", |b| {
+        b.iter(|| dom.select_one("title").unwrap())
     });
 }
 
-fn bench_query_selector_all(c: &mut Criterion) {
+fn bench_select(c: &mut Criterion) {
     let html = include_str!("../examples/hn.html");
     let dom = html2json::Dom::parse(html).unwrap();
 
-    c.bench_function("query_selector_all", |b| {
-        b.iter(|| dom.query_selector_all("tr.athing").unwrap())
+    c.bench_function("select", |b| b.iter(|| dom.select("tr.athing").unwrap()));
+}
+
+fn bench_indexed_select(c: &mut Criterion) {
+    let html = include_str!("../examples/hn.html");
+    let dom = html2json::Dom::parse_indexed(html).unwrap();
+
+    c.bench_function("indexed_select", |b| {
+        b.iter(|| dom.select(".athing").unwrap())
+    });
+}
+
+// Benchmark: first-match queries should stop early instead of collecting all matches
+fn bench_scoped_first_match(c: &mut Criterion) {
+    let html = include_str!("../examples/hn.html");
+    let dom = html2json::Dom::parse(html).unwrap();
+    let main = dom.select_one("#hnmain").unwrap().unwrap();
+
+    c.bench_function("scoped_first_match", |b| {
+        b.iter(|| dom.select_one_in(&main, "a").unwrap())
+    });
+    c.bench_function("scoped_all_matches", |b| {
+        b.iter(|| dom.select_in(&main, "a").unwrap())
     });
 }
 
@@ -76,7 +188,7 @@ fn bench_repeated_selector_parse(c: &mut Criterion) {
         b.iter(|| {
             // Simulate repeated queries with the same selector
             for _ in 0..10 {
-                std::hint::black_box(dom.query_selector_all("tr.athing").unwrap());
+                std::hint::black_box(dom.select("tr.athing").unwrap());
             }
         })
     });
@@ -89,7 +201,7 @@ fn bench_text_extraction(c: &mut Criterion) {
 
     c.bench_function("text_extraction", |b| {
         b.iter(|| {
-            let nodes = dom.query_selector_all("tr.athing").unwrap();
+            let nodes = dom.select("tr.athing").unwrap();
             // Extract text from all nodes
             for node in &nodes {
                 std::hint::black_box(node.text());
@@ -104,9 +216,15 @@ criterion_group!(
     bench_array_extraction,
     bench_nested_extraction,
     bench_full_hackernews,
+    bench_wide_item_extraction,
+    bench_wide_deep_item_extraction,
+    bench_parallel_array_extraction,
     bench_dom_parse,
-    bench_query_selector,
-    bench_query_selector_all,
+    bench_spec_set,
+    bench_select_one,
+    bench_select,
+    bench_indexed_select,
+    bench_scoped_first_match,
     bench_repeated_selector_parse,
     bench_text_extraction
 );
@@ -0,0 +1,224 @@
+//! Derive macro for html2json
+//!
+//! Generates an extraction spec and a typed `from_html()` constructor from
+//! struct field attributes. Use it through the `derive` feature of `html2json`.
+//!
+//! ```ignore
+//! use html2json::HtmlExtract;
+//!
+//! #[derive(serde::Deserialize, HtmlExtract)]
+//! #[selector("article")]
+//! struct Article {
+//!     #[selector("h1")]
+//!     title: String,
+//!     #[selector(".price", pipe = "parseAs:number")]
+//!     price: f64,
+//!     #[selector(".tags li", collection)]
+//!     tags: Vec<Tag>,
+//! }
+//! ```
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::parse::ParseStream;
+use syn::{Attribute, Data, DeriveInput, Fields, GenericArgument, LitStr, PathArguments, Type};
+
+/// Derive `html2json::HtmlExtract` for a struct with named fields
+///
+/// Field attributes:
+/// - `#[selector("css")]` - extract the text of the first match
+/// - `#[selector("css", pipe = "attr:href | trim")]` - apply pipes to the match
+/// - `#[selector("css", nested)]` - field type implements `HtmlExtract`, scoped to the match
+/// - `#[selector("css", collection)]` - `Vec<T>` where `T: HtmlExtract`, one item per match
+///
+/// An optional `#[selector("css")]` on the struct itself sets the scope (`$`).
+/// `Option<T>` fields are emitted as optional (`key?`) spec fields.
+#[proc_macro_derive(HtmlExtract, attributes(selector))]
+pub fn derive_html_extract(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// How a field's value is produced
+enum FieldKind {
+    Value,
+    Nested,
+    Collection,
+}
+
+/// Parsed `#[selector(...)]` attribute
+struct SelectorAttr {
+    selector: LitStr,
+    pipe: Option<LitStr>,
+    kind: FieldKind,
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "HtmlExtract can only be derived for structs",
+        ));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "HtmlExtract requires a struct with named fields",
+        ));
+    };
+
+    let scope = match find_selector_attr(&input.attrs)? {
+        Some(attr) => {
+            let selector = attr.selector;
+            quote! {
+                spec.insert(
+                    "$".to_string(),
+                    ::html2json::__private::serde_json::Value::String(#selector.to_string()),
+                );
+            }
+        }
+        None => quote! {},
+    };
+
+    let mut inserts = Vec::new();
+    for field in &fields.named {
+        let Some(attr) = find_selector_attr(&field.attrs)? else {
+            return Err(syn::Error::new_spanned(
+                field,
+                "missing #[selector(...)] attribute",
+            ));
+        };
+        let ident = field.ident.as_ref().expect("named field");
+        let (inner_ty, optional) = match option_inner(&field.ty) {
+            Some(inner) => (inner, true),
+            None => (&field.ty, false),
+        };
+        let key = if optional {
+            format!("{}?", ident)
+        } else {
+            ident.to_string()
+        };
+
+        let selector = &attr.selector;
+        let value = match attr.kind {
+            FieldKind::Value => {
+                let spec_str = match &attr.pipe {
+                    Some(pipe) => format!("{} | {}", selector.value(), pipe.value()),
+                    None => selector.value(),
+                };
+                quote! {
+                    ::html2json::__private::serde_json::Value::String(#spec_str.to_string())
+                }
+            }
+            FieldKind::Nested => quote! {
+                ::html2json::__private::scoped_spec(
+                    <#inner_ty as ::html2json::HtmlExtract>::spec_value(),
+                    #selector,
+                )
+            },
+            FieldKind::Collection => {
+                let Some(item_ty) = vec_inner(inner_ty) else {
+                    return Err(syn::Error::new_spanned(
+                        &field.ty,
+                        "collection fields must be Vec<T> or Option<Vec<T>>",
+                    ));
+                };
+                quote! {
+                    ::html2json::__private::serde_json::Value::Array(vec![
+                        ::html2json::__private::scoped_spec(
+                            <#item_ty as ::html2json::HtmlExtract>::spec_value(),
+                            #selector,
+                        ),
+                    ])
+                }
+            }
+        };
+        inserts.push(quote! {
+            spec.insert(#key.to_string(), #value);
+        });
+    }
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::html2json::HtmlExtract for #name #ty_generics #where_clause {
+            fn spec_value() -> ::html2json::__private::serde_json::Value {
+                let mut spec = ::html2json::__private::serde_json::Map::new();
+                #scope
+                #(#inserts)*
+                ::html2json::__private::serde_json::Value::Object(spec)
+            }
+        }
+    })
+}
+
+/// Find and parse the `#[selector(...)]` attribute, if present
+fn find_selector_attr(attrs: &[Attribute]) -> syn::Result<Option<SelectorAttr>> {
+    let Some(attr) = attrs.iter().find(|a| a.path().is_ident("selector")) else {
+        return Ok(None);
+    };
+
+    attr.parse_args_with(|input: ParseStream| {
+        let selector: LitStr = input.parse()?;
+        let mut pipe = None;
+        let mut kind = FieldKind::Value;
+
+        while !input.is_empty() {
+            input.parse::<syn::Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+            let option: syn::Ident = input.parse()?;
+            match option.to_string().as_str() {
+                "pipe" => {
+                    input.parse::<syn::Token![=]>()?;
+                    pipe = Some(input.parse::<LitStr>()?);
+                }
+                "nested" => kind = FieldKind::Nested,
+                "collection" => kind = FieldKind::Collection,
+                other => {
+                    return Err(syn::Error::new(
+                        option.span(),
+                        format!("unknown selector option '{}'", other),
+                    ));
+                }
+            }
+        }
+
+        Ok(Some(SelectorAttr {
+            selector,
+            pipe,
+            kind,
+        }))
+    })
+}
+
+/// Return `T` for `Option<T>`
+fn option_inner(ty: &Type) -> Option<&Type> {
+    generic_inner(ty, "Option")
+}
+
+/// Return `T` for `Vec<T>`
+fn vec_inner(ty: &Type) -> Option<&Type> {
+    generic_inner(ty, "Vec")
+}
+
+fn generic_inner<'a>(ty: &'a Type, wrapper: &str) -> Option<&'a Type> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    if segment.ident != wrapper {
+        return None;
+    }
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    match args.args.first()? {
+        GenericArgument::Type(inner) => Some(inner),
+        _ => None,
+    }
+}
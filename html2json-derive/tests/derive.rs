@@ -0,0 +1,53 @@
+use html2json::HtmlExtract;
+use serde::Deserialize;
+
+const HTML: &str = include_str!("../../examples/hn.html");
+
+#[derive(Debug, Deserialize, HtmlExtract)]
+struct Story {
+    #[selector("$", pipe = "attr:id")]
+    id: String,
+    #[selector(".titleline a")]
+    title: String,
+    #[selector(".rank", pipe = "regex:(\\d+) | parseAs:int")]
+    rank: i64,
+    #[selector(".nonexistent")]
+    missing: Option<String>,
+}
+
+#[derive(Debug, Deserialize, HtmlExtract)]
+struct Head {
+    #[selector("title")]
+    title: String,
+}
+
+#[derive(Debug, Deserialize, HtmlExtract)]
+#[selector("html")]
+struct FrontPage {
+    #[selector("head", nested)]
+    head: Head,
+    #[selector("tr.athing", collection)]
+    stories: Vec<Story>,
+}
+
+#[test]
+fn derived_spec_extracts_typed_struct() {
+    let page = FrontPage::from_html(HTML).unwrap();
+    assert_eq!(page.head.title, "Hacker News");
+    assert_eq!(page.stories[0].id, "46446815");
+    assert_eq!(page.stories[0].title, "I canceled my book deal");
+    assert_eq!(page.stories[0].rank, 1);
+    assert_eq!(page.stories[0].missing, None);
+}
+
+#[test]
+fn derived_spec_value_shape() {
+    let spec = Story::spec_value();
+    assert_eq!(spec["id"], "$ | attr:id");
+    assert_eq!(spec["missing?"], ".nonexistent");
+
+    let spec = FrontPage::spec_value();
+    assert_eq!(spec["$"], "html");
+    assert_eq!(spec["head"]["$"], "head");
+    assert_eq!(spec["stories"][0]["$"], "tr.athing");
+}
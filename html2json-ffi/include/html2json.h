@@ -0,0 +1,71 @@
+/*
+ * C interface to html2json
+ *
+ * Strings passed in are UTF-8 byte ranges (pointer and length, no NUL
+ * needed). Strings handed out (results and error messages) are
+ * NUL-terminated UTF-8, owned by the caller, and must be released with
+ * html2json_string_free. A parsed spec is immutable and may be shared by
+ * any number of threads until it is released with html2json_spec_free.
+ */
+
+#ifndef HTML2JSON_H
+#define HTML2JSON_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef enum html2json_status {
+    HTML2JSON_OK = 0,
+    /* A required pointer was NULL, or an input was not valid UTF-8 */
+    HTML2JSON_INVALID_ARGUMENT = 1,
+    /* The spec is not valid JSON or not a valid spec */
+    HTML2JSON_INVALID_SPEC = 2,
+    /* Extraction stopped at a resource limit */
+    HTML2JSON_LIMIT_EXCEEDED = 3,
+    /* Extraction failed for any other reason */
+    HTML2JSON_EXTRACTION_FAILED = 4,
+    /* An internal error; the library is still usable */
+    HTML2JSON_PANIC = 5,
+} html2json_status;
+
+typedef struct html2json_spec html2json_spec;
+
+/* The library version, as a static string (do not free) */
+const char *html2json_version(void);
+
+/*
+ * Parse a spec from JSON. On success *out_spec holds the spec; on failure
+ * *out_error (if out_error is not NULL) holds a message.
+ */
+html2json_status html2json_spec_parse(const char *spec_json, size_t spec_len,
+                                      html2json_spec **out_spec, char **out_error);
+
+/* Release a spec (NULL is ignored) */
+void html2json_spec_free(html2json_spec *spec);
+
+/*
+ * Extract from HTML with a parsed spec. On success *out_json holds the
+ * result as JSON and *out_len (if out_len is not NULL) its length in bytes,
+ * not counting the NUL; on failure *out_error (if not NULL) holds a message.
+ */
+html2json_status html2json_extract(const char *html, size_t html_len,
+                                   const html2json_spec *spec,
+                                   char **out_json, size_t *out_len, char **out_error);
+
+/* Parse spec_json and extract with it in one call */
+html2json_status html2json_extract_with_json_spec(const char *html, size_t html_len,
+                                                  const char *spec_json, size_t spec_len,
+                                                  char **out_json, size_t *out_len,
+                                                  char **out_error);
+
+/* Release a string returned by this library (NULL is ignored) */
+void html2json_string_free(char *s);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* HTML2JSON_H */
@@ -0,0 +1,332 @@
+//! C interface to html2json
+//!
+//! For embedding the extractor in Go, Java, Swift and other languages with a
+//! C FFI; the matching header is `include/html2json.h`. Every function
+//! reports failure through a [`Status`] code, never by unwinding, and every
+//! string this library hands out is freed with [`html2json_string_free`].
+
+use std::ffi::{CString, c_char};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+use html2json::{LimitExceeded, Spec};
+
+/// Result of a call (`html2json_status` in C)
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Ok = 0,
+    /// A required pointer was null, or an input was not valid UTF-8
+    InvalidArgument = 1,
+    /// The spec is not valid JSON or not a valid spec
+    InvalidSpec = 2,
+    /// Extraction stopped at a resource limit
+    LimitExceeded = 3,
+    /// Extraction failed for any other reason
+    ExtractionFailed = 4,
+    /// An internal error; the library is still usable
+    Panic = 5,
+}
+
+/// A parsed spec (`html2json_spec` in C), opaque to callers
+pub struct Html2JsonSpec(Spec);
+
+struct Failure(Status, String);
+
+impl Failure {
+    fn spec(e: impl std::fmt::Display) -> Self {
+        Self(Status::InvalidSpec, format!("Invalid spec: {}", e))
+    }
+
+    fn extraction(e: anyhow::Error) -> Self {
+        if e.downcast_ref::<LimitExceeded>().is_some() {
+            Self(Status::LimitExceeded, e.to_string())
+        } else {
+            Self(
+                Status::ExtractionFailed,
+                format!("Extraction failed: {}", e),
+            )
+        }
+    }
+}
+
+/// The library version, as a static NUL-terminated string (do not free)
+#[unsafe(no_mangle)]
+pub extern "C" fn html2json_version() -> *const c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr().cast()
+}
+
+/// Parse a spec from JSON
+///
+/// # Safety
+///
+/// `spec_json` must point to `spec_len` readable bytes, and `out_spec` and
+/// `out_error` must each be null or valid for writing a pointer.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn html2json_spec_parse(
+    spec_json: *const c_char,
+    spec_len: usize,
+    out_spec: *mut *mut Html2JsonSpec,
+    out_error: *mut *mut c_char,
+) -> Status {
+    unsafe {
+        reset(out_error);
+        if out_spec.is_null() {
+            return fail(
+                out_error,
+                Failure(Status::InvalidArgument, "out_spec is null".into()),
+            );
+        }
+        *out_spec = ptr::null_mut();
+        guard(out_error, || {
+            let spec = parse_spec(str_arg(spec_json, spec_len, "spec_json")?)?;
+            *out_spec = Box::into_raw(Box::new(Html2JsonSpec(spec)));
+            Ok(())
+        })
+    }
+}
+
+/// Release a spec (null is ignored)
+///
+/// # Safety
+///
+/// `spec` must be null or a pointer from [`html2json_spec_parse`] that has not
+/// been freed yet, and no other thread may be using it.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn html2json_spec_free(spec: *mut Html2JsonSpec) {
+    if !spec.is_null() {
+        drop(unsafe { Box::from_raw(spec) });
+    }
+}
+
+/// Extract from HTML with a parsed spec
+///
+/// # Safety
+///
+/// `html` must point to `html_len` readable bytes, `spec` must be a live
+/// pointer from [`html2json_spec_parse`], `out_json` must be valid for writing
+/// a pointer, and `out_len` and `out_error` must each be null or valid for
+/// writing.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn html2json_extract(
+    html: *const c_char,
+    html_len: usize,
+    spec: *const Html2JsonSpec,
+    out_json: *mut *mut c_char,
+    out_len: *mut usize,
+    out_error: *mut *mut c_char,
+) -> Status {
+    unsafe {
+        reset(out_error);
+        if out_json.is_null() || spec.is_null() {
+            let what = if spec.is_null() { "spec" } else { "out_json" };
+            return fail(
+                out_error,
+                Failure(Status::InvalidArgument, format!("{} is null", what)),
+            );
+        }
+        guard(out_error, || {
+            let html = str_arg(html, html_len, "html")?;
+            write_result(extract(html, &(*spec).0)?, out_json, out_len);
+            Ok(())
+        })
+    }
+}
+
+/// Parse a spec from JSON and extract from HTML with it in one call
+///
+/// # Safety
+///
+/// As for [`html2json_extract`], with `spec_json` pointing to `spec_len`
+/// readable bytes in place of a parsed spec.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn html2json_extract_with_json_spec(
+    html: *const c_char,
+    html_len: usize,
+    spec_json: *const c_char,
+    spec_len: usize,
+    out_json: *mut *mut c_char,
+    out_len: *mut usize,
+    out_error: *mut *mut c_char,
+) -> Status {
+    unsafe {
+        reset(out_error);
+        if out_json.is_null() {
+            return fail(
+                out_error,
+                Failure(Status::InvalidArgument, "out_json is null".into()),
+            );
+        }
+        guard(out_error, || {
+            let spec = parse_spec(str_arg(spec_json, spec_len, "spec_json")?)?;
+            let html = str_arg(html, html_len, "html")?;
+            write_result(extract(html, &spec)?, out_json, out_len);
+            Ok(())
+        })
+    }
+}
+
+/// Release a string returned by this library (null is ignored)
+///
+/// # Safety
+///
+/// `s` must be null or a string returned by this library that has not been
+/// freed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn html2json_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(unsafe { CString::from_raw(s) });
+    }
+}
+
+fn parse_spec(json: &str) -> Result<Spec, Failure> {
+    let value = html2json::spec::parse_json(json).map_err(Failure::spec)?;
+    Spec::from_json(&value).map_err(Failure::spec)
+}
+
+fn extract(html: &str, spec: &Spec) -> Result<String, Failure> {
+    let result = html2json::extract(html, spec).map_err(Failure::extraction)?;
+    serde_json::to_string(&result).map_err(|e| Failure::extraction(e.into()))
+}
+
+/// Borrow a caller's byte range as a string
+unsafe fn str_arg<'a>(data: *const c_char, len: usize, name: &str) -> Result<&'a str, Failure> {
+    let bytes: &[u8] = if len == 0 {
+        &[]
+    } else if data.is_null() {
+        return Err(Failure(
+            Status::InvalidArgument,
+            format!("{} is null", name),
+        ));
+    } else {
+        unsafe { std::slice::from_raw_parts(data.cast(), len) }
+    };
+    std::str::from_utf8(bytes).map_err(|e| {
+        Failure(
+            Status::InvalidArgument,
+            format!("{} is not UTF-8: {}", name, e),
+        )
+    })
+}
+
+unsafe fn write_result(json: String, out_json: *mut *mut c_char, out_len: *mut usize) {
+    unsafe {
+        if !out_len.is_null() {
+            *out_len = json.len();
+        }
+        // Serialized JSON escapes NUL, so the conversion can't fail
+        *out_json = CString::new(json).unwrap_or_default().into_raw();
+    }
+}
+
+/// Run `f`, turning failures and panics into a status and error message
+unsafe fn guard(out_error: *mut *mut c_char, f: impl FnOnce() -> Result<(), Failure>) -> Status {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => Status::Ok,
+        Ok(Err(failure)) => unsafe { fail(out_error, failure) },
+        Err(_) => unsafe {
+            fail(
+                out_error,
+                Failure(Status::Panic, "internal error in html2json".into()),
+            )
+        },
+    }
+}
+
+unsafe fn fail(out_error: *mut *mut c_char, Failure(status, message): Failure) -> Status {
+    if !out_error.is_null() {
+        let message = CString::new(message.replace('\0', "")).unwrap_or_default();
+        unsafe { *out_error = message.into_raw() };
+    }
+    status
+}
+
+unsafe fn reset(out_error: *mut *mut c_char) {
+    if !out_error.is_null() {
+        unsafe { *out_error = ptr::null_mut() };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CStr;
+
+    unsafe fn take(s: *mut c_char) -> String {
+        unsafe {
+            let text = CStr::from_ptr(s).to_str().unwrap().to_string();
+            html2json_string_free(s);
+            text
+        }
+    }
+
+    #[test]
+    fn extract_with_parsed_spec() {
+        let spec_json = r#"{"title": "h1", "items": [{"$": "li", "name": "$"}]}"#;
+        let html = "<h1>Hi</h1><ul><li>a</li><li>b</li></ul>";
+        unsafe {
+            let mut spec = ptr::null_mut();
+            let mut error = ptr::null_mut();
+            let status = html2json_spec_parse(
+                spec_json.as_ptr().cast(),
+                spec_json.len(),
+                &mut spec,
+                &mut error,
+            );
+            assert_eq!(status, Status::Ok);
+            assert!(error.is_null());
+
+            let (mut json, mut len) = (ptr::null_mut(), 0);
+            let status = html2json_extract(
+                html.as_ptr().cast(),
+                html.len(),
+                spec,
+                &mut json,
+                &mut len,
+                &mut error,
+            );
+            assert_eq!(status, Status::Ok);
+            let json = take(json);
+            assert_eq!(json.len(), len);
+            assert_eq!(
+                json,
+                r#"{"items":[{"name":"a"},{"name":"b"}],"title":"Hi"}"#
+            );
+            html2json_spec_free(spec);
+        }
+    }
+
+    #[test]
+    fn failures_set_status_and_message() {
+        let html = "<h1>Hi</h1>";
+        let bad_spec = r#"{"title": "h1 | nosuchpipe"}"#;
+        unsafe {
+            let (mut json, mut error) = (ptr::null_mut(), ptr::null_mut());
+            let status = html2json_extract_with_json_spec(
+                html.as_ptr().cast(),
+                html.len(),
+                bad_spec.as_ptr().cast(),
+                bad_spec.len(),
+                &mut json,
+                ptr::null_mut(),
+                &mut error,
+            );
+            assert_eq!(status, Status::InvalidSpec);
+            assert!(json.is_null());
+            assert!(take(error).contains("nosuchpipe"));
+
+            let invalid_utf8 = [0xffu8, 0xfe];
+            let spec = r#"{"title": "h1"}"#;
+            let status = html2json_extract_with_json_spec(
+                invalid_utf8.as_ptr().cast(),
+                invalid_utf8.len(),
+                spec.as_ptr().cast(),
+                spec.len(),
+                &mut json,
+                ptr::null_mut(),
+                ptr::null_mut(),
+            );
+            assert_eq!(status, Status::InvalidArgument);
+        }
+    }
+}
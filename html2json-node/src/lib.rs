@@ -0,0 +1,98 @@
+//! Node.js bindings for html2json
+//!
+//! Build with the [napi-rs CLI](https://napi.rs) from this directory
+//! (`npx napi build --release --platform`). Specs are passed as objects or
+//! JSON strings, in the same format cheerio-json-mapper templates use, and
+//! results come back as plain objects.
+
+use napi::JsUnknown;
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use serde_json::Value;
+
+/// Extract data from HTML using a spec (an object or a JSON string)
+#[napi(
+    ts_args_type = "html: string, spec: string | object",
+    ts_return_type = "any"
+)]
+pub fn extract(html: String, spec: Value) -> Result<Value> {
+    run(&html, &parse_spec(spec)?)
+}
+
+/// Like `extract`, running on the libuv thread pool instead of the main thread
+#[napi(
+    ts_args_type = "html: string, spec: string | object",
+    ts_return_type = "Promise<any>"
+)]
+pub fn extract_async(html: String, spec: Value) -> Result<AsyncTask<Extraction>> {
+    Ok(AsyncTask::new(Extraction {
+        html,
+        spec: parse_spec(spec)?,
+    }))
+}
+
+/// Extracts data from HTML pages with one spec, parsed once
+#[napi]
+pub struct Extractor {
+    spec: html2json::Spec,
+}
+
+#[napi]
+impl Extractor {
+    #[napi(constructor, ts_args_type = "spec: string | object")]
+    pub fn new(spec: Value) -> Result<Self> {
+        Ok(Self {
+            spec: parse_spec(spec)?,
+        })
+    }
+
+    /// Extract data from `html`
+    #[napi(ts_return_type = "any")]
+    pub fn extract(&self, html: String) -> Result<Value> {
+        run(&html, &self.spec)
+    }
+
+    /// Like `extract`, running on the libuv thread pool
+    #[napi(ts_return_type = "Promise<any>")]
+    pub fn extract_async(&self, html: String) -> AsyncTask<Extraction> {
+        AsyncTask::new(Extraction {
+            html,
+            spec: self.spec.clone(),
+        })
+    }
+}
+
+/// An extraction run off the main thread
+pub struct Extraction {
+    html: String,
+    spec: html2json::Spec,
+}
+
+impl Task for Extraction {
+    type Output = Value;
+    type JsValue = JsUnknown;
+
+    fn compute(&mut self) -> Result<Value> {
+        run(&self.html, &self.spec)
+    }
+
+    fn resolve(&mut self, env: Env, output: Value) -> Result<JsUnknown> {
+        env.to_js_value(&output)
+    }
+}
+
+/// Parse a spec given as an object, or as a JSON string
+fn parse_spec(spec: Value) -> Result<html2json::Spec> {
+    let spec = match spec {
+        Value::String(json) => html2json::spec::parse_json(&json)
+            .map_err(|e| Error::new(Status::InvalidArg, format!("Invalid spec: {}", e)))?,
+        spec => spec,
+    };
+    html2json::Spec::from_json(&spec)
+        .map_err(|e| Error::new(Status::InvalidArg, format!("Invalid spec: {}", e)))
+}
+
+fn run(html: &str, spec: &html2json::Spec) -> Result<Value> {
+    html2json::extract(html, spec)
+        .map_err(|e| Error::from_reason(format!("Extraction failed: {}", e)))
+}
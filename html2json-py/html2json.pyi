@@ -0,0 +1,27 @@
+from typing import Any, Union
+
+SpecLike = Union["Spec", str, dict[str, Any], list[Any]]
+
+class Spec:
+    """A parsed extraction spec; raises ValueError if it is invalid"""
+
+    def __init__(self, spec: SpecLike) -> None: ...
+    @staticmethod
+    def validate(spec: SpecLike) -> None:
+        """Check a spec without keeping it, raising ValueError if it is invalid"""
+    def to_json(self) -> str: ...
+
+class Extractor:
+    """Extracts data from HTML pages with one spec"""
+
+    def __init__(self, spec: SpecLike) -> None: ...
+    @property
+    def spec(self) -> Spec: ...
+    def extract(self, html: str) -> Any: ...
+    def extract_json(self, html: str) -> str: ...
+
+def extract(html: str, spec: SpecLike) -> Any:
+    """Extract data from html using spec"""
+
+def extract_json(html: str, spec: SpecLike) -> str:
+    """Like extract, returning the result as a JSON string"""
@@ -0,0 +1,132 @@
+//! Python bindings for html2json
+//!
+//! Build with [maturin](https://www.maturin.rs) from this directory
+//! (`maturin develop`, or `maturin build --release` for a wheel). Specs are
+//! passed as JSON strings or as the equivalent dicts and lists, and results
+//! come back as plain Python objects. Parsing and extraction run with the GIL
+//! released, so threads extracting different pages proceed in parallel.
+
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+
+/// A parsed extraction spec
+///
+/// Parsing a spec compiles its selectors and regexes, so build one `Spec`
+/// (or `Extractor`) and reuse it across pages.
+#[pyclass(frozen, module = "html2json")]
+struct Spec {
+    inner: html2json::Spec,
+}
+
+#[pymethods]
+impl Spec {
+    #[new]
+    fn new(spec: &Bound<'_, PyAny>) -> PyResult<Self> {
+        Ok(Self {
+            inner: parse_spec(spec)?,
+        })
+    }
+
+    /// Check a spec without keeping it, raising `ValueError` if it is invalid
+    #[staticmethod]
+    fn validate(spec: &Bound<'_, PyAny>) -> PyResult<()> {
+        parse_spec(spec).map(drop)
+    }
+
+    /// The spec as a JSON string
+    fn to_json(&self) -> String {
+        self.inner.to_json().to_string()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Spec({})", self.to_json())
+    }
+}
+
+/// Extracts data from HTML pages with one spec
+#[pyclass(frozen, module = "html2json")]
+struct Extractor {
+    spec: html2json::Spec,
+}
+
+#[pymethods]
+impl Extractor {
+    #[new]
+    fn new(spec: &Bound<'_, PyAny>) -> PyResult<Self> {
+        Ok(Self {
+            spec: parse_spec(spec)?,
+        })
+    }
+
+    /// Extract data from `html`
+    fn extract(&self, py: Python<'_>, html: &str) -> PyResult<Py<PyAny>> {
+        let json = extract_json_string(py, html, &self.spec)?;
+        json_to_python(py, &json)
+    }
+
+    /// Extract data from `html`, returning it as a JSON string
+    fn extract_json(&self, py: Python<'_>, html: &str) -> PyResult<String> {
+        extract_json_string(py, html, &self.spec)
+    }
+
+    #[getter]
+    fn spec(&self) -> Spec {
+        Spec {
+            inner: self.spec.clone(),
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Extractor({})", self.spec.to_json())
+    }
+}
+
+/// Extract data from `html` using `spec` (a `Spec`, JSON string, dict or list)
+#[pyfunction]
+fn extract(py: Python<'_>, html: &str, spec: &Bound<'_, PyAny>) -> PyResult<Py<PyAny>> {
+    let json = extract_json_string(py, html, &parse_spec(spec)?)?;
+    json_to_python(py, &json)
+}
+
+/// Like `extract`, returning the result as a JSON string
+#[pyfunction]
+fn extract_json(py: Python<'_>, html: &str, spec: &Bound<'_, PyAny>) -> PyResult<String> {
+    extract_json_string(py, html, &parse_spec(spec)?)
+}
+
+fn parse_spec(spec: &Bound<'_, PyAny>) -> PyResult<html2json::Spec> {
+    if let Ok(spec) = spec.cast::<Spec>() {
+        return Ok(spec.get().inner.clone());
+    }
+    let json: String = match spec.extract() {
+        Ok(json) => json,
+        Err(_) => spec
+            .py()
+            .import("json")?
+            .call_method1("dumps", (spec,))?
+            .extract()?,
+    };
+    serde_json::from_str(&json).map_err(|e| PyValueError::new_err(format!("Invalid spec: {}", e)))
+}
+
+/// Run the extraction with the GIL released
+fn extract_json_string(py: Python<'_>, html: &str, spec: &html2json::Spec) -> PyResult<String> {
+    py.detach(|| {
+        let result = html2json::extract(html, spec)?;
+        Ok::<_, anyhow::Error>(serde_json::to_string(&result)?)
+    })
+    .map_err(|e| PyRuntimeError::new_err(format!("Extraction failed: {}", e)))
+}
+
+fn json_to_python(py: Python<'_>, json: &str) -> PyResult<Py<PyAny>> {
+    Ok(py.import("json")?.call_method1("loads", (json,))?.unbind())
+}
+
+#[pymodule(name = "html2json")]
+fn html2json_py(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<Spec>()?;
+    module.add_class::<Extractor>()?;
+    module.add_function(wrap_pyfunction!(extract, module)?)?;
+    module.add_function(wrap_pyfunction!(extract_json, module)?)?;
+    Ok(())
+}
@@ -1,27 +1,48 @@
 // Auto-initializing wrapper for convenience
 import _init from "./html2json.js";
-import { extract as _extract, initSync } from "./html2json.js";
+import {
+  extract as _extract,
+  extractJson as _extractJson,
+  initSync,
+} from "./html2json.js";
 
 let initPromise;
 
+// Node can't fetch the module from a file URL, so it is read from disk
+async function load() {
+  if (typeof process !== "undefined" && process.versions?.node) {
+    const { readFile } = await import("node:fs/promises");
+    const bytes = await readFile(new URL("./html2json_bg.wasm", import.meta.url));
+    return _init({ module_or_path: bytes });
+  }
+  return _init();
+}
+
 // Auto-init on first call
 function init() {
   if (!initPromise) {
-    initPromise = _init();
+    initPromise = load();
   }
   return initPromise;
 }
 
-// Export auto-initialized extract (async for first call)
-export async function extract(html, spec_json) {
+// Export auto-initialized extract (async for first call); `spec` is a JSON
+// string or an object, and the result is a plain object
+export async function extract(html, spec) {
+  await init();
+  return _extract(html, spec);
+}
+
+// Same as extract, returning the result as a JSON string
+export async function extractJson(html, spec) {
   await init();
-  return _extract(html, spec_json);
+  return _extractJson(html, spec);
 }
 
 // Also export init functions for those who want to control timing
 export { init, initSync };
 
 // Export raw extract for advanced use (sync, requires manual init)
-export { _extract as extractSync };
+export { _extract as extractSync, _extractJson as extractJsonSync };
 
 export default init;
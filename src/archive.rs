@@ -0,0 +1,290 @@
+//! Pages saved by browsers: HAR captures and MHTML archives
+//!
+//! Both hold a page's HTML alongside its other resources; these functions
+//! pull out the document itself so a spec can run against it.
+
+use anyhow::Result;
+use base64::Engine;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct Har {
+    log: HarLog,
+}
+
+#[derive(Deserialize)]
+struct HarLog {
+    #[serde(default)]
+    pages: Vec<HarPage>,
+    entries: Vec<HarEntry>,
+}
+
+#[derive(Deserialize)]
+struct HarPage {
+    id: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HarEntry {
+    pageref: Option<String>,
+    response: HarResponse,
+}
+
+#[derive(Deserialize)]
+struct HarResponse {
+    status: u16,
+    content: HarContent,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HarContent {
+    #[serde(default)]
+    mime_type: String,
+    text: Option<String>,
+    encoding: Option<String>,
+}
+
+/// The main document of a HAR capture: the first successful HTML response
+/// of its first page
+///
+/// Later pages of a capture that navigated more than once are ignored.
+pub fn har_html(content: &str) -> Result<String> {
+    let har: Har =
+        serde_json::from_str(content).map_err(|e| anyhow::anyhow!("Invalid HAR file: {}", e))?;
+    let page = har.log.pages.first().map(|page| page.id.as_str());
+    if har.log.pages.len() > 1 {
+        tracing::warn!(
+            pages = har.log.pages.len(),
+            "HAR has several pages, using the first"
+        );
+    }
+
+    let entry = har
+        .log
+        .entries
+        .iter()
+        .filter(|entry| page.is_none() || entry.pageref.as_deref() == page)
+        .find(|entry| {
+            (200..300).contains(&entry.response.status)
+                && entry.response.content.mime_type.starts_with("text/html")
+                && entry.response.content.text.is_some()
+        })
+        .ok_or_else(|| anyhow::anyhow!("No HTML document in HAR file"))?;
+
+    let content = &entry.response.content;
+    let text = content.text.clone().unwrap_or_default();
+    match content.encoding.as_deref() {
+        Some("base64") => {
+            let bytes = base64::engine::general_purpose::STANDARD
+                .decode(text.trim())
+                .map_err(|e| anyhow::anyhow!("Invalid base64 in HAR response: {}", e))?;
+            Ok(decode(&bytes, &content.mime_type))
+        }
+        _ => Ok(text),
+    }
+}
+
+/// The HTML document of an MHTML archive: the part named by the `start`
+/// parameter, or else the first `text/html` part
+///
+/// The part is decoded in the charset its `Content-Type` declares.
+pub fn mhtml_html(content: &[u8]) -> Result<String> {
+    let (headers, body) = split_headers(content);
+    let content_type = header(&headers, "content-type")
+        .ok_or_else(|| anyhow::anyhow!("Invalid MHTML file: no Content-Type"))?;
+    let boundary = parameter(&content_type, "boundary")
+        .ok_or_else(|| anyhow::anyhow!("Invalid MHTML file: no multipart boundary"))?;
+    let start = parameter(&content_type, "start");
+
+    let delimiter = format!("--{}", boundary);
+    let mut html = None;
+    for part in split_parts(body, delimiter.as_bytes()) {
+        let (headers, body) = split_headers(part);
+        let is_start = start.is_some()
+            && header(&headers, "content-id").map(|id| id.trim().to_string()) == start;
+        let part_type = header(&headers, "content-type").unwrap_or_default();
+        let is_html = part_type.to_ascii_lowercase().starts_with("text/html");
+        if is_start || (is_html && html.is_none()) {
+            let encoding = header(&headers, "content-transfer-encoding")
+                .unwrap_or_default()
+                .trim()
+                .to_ascii_lowercase();
+            let decoded = match encoding.as_str() {
+                "quoted-printable" => {
+                    quoted_printable::decode(body, quoted_printable::ParseMode::Robust)
+                        .map_err(|e| anyhow::anyhow!("Invalid quoted-printable in MHTML: {}", e))?
+                }
+                "base64" => {
+                    let compact: Vec<u8> = body
+                        .iter()
+                        .copied()
+                        .filter(|b| !b.is_ascii_whitespace())
+                        .collect();
+                    base64::engine::general_purpose::STANDARD
+                        .decode(compact)
+                        .map_err(|e| anyhow::anyhow!("Invalid base64 in MHTML: {}", e))?
+                }
+                _ => body.to_vec(),
+            };
+            html = Some(decode(&decoded, &part_type));
+            if is_start {
+                break;
+            }
+        }
+    }
+    html.ok_or_else(|| anyhow::anyhow!("No HTML document in MHTML file"))
+}
+
+/// Decode a body in the charset of its content type, UTF-8 if none is given
+fn decode(bytes: &[u8], content_type: &str) -> String {
+    let encoding = parameter(content_type, "charset")
+        .and_then(|label| encoding_rs::Encoding::for_label(label.as_bytes()))
+        .unwrap_or(encoding_rs::UTF_8);
+    encoding.decode(bytes).0.into_owned()
+}
+
+/// Split a MIME entity into its unfolded header lines and its body
+fn split_headers(entity: &[u8]) -> (Vec<String>, &[u8]) {
+    let (head, body) = match find(entity, b"\r\n\r\n") {
+        Some(i) => (&entity[..i], &entity[i + 4..]),
+        None => match find(entity, b"\n\n") {
+            Some(i) => (&entity[..i], &entity[i + 2..]),
+            None => (entity, &[][..]),
+        },
+    };
+    let mut headers: Vec<String> = Vec::new();
+    for line in String::from_utf8_lossy(head).lines() {
+        match headers.last_mut() {
+            // Continuation of a folded header
+            Some(last) if line.starts_with([' ', '\t']) => {
+                last.push(' ');
+                last.push_str(line.trim());
+            }
+            _ => headers.push(line.to_string()),
+        }
+    }
+    (headers, body)
+}
+
+/// The parts of a multipart body, without their delimiter lines
+fn split_parts<'a>(body: &'a [u8], delimiter: &[u8]) -> Vec<&'a [u8]> {
+    let mut parts = Vec::new();
+    let mut rest = body;
+    let mut started = false;
+    while let Some(i) = find(rest, delimiter) {
+        if started {
+            let part = &rest[..i];
+            let part = part.strip_suffix(b"\n").unwrap_or(part);
+            parts.push(part.strip_suffix(b"\r").unwrap_or(part));
+        }
+        started = true;
+        rest = &rest[i + delimiter.len()..];
+        if rest.starts_with(b"--") {
+            break;
+        }
+        let line_end = find(rest, b"\n").map_or(rest.len(), |i| i + 1);
+        rest = &rest[line_end..];
+    }
+    parts
+}
+
+fn header(headers: &[String], name: &str) -> Option<String> {
+    headers.iter().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        key.trim()
+            .eq_ignore_ascii_case(name)
+            .then(|| value.trim().to_string())
+    })
+}
+
+/// A parameter of a header value, like `boundary` in `multipart/related; boundary="x"`
+fn parameter(value: &str, name: &str) -> Option<String> {
+    value.split(';').skip(1).find_map(|param| {
+        let (key, value) = param.split_once('=')?;
+        key.trim()
+            .eq_ignore_ascii_case(name)
+            .then(|| value.trim().trim_matches('"').to_string())
+    })
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn har_picks_first_page_html() {
+        let har = r#"{"log": {
+            "pages": [{"id": "page_1"}, {"id": "page_2"}],
+            "entries": [
+                {"pageref": "page_2", "response": {"status": 200,
+                    "content": {"mimeType": "text/html", "text": "<p>second</p>"}}},
+                {"pageref": "page_1", "response": {"status": 200,
+                    "content": {"mimeType": "text/css", "text": "p {}"}}},
+                {"pageref": "page_1", "response": {"status": 404,
+                    "content": {"mimeType": "text/html", "text": "<p>missing</p>"}}},
+                {"pageref": "page_1", "response": {"status": 200,
+                    "content": {"mimeType": "text/html; charset=iso-8859-1",
+                        "text": "PHA+Y2Fm6TwvcD4=", "encoding": "base64"}}}
+            ]
+        }}"#;
+        assert_eq!(har_html(har).unwrap(), "<p>café</p>");
+
+        let har = r#"{"log": {"entries": [{"response": {"status": 200,
+            "content": {"mimeType": "text/html", "text": "<p>plain</p>"}}}]}}"#;
+        assert_eq!(har_html(har).unwrap(), "<p>plain</p>");
+        assert!(har_html(r#"{"log": {"entries": []}}"#).is_err());
+    }
+
+    #[test]
+    fn mhtml_follows_start_and_charset() {
+        let mhtml = "From: <Saved by Blink>\r\n\
+            Content-Type: multipart/related;\r\n\
+            \ttype=\"text/html\";\r\n\
+            \tboundary=\"----MultipartBoundary\";\r\n\
+            \tstart=\"<main@mhtml>\"\r\n\
+            \r\n\
+            ------MultipartBoundary\r\n\
+            Content-Type: text/html\r\n\
+            Content-ID: <frame@mhtml>\r\n\
+            \r\n\
+            <p>frame</p>\r\n\
+            ------MultipartBoundary\r\n\
+            Content-Type: text/html; charset=windows-1252\r\n\
+            Content-ID: <main@mhtml>\r\n\
+            Content-Transfer-Encoding: quoted-printable\r\n\
+            \r\n\
+            <p class=3D\"x\">caf=E9</p>\r\n\
+            ------MultipartBoundary--\r\n";
+        assert_eq!(
+            mhtml_html(mhtml.as_bytes()).unwrap(),
+            "<p class=\"x\">café</p>"
+        );
+    }
+
+    #[test]
+    fn mhtml_without_start_takes_first_html() {
+        let mhtml = "Content-Type: multipart/related; boundary=b\n\
+            \n\
+            --b\n\
+            Content-Type: text/css\n\
+            \n\
+            p {}\n\
+            --b\n\
+            Content-Type: text/html; charset=\"utf-8\"\n\
+            Content-Transfer-Encoding: base64\n\
+            \n\
+            PHA+bmHDr3Zl\n\
+            PC9wPg==\n\
+            --b--\n";
+        assert_eq!(mhtml_html(mhtml.as_bytes()).unwrap(), "<p>naïve</p>");
+        assert!(mhtml_html(b"Content-Type: text/html\n\n<p>x</p>").is_err());
+    }
+}
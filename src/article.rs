@@ -0,0 +1,562 @@
+//! Readability-style main content extraction
+//!
+//! Available with the `article` feature. Paragraphs are scored by length and
+//! commas, and each score is credited to the paragraph's nearest ancestors.
+//! The ancestor with the best score, discounted by how much of its text is
+//! links, is taken as the main content, together with siblings that look
+//! like more of it. Navigation, forms, ads and other boilerplate are left out
+//! of the cleaned content.
+
+use ego_tree::{NodeId, NodeRef};
+use regex::Regex;
+use scraper::{ElementRef, Html, Node};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+/// The main content of a page, with its title, author and date
+///
+/// Missing metadata is left out when serialized.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Article {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// The author, as credited on the page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub byline: Option<String>,
+    /// Publication date as written in the page's metadata (usually ISO 8601)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub published: Option<String>,
+    /// The content as plain text, with paragraphs separated by blank lines
+    pub text: String,
+    /// The content as HTML, keeping only structural tags and link/image attributes
+    pub html: String,
+}
+
+/// Fields of [`Article`], as accepted by `$article:FIELD`
+pub(crate) const FIELDS: [&str; 5] = ["title", "byline", "published", "text", "html"];
+
+/// Class/id words of page furniture
+static UNLIKELY: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        "(?i)-ad-|agegate|banner|breadcrumb|combx|comment|community|cookie|disqus|extra|foot\
+         |gdpr|header|menu|modal|newsletter|pager|pagination|popup|related|remark|replies|rss\
+         |share|shoutbox|sidebar|skyscraper|social|sponsor|subscribe|tweet|twitter",
+    )
+    .expect("unlikely regex")
+});
+/// Class/id words that rescue an otherwise unlikely element
+static MAYBE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new("(?i)and|article|body|column|content|main|shadow").expect("maybe regex")
+});
+static POSITIVE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new("(?i)article|body|content|entry|hentry|h-entry|main|page|post|text|blog|story")
+        .expect("positive regex")
+});
+static NEGATIVE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        "(?i)-ad-|hidden|banner|combx|comment|com-|contact|foot|footnote|gdpr|masthead|media\
+         |meta|outbrain|promo|related|scroll|share|shoutbox|sidebar|skyscraper|sponsor\
+         |shopping|tags|tool|widget",
+    )
+    .expect("negative regex")
+});
+static BYLINE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new("(?i)byline|author|dateline|writtenby|p-author").expect("byline regex")
+});
+
+/// Elements that start a new paragraph of text
+const BLOCKS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "dd",
+    "div",
+    "dl",
+    "dt",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "li",
+    "main",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "table",
+    "tr",
+    "ul",
+];
+
+/// Attributes kept in the cleaned HTML
+const KEPT_ATTRIBUTES: &[&str] = &[
+    "alt", "cite", "colspan", "datetime", "href", "rowspan", "src", "srcset", "title",
+];
+
+/// Find the article in a document, with `json_ld` items as a source of dates
+pub(crate) fn extract(html: &Html, json_ld: &[Value]) -> Article {
+    let content = main_content(html);
+    let mut render = Render::default();
+    if content.len() > 1 {
+        render.html.push_str("<div>");
+    }
+    for el in &content {
+        render.element(*el, true);
+    }
+    if content.len() > 1 {
+        render.html.push_str("</div>");
+    }
+    render.break_block();
+
+    Article {
+        title: title(html),
+        byline: byline(html),
+        published: published(html, json_ld),
+        text: render.blocks.join("\n\n"),
+        html: render.html,
+    }
+}
+
+/// The best-scoring content element and its sibling content, in document order
+fn main_content(html: &Html) -> Vec<ElementRef<'_>> {
+    let mut paragraphs = Vec::new();
+    collect_paragraphs(html.root_element(), &mut paragraphs);
+
+    let mut scores: HashMap<NodeId, f64> = HashMap::new();
+    let mut candidates = Vec::new();
+    for paragraph in paragraphs {
+        let text = text_of(paragraph);
+        let len = text.chars().count();
+        if len < 25 {
+            continue;
+        }
+        let score = 1.0 + text.matches([',', '，']).count() as f64 + (len / 100).min(3) as f64;
+        let ancestors = paragraph.ancestors().filter_map(ElementRef::wrap).take(5);
+        for (level, ancestor) in ancestors.enumerate() {
+            let divider = match level {
+                0 => 1.0,
+                1 => 2.0,
+                level => level as f64 * 3.0,
+            };
+            let entry = scores.entry(ancestor.id()).or_insert_with(|| {
+                candidates.push(ancestor);
+                initial_score(ancestor)
+            });
+            *entry += score / divider;
+        }
+    }
+    // Links-heavy blocks are navigation, however long
+    let adjusted = |el: ElementRef| scores.get(&el.id()).map(|s| s * (1.0 - link_density(el)));
+
+    let mut best: Option<(ElementRef, f64)> = None;
+    for candidate in candidates {
+        let score = adjusted(candidate).unwrap_or_default();
+        if best.is_none_or(|(_, best)| score > best) {
+            best = Some((candidate, score));
+        }
+    }
+    let Some((top, top_score)) = best else {
+        return vec![fallback(html)];
+    };
+    let Some(parent) = top.parent().and_then(ElementRef::wrap) else {
+        return vec![top];
+    };
+
+    let threshold = (top_score * 0.2).max(10.0);
+    parent
+        .children()
+        .filter_map(ElementRef::wrap)
+        .filter(|sibling| {
+            if sibling.id() == top.id() {
+                return true;
+            }
+            if is_boilerplate(*sibling) {
+                return false;
+            }
+            if adjusted(*sibling).is_some_and(|score| score >= threshold) {
+                return true;
+            }
+            // Loose paragraphs next to the content are more of it
+            sibling.value().name() == "p" && {
+                let text = text_of(*sibling);
+                let density = link_density(*sibling);
+                (text.len() > 80 && density < 0.25) || (density == 0.0 && text.contains(". "))
+            }
+        })
+        .collect()
+}
+
+/// `<article>`, `<main>` or the whole document, for pages without paragraphs
+fn fallback(html: &Html) -> ElementRef<'_> {
+    elements(html)
+        .find(|el| matches!(el.value().name(), "article" | "main"))
+        .unwrap_or_else(|| html.root_element())
+}
+
+/// Elements whose own text is scored: paragraphs, and divs or cells used as paragraphs
+fn collect_paragraphs<'a>(parent: ElementRef<'a>, out: &mut Vec<ElementRef<'a>>) {
+    for child in parent.children().filter_map(ElementRef::wrap) {
+        if is_boilerplate(child) {
+            continue;
+        }
+        let is_paragraph = match child.value().name() {
+            "p" | "pre" => true,
+            "div" | "td" => !child
+                .children()
+                .filter_map(ElementRef::wrap)
+                .any(|el| BLOCKS.contains(&el.value().name())),
+            _ => false,
+        };
+        if is_paragraph {
+            out.push(child);
+        } else {
+            collect_paragraphs(child, out);
+        }
+    }
+}
+
+fn initial_score(el: ElementRef) -> f64 {
+    let tag = match el.value().name() {
+        "div" | "article" | "main" => 5.0,
+        "pre" | "td" | "blockquote" => 3.0,
+        "address" | "ol" | "ul" | "dl" | "dd" | "dt" | "li" | "form" => -3.0,
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "th" => -5.0,
+        _ => 0.0,
+    };
+    tag + class_weight(el)
+}
+
+/// +25 or -25 for each of `class` and `id` that looks like content or clutter
+fn class_weight(el: ElementRef) -> f64 {
+    [el.value().attr("class"), el.value().id()]
+        .into_iter()
+        .flatten()
+        .map(|names| {
+            let mut weight = 0.0;
+            if NEGATIVE.is_match(names) {
+                weight -= 25.0;
+            }
+            if POSITIVE.is_match(names) {
+                weight += 25.0;
+            }
+            weight
+        })
+        .sum()
+}
+
+/// Elements never part of an article: scripts, navigation, forms, hidden
+/// elements, and anything whose class or id names page furniture
+fn is_boilerplate(el: ElementRef) -> bool {
+    let element = el.value();
+    if matches!(
+        element.name(),
+        "aside"
+            | "button"
+            | "embed"
+            | "footer"
+            | "form"
+            | "iframe"
+            | "input"
+            | "link"
+            | "meta"
+            | "nav"
+            | "noscript"
+            | "object"
+            | "script"
+            | "select"
+            | "style"
+            | "svg"
+            | "template"
+            | "textarea"
+    ) {
+        return true;
+    }
+    if element.attr("hidden").is_some()
+        || element.attr("aria-hidden") == Some("true")
+        || element.attr("role").is_some_and(|role| {
+            matches!(
+                role,
+                "banner" | "complementary" | "contentinfo" | "dialog" | "menu" | "navigation"
+            )
+        })
+        || element
+            .attr("style")
+            .is_some_and(|style| style.replace(' ', "").contains("display:none"))
+    {
+        return true;
+    }
+    if matches!(element.name(), "article" | "body" | "main") {
+        return false;
+    }
+    let names = format!(
+        "{} {}",
+        element.attr("class").unwrap_or_default(),
+        element.id().unwrap_or_default()
+    );
+    UNLIKELY.is_match(&names) && !MAYBE.is_match(&names)
+}
+
+/// Share of an element's text that is link text
+fn link_density(el: ElementRef) -> f64 {
+    let total = text_of(el).chars().count();
+    if total == 0 {
+        return 0.0;
+    }
+    let links: usize = el
+        .descendants()
+        .filter_map(ElementRef::wrap)
+        .filter(|el| el.value().name() == "a")
+        .map(|a| text_of(a).chars().count())
+        .sum();
+    links as f64 / total as f64
+}
+
+/// Cleaned HTML and paragraphs of text, written in one pass over the content
+#[derive(Default)]
+struct Render {
+    html: String,
+    blocks: Vec<String>,
+    block: String,
+    /// Depth of `<pre>` elements, inside which whitespace is kept
+    pre: usize,
+}
+
+impl Render {
+    fn element(&mut self, el: ElementRef, top: bool) {
+        // The content itself was chosen over its score, so only its
+        // descendants are cleaned conditionally. The byline is reported
+        // separately.
+        if is_boilerplate(el) || (!top && (is_clutter(el) || is_byline(el))) {
+            return;
+        }
+        let name = el.value().name();
+        let is_block = BLOCKS.contains(&name);
+        if is_block {
+            self.break_block();
+        }
+        self.html.push('<');
+        self.html.push_str(name);
+        for (attr, value) in el.value().attrs() {
+            if KEPT_ATTRIBUTES.contains(&attr) {
+                self.html.push(' ');
+                self.html.push_str(attr);
+                self.html.push_str("=\"");
+                escape(&mut self.html, value, true);
+                self.html.push('"');
+            }
+        }
+        self.html.push('>');
+        if crate::dom::is_void_element(name) {
+            if name == "br" {
+                self.block.truncate(self.block.trim_end().len());
+                self.block.push('\n');
+            }
+            return;
+        }
+
+        self.pre += usize::from(name == "pre");
+        for child in el.children() {
+            self.node(child);
+        }
+        self.pre -= usize::from(name == "pre");
+
+        self.html.push_str("</");
+        self.html.push_str(name);
+        self.html.push('>');
+        if is_block {
+            self.break_block();
+        }
+    }
+
+    fn node(&mut self, node: NodeRef<Node>) {
+        match node.value() {
+            Node::Text(text) => {
+                escape(&mut self.html, text, false);
+                if self.pre > 0 {
+                    self.block.push_str(text);
+                    return;
+                }
+                if text.starts_with(char::is_whitespace) {
+                    self.space();
+                }
+                for (i, word) in text.split_whitespace().enumerate() {
+                    if i > 0 {
+                        self.block.push(' ');
+                    }
+                    self.block.push_str(word);
+                }
+                if text.ends_with(char::is_whitespace) {
+                    self.space();
+                }
+            }
+            Node::Element(_) => {
+                if let Some(el) = ElementRef::wrap(node) {
+                    self.element(el, false);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn space(&mut self) {
+        if !self.block.is_empty() && !self.block.ends_with(char::is_whitespace) {
+            self.block.push(' ');
+        }
+    }
+
+    fn break_block(&mut self) {
+        let text = self.block.trim();
+        if !text.is_empty() {
+            self.blocks.push(text.to_string());
+        }
+        self.block.clear();
+    }
+}
+
+/// Containers inside the content that are mostly links or look like clutter
+fn is_clutter(el: ElementRef) -> bool {
+    matches!(
+        el.value().name(),
+        "div" | "dl" | "ol" | "section" | "table" | "ul"
+    ) && (class_weight(el) < 0.0 || link_density(el) > 0.5)
+}
+
+fn escape(out: &mut String, text: &str, attribute: bool) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' if attribute => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+}
+
+/// OpenGraph title, else the page's only `<h1>`, else `<title>`
+fn title(html: &Html) -> Option<String> {
+    meta(html, &["og:title", "twitter:title"])
+        .or_else(|| {
+            let mut headings = elements(html).filter(|el| el.value().name() == "h1");
+            match (headings.next(), headings.next()) {
+                (Some(h1), None) => non_empty(text_of(h1)),
+                _ => None,
+            }
+        })
+        .or_else(|| {
+            elements(html)
+                .find(|el| el.value().name() == "title")
+                .and_then(|title| non_empty(text_of(title)))
+        })
+}
+
+/// The author meta tag, else a short element marked up or named as a byline
+fn byline(html: &Html) -> Option<String> {
+    meta(html, &["author", "article:author", "parsely-author"])
+        .filter(|author| !author.starts_with("http"))
+        .or_else(|| {
+            elements(html)
+                .filter(|el| is_byline(*el))
+                .filter(|el| {
+                    !el.ancestors()
+                        .filter_map(ElementRef::wrap)
+                        .any(is_boilerplate)
+                })
+                .find_map(|el| {
+                    let text = text_of(el);
+                    let text = text
+                        .strip_prefix("By ")
+                        .or_else(|| text.strip_prefix("by "))
+                        .unwrap_or(&text);
+                    (text.chars().count() <= 100).then(|| non_empty(text.to_string()))?
+                })
+        })
+}
+
+/// Elements marked up or named as the author credit
+fn is_byline(el: ElementRef) -> bool {
+    let element = el.value();
+    element.attr("rel") == Some("author")
+        || element
+            .attr("itemprop")
+            .is_some_and(|prop| prop.split_whitespace().any(|p| p == "author"))
+        || [element.attr("class"), element.id()]
+            .into_iter()
+            .flatten()
+            .any(|names| BYLINE.is_match(names))
+}
+
+/// The publication date from meta tags, JSON-LD, or the first `<time>`
+fn published(html: &Html, json_ld: &[Value]) -> Option<String> {
+    meta(
+        html,
+        &[
+            "article:published_time",
+            "datePublished",
+            "date",
+            "pubdate",
+            "publish-date",
+            "dc.date",
+        ],
+    )
+    .or_else(|| {
+        json_ld
+            .iter()
+            .find_map(|item| item.get("datePublished")?.as_str().map(String::from))
+    })
+    .or_else(|| {
+        elements(html)
+            .filter(|el| el.value().name() == "time")
+            .find_map(|el| el.value().attr("datetime").map(String::from))
+    })
+}
+
+/// `content` of the first `<meta>` whose name, property or itemprop is one
+/// of `names`, trying the names in order
+fn meta(html: &Html, names: &[&str]) -> Option<String> {
+    names.iter().find_map(|name| {
+        elements(html)
+            .filter(|el| el.value().name() == "meta")
+            .find(|el| {
+                ["name", "property", "itemprop"].iter().any(|attr| {
+                    el.value()
+                        .attr(attr)
+                        .is_some_and(|value| value.eq_ignore_ascii_case(name))
+                })
+            })
+            .and_then(|el| non_empty(el.value().attr("content")?.trim().to_string()))
+    })
+}
+
+/// Elements of the document in document order
+fn elements(html: &Html) -> impl Iterator<Item = ElementRef<'_>> {
+    html.tree.root().descendants().filter_map(ElementRef::wrap)
+}
+
+/// Text of an element with whitespace collapsed
+fn text_of(el: ElementRef) -> String {
+    let mut text = String::new();
+    for word in el.text().flat_map(str::split_whitespace) {
+        if !text.is_empty() {
+            text.push(' ');
+        }
+        text.push_str(word);
+    }
+    text
+}
+
+fn non_empty(text: String) -> Option<String> {
+    (!text.is_empty()).then_some(text)
+}
@@ -0,0 +1,181 @@
+//! Benchmarking a spec against a page
+//!
+//! Parsing and extraction are timed separately over a number of iterations,
+//! with per-field times from the extraction statistics. Allocations are
+//! counted by wrapping the system allocator, which costs the rest of the
+//! CLI one relaxed atomic add per allocation.
+
+use crate::{Args, BOLD, RESET, read_html};
+use anyhow::Result;
+use html2json::{Dom, Spec};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Options of the `bench` subcommand
+#[derive(clap::Args, Debug)]
+pub struct BenchArgs {
+    /// HTML file or http(s) URL (fetched once)
+    #[arg(value_name = "FILE|URL")]
+    input: String,
+
+    /// Spec to benchmark
+    #[arg(short, long, value_name = "SPEC", required = true)]
+    spec: String,
+
+    /// Timed iterations
+    #[arg(short = 'n', long, value_name = "N", default_value_t = 100)]
+    iterations: usize,
+
+    /// Untimed iterations run first to warm caches
+    #[arg(long, value_name = "N", default_value_t = 3)]
+    warmup: usize,
+}
+
+struct Counting;
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+static ALLOCATED_BYTES: AtomicU64 = AtomicU64::new(0);
+
+// SAFETY: defers to the system allocator, only counting requests
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(layout.size() as u64, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(new_size as u64, Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+/// Time and allocations of one phase over all iterations
+#[derive(Default)]
+struct Phase {
+    times: Vec<Duration>,
+    allocations: u64,
+    bytes: u64,
+}
+
+impl Phase {
+    /// Run `f`, adding its time and allocations to the phase
+    fn measure<T>(&mut self, f: impl FnOnce() -> T) -> T {
+        let (allocations, bytes) = allocated();
+        let start = Instant::now();
+        let result = f();
+        self.times.push(start.elapsed());
+        let (allocations_after, bytes_after) = allocated();
+        self.allocations += allocations_after - allocations;
+        self.bytes += bytes_after - bytes;
+        result
+    }
+
+    fn print(&mut self, name: &str) {
+        self.times.sort();
+        let n = self.times.len().max(1);
+        let mean = self.times.iter().sum::<Duration>() / n as u32;
+        println!(
+            "{:<8} {:>10.3?} {:>10.3?} {:>10.3?} {:>10.3?} {:>12} {:>12}",
+            name,
+            self.times.first().copied().unwrap_or_default(),
+            self.times[self.times.len() / 2],
+            mean,
+            self.times.last().copied().unwrap_or_default(),
+            self.allocations / n as u64,
+            bytes(self.bytes / n as u64),
+        );
+    }
+}
+
+pub fn run(args: &Args, bench: &BenchArgs) -> Result<()> {
+    if bench.iterations == 0 {
+        return Err(anyhow::anyhow!("--iterations must be at least 1"));
+    }
+    let spec = Spec::from_json(&crate::load_spec(&bench.spec)?)?;
+    let html = read_html(args, Some(&bench.input))?;
+
+    for _ in 0..bench.warmup {
+        Dom::parse(&html)?.extract(&spec)?;
+    }
+
+    let (mut parse, mut extract) = (Phase::default(), Phase::default());
+    // Per-field totals over all iterations, in first-evaluated order
+    let mut fields: Vec<html2json::FieldStats> = Vec::new();
+    for _ in 0..bench.iterations {
+        let dom = parse.measure(|| Dom::parse(&html))?;
+        extract.measure(|| dom.extract(&spec))?;
+
+        let (_, stats) = dom.extract_with_stats(&spec)?;
+        for field in stats.fields {
+            match fields.iter_mut().find(|f| f.path == field.path) {
+                Some(total) => {
+                    total.calls += field.calls;
+                    total.matched += field.matched;
+                    total.elapsed += field.elapsed;
+                }
+                None => fields.push(field),
+            }
+        }
+    }
+
+    println!(
+        "{} ({}) with {}, {} iterations\n",
+        bench.input,
+        bytes(html.len() as u64),
+        bench.spec,
+        bench.iterations
+    );
+    println!(
+        "{}{:<8} {:>10} {:>10} {:>10} {:>10} {:>12} {:>12}{}",
+        BOLD, "", "min", "median", "mean", "max", "allocs/iter", "bytes/iter", RESET
+    );
+    parse.print("parse");
+    extract.print("extract");
+
+    if !fields.is_empty() {
+        println!(
+            "\n{}Per field (mean per iteration, slowest first; includes nested fields){}",
+            BOLD, RESET
+        );
+        fields.sort_by_key(|field| std::cmp::Reverse(field.elapsed));
+        let width = fields.iter().map(|f| f.path.len()).max().unwrap_or(0);
+        let n = bench.iterations;
+        for field in &fields {
+            println!(
+                "{:>10.3?} {:>7} calls {:>7} matched  {:<width$}  {}",
+                field.elapsed / n as u32,
+                field.calls / n,
+                field.matched / n,
+                field.path,
+                field.selector
+            );
+        }
+    }
+    Ok(())
+}
+
+fn allocated() -> (u64, u64) {
+    (
+        ALLOCATIONS.load(Ordering::Relaxed),
+        ALLOCATED_BYTES.load(Ordering::Relaxed),
+    )
+}
+
+/// Human-readable byte count
+fn bytes(n: u64) -> String {
+    match n {
+        n if n >= 1 << 20 => format!("{:.1} MiB", n as f64 / (1 << 20) as f64),
+        n if n >= 1 << 10 => format!("{:.1} KiB", n as f64 / (1 << 10) as f64),
+        n => format!("{} B", n),
+    }
+}
@@ -0,0 +1,113 @@
+//! Breadcrumb trails as `[{"name", "url"}]`
+//!
+//! Sources are tried in order: a JSON-LD `BreadcrumbList`, a microdata
+//! `BreadcrumbList`, then common markup (`nav[aria-label=breadcrumb]`,
+//! `.breadcrumb`, `.breadcrumbs`). `url` is resolved against the page's base
+//! URL and left out for crumbs without a link, usually the current page.
+
+use scraper::{ElementRef, Html, Selector};
+use serde_json::{Map, Value};
+use std::sync::LazyLock;
+use url::Url;
+
+/// The trail of the first source that has one, or an empty list
+///
+/// `lists` are the document's `BreadcrumbList` items, JSON-LD first.
+pub(crate) fn breadcrumbs(html: &Html, lists: &[Value], base: Option<&Url>) -> Vec<Value> {
+    lists
+        .iter()
+        .map(|list| from_list(list, base))
+        .find(|trail| !trail.is_empty())
+        .unwrap_or_else(|| from_markup(html, base))
+}
+
+/// Crumbs of a JSON-LD or microdata `BreadcrumbList`, ordered by `position`
+fn from_list(list: &Value, base: Option<&Url>) -> Vec<Value> {
+    let elements = match list.get("itemListElement") {
+        Some(Value::Array(elements)) => elements.iter().collect(),
+        Some(element) => vec![element],
+        None => Vec::new(),
+    };
+    let mut crumbs: Vec<(f64, Value)> = elements
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, element)| {
+            let item = element.get("item");
+            let name = element
+                .get("name")
+                .or_else(|| item.and_then(|item| item.get("name")))
+                .and_then(Value::as_str)?;
+            let url = match item {
+                Some(Value::String(url)) => Some(url.as_str()),
+                Some(item) => item
+                    .get("@id")
+                    .or_else(|| item.get("url"))
+                    .and_then(Value::as_str),
+                None => element.get("url").and_then(Value::as_str),
+            };
+            let position = match element.get("position") {
+                Some(Value::Number(n)) => n.as_f64(),
+                Some(Value::String(s)) => s.trim().parse().ok(),
+                _ => None,
+            };
+            Some((position.unwrap_or(i as f64), crumb(name, url, base)))
+        })
+        .collect();
+    crumbs.sort_by(|a, b| a.0.total_cmp(&b.0));
+    crumbs.into_iter().map(|(_, crumb)| crumb).collect()
+}
+
+/// Crumbs of the first breadcrumb navigation in the markup
+fn from_markup(html: &Html, base: Option<&Url>) -> Vec<Value> {
+    static TRAIL: LazyLock<Selector> = LazyLock::new(|| {
+        Selector::parse(
+            "nav[aria-label=breadcrumb i], nav[aria-label=breadcrumbs i], .breadcrumb, .breadcrumbs",
+        )
+        .expect("breadcrumb selector")
+    });
+    static ITEMS: LazyLock<Selector> =
+        LazyLock::new(|| Selector::parse("li").expect("list item selector"));
+    static LINKS: LazyLock<Selector> =
+        LazyLock::new(|| Selector::parse("a[href]").expect("link selector"));
+
+    let Some(trail) = html.select(&TRAIL).next() else {
+        return Vec::new();
+    };
+    let mut items: Vec<ElementRef> = trail.select(&ITEMS).collect();
+    if items.is_empty() {
+        items = trail.select(&LINKS).collect();
+    }
+    items
+        .into_iter()
+        .filter_map(|item| {
+            let name = item
+                .text()
+                .flat_map(str::split_whitespace)
+                .collect::<Vec<_>>();
+            if name.is_empty() {
+                return None;
+            }
+            let url = match item.value().attr("href") {
+                Some(href) => Some(href),
+                None => item
+                    .select(&LINKS)
+                    .next()
+                    .and_then(|a| a.value().attr("href")),
+            };
+            Some(crumb(&name.join(" "), url, base))
+        })
+        .collect()
+}
+
+fn crumb(name: &str, url: Option<&str>, base: Option<&Url>) -> Value {
+    let mut crumb = Map::new();
+    crumb.insert("name".into(), name.trim().into());
+    if let Some(url) = url.map(str::trim).filter(|url| !url.is_empty()) {
+        let url = match base.and_then(|base| base.join(url).ok()) {
+            Some(resolved) => resolved.to_string(),
+            None => url.to_string(),
+        };
+        crumb.insert("url".into(), url.into());
+    }
+    Value::Object(crumb)
+}
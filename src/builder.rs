@@ -0,0 +1,211 @@
+//! Programmatic spec construction
+//!
+//! [`SpecBuilder`] builds the same [`ObjectSpec`]/[`ArraySpec`] types as
+//! [`Spec::from_json`], without writing or escaping JSON by hand.
+//!
+//! ```
+//! use html2json::Spec;
+//!
+//! let spec = Spec::builder()
+//!     .scope("#main")
+//!     .field("title", "h1 | trim")
+//!     .optional_field("subtitle", "h2")
+//!     .collection("items", |item| {
+//!         item.scope("li").field("name", "$").field("link", "a | attr:href")
+//!     })
+//!     .build()?;
+//! assert_eq!(spec.to_json()["items"][0]["$"], "li");
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use crate::spec::{ArraySpec, Field, FieldSpec, LiteralValue, ObjectSpec, SelectorRef, Spec};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Fluent builder for object and collection specs
+///
+/// Selector strings use the same syntax as JSON specs (`"a | attr:href"`,
+/// `"h1.main || h1"`). Parse errors are collected and reported by
+/// [`SpecBuilder::build`].
+#[derive(Debug, Default)]
+pub struct SpecBuilder {
+    scope: Option<SelectorRef>,
+    scope_fallbacks: Vec<SelectorRef>,
+    all: bool,
+    with_next: usize,
+    root: bool,
+    fields: HashMap<String, Field>,
+    consts: HashMap<String, String>,
+    error: Option<anyhow::Error>,
+}
+
+impl SpecBuilder {
+    /// Create an empty builder
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the scope selector (`$`), with any `||` fallbacks
+    pub fn scope(mut self, selector: &str) -> Self {
+        match ObjectSpec::parse_scope(selector) {
+            Ok((scope, fallbacks)) => {
+                self.scope = Some(scope);
+                self.scope_fallbacks = fallbacks;
+                self
+            }
+            Err(e) => self.fail(e),
+        }
+    }
+
+    /// Search from the document root instead of the parent's scope (`"$root": true`)
+    ///
+    /// For nested objects and collections; at the top level it changes nothing.
+    pub fn root(mut self) -> Self {
+        self.root = true;
+        self
+    }
+
+    /// Give a collection an item for every element in its scope (`"$all": true`)
+    ///
+    /// Collections otherwise need a [`scope`](Self::scope) naming the
+    /// repeating element.
+    pub fn all(mut self) -> Self {
+        self.all = true;
+        self
+    }
+
+    /// Make the `rows` elements after each collection item's element part of
+    /// the item (`"$withNext": rows`), for `$next` selectors
+    pub fn with_next(mut self, rows: usize) -> Self {
+        self.with_next = rows;
+        self
+    }
+
+    /// Define a constant for `$name` pipe arguments (`"$consts"`)
+    ///
+    /// For the top-level builder; nested objects and collections can't have
+    /// constants.
+    pub fn constant(mut self, name: &str, value: &str) -> Self {
+        self.consts.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    /// Add a selector field (`"selector | pipe"` syntax)
+    pub fn field(self, name: &str, selector: &str) -> Self {
+        self.selector_field(name, selector, false)
+    }
+
+    /// Add a selector field that is dropped from the output when null
+    pub fn optional_field(self, name: &str, selector: &str) -> Self {
+        self.selector_field(name, selector, true)
+    }
+
+    /// Add a field collecting every match of a selector as an array
+    /// (`"name*": "selector"`)
+    pub fn all_field(self, name: &str, selector: &str) -> Self {
+        match FieldSpec::parse_selector_or_fallback(selector).and_then(FieldSpec::into_all) {
+            Ok(spec) => self.insert(name, spec, false),
+            Err(e) => self.fail(e.context(format!("Invalid field '{}'", name))),
+        }
+    }
+
+    /// Add a literal field
+    pub fn literal(self, name: &str, value: LiteralValue) -> Self {
+        self.insert(name, FieldSpec::Literal(value), false)
+    }
+
+    /// Add a nested object field
+    pub fn nested<F>(self, name: &str, build: F) -> Self
+    where
+        F: FnOnce(SpecBuilder) -> SpecBuilder,
+    {
+        let built = build(SpecBuilder::new())
+            .build_object()
+            .and_then(|mut obj| {
+                obj.check_next_rows()?;
+                FieldSpec::check_no_consts(&obj)?;
+                Ok(obj)
+            });
+        match built {
+            Ok(obj) => self.insert(name, FieldSpec::Nested(obj), false),
+            Err(e) => self.fail(e),
+        }
+    }
+
+    /// Add a collection field extracting one object per matched element
+    pub fn collection<F>(self, name: &str, build: F) -> Self
+    where
+        F: FnOnce(SpecBuilder) -> SpecBuilder,
+    {
+        let built = build(SpecBuilder::new())
+            .build_array()
+            .and_then(|array| FieldSpec::check_no_consts(&array.item_spec).map(|()| array));
+        match built {
+            Ok(array) => self.insert(name, FieldSpec::NestedArray(array), false),
+            Err(e) => self.fail(e),
+        }
+    }
+
+    /// Build an object spec
+    pub fn build(self) -> Result<Spec, anyhow::Error> {
+        let mut obj = self.build_object()?;
+        obj.check_next_rows()?;
+        obj.check_document_level()?;
+        Ok(Spec::Object(Arc::new(obj)))
+    }
+
+    /// Build a top-level collection spec, using the scope as the item selector
+    pub fn build_collection(self) -> Result<Spec, anyhow::Error> {
+        self.build_array().map(|array| Spec::Array(Arc::new(array)))
+    }
+
+    fn build_array(mut self) -> Result<ArraySpec, anyhow::Error> {
+        let all = std::mem::take(&mut self.all);
+        let with_next = std::mem::take(&mut self.with_next);
+        ArraySpec::new(self.build_object()?, all, with_next)
+    }
+
+    fn build_object(self) -> Result<ObjectSpec, anyhow::Error> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+        if self.all {
+            return Err(anyhow::anyhow!("`all` only applies to collections"));
+        }
+        if self.with_next > 0 {
+            return Err(anyhow::anyhow!("`with_next` only applies to collections"));
+        }
+        let spec = ObjectSpec {
+            scope_selector: self.scope,
+            scope_fallbacks: self.scope_fallbacks,
+            root: self.root,
+            fields: self.fields,
+            consts: self.consts,
+        };
+        if spec.root {
+            spec.check_document_level()?;
+        }
+        Ok(spec)
+    }
+
+    fn selector_field(self, name: &str, selector: &str, optional: bool) -> Self {
+        match FieldSpec::parse_selector_or_fallback(selector) {
+            Ok(spec) => self.insert(name, spec, optional),
+            Err(e) => self.fail(e.context(format!("Invalid field '{}'", name))),
+        }
+    }
+
+    fn insert(mut self, name: &str, spec: FieldSpec, optional: bool) -> Self {
+        if self.fields.contains_key(name) {
+            return self.fail(anyhow::anyhow!("Duplicate field '{}'", name));
+        }
+        self.fields
+            .insert(name.to_string(), Field { spec, optional });
+        self
+    }
+
+    fn fail(mut self, e: anyhow::Error) -> Self {
+        self.error.get_or_insert(e);
+        self
+    }
+}
@@ -0,0 +1,131 @@
+//! What changed between two extraction results
+//!
+//! For monitoring a page, extract it again with
+//! [`extract_changes`](crate::extract_changes) and act on the paths that
+//! changed, e.g. `.price` for "tell me when the price changes".
+
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt;
+
+/// How a value changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    /// Only in the new result
+    Added,
+    /// Only in the old result
+    Removed,
+    /// In both results, with different values
+    Changed,
+}
+
+/// A difference between two results
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Change {
+    /// jq-style path of the value, like `.items[2].price` or `."unit price"`
+    /// (`.` for the whole result)
+    pub path: String,
+    /// How it changed
+    pub kind: ChangeKind,
+    /// The old value, unless it was added
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old: Option<Value>,
+    /// The new value, unless it was removed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new: Option<Value>,
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let null = Value::Null;
+        let old = self.old.as_ref().unwrap_or(&null);
+        let new = self.new.as_ref().unwrap_or(&null);
+        match self.kind {
+            ChangeKind::Added => write!(f, "+ {}: {}", self.path, new),
+            ChangeKind::Removed => write!(f, "- {}: {}", self.path, old),
+            ChangeKind::Changed => write!(f, "~ {}: {} → {}", self.path, old, new),
+        }
+    }
+}
+
+/// The paths where `old` and `new` differ
+///
+/// Objects are compared field by field and arrays item by item, by position,
+/// so an item inserted at the front of an array changes every later item and
+/// adds one at the end. Changes are listed in the order of `old`, followed by
+/// what only `new` has.
+pub fn diff(old: &Value, new: &Value) -> Vec<Change> {
+    let mut changes = Vec::new();
+    collect(old, new, String::new(), &mut changes);
+    changes
+}
+
+fn collect(old: &Value, new: &Value, path: String, out: &mut Vec<Change>) {
+    let change = |path: String, kind, old: Option<&Value>, new: Option<&Value>| Change {
+        path,
+        kind,
+        old: old.cloned(),
+        new: new.cloned(),
+    };
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) => {
+            for (key, value) in old {
+                let path = key_path(&path, key);
+                match new.get(key) {
+                    Some(other) => collect(value, other, path, out),
+                    None => out.push(change(path, ChangeKind::Removed, Some(value), None)),
+                }
+            }
+            for (key, value) in new.iter().filter(|(key, _)| !old.contains_key(*key)) {
+                let path = key_path(&path, key);
+                out.push(change(path, ChangeKind::Added, None, Some(value)));
+            }
+        }
+        (Value::Array(old), Value::Array(new)) => {
+            for (i, (x, y)) in old.iter().zip(new).enumerate() {
+                collect(x, y, index_path(&path, i), out);
+            }
+            for (i, value) in old.iter().enumerate().skip(new.len()) {
+                let path = index_path(&path, i);
+                out.push(change(path, ChangeKind::Removed, Some(value), None));
+            }
+            for (i, value) in new.iter().enumerate().skip(old.len()) {
+                let path = index_path(&path, i);
+                out.push(change(path, ChangeKind::Added, None, Some(value)));
+            }
+        }
+        _ if old != new => {
+            let path = if path.is_empty() {
+                ".".to_string()
+            } else {
+                path
+            };
+            out.push(change(path, ChangeKind::Changed, Some(old), Some(new)));
+        }
+        _ => {}
+    }
+}
+
+/// `path.key`, quoting keys that aren't identifiers as jq does (`."a.b"`)
+fn key_path(path: &str, key: &str) -> String {
+    let mut chars = key.chars();
+    let identifier = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if identifier {
+        format!("{}.{}", path, key)
+    } else {
+        format!("{}.{}", path, Value::from(key))
+    }
+}
+
+/// `path[i]`, or `.[i]` for the whole result
+fn index_path(path: &str, i: usize) -> String {
+    if path.is_empty() {
+        format!(".[{}]", i)
+    } else {
+        format!("{}[{}]", path, i)
+    }
+}
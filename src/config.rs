@@ -0,0 +1,296 @@
+//! `html2json.toml`: default options and named jobs
+//!
+//! `[defaults]` applies to every run and `[jobs.NAME]` to `html2json run
+//! NAME`. Keys are the long option names (`user-agent`, `max-rps`, ...),
+//! with lists for repeatable options. Options given on the command line win
+//! over the job, which wins over the defaults.
+
+use crate::Args;
+use crate::output::Format;
+use anyhow::Result;
+use clap::ArgMatches;
+use clap::parser::ValueSource;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Config file read from the working directory when `--config` isn't given
+pub const DEFAULT_PATH: &str = "html2json.toml";
+
+/// Options that clap rejects together on the command line, which a config
+/// could otherwise combine
+const CONFLICTS: &[(&str, &[&str])] = &[
+    ("preset", &["select"]),
+    ("select", &["spec", "check"]),
+    (
+        "template",
+        &["format", "compact", "select", "explain", "check"],
+    ),
+    ("follow_next", &["explain", "cache_dom", "select"]),
+];
+
+/// Options that clap only accepts along with another
+const REQUIRES: &[(&str, &str)] = &[
+    ("url_filter", "sitemap"),
+    ("pipe", "select"),
+    ("wait_for", "render"),
+    ("render_timeout", "render"),
+    ("max_pages", "follow_next"),
+];
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    defaults: Options,
+    jobs: BTreeMap<String, Options>,
+}
+
+/// Options settable from the config file
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+struct Options {
+    inputs: Option<Vec<String>>,
+    input_list: Option<String>,
+    sitemap: Option<String>,
+    url_filter: Option<Vec<String>>,
+    spec: Option<String>,
+    preset: Option<crate::Preset>,
+    select: Option<Vec<String>>,
+    pipe: Option<String>,
+    format: Option<Format>,
+    compact: Option<bool>,
+    add_source: Option<String>,
+    output: Option<String>,
+    query: Option<String>,
+    template: Option<String>,
+    follow_next: Option<String>,
+    max_pages: Option<usize>,
+    fail_on_null: Option<bool>,
+    fail_on_empty_array: Option<bool>,
+    min_items: Option<usize>,
+    validate: Option<String>,
+    jobs: Option<usize>,
+    headers: Option<Vec<String>>,
+    cookies: Option<Vec<String>>,
+    user_agent: Option<String>,
+    proxy: Option<String>,
+    retries: Option<u32>,
+    cache_dir: Option<String>,
+    cache_ttl: Option<u64>,
+    delay: Option<f64>,
+    max_rps: Option<f64>,
+    max_per_host: Option<usize>,
+    respect_robots: Option<bool>,
+    render: Option<String>,
+    wait_for: Option<String>,
+    render_timeout: Option<f64>,
+}
+
+impl Config {
+    /// Read `path`, or `html2json.toml` if present
+    pub fn load(path: Option<&str>) -> Result<Option<Self>> {
+        let path = match path {
+            Some(path) => path,
+            None if Path::new(DEFAULT_PATH).is_file() => DEFAULT_PATH,
+            None => return Ok(None),
+        };
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read config '{}': {}", path, e))?;
+        let config = toml::from_str(&content)
+            .map_err(|e| anyhow::anyhow!("Invalid config '{}': {}", path, e))?;
+        Ok(Some(config))
+    }
+
+    /// Names of the configured jobs
+    pub fn job_names(&self) -> impl Iterator<Item = &str> {
+        self.jobs.keys().map(String::as_str)
+    }
+
+    /// Fill in options the command line left unset from the defaults, then
+    /// from `job` if given
+    ///
+    /// Combinations the command line would reject, like `template` with
+    /// `format`, are errors naming the job.
+    pub fn apply(&self, args: &mut Args, matches: &ArgMatches, job: Option<&str>) -> Result<()> {
+        let mut filled = self.defaults.apply(args, matches);
+        if let Some(name) = job {
+            let job = self.jobs.get(name).ok_or_else(|| {
+                let names: Vec<&str> = self.job_names().collect();
+                anyhow::anyhow!(
+                    "No job '{}' in config (jobs: {})",
+                    name,
+                    if names.is_empty() {
+                        "none".to_string()
+                    } else {
+                        names.join(", ")
+                    }
+                )
+            })?;
+            filled.extend(job.apply(args, matches));
+        }
+        // `compact = false` turns nothing on
+        if !args.compact {
+            filled.retain(|id| *id != "compact");
+        }
+        check(&filled, matches).map_err(|e| match job {
+            Some(name) => anyhow::anyhow!("Job '{}' in config: {}", name, e),
+            None => anyhow::anyhow!("[defaults] in config: {}", e),
+        })
+    }
+}
+
+/// Check the clap rules between options for those the config `filled`
+fn check(filled: &[&str], matches: &ArgMatches) -> Result<()> {
+    let is_set = |id: &str| {
+        filled.contains(&id) || matches.value_source(id) == Some(ValueSource::CommandLine)
+    };
+    let option = |id: &str| format!("--{}", id.replace('_', "-"));
+    for (id, others) in CONFLICTS {
+        for other in *others {
+            if is_set(id) && is_set(other) && (filled.contains(id) || filled.contains(other)) {
+                return Err(anyhow::anyhow!(
+                    "{} cannot be used with {}",
+                    option(id),
+                    option(other)
+                ));
+            }
+        }
+    }
+    for (id, required) in REQUIRES {
+        if filled.contains(id) && !is_set(required) {
+            return Err(anyhow::anyhow!(
+                "{} requires {}",
+                option(id),
+                option(required)
+            ));
+        }
+    }
+    Ok(())
+}
+
+impl Options {
+    /// Fill in unset options, returning the ids of those filled
+    fn apply(&self, args: &mut Args, matches: &ArgMatches) -> Vec<&'static str> {
+        let unset = |id: &str| matches.value_source(id) != Some(ValueSource::CommandLine);
+        let mut filled = Vec::new();
+        macro_rules! fill {
+            ($($field:ident),*) => {$(
+                if let Some(value) = &self.$field
+                    && unset(stringify!($field))
+                {
+                    args.$field = value.clone().into();
+                    filled.push(stringify!($field));
+                }
+            )*};
+        }
+        fill!(
+            input_list,
+            sitemap,
+            url_filter,
+            spec,
+            preset,
+            select,
+            pipe,
+            format,
+            compact,
+            add_source,
+            output,
+            query,
+            template,
+            follow_next,
+            max_pages,
+            fail_on_null,
+            fail_on_empty_array,
+            min_items,
+            validate,
+            jobs,
+            headers,
+            cookies,
+            user_agent,
+            proxy,
+            retries,
+            cache_dir,
+            cache_ttl,
+            delay,
+            max_rps,
+            max_per_host,
+            respect_robots,
+            render,
+            wait_for,
+            render_timeout
+        );
+        if let Some(inputs) = &self.inputs
+            && unset("input")
+        {
+            args.input = inputs.clone();
+            filled.push("input");
+        }
+        filled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::{CommandFactory, FromArgMatches};
+
+    fn apply(config: &str, argv: &[&str], job: Option<&str>) -> Result<Args> {
+        let config: Config = toml::from_str(config).unwrap();
+        let matches = Args::command().get_matches_from(argv);
+        let mut args = Args::from_arg_matches(&matches).unwrap();
+        config.apply(&mut args, &matches, job)?;
+        Ok(args)
+    }
+
+    #[test]
+    fn config_options_follow_clap_rules() {
+        let config = r#"
+            [defaults]
+            format = "csv"
+
+            [jobs.page]
+            template = "page.html"
+
+            [jobs.links]
+            pipe = "attr:href"
+
+            [jobs.links-selected]
+            select = ["a"]
+            pipe = "attr:href"
+        "#;
+        let err = apply(config, &["html2json"], Some("page")).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Job 'page' in config: --template cannot be used with --format"
+        );
+        let err = apply(config, &["html2json"], Some("links")).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Job 'links' in config: --pipe requires --select"
+        );
+
+        let args = apply(config, &["html2json"], Some("links-selected")).unwrap();
+        assert_eq!(args.pipe.as_deref(), Some("attr:href"));
+        assert!(apply(config, &["html2json", "--select", "a"], Some("links")).is_ok());
+        let err = apply(config, &["html2json", "--explain"], Some("page")).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("--template cannot be used with --format")
+        );
+        let err = apply(
+            "[defaults]\nfollow-next = \"a\"",
+            &["html2json", "--cache-dom", "x", "in.html"],
+            None,
+        )
+        .unwrap_err();
+        assert!(
+            err.to_string()
+                .ends_with("--follow-next cannot be used with --cache-dom")
+        );
+        let err = apply("[defaults]\nwait-for = \"main\"", &["html2json"], None).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "[defaults] in config: --wait-for requires --render"
+        );
+    }
+}
@@ -0,0 +1,322 @@
+//! Breadth-first crawling from start URLs
+//!
+//! Each level is fetched concurrently with the shared fetcher, so `--jobs`,
+//! `--delay`, robots.txt and the cache apply as in batch mode. Pages are
+//! extracted with the first spec whose URL pattern matches, and their links
+//! (selected by `--links`) form the next level.
+
+use crate::query::Query;
+use crate::{Args, check_html_size, extract_dom, page_context, select_values};
+use anyhow::Result;
+use html2json::Spec;
+use html2json::fetch::{self, FetchedDoc, Fetcher};
+use serde_json::Value;
+use std::collections::HashSet;
+
+/// Options of the `crawl` subcommand
+#[derive(clap::Args, Debug)]
+pub struct CrawlArgs {
+    /// URLs to start from
+    #[arg(value_name = "URL", required = true)]
+    start: Vec<String>,
+
+    /// Spec for visited pages: FILE, or PATTERN=FILE to use it only for URLs
+    /// matching a glob pattern (repeatable, first match wins)
+    #[arg(short, long = "spec", value_name = "[PATTERN=]FILE")]
+    specs: Vec<String>,
+
+    /// Selector and pipes extracting the links to follow
+    #[arg(long, value_name = "EXPR", default_value = "a[href] | attr:href")]
+    links: String,
+
+    /// How many links away from a start URL to go
+    #[arg(long, value_name = "N", default_value_t = 2)]
+    depth: usize,
+
+    /// Maximum pages to visit
+    #[arg(long, value_name = "N", default_value_t = 100)]
+    max_pages: usize,
+
+    /// Also follow links to HOST (by default only the start URLs' hosts), repeatable
+    #[arg(long = "allow-host", value_name = "HOST")]
+    allow_hosts: Vec<String>,
+
+    /// Follow links to any host
+    #[arg(long, conflicts_with = "allow_hosts")]
+    any_host: bool,
+
+    /// Only follow URLs matching a glob pattern, repeatable
+    #[arg(long = "include", value_name = "PATTERN")]
+    include: Vec<String>,
+
+    /// Never follow URLs matching a glob pattern, repeatable
+    #[arg(long = "exclude", value_name = "PATTERN")]
+    exclude: Vec<String>,
+}
+
+/// Which URLs to visit and which spec to apply to them
+struct Scope {
+    specs: Vec<(Option<glob::Pattern>, Spec)>,
+    hosts: Option<HashSet<String>>,
+    include: Vec<glob::Pattern>,
+    exclude: Vec<glob::Pattern>,
+}
+
+impl Scope {
+    fn new(crawl: &CrawlArgs) -> Result<Self> {
+        let mut specs = Vec::new();
+        for entry in &crawl.specs {
+            // The pattern may contain `=` (query strings); a file name rarely does
+            let (pattern, path) = match entry.rsplit_once('=') {
+                Some((pattern, path)) => (Some(parse_pattern(pattern)?), path),
+                None => (None, entry.as_str()),
+            };
+            specs.push((pattern, Spec::from_json(&crate::load_spec(path)?)?));
+        }
+
+        let hosts = if crawl.any_host {
+            None
+        } else {
+            let mut hosts: HashSet<String> = crawl.allow_hosts.iter().cloned().collect();
+            for url in &crawl.start {
+                hosts.insert(
+                    host(url).ok_or_else(|| anyhow::anyhow!("Invalid start URL '{}'", url))?,
+                );
+            }
+            Some(hosts)
+        };
+
+        Ok(Self {
+            specs,
+            hosts,
+            include: crawl
+                .include
+                .iter()
+                .map(|p| parse_pattern(p))
+                .collect::<Result<_>>()?,
+            exclude: crawl
+                .exclude
+                .iter()
+                .map(|p| parse_pattern(p))
+                .collect::<Result<_>>()?,
+        })
+    }
+
+    /// Whether a discovered link should be visited
+    fn follows(&self, url: &str) -> bool {
+        let host_allowed = match &self.hosts {
+            Some(hosts) => host(url).is_some_and(|host| hosts.contains(&host)),
+            None => true,
+        };
+        host_allowed
+            && (self.include.is_empty() || self.include.iter().any(|p| p.matches(url)))
+            && !self.exclude.iter().any(|p| p.matches(url))
+    }
+
+    /// The spec for a page, if any applies
+    fn spec(&self, url: &str) -> Option<&Spec> {
+        self.specs
+            .iter()
+            .find(|(pattern, _)| pattern.as_ref().is_none_or(|p| p.matches(url)))
+            .map(|(_, spec)| spec)
+    }
+}
+
+/// Crawl and print one JSON line per visited page
+pub fn run(args: &Args, crawl: &CrawlArgs) -> Result<()> {
+    let scope = Scope::new(crawl)?;
+    let query = args.query.as_deref().map(Query::compile).transpose()?;
+    let (selector, pipes) = html2json::spec::split_pipes(&crawl.links);
+    let fetcher = crate::build_fetcher(args)?;
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    let permits = std::sync::Arc::new(tokio::sync::Semaphore::new(args.jobs.max(1)));
+
+    let mut seen = HashSet::new();
+    let mut level = Vec::new();
+    for url in &crawl.start {
+        let url = fetch::resolve_url(url, "")?;
+        if seen.insert(url.clone()) {
+            level.push(url);
+        }
+    }
+    level.truncate(crawl.max_pages);
+
+    let (mut visited, mut failed) = (0, 0);
+    for depth in 0..=crawl.depth {
+        if level.is_empty() {
+            break;
+        }
+        visited += level.len();
+        let pages = runtime.block_on(async {
+            let mut tasks = tokio::task::JoinSet::new();
+            for (index, url) in level.iter().cloned().enumerate() {
+                let fetcher = fetcher.clone();
+                let permits = permits.clone();
+                tasks.spawn(async move {
+                    let _permit = permits.acquire_owned().await;
+                    let doc = fetcher.fetch(&url).await.and_then(|doc| {
+                        let body = check_html_size(doc.body)?;
+                        Ok(FetchedDoc { body, ..doc })
+                    });
+                    (index, doc)
+                });
+            }
+            let mut pages: Vec<Option<Result<FetchedDoc>>> = level.iter().map(|_| None).collect();
+            while let Some(joined) = tasks.join_next().await {
+                let (index, page) = joined?;
+                pages[index] = Some(page);
+            }
+            Ok::<_, anyhow::Error>(pages)
+        })?;
+
+        let mut next = Vec::new();
+        for (url, page) in level.iter().zip(pages) {
+            let page = page.unwrap_or_else(|| Err(anyhow::anyhow!("Fetch did not finish")));
+            let result = page.and_then(|doc| {
+                let dom = html2json::Dom::parse(&doc.body)?;
+                if depth < crawl.depth {
+                    for value in select_values(&dom, selector, pipes)? {
+                        let Value::String(href) = value else { continue };
+                        // Relative to where the page was served from, after redirects
+                        let Ok(link) = fetch::resolve_url(&doc.url, &href) else {
+                            continue;
+                        };
+                        if fetch::is_url(&link) && scope.follows(&link) && seen.insert(link.clone())
+                        {
+                            next.push(link);
+                        }
+                    }
+                }
+                let Some(spec) = scope.spec(url) else {
+                    return Ok(None);
+                };
+                let page = page_context(Some(url), Some(&doc.url));
+                let data = extract_dom(args, spec, &dom, &page)?;
+                match &query {
+                    Some(query) => query.apply(data).map(Some),
+                    None => Ok(Some(data)),
+                }
+            });
+
+            let field = match result {
+                Ok(Some(data)) => Some(("data", data)),
+                // Another spec's pattern: this page only contributes links
+                Ok(None) if !scope.specs.is_empty() => continue,
+                Ok(None) => None,
+                Err(e) => {
+                    failed += 1;
+                    eprintln!("{}: {}", url, e);
+                    Some(("error", Value::String(e.to_string())))
+                }
+            };
+            // Written by hand so `url` and `depth` lead each line
+            let field = field
+                .map(|(key, value)| format!(",\"{}\":{}", key, value))
+                .unwrap_or_default();
+            println!(
+                "{{\"url\":{},\"depth\":{}{}}}",
+                Value::from(url.as_str()),
+                depth,
+                field
+            );
+        }
+
+        next.truncate(crawl.max_pages.saturating_sub(visited));
+        level = next;
+    }
+
+    if failed > 0 {
+        eprintln!("{} of {} pages failed", failed, visited);
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn parse_pattern(pattern: &str) -> Result<glob::Pattern> {
+    glob::Pattern::new(pattern).map_err(|e| anyhow::anyhow!("Invalid pattern '{}': {}", pattern, e))
+}
+
+fn host(url: &str) -> Option<String> {
+    reqwest::Url::parse(url)
+        .ok()?
+        .host_str()
+        .map(|host| host.to_ascii_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn crawl_args(start: &[&str], specs: Vec<String>) -> CrawlArgs {
+        CrawlArgs {
+            start: start.iter().map(|s| s.to_string()).collect(),
+            specs,
+            links: "a[href] | attr:href".to_string(),
+            depth: 2,
+            max_pages: 100,
+            allow_hosts: Vec::new(),
+            any_host: false,
+            include: Vec::new(),
+            exclude: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn follows_start_hosts_and_patterns() {
+        let mut args = crawl_args(&["https://Example.com/"], Vec::new());
+        let scope = Scope::new(&args).unwrap();
+        assert!(scope.follows("https://example.com/a"));
+        assert!(!scope.follows("https://other.com/a"));
+        assert!(!scope.follows("not a url"));
+
+        args.allow_hosts = vec!["other.com".to_string()];
+        args.include = vec!["*/posts/*".to_string()];
+        args.exclude = vec!["*/posts/drafts/*".to_string()];
+        let scope = Scope::new(&args).unwrap();
+        assert!(scope.follows("https://other.com/posts/1"));
+        assert!(!scope.follows("https://example.com/about"));
+        assert!(!scope.follows("https://example.com/posts/drafts/2"));
+
+        args.allow_hosts.clear();
+        args.any_host = true;
+        let scope = Scope::new(&args).unwrap();
+        assert!(scope.follows("https://anywhere.org/posts/1"));
+    }
+
+    #[test]
+    fn spec_splits_at_last_equals() {
+        let dir = std::env::temp_dir().join(format!("html2json-crawl-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let page = dir.join("page.json");
+        let other = dir.join("other.json");
+        std::fs::write(&page, r#"{"page": "h1"}"#).unwrap();
+        std::fs::write(&other, r#"{"other": "h1"}"#).unwrap();
+
+        let args = crawl_args(
+            &["https://example.com/"],
+            vec![
+                format!("*?page=*={}", page.display()),
+                other.display().to_string(),
+            ],
+        );
+        let scope = Scope::new(&args);
+        std::fs::remove_dir_all(&dir).unwrap();
+        let scope = scope.unwrap();
+
+        let html = "<h1>Hi</h1>";
+        let extract = |url| {
+            let spec = scope.spec(url).unwrap();
+            html2json::Dom::parse(html).unwrap().extract(spec).unwrap()
+        };
+        assert_eq!(
+            extract("https://example.com/list?page=2"),
+            serde_json::json!({"page": "Hi"})
+        );
+        assert_eq!(
+            extract("https://example.com/about"),
+            serde_json::json!({"other": "Hi"})
+        );
+    }
+}
@@ -0,0 +1,110 @@
+//! Comparing extractions of two specs or two pages
+//!
+//! `--spec-a`/`--spec-b` extract one page with an old and a new spec, as
+//! when refactoring a spec; `--spec` with two pages shows what changed on
+//! a site. Changed paths are listed first, then a line diff of the results.
+
+use crate::query::Query;
+use crate::{Args, BOLD, GREEN, RED, RESET, extract_html, page_context, read_html};
+use anyhow::Result;
+use html2json::{Change, ChangeKind, Spec};
+
+/// Options of the `diff` subcommand
+#[derive(clap::Args, Debug)]
+pub struct DiffArgs {
+    /// One page to extract with both specs, or two pages to extract with --spec
+    #[arg(value_name = "FILE|URL", required = true, num_args = 1..=2)]
+    inputs: Vec<String>,
+
+    /// Spec extracting both pages
+    #[arg(short, long, value_name = "SPEC", conflicts_with_all = ["spec_a", "spec_b"])]
+    spec: Option<String>,
+
+    /// Old spec, shown as removed lines
+    #[arg(long, value_name = "SPEC", requires = "spec_b")]
+    spec_a: Option<String>,
+
+    /// New spec, shown as added lines
+    #[arg(long, value_name = "SPEC", requires = "spec_a")]
+    spec_b: Option<String>,
+}
+
+/// Most changed paths listed before the rest are counted
+const MAX_CHANGES: usize = 50;
+
+/// Extract both sides and print their differences; returns whether they match
+pub fn run(args: &Args, diff: &DiffArgs) -> Result<bool> {
+    let sides = match (
+        &diff.spec,
+        &diff.spec_a,
+        &diff.spec_b,
+        diff.inputs.as_slice(),
+    ) {
+        (Some(spec), None, None, [a, b]) => [(spec, a), (spec, b)],
+        (None, Some(spec_a), Some(spec_b), [input]) => [(spec_a, input), (spec_b, input)],
+        (Some(_), ..) => {
+            return Err(anyhow::anyhow!("--spec compares two inputs, got one"));
+        }
+        (None, Some(_), Some(_), _) => {
+            return Err(anyhow::anyhow!(
+                "--spec-a and --spec-b compare one input, got two"
+            ));
+        }
+        _ => {
+            return Err(anyhow::anyhow!(
+                "Pass --spec with two inputs, or --spec-a and --spec-b with one"
+            ));
+        }
+    };
+
+    let query = args.query.as_deref().map(Query::compile).transpose()?;
+    let mut results = Vec::new();
+    for (spec, input) in sides {
+        let spec = Spec::from_json(&crate::load_spec(spec)?)?;
+        let fetched = html2json::fetch::is_url(input).then_some(input.as_str());
+        let page = page_context(Some(input), fetched);
+        let result = extract_html(args, &spec, &read_html(args, Some(input))?, &page)?;
+        results.push(match &query {
+            Some(query) => query.apply(result)?,
+            None => result,
+        });
+    }
+    let (a, b) = (&results[0], &results[1]);
+    let label = |(spec, input): (&String, &String)| match diff.spec {
+        Some(_) => input.clone(),
+        None => spec.clone(),
+    };
+
+    if a == b {
+        eprintln!("{}✓{} Results are identical", GREEN, RESET);
+        return Ok(true);
+    }
+    println!("{}{}--- {}{}", BOLD, RED, label(sides[0]), RESET);
+    println!("{}{}+++ {}{}", BOLD, GREEN, label(sides[1]), RESET);
+
+    let changed = html2json::changes::diff(a, b);
+    for change in changed.iter().take(MAX_CHANGES) {
+        println!("{}", line(change));
+    }
+    if changed.len() > MAX_CHANGES {
+        println!("... and {} more", changed.len() - MAX_CHANGES);
+    }
+    println!();
+    crate::print_diff(
+        &serde_json::to_string_pretty(a)?,
+        &serde_json::to_string_pretty(b)?,
+    );
+    Ok(false)
+}
+
+/// A change as a line: `-` only in the first result, `+` only in the
+/// second, `~` in both with different values
+fn line(change: &Change) -> String {
+    match (change.kind, &change.old, &change.new) {
+        (ChangeKind::Changed, Some(old), Some(new)) => {
+            format!("{}~{} {}: {} → {}", BOLD, RESET, change.path, old, new)
+        }
+        (ChangeKind::Removed, ..) => format!("{}-{} {}", RED, RESET, change.path),
+        _ => format!("{}+{} {}", GREEN, RESET, change.path),
+    }
+}
@@ -2,18 +2,23 @@
 //!
 //! Parses HTML once and reuses the parsed document for all selections.
 
+use crate::options::Context;
 use ego_tree::NodeId;
 use scraper::{ElementRef, Html, Selector};
+use std::borrow::Cow;
 use std::collections::HashMap;
-use std::rc::Rc;
-use std::sync::OnceLock;
+use std::sync::{LazyLock, OnceLock};
+
+/// Minimum number of selector fields before array items match their fields
+/// in one walk (see [`FieldSelectorSet`])
+const ONE_PASS_MIN_FIELDS: usize = 4;
 
 /// A DOM node/element
 ///
-/// Lightweight reference to a node in the DOM tree.
+/// Lightweight reference to a node in the DOM tree, borrowed from its [`Dom`].
 /// Only stores NodeId + DOM reference; everything else is lazy-computed and cached.
 #[derive(Debug, Clone)]
-pub struct Node {
+pub struct Node<'a> {
     /// Node ID in the DOM tree (O(1) lookup via tree.get())
     node_id: NodeId,
     /// Cached text content
@@ -21,19 +26,21 @@ pub struct Node {
     /// Cached HTML content
     html: OnceLock<String>,
     /// Reference to the DOM tree
-    dom_html: Rc<Html>,
+    dom_html: &'a Html,
 }
 
 // Implement PartialEq for easier testing
-impl PartialEq for Node {
+impl PartialEq for Node<'_> {
     fn eq(&self, other: &Self) -> bool {
         self.node_id == other.node_id
     }
 }
-impl Eq for Node {}
+impl Eq for Node<'_> {}
 
-impl Node {
+impl<'a> Node<'a> {
     /// Returns the text content of this element
+    ///
+    /// The contents of `<script>` and `<style>` elements are kept as written.
     pub fn text(&self) -> &str {
         self.text.get_or_init(|| {
             // Fast path: get ElementRef directly without going through Result
@@ -49,13 +56,47 @@ impl Node {
             let text_content = el.text().collect::<String>();
             // For void elements, check if next sibling is a text node
             if text_content.is_empty() && is_void_element(el.value().name()) {
-                get_void_text_from_tree(el, &self.dom_html).unwrap_or(text_content)
+                get_void_text_from_tree(el).unwrap_or(text_content)
             } else {
                 text_content
             }
         })
     }
 
+    /// Length in bytes of [`Node::text`], without caching the text
+    pub(crate) fn text_len(&self) -> usize {
+        if let Some(text) = self.text.get() {
+            return text.len();
+        }
+        self.element_ref()
+            .map(|el| el.text().map(str::len).sum())
+            .unwrap_or(0)
+    }
+
+    /// Text content with whitespace kept as written, for `<pre>` and code
+    ///
+    /// Unlike [`Node::text`], `<br>` becomes a newline and block elements
+    /// (such as the per-line `<div>`s of highlighted code) start a new line.
+    pub fn raw_text(&self) -> String {
+        let mut out = String::new();
+        if let Ok(el) = self.element_ref() {
+            push_text_lines(el, &mut out, true);
+        }
+        out
+    }
+
+    /// Lines of text separated by `<br>` or block elements, for addresses
+    /// and opening hours
+    ///
+    /// Whitespace within a line is collapsed and blank lines are dropped.
+    pub fn lines(&self) -> Vec<String> {
+        let mut out = String::new();
+        if let Ok(el) = self.element_ref() {
+            push_text_lines(el, &mut out, false);
+        }
+        crate::pipe::split_lines(&out)
+    }
+
     /// Returns the value of the specified attribute
     pub fn attr(&self, name: &str) -> Option<&str> {
         // Fast path: get ElementRef directly
@@ -68,6 +109,9 @@ impl Node {
     }
 
     /// Returns the HTML string of this element (cached)
+    ///
+    /// This is the whole serialized subtree, however large, and it is not
+    /// subject to [`ExtractOptions`](crate::ExtractOptions) limits.
     pub fn html(&self) -> &str {
         self.html.get_or_init(|| {
             // Fast path: get ElementRef directly
@@ -80,8 +124,49 @@ impl Node {
         })
     }
 
+    /// At most `max` bytes of [`Node::html`], serializing no more than that
+    /// and bypassing the cache
+    pub(crate) fn html_prefix(&self, max: usize) -> String {
+        /// Accepts bytes until full, then fails to stop the serializer
+        struct Capped(Vec<u8>, usize);
+        impl std::io::Write for Capped {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                let room = self.1 - self.0.len();
+                if room == 0 {
+                    return Err(std::io::Error::other("full"));
+                }
+                let len = buf.len().min(room);
+                self.0.extend_from_slice(&buf[..len]);
+                Ok(len)
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let Ok(el) = self.element_ref() else {
+            return String::new();
+        };
+        let mut out = Capped(Vec::new(), max);
+        let opts = html5ever::serialize::SerializeOpts {
+            traversal_scope: html5ever::serialize::TraversalScope::IncludeNode,
+            ..Default::default()
+        };
+        // Running out of room is the expected way to stop
+        let _ = html5ever::serialize::serialize(&mut out, &el, opts);
+        match String::from_utf8(out.0) {
+            Ok(html) => html,
+            Err(e) => {
+                let valid = e.utf8_error().valid_up_to();
+                let mut bytes = e.into_bytes();
+                bytes.truncate(valid);
+                String::from_utf8(bytes).expect("valid up to here")
+            }
+        }
+    }
+
     /// Get the ElementRef for this node (O(1) lookup by NodeId)
-    pub(crate) fn element_ref(&self) -> Result<ElementRef<'_>, anyhow::Error> {
+    pub(crate) fn element_ref(&self) -> Result<ElementRef<'a>, anyhow::Error> {
         self.dom_html
             .tree
             .get(self.node_id)
@@ -90,167 +175,783 @@ impl Node {
     }
 }
 
+/// A selector simple enough to answer from the element index
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum SimpleSelector {
+    /// `#id`
+    Id(String),
+    /// `.class`
+    Class(String),
+    /// `tag`
+    Tag(String),
+}
+
+impl SimpleSelector {
+    /// Classify a selector string, returning None for anything non-trivial
+    pub(crate) fn parse(selector: &str) -> Option<Self> {
+        fn is_ident(s: &str) -> bool {
+            !s.is_empty()
+                && !s.starts_with(|c: char| c.is_ascii_digit())
+                && s.chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        }
+
+        let selector = selector.trim();
+        if let Some(id) = selector.strip_prefix('#') {
+            is_ident(id).then(|| Self::Id(id.to_string()))
+        } else if let Some(class) = selector.strip_prefix('.') {
+            is_ident(class).then(|| Self::Class(class.to_string()))
+        } else if selector.starts_with(|c: char| c.is_ascii_alphabetic())
+            && is_ident(selector)
+            && !selector.contains(|c: char| c.is_ascii_uppercase())
+        {
+            Some(Self::Tag(selector.to_string()))
+        } else {
+            None
+        }
+    }
+}
+
+/// Lookup tables from id, class and tag name to element nodes
+///
+/// Node lists are kept in the same order scraper visits nodes, so indexed
+/// lookups return exactly what a full-tree selector match would.
+#[derive(Debug, Clone, Default)]
+struct ElementIndex {
+    ids: HashMap<String, Vec<NodeId>>,
+    classes: HashMap<String, Vec<NodeId>>,
+    tags: HashMap<String, Vec<NodeId>>,
+}
+
+impl ElementIndex {
+    fn build(html: &Html) -> Self {
+        let mut index = Self::default();
+        for node in html.tree.nodes() {
+            let Some(el) = node.value().as_element() else {
+                continue;
+            };
+            if let Some(id) = el.id() {
+                // Scraped pages often repeat ids, and selectors match them all
+                index.ids.entry(id.to_string()).or_default().push(node.id());
+            }
+            for class in el.classes() {
+                index
+                    .classes
+                    .entry(class.to_string())
+                    .or_default()
+                    .push(node.id());
+            }
+            index
+                .tags
+                .entry(el.name().to_string())
+                .or_default()
+                .push(node.id());
+        }
+        index
+    }
+
+    fn lookup(&self, simple: &SimpleSelector) -> &[NodeId] {
+        let found = match simple {
+            SimpleSelector::Id(id) => self.ids.get(id).map(Vec::as_slice),
+            SimpleSelector::Class(class) => self.classes.get(class).map(Vec::as_slice),
+            SimpleSelector::Tag(tag) => self.tags.get(tag).map(Vec::as_slice),
+        };
+        found.unwrap_or_default()
+    }
+}
+
 /// DOM parser - parses HTML once and reuses for all queries
+///
+/// A `Dom` owns its parsed document and is `Send`, so it can be parsed on one
+/// thread and handed to another (or kept behind a `Mutex` in a thread pool).
+/// It is not `Sync`, and cannot be shared by reference between threads: the
+/// underlying html5ever tree caches per-element data without
+/// synchronization. [`Node`]s borrow from the `Dom` they came from.
+///
+/// In a multithreaded service, share the specs instead: a
+/// [`SpecSet`](crate::SpecSet) is `Send + Sync`, and each request parses
+/// its page into its own `Dom` through an [`Extractor`](crate::pool::Extractor).
+///
+/// Cloning a `Dom` copies the whole tree, so it is as costly as parsing the
+/// page again.
 #[derive(Debug, Clone)]
 pub struct Dom {
     /// Parsed HTML document
-    html: Rc<Html>,
+    html: Html,
+    /// Optional id/class/tag index (see [`Dom::parse_indexed`])
+    index: Option<ElementIndex>,
+    /// Main content, found on first use (see [`Dom::article`])
+    #[cfg(feature = "article")]
+    article: OnceLock<crate::article::Article>,
 }
 
 impl Dom {
+    fn new(html: Html, index: Option<ElementIndex>) -> Self {
+        Self {
+            html,
+            index,
+            #[cfg(feature = "article")]
+            article: OnceLock::new(),
+        }
+    }
+
     /// Parse HTML string into a DOM
     pub fn parse(source: &str) -> Result<Self, anyhow::Error> {
-        Ok(Self {
-            html: Rc::new(Html::parse_fragment(source)),
-        })
+        Ok(Self::new(parse_html(source), None))
     }
 
-    /// Query selector - returns first matching element
-    pub fn query_selector(&self, selector_str: &str) -> Result<Option<Node>, anyhow::Error> {
-        let selector = Selector::parse(selector_str)
-            .map_err(|e| anyhow::anyhow!("Invalid selector '{}': {}", selector_str, e))?;
-        Ok(self
-            .html
-            .select(&selector)
-            .next()
-            .map(|el| node_from_element(el, self.html.clone())))
+    /// Parse HTML and build an id/class/tag index
+    ///
+    /// Document-level queries for simple selectors (`#id`, `.class`, `tag`)
+    /// are then answered from the index instead of scanning the whole tree.
+    /// Building the index costs one extra pass over the document, which pays
+    /// off for large documents queried with many simple selectors.
+    pub fn parse_indexed(source: &str) -> Result<Self, anyhow::Error> {
+        let html = parse_html(source);
+        let index = Some(ElementIndex::build(&html));
+        Ok(Self::new(html, index))
     }
 
-    /// Query selector all - returns all matching elements
-    pub fn query_selector_all(&self, selector_str: &str) -> Result<Vec<Node>, anyhow::Error> {
-        let selector = Selector::parse(selector_str)
-            .map_err(|e| anyhow::anyhow!("Invalid selector '{}': {}", selector_str, e))?;
-        Ok(self
-            .html
-            .select(&selector)
-            .map(|el| node_from_element(el, self.html.clone()))
-            .collect())
+    /// Write the parsed document as a compact binary snapshot
+    ///
+    /// Reloading it with [`Dom::read_snapshot`] is much cheaper than parsing
+    /// the HTML again, which helps when iterating on specs against one large page.
+    /// The element index is not stored.
+    pub fn write_snapshot<W: std::io::Write>(&self, writer: W) -> Result<(), anyhow::Error> {
+        crate::snapshot::write(&self.html, writer)
     }
 
-    /// Query selector relative to a base element
-    pub fn query_selector_relative(
-        &self,
-        base: &Node,
+    /// Load a document written by [`Dom::write_snapshot`]
+    pub fn read_snapshot<R: std::io::Read>(reader: R) -> Result<Self, anyhow::Error> {
+        Ok(Self::new(crate::snapshot::read(reader)?, None))
+    }
+
+    /// Indexed matches for a document-level simple selector, if an index exists
+    fn indexed(&self, simple: Option<&SimpleSelector>, base: Option<&Node>) -> Option<&[NodeId]> {
+        if base.is_some() {
+            return None;
+        }
+        Some(self.index.as_ref()?.lookup(simple?))
+    }
+
+    /// First element matching `selector` in the document
+    ///
+    /// Simple selectors (`#id`, `.class`, `tag`) are answered from the element
+    /// index of a [`Dom::parse_indexed`] document.
+    pub fn select_one(&self, selector: &str) -> Result<Option<Node<'_>>, anyhow::Error> {
+        let simple = SimpleSelector::parse(selector);
+        self.first_match(&parse_selector(selector)?, simple.as_ref(), None)
+    }
+
+    /// All elements matching `selector` in the document, in document order
+    pub fn select(&self, selector: &str) -> Result<Vec<Node<'_>>, anyhow::Error> {
+        let simple = SimpleSelector::parse(selector);
+        self.all_matches(&parse_selector(selector)?, simple.as_ref(), None)
+    }
+
+    /// First descendant of `scope` matching `selector`
+    pub fn select_one_in<'a>(
+        &'a self,
+        scope: &Node<'a>,
+        selector: &str,
+    ) -> Result<Option<Node<'a>>, anyhow::Error> {
+        self.first_match(&parse_selector(selector)?, None, Some(scope))
+    }
+
+    /// All descendants of `scope` matching `selector`, in document order
+    pub fn select_in<'a>(
+        &'a self,
+        scope: &Node<'a>,
+        selector: &str,
+    ) -> Result<Vec<Node<'a>>, anyhow::Error> {
+        self.all_matches(&parse_selector(selector)?, None, Some(scope))
+    }
+
+    /// First element matching `selector` inside the siblings after `node`
+    ///
+    /// The descendants of each following sibling are searched in turn; the
+    /// siblings themselves are not matched. This is what a spec's
+    /// `+ selector` selects.
+    pub fn select_next_sibling<'a>(
+        &'a self,
+        node: &Node<'a>,
+        selector: &str,
+    ) -> Result<Option<Node<'a>>, anyhow::Error> {
+        self.next_sibling_match(&parse_selector(selector)?, node, 1)
+    }
+
+    #[deprecated(since = "0.5.13", note = "use `Dom::select_one`")]
+    pub fn query_selector(&self, selector_str: &str) -> Result<Option<Node<'_>>, anyhow::Error> {
+        self.select_one(selector_str)
+    }
+
+    #[deprecated(since = "0.5.13", note = "use `Dom::select`")]
+    pub fn query_selector_all(&self, selector_str: &str) -> Result<Vec<Node<'_>>, anyhow::Error> {
+        self.select(selector_str)
+    }
+
+    #[deprecated(since = "0.5.13", note = "use `Dom::select_one_in`")]
+    pub fn query_selector_relative<'a>(
+        &'a self,
+        base: &Node<'a>,
         selector_str: &str,
-    ) -> Result<Option<Node>, anyhow::Error> {
-        let selector = Selector::parse(selector_str)
-            .map_err(|e| anyhow::anyhow!("Invalid selector '{}': {}", selector_str, e))?;
-        let base_el = base.element_ref()?;
-        Ok(base_el
-            .select(&selector)
-            .next()
-            .map(|el| node_from_element(el, self.html.clone())))
+    ) -> Result<Option<Node<'a>>, anyhow::Error> {
+        self.select_one_in(base, selector_str)
     }
 
-    /// Query selector all relative to a base element
-    pub fn query_selector_all_relative(
-        &self,
-        base: &Node,
+    #[deprecated(since = "0.5.13", note = "use `Dom::select_in`")]
+    pub fn query_selector_all_relative<'a>(
+        &'a self,
+        base: &Node<'a>,
         selector_str: &str,
-    ) -> Result<Vec<Node>, anyhow::Error> {
-        let selector = Selector::parse(selector_str)
-            .map_err(|e| anyhow::anyhow!("Invalid selector '{}': {}", selector_str, e))?;
-        let base_el = base.element_ref()?;
-        Ok(base_el
-            .select(&selector)
-            .map(|el| node_from_element(el, self.html.clone()))
-            .collect())
+    ) -> Result<Vec<Node<'a>>, anyhow::Error> {
+        self.select_in(base, selector_str)
+    }
+
+    /// First element matching a compiled selector, optionally within a base element
+    ///
+    /// Traversal stops at the first match, for document and scoped queries alike.
+    /// Document-level simple selectors are answered from the element index when present.
+    fn first_match<'a>(
+        &'a self,
+        selector: &Selector,
+        simple: Option<&SimpleSelector>,
+        base: Option<&Node<'a>>,
+    ) -> Result<Option<Node<'a>>, anyhow::Error> {
+        if let Some(ids) = self.indexed(simple, base) {
+            return Ok(ids.first().and_then(|id| self.node_by_id(*id)));
+        }
+        let found = match base {
+            Some(b) => b.element_ref()?.select(selector).next(),
+            None => self.html.select(selector).next(),
+        };
+        Ok(found.map(|el| node_from_element(el, &self.html)))
+    }
+
+    /// All elements matching a compiled selector, optionally within a base element
+    ///
+    /// Document-level simple selectors are answered from the element index when present.
+    fn all_matches<'a>(
+        &'a self,
+        selector: &Selector,
+        simple: Option<&SimpleSelector>,
+        base: Option<&Node<'a>>,
+    ) -> Result<Vec<Node<'a>>, anyhow::Error> {
+        if let Some(ids) = self.indexed(simple, base) {
+            return Ok(ids.iter().filter_map(|id| self.node_by_id(*id)).collect());
+        }
+        Ok(match base {
+            Some(b) => b
+                .element_ref()?
+                .select(selector)
+                .map(|el| node_from_element(el, &self.html))
+                .collect(),
+            None => self
+                .html
+                .select(selector)
+                .map(|el| node_from_element(el, &self.html))
+                .collect(),
+        })
+    }
+
+    /// JSON-LD structured data from the document's
+    /// `<script type="application/ld+json">` blocks
+    ///
+    /// Top-level arrays and `@graph` lists are flattened into their items, and
+    /// blocks that aren't valid JSON are skipped. With `ty`, only items whose
+    /// `@type` is (or includes) `ty` are returned, e.g. `Some("Product")`.
+    pub fn json_ld(&self, ty: Option<&str>) -> Vec<serde_json::Value> {
+        self.json_ld_scripts()
+            .iter()
+            .flat_map(|script| json_ld_items(script.text(), ty))
+            .collect()
+    }
+
+    /// Microdata (`itemscope`/`itemprop`) items of the document as JSON
+    ///
+    /// Items are shaped like JSON-LD (`{"@type": "Product", "name": ...}`),
+    /// with nested items as nested objects and repeated properties as arrays.
+    /// With `ty`, only items whose `itemtype` is (or includes) `ty` are returned.
+    pub fn microdata(&self, ty: Option<&str>) -> Vec<serde_json::Value> {
+        crate::microdata::items(&self.html, None)
+            .into_iter()
+            .map(|el| crate::microdata::item(&self.html, el))
+            .filter(|item| ty.is_none_or(|ty| has_type(item, ty)))
+            .collect()
+    }
+
+    /// Images (`<img>`, `<source>`) of the document as JSON
+    ///
+    /// Available with the `url` feature (on by default).
+    ///
+    /// Each is `{"src", "alt", "width", "height", "srcset"}` without missing
+    /// attributes, with URLs resolved against the document's `<base href>`
+    /// and `base_url`.
+    #[cfg(feature = "url")]
+    pub fn images(&self, base_url: Option<&str>) -> Vec<serde_json::Value> {
+        let base = crate::links::base(&self.html, base_url);
+        crate::images::images(&self.html, None)
+            .into_iter()
+            .map(|el| crate::images::image(el, base.as_ref()))
+            .collect()
+    }
+
+    /// Links (`<a href>`) of the document as JSON
+    ///
+    /// Available with the `url` feature (on by default).
+    ///
+    /// Each is `{"href", "text", "rel", "nofollow"}` with `href` resolved
+    /// against the document's `<base href>` and `base_url`. With `filter`,
+    /// only internal or external links are returned.
+    #[cfg(feature = "url")]
+    pub fn links(
+        &self,
+        base_url: Option<&str>,
+        filter: Option<crate::spec::LinkFilter>,
+    ) -> Vec<serde_json::Value> {
+        let base = crate::links::base(&self.html, base_url);
+        crate::links::links(&self.html, None)
+            .into_iter()
+            .filter_map(|el| crate::links::link(el, base.as_ref(), filter))
+            .collect()
+    }
+
+    /// The page's pagination links as `{"next", "prev", "canonical",
+    /// "current", "pages"}`
+    ///
+    /// `next`/`prev` come from `rel="next"`/`rel="prev"`, else from
+    /// pagination markup; `pages` are its numbered links as
+    /// `{"number", "url"}`. URLs are resolved against the document's
+    /// `<base href>` and `base_url`; missing values are left out. Available
+    /// with the `url` feature (on by default).
+    #[cfg(feature = "url")]
+    pub fn pagination(&self, base_url: Option<&str>) -> serde_json::Value {
+        let base = crate::links::base(&self.html, base_url);
+        crate::pagination::pagination(&self.html, base.as_ref())
+    }
+
+    /// The document's forms as `{"action", "method", "enctype", "fields"}`
+    ///
+    /// Each named control is a field `{"name", "type", "value"}`, with
+    /// `"checked"` for checkboxes and radio buttons and `"options"` for
+    /// selects. `action` is resolved against the document's `<base href>`
+    /// and `base_url`. Available with the `url` feature (on by default).
+    #[cfg(feature = "url")]
+    pub fn forms(&self, base_url: Option<&str>) -> Vec<serde_json::Value> {
+        static FORM: LazyLock<Selector> =
+            LazyLock::new(|| Selector::parse("form").expect("form selector"));
+        let base = crate::links::base(&self.html, base_url);
+        self.html
+            .select(&FORM)
+            .map(|form| crate::forms::form(form, base.as_ref()))
+            .collect()
+    }
+
+    /// The page's breadcrumb trail as `[{"name", "url"}]`
+    ///
+    /// Read from a JSON-LD or microdata `BreadcrumbList` if there is one,
+    /// otherwise from breadcrumb navigation markup (`.breadcrumb li`,
+    /// `nav[aria-label=breadcrumb] a`). URLs are resolved against the
+    /// document's `<base href>` and `base_url`. Available with the `url`
+    /// feature (on by default).
+    #[cfg(feature = "url")]
+    pub fn breadcrumbs(&self, base_url: Option<&str>) -> Vec<serde_json::Value> {
+        let mut lists = self.json_ld(Some("BreadcrumbList"));
+        lists.extend(self.microdata(Some("BreadcrumbList")));
+        let base = crate::links::base(&self.html, base_url);
+        crate::breadcrumbs::breadcrumbs(&self.html, &lists, base.as_ref())
+    }
+
+    /// The document's `h1`–`h6` headings nested into a tree
+    ///
+    /// Each is `{"level", "text", "id", "children"}`, with `children` the
+    /// lower-ranked headings that follow it, for tables of contents.
+    pub fn outline(&self) -> serde_json::Value {
+        crate::outline::outline(&crate::outline::headings(&self.html, None))
+    }
+
+    /// Page metadata: title, description, canonical URL, favicon, and
+    /// OpenGraph and Twitter card properties (see [`presets::metadata`])
+    ///
+    /// [`presets::metadata`]: crate::presets::metadata
+    pub fn metadata(&self) -> Result<serde_json::Value, anyhow::Error> {
+        self.extract(&crate::presets::metadata())
+    }
+
+    /// The page's main content, with its title, byline and publication date
+    ///
+    /// The content block is found with readability-style heuristics (see
+    /// [`article`](crate::article)). It is found once and reused, including by
+    /// `$article` fields of specs run on this DOM.
+    #[cfg(feature = "article")]
+    pub fn article(&self) -> &crate::article::Article {
+        self.article
+            .get_or_init(|| crate::article::extract(&self.html, &self.json_ld(None)))
     }
 
     /// Extract JSON data from this DOM using a spec
     ///
     /// This is the main extraction method that applies the spec to the parsed HTML.
     pub fn extract(&self, spec: &crate::spec::Spec) -> Result<serde_json::Value, anyhow::Error> {
-        match spec {
-            crate::spec::Spec::Object(obj_spec) => self.extract_object(obj_spec, None),
-            crate::spec::Spec::Array(arr_spec) => self.extract_array(arr_spec, None),
+        self.extract_with_context(spec, &Context::default().with_consts(spec.consts()))
+    }
+
+    /// Extract JSON data with resource limits
+    ///
+    /// Fails with a [`LimitExceeded`](crate::LimitExceeded) error as soon as
+    /// the extraction reads more text or visits more nodes than allowed.
+    pub fn extract_with_options(
+        &self,
+        spec: &crate::spec::Spec,
+        options: &crate::ExtractOptions,
+    ) -> Result<serde_json::Value, anyhow::Error> {
+        self.extract_with_context(spec, &Context::new(options).with_consts(spec.consts()))
+    }
+
+    /// Extract JSON data and report where the time went
+    ///
+    /// Returns per-field timings and match counts alongside the result, which
+    /// makes it easy to spot the one selector dominating runtime on a big page.
+    pub fn extract_with_stats(
+        &self,
+        spec: &crate::spec::Spec,
+    ) -> Result<(serde_json::Value, crate::ExtractStats), anyhow::Error> {
+        self.extract_with_stats_and_options(spec, &crate::ExtractOptions::default())
+    }
+
+    /// [`extract_with_stats`](Self::extract_with_stats) with limits and
+    /// other options
+    pub fn extract_with_stats_and_options(
+        &self,
+        spec: &crate::spec::Spec,
+        options: &crate::ExtractOptions,
+    ) -> Result<(serde_json::Value, crate::ExtractStats), anyhow::Error> {
+        let ctx = Context::with_stats(options).with_consts(spec.consts());
+        let start = std::time::Instant::now();
+        let value = self.extract_with_context(spec, &ctx)?;
+        let elapsed = start.elapsed();
+        let stats = ctx.into_stats().unwrap_or_default();
+        Ok((value, stats.finish(elapsed)))
+    }
+
+    fn extract_with_context(
+        &self,
+        spec: &crate::spec::Spec,
+        ctx: &Context,
+    ) -> Result<serde_json::Value, anyhow::Error> {
+        #[cfg(feature = "metrics")]
+        let start = std::time::Instant::now();
+        let result = match spec {
+            crate::spec::Spec::Object(obj_spec) => self.extract_object(obj_spec, None, ctx),
+            crate::spec::Spec::Array(arr_spec) => self.extract_array(arr_spec, None, 0, ctx),
             crate::spec::Spec::Literal(lit) => Ok(self.literal_to_json(lit)),
         }
+        .map(|value| match ctx.provenance() {
+            crate::Provenance::Sidecar => split_provenance(value),
+            _ => value,
+        });
+        #[cfg(feature = "metrics")]
+        crate::metrics::extracted(start.elapsed(), &result);
+        result
     }
 
-    /// Extract an object from the DOM
-    fn extract_object(
+    /// Lazily extract a spec one item at a time
+    ///
+    /// For top-level array specs each matched element is extracted only when
+    /// the iterator is advanced, so large listing pages never materialize the
+    /// full result array. Any other spec yields its whole result as a single item.
+    ///
+    /// Like [`Dom::extract`], this runs without [`ExtractOptions`](crate::ExtractOptions)
+    /// limits; use [`Dom::extract_with_options`] for untrusted specs or pages.
+    pub fn extract_iter<'a>(&'a self, spec: &'a crate::spec::Spec) -> ExtractIter<'a> {
+        let crate::spec::Spec::Array(arr_spec) = spec else {
+            return ExtractIter {
+                dom: self,
+                spec: None,
+                nodes: Vec::new().into_iter(),
+                index: 0,
+                selector_set: None,
+                ctx: Context::default(),
+                pending: Some(self.extract(spec)),
+            };
+        };
+
+        match self.array_item_nodes(arr_spec, None) {
+            Ok(nodes) => ExtractIter {
+                dom: self,
+                spec: Some(arr_spec),
+                nodes: nodes.into_iter(),
+                index: 0,
+                selector_set: FieldSelectorSet::new(&arr_spec.item_spec.fields),
+                ctx: Context::default().with_consts(spec.consts()),
+                pending: None,
+            },
+            Err(e) => ExtractIter {
+                dom: self,
+                spec: None,
+                nodes: Vec::new().into_iter(),
+                index: 0,
+                selector_set: None,
+                ctx: Context::default(),
+                pending: Some(Err(e)),
+            },
+        }
+    }
+
+    /// Extract a spec and serialize the result straight into a writer
+    ///
+    /// Top-level array specs are serialized item by item, so the full result
+    /// array is never held in memory. On error the writer may contain a partial
+    /// document.
+    pub fn extract_to_writer<W: std::io::Write>(
         &self,
-        spec: &crate::spec::ObjectSpec,
-        scope_node: Option<&Node>,
+        spec: &crate::spec::Spec,
+        writer: W,
+    ) -> Result<(), anyhow::Error> {
+        use serde::Serialize;
+        use serde::ser::{SerializeSeq, Serializer};
+
+        let mut serializer = serde_json::Serializer::new(writer);
+        match spec {
+            crate::spec::Spec::Array(_) => {
+                let mut seq = (&mut serializer).serialize_seq(None)?;
+                for item in self.extract_iter(spec) {
+                    seq.serialize_element(&item?)?;
+                }
+                seq.end()?;
+            }
+            _ => self.extract(spec)?.serialize(&mut serializer)?,
+        }
+        Ok(())
+    }
+
+    /// Extract a spec and deserialize the result into `T`
+    ///
+    /// Errors name the path of the field that failed to deserialize.
+    pub fn extract_as<T: serde::de::DeserializeOwned>(
+        &self,
+        spec: &crate::spec::Spec,
+    ) -> Result<T, anyhow::Error> {
+        let value = self.extract(spec)?;
+        serde_path_to_error::deserialize(value).map_err(|e| {
+            anyhow::anyhow!(
+                "Failed to deserialize extraction result at '{}': {}",
+                e.path(),
+                e.inner()
+            )
+        })
+    }
+
+    /// Extract a spec, evaluating top-level collection items in parallel
+    ///
+    /// The matched items are split into one chunk per rayon worker and each
+    /// chunk is extracted against its own clone of the document, since a
+    /// `Dom` is not `Sync`. Every clone copies the whole tree, so this only
+    /// pays off when the items cost much more to extract than the page costs
+    /// to copy: large collections or expensive item specs. On a single
+    /// rayon thread, and for non-array specs, nothing is copied and items are
+    /// extracted sequentially. Item order matches [`Dom::extract`].
+    ///
+    /// Like [`Dom::extract`], this runs without [`ExtractOptions`](crate::ExtractOptions)
+    /// limits; use [`Dom::extract_with_options`] for untrusted specs or pages.
+    #[cfg(feature = "parallel")]
+    pub fn extract_par(
+        &self,
+        spec: &crate::spec::Spec,
     ) -> Result<serde_json::Value, anyhow::Error> {
-        let scope = self.resolve_scope(&spec.scope_selector, scope_node)?;
-        let result = spec
-            .fields
+        let crate::spec::Spec::Array(arr_spec) = spec else {
+            return self.extract(spec);
+        };
+        if rayon::current_num_threads() == 1 {
+            return self.extract(spec);
+        }
+        #[cfg(feature = "metrics")]
+        let start = std::time::Instant::now();
+        let result = self.extract_array_par(arr_spec);
+        #[cfg(feature = "metrics")]
+        crate::metrics::extracted(start.elapsed(), &result);
+        result
+    }
+
+    /// Extract a top-level array spec's items in parallel
+    #[cfg(feature = "parallel")]
+    fn extract_array_par(
+        &self,
+        arr_spec: &crate::spec::ArraySpec,
+    ) -> Result<serde_json::Value, anyhow::Error> {
+        use rayon::prelude::*;
+
+        let node_ids: Vec<NodeId> = self
+            .array_item_nodes(arr_spec, None)?
             .iter()
-            .map(|(key, field): (&String, &crate::spec::Field)| {
-                self.extract_field(&field.spec, scope.as_ref())
-                    .map(|value| (key.clone(), value, field.optional))
+            .map(|node| node.node_id)
+            .collect();
+        if node_ids.is_empty() {
+            return Ok(serde_json::Value::Array(Vec::new()));
+        }
+
+        let ctx = Context::default().with_consts(Some(&arr_spec.item_spec.consts));
+        let workers = rayon::current_num_threads().min(node_ids.len());
+        let chunk_size = node_ids.len().div_ceil(workers);
+        let chunks: Vec<(Dom, usize, &[NodeId])> = node_ids
+            .chunks(chunk_size)
+            .enumerate()
+            .map(|(i, chunk)| (self.clone(), i * chunk_size, chunk))
+            .collect();
+
+        let results = chunks
+            .into_par_iter()
+            .map(|(dom, offset, chunk)| {
+                let fields = &arr_spec.item_spec.fields;
+                let selector_set = FieldSelectorSet::new(fields);
+                chunk
+                    .iter()
+                    .enumerate()
+                    .map(|(i, id)| {
+                        let node = dom.node_by_id(*id);
+                        dom.extract_object_from_fields(
+                            fields,
+                            selector_set.as_ref(),
+                            node.as_ref(),
+                            Some(Item {
+                                spec: arr_spec,
+                                index: offset + i,
+                                depth: 0,
+                            }),
+                            &ctx,
+                        )
+                    })
+                    .collect::<Result<Vec<_>, _>>()
             })
             .collect::<Result<Vec<_>, _>>()?;
 
-        // Filter out null optional fields and recursively clean nested objects
-        let cleaned = Self::filter_optional_fields(result);
+        Ok(serde_json::Value::Array(
+            results.into_iter().flatten().collect(),
+        ))
+    }
+
+    /// Look up an element node by its tree id
+    fn node_by_id(&self, node_id: NodeId) -> Option<Node<'_>> {
+        self.html
+            .tree
+            .get(node_id)
+            .and_then(ElementRef::wrap)
+            .map(|el| node_from_element(el, &self.html))
+    }
 
-        Ok(serde_json::Value::Object(cleaned))
+    /// Extract an object from the DOM
+    fn extract_object(
+        &self,
+        spec: &crate::spec::ObjectSpec,
+        scope_node: Option<&Node>,
+        ctx: &Context,
+    ) -> Result<serde_json::Value, anyhow::Error> {
+        let scope_node = if spec.root { None } else { scope_node };
+        let scope = self.resolve_scope(spec, scope_node)?;
+        if spec.scope_selector.is_some() {
+            ctx.matched(scope.as_slice())?;
+        }
+        self.extract_object_from_fields(&spec.fields, None, scope.as_ref(), None, ctx)
     }
 
-    /// Extract an object from fields (helper to avoid cloning)
+    /// Extract an object from fields borrowed from the spec, with an already
+    /// resolved scope
+    ///
+    /// The output map is built directly; keys are only cloned for fields that
+    /// survive optional-field filtering. Array items pass a prebuilt
+    /// [`FieldSelectorSet`] so their selector fields are matched in one walk,
+    /// and their [`Item`] for `$index` and `$recurse` fields.
     fn extract_object_from_fields(
         &self,
         fields: &HashMap<String, crate::spec::Field>,
+        selector_set: Option<&FieldSelectorSet>,
         scope: Option<&Node>,
+        item: Option<Item>,
+        ctx: &Context,
     ) -> Result<serde_json::Value, anyhow::Error> {
-        let result = fields
-            .iter()
-            .map(|(key, field): (&String, &crate::spec::Field)| {
-                self.extract_field(&field.spec, scope)
-                    .map(|value| (key.clone(), value, field.optional))
-            })
-            .collect::<Result<Vec<_>, _>>()?;
-
-        // Filter out null optional fields and recursively clean nested objects
-        let cleaned = Self::filter_optional_fields(result);
-        Ok(serde_json::Value::Object(cleaned))
-    }
+        // Set members come back in field iteration order
+        let mut prematched = match (selector_set, scope) {
+            (Some(set), Some(base)) => Some(set.match_in(self, base)?.into_iter()),
+            _ => None,
+        };
 
-    /// Filter out null optional fields and recursively clean nested objects
-    ///
-    /// Returns a map with null optional fields removed.
-    /// Nested objects with all null fields are also removed.
-    fn filter_optional_fields(
-        fields: Vec<(String, serde_json::Value, bool)>,
-    ) -> serde_json::Map<String, serde_json::Value> {
         let mut result = serde_json::Map::new();
-
-        for (key, value, optional) in fields {
-            match value {
-                // Null values: include only if not optional
-                serde_json::Value::Null if optional => continue,
-                serde_json::Value::Null => {
-                    result.insert(key, value);
-                }
-                // Objects: recursively clean and insert if non-empty
-                serde_json::Value::Object(_) => {
-                    let cleaned = Self::recursively_clean_object(value);
-                    if !cleaned.is_null() {
-                        result.insert(key, cleaned);
+        for (key, field) in fields {
+            let value = ctx.field(key, &field.spec, || match (&mut prematched, &field.spec) {
+                (Some(matches), crate::spec::FieldSpec::Selector(selector, pipes))
+                    if FieldSelectorSet::member(&field.spec).is_some() =>
+                {
+                    let node = matches.next().flatten();
+                    ctx.matched(node.as_slice())?;
+                    #[cfg(feature = "metrics")]
+                    if node.is_none() {
+                        crate::metrics::selector_miss();
                     }
+                    let id = node.as_ref().map(|n| n.node_id);
+                    let value = Self::apply_pipes_to_node(node, pipes, ctx)?;
+                    Ok(self.with_source(value, selector.as_str(), id, ctx))
                 }
-                // Arrays: recursively clean each item
-                serde_json::Value::Array(arr) => {
-                    let cleaned: Vec<_> = arr
-                        .into_iter()
-                        .map(Self::recursively_clean_object)
-                        .collect();
-                    // Only insert if array has items or is not optional
-                    if !cleaned.is_empty() || !optional {
-                        result.insert(key, serde_json::Value::Array(cleaned));
-                    }
+                (_, crate::spec::FieldSpec::Index) => {
+                    Ok(item.map_or(serde_json::Value::Null, |item| item.index.into()))
                 }
-                // All other values: always include
-                _ => {
-                    result.insert(key, value);
+                (_, crate::spec::FieldSpec::Rank) => {
+                    Ok(item.map_or(serde_json::Value::Null, |item| (item.index + 1).into()))
                 }
+                (_, crate::spec::FieldSpec::Recurse(within, max_depth)) => match item {
+                    Some(item) if item.depth < *max_depth => {
+                        let base = match within {
+                            Some(selector) => self.select_node(selector, scope)?,
+                            None => scope.cloned(),
+                        };
+                        match base {
+                            Some(base) => {
+                                self.extract_array(item.spec, Some(&base), item.depth + 1, ctx)
+                            }
+                            None => Ok(serde_json::Value::Array(Vec::new())),
+                        }
+                    }
+                    _ => Ok(serde_json::Value::Array(Vec::new())),
+                },
+                _ => self.extract_field(&field.spec, scope, ctx),
+            })?;
+            #[cfg(feature = "metrics")]
+            crate::metrics::field(value.is_null());
+            if value.is_null() && !field.optional {
+                tracing::warn!(field = %key, "no match");
+            }
+            if let Some(cleaned) = Self::clean_field_value(value, field.optional) {
+                result.insert(key.clone(), cleaned);
             }
         }
+        Ok(serde_json::Value::Object(result))
+    }
 
-        result
+    /// Filter out null optional fields and recursively clean nested objects
+    ///
+    /// Returns `None` when the field should be dropped from the output.
+    /// Nested objects with all null fields are also removed.
+    fn clean_field_value(value: serde_json::Value, optional: bool) -> Option<serde_json::Value> {
+        match value {
+            // Null values: include only if not optional
+            serde_json::Value::Null if optional => None,
+            serde_json::Value::Null => Some(value),
+            // Objects: recursively clean and insert if non-empty
+            serde_json::Value::Object(_) => {
+                let cleaned = Self::recursively_clean_object(value);
+                (!cleaned.is_null()).then_some(cleaned)
+            }
+            // Arrays: recursively clean each item
+            serde_json::Value::Array(arr) => {
+                let cleaned: Vec<_> = arr
+                    .into_iter()
+                    .map(Self::recursively_clean_object)
+                    .collect();
+                // Only insert if array has items or is not optional
+                (!cleaned.is_empty() || !optional).then_some(serde_json::Value::Array(cleaned))
+            }
+            // All other values: always include
+            _ => Some(value),
+        }
     }
 
     /// Recursively clean an object by removing null values
@@ -285,151 +986,431 @@ impl Dom {
         }
     }
 
-    /// Extract an array from the DOM
+    /// Extract an array from the DOM, `depth` levels of `$recurse` down
     fn extract_array(
         &self,
         spec: &crate::spec::ArraySpec,
         scope: Option<&Node>,
+        depth: usize,
+        ctx: &Context,
     ) -> Result<serde_json::Value, anyhow::Error> {
-        const DIRECT_CHILD_PREFIX: char = '>';
+        let scope = if spec.item_spec.root { None } else { scope };
 
         // Special case: self-selector in array context
         let is_self_ref = spec
             .item_spec
             .scope_selector
             .as_ref()
-            .map(|s: &crate::spec::SelectorRef| s.as_str() == "$")
-            .unwrap_or(false);
+            .is_some_and(|s| s.is_self_ref());
 
         if is_self_ref && let Some(base) = scope {
-            let obj = self.extract_object(&spec.item_spec, Some(base))?;
+            ctx.matched(std::slice::from_ref(base))?;
+            let fields = &spec.item_spec.fields;
+            let item = Item {
+                spec,
+                index: 0,
+                depth,
+            };
+            let obj = self.extract_object_from_fields(fields, None, Some(base), Some(item), ctx)?;
             return Ok(serde_json::Value::Array(vec![obj]));
         }
 
-        // Get the effective selector
-        let selector_str = spec
-            .item_spec
-            .scope_selector
-            .as_ref()
-            .map(|s: &crate::spec::SelectorRef| s.as_str())
-            .unwrap_or("*");
-
-        let effective_selector = selector_str
-            .trim()
-            .strip_prefix(DIRECT_CHILD_PREFIX)
-            .map(|s: &str| s.trim())
-            .unwrap_or(selector_str);
-
-        let nodes = match scope {
-            Some(base) => self.query_selector_all_relative(base, effective_selector)?,
-            None => self.query_selector_all(effective_selector)?,
-        };
-
+        let nodes = self.array_item_nodes(spec, scope)?;
+        ctx.matched(&nodes)?;
         if nodes.is_empty() {
             return Ok(serde_json::Value::Array(Vec::new()));
         }
 
+        let fields = &spec.item_spec.fields;
+        let selector_set = FieldSelectorSet::new(fields);
         let results = nodes
             .iter()
-            .map(|node| self.extract_object_from_fields(&spec.item_spec.fields, Some(node)))
+            .enumerate()
+            .map(|(i, node)| {
+                self.extract_object_from_fields(
+                    fields,
+                    selector_set.as_ref(),
+                    Some(node),
+                    Some(Item {
+                        spec,
+                        index: i,
+                        depth,
+                    }),
+                    ctx,
+                )
+            })
             .collect::<Result<Vec<_>, _>>()?;
 
         Ok(serde_json::Value::Array(results))
     }
 
+    /// Resolve the nodes an array spec iterates over
+    fn array_item_nodes<'a>(
+        &'a self,
+        spec: &crate::spec::ArraySpec,
+        scope: Option<&Node<'a>>,
+    ) -> Result<Vec<Node<'a>>, anyhow::Error> {
+        static UNIVERSAL: LazyLock<Selector> =
+            LazyLock::new(|| Selector::parse("*").expect("universal selector"));
+
+        // Collections without a scope have `$all` set: every element is an item
+        if spec.item_spec.scope_selector.is_none() {
+            return self.all_matches(&UNIVERSAL, None, scope);
+        }
+
+        // The first scope alternative matching any element wins
+        let mut nodes = Vec::new();
+        for selector in spec.item_spec.scopes() {
+            if selector.is_self_ref() && scope.is_none() {
+                return Err(anyhow::anyhow!(
+                    "Invalid selector '$': a collection can only be its own scope inside another element"
+                ));
+            }
+            let css = selector.selector().unwrap_or(&UNIVERSAL);
+            nodes = self.all_matches(css, selector.simple(), scope)?;
+            if !nodes.is_empty() {
+                break;
+            }
+        }
+        Ok(nodes)
+    }
+
+    /// Wrap a value read from element `id` with its source, when provenance is on
+    ///
+    /// Null values stay null so optional fields are still dropped.
+    fn with_source(
+        &self,
+        value: serde_json::Value,
+        selector: &str,
+        id: Option<NodeId>,
+        ctx: &Context,
+    ) -> serde_json::Value {
+        let Some(id) = id else {
+            return value;
+        };
+        if ctx.provenance() == crate::Provenance::Off || value.is_null() {
+            return value;
+        }
+        let index = ctx.position(id, || {
+            self.html
+                .root_element()
+                .descendants()
+                .filter(|n| n.value().is_element())
+                .enumerate()
+                .map(|(i, n)| (n.id(), i))
+                .collect()
+        });
+        serde_json::json!({
+            "value": value,
+            "$src": {"selector": selector, "index": index},
+        })
+    }
+
     /// Extract a single field value
     fn extract_field(
         &self,
         spec: &crate::spec::FieldSpec,
         scope: Option<&Node>,
+        ctx: &Context,
     ) -> Result<serde_json::Value, anyhow::Error> {
         match spec {
             crate::spec::FieldSpec::Literal(lit) => Ok(self.literal_to_json(lit)),
-            crate::spec::FieldSpec::Nested(obj_spec) => self.extract_object(obj_spec, scope),
-            crate::spec::FieldSpec::NestedArray(arr_spec) => self.extract_array(arr_spec, scope),
+            crate::spec::FieldSpec::Nested(obj_spec) => self.extract_object(obj_spec, scope, ctx),
+            crate::spec::FieldSpec::NestedArray(arr_spec) => {
+                self.extract_array(arr_spec, scope, 0, ctx)
+            }
             crate::spec::FieldSpec::Selector(selector_ref, pipes) => {
                 let node = self.select_node(selector_ref, scope)?;
-                Self::apply_pipes_to_node(node, pipes)
+                ctx.matched(node.as_slice())?;
+                #[cfg(feature = "metrics")]
+                if node.is_none() {
+                    crate::metrics::selector_miss();
+                }
+                let id = node.as_ref().map(|n| n.node_id);
+                let value = Self::apply_pipes_to_node(node, pipes, ctx)?;
+                Ok(self.with_source(value, selector_ref.as_str(), id, ctx))
             }
             crate::spec::FieldSpec::FallbackSelector(selectors) => {
-                self.extract_fallback_selector(selectors, scope)
+                self.extract_fallback_selector(selectors, scope, ctx)
+            }
+            crate::spec::FieldSpec::All(selectors) => self.extract_all(selectors, scope, ctx),
+            // Only collection items have a position, see `extract_object_from_fields`
+            crate::spec::FieldSpec::Index | crate::spec::FieldSpec::Rank => {
+                Ok(serde_json::Value::Null)
+            }
+            crate::spec::FieldSpec::Recurse(..) => Ok(serde_json::Value::Array(Vec::new())),
+            crate::spec::FieldSpec::Now(format) => {
+                use crate::spec::NowFormat;
+                let now = ctx.now();
+                Ok(match format {
+                    NowFormat::Iso => now
+                        .to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+                        .into(),
+                    NowFormat::Date => now.format("%Y-%m-%d").to_string().into(),
+                    NowFormat::Unix => now.timestamp().into(),
+                })
+            }
+            crate::spec::FieldSpec::Env(name) => {
+                Ok(std::env::var(name).map_or(serde_json::Value::Null, Into::into))
+            }
+            crate::spec::FieldSpec::Context(key) => Ok(ctx.page().get(key)),
+            crate::spec::FieldSpec::JsonLd(ty) => {
+                // Structured data describes the whole page, so scope is ignored
+                let scripts = self.json_ld_scripts();
+                ctx.matched(&scripts)?;
+                let mut items = Vec::new();
+                for script in &scripts {
+                    ctx.read(script.text_len())?;
+                    items.extend(json_ld_items(script.text(), ty.as_deref()));
+                }
+                Ok(serde_json::Value::Array(items))
+            }
+            crate::spec::FieldSpec::Microdata(ty) => {
+                let scope = scope.map(Node::element_ref).transpose()?;
+                let items = crate::microdata::items(&self.html, scope);
+                let nodes: Vec<Node> = items
+                    .iter()
+                    .map(|el| node_from_element(*el, &self.html))
+                    .collect();
+                ctx.matched(&nodes)?;
+                Ok(serde_json::Value::Array(
+                    items
+                        .into_iter()
+                        .map(|el| crate::microdata::item(&self.html, el))
+                        .filter(|item| ty.as_deref().is_none_or(|ty| has_type(item, ty)))
+                        .collect(),
+                ))
+            }
+            #[cfg(feature = "url")]
+            crate::spec::FieldSpec::Images => {
+                let scope = scope.map(Node::element_ref).transpose()?;
+                let images = crate::images::images(&self.html, scope);
+                let nodes: Vec<Node> = images
+                    .iter()
+                    .map(|el| node_from_element(*el, &self.html))
+                    .collect();
+                ctx.matched(&nodes)?;
+                let base = crate::links::base(&self.html, ctx.base_url());
+                Ok(serde_json::Value::Array(
+                    images
+                        .into_iter()
+                        .map(|el| crate::images::image(el, base.as_ref()))
+                        .collect(),
+                ))
+            }
+            #[cfg(feature = "url")]
+            crate::spec::FieldSpec::Pagination => Ok(self.pagination(ctx.base_url())),
+            #[cfg(feature = "url")]
+            crate::spec::FieldSpec::Form(selector) => {
+                static FORM: LazyLock<Selector> =
+                    LazyLock::new(|| Selector::parse("form").expect("form selector"));
+                let node = match (selector, scope) {
+                    (Some(selector), _) => self.select_node(selector, scope)?,
+                    (None, Some(scope)) if scope.element_ref()?.value().name() == "form" => {
+                        Some(scope.clone())
+                    }
+                    (None, _) => self.first_match(&FORM, None, scope)?,
+                };
+                ctx.matched(node.as_slice())?;
+                let Some(node) = node else {
+                    return Ok(serde_json::Value::Null);
+                };
+                let base = crate::links::base(&self.html, ctx.base_url());
+                Ok(crate::forms::form(node.element_ref()?, base.as_ref()))
+            }
+            #[cfg(feature = "url")]
+            crate::spec::FieldSpec::Breadcrumbs => {
+                // Like structured data, the trail is found in the whole page
+                Ok(serde_json::Value::Array(self.breadcrumbs(ctx.base_url())))
+            }
+            crate::spec::FieldSpec::Outline => {
+                let scope = scope.map(Node::element_ref).transpose()?;
+                let headings = crate::outline::headings(&self.html, scope);
+                let nodes: Vec<Node> = headings
+                    .iter()
+                    .map(|el| node_from_element(*el, &self.html))
+                    .collect();
+                ctx.matched(&nodes)?;
+                Ok(crate::outline::outline(&headings))
+            }
+            #[cfg(feature = "url")]
+            crate::spec::FieldSpec::Links(filter) => {
+                let scope = scope.map(Node::element_ref).transpose()?;
+                let links = crate::links::links(&self.html, scope);
+                let nodes: Vec<Node> = links
+                    .iter()
+                    .map(|el| node_from_element(*el, &self.html))
+                    .collect();
+                ctx.matched(&nodes)?;
+                let base = crate::links::base(&self.html, ctx.base_url());
+                Ok(serde_json::Value::Array(
+                    links
+                        .into_iter()
+                        .filter_map(|el| crate::links::link(el, base.as_ref(), *filter))
+                        .collect(),
+                ))
             }
+            #[cfg(feature = "article")]
+            crate::spec::FieldSpec::Article(field) => {
+                // Like structured data, the article is found in the whole page
+                let article = self.article();
+                ctx.read(article.text.len())?;
+                let article = serde_json::to_value(article)?;
+                Ok(match field {
+                    Some(field) => article.get(field).cloned().unwrap_or_default(),
+                    None => article,
+                })
+            }
+            #[cfg(not(feature = "article"))]
+            crate::spec::FieldSpec::Article(_) => Err(anyhow::anyhow!(
+                "$article needs html2json to be built with the `article` feature"
+            )),
+            #[cfg(not(feature = "url"))]
+            crate::spec::FieldSpec::Images
+            | crate::spec::FieldSpec::Pagination
+            | crate::spec::FieldSpec::Form(_)
+            | crate::spec::FieldSpec::Breadcrumbs
+            | crate::spec::FieldSpec::Links(_) => Err(crate::pipe::url_disabled(
+                spec.to_json().as_str().unwrap_or_default(),
+            )),
         }
     }
 
+    /// The document's `<script type="application/ld+json">` elements
+    fn json_ld_scripts(&self) -> Vec<Node<'_>> {
+        static JSON_LD: LazyLock<Selector> = LazyLock::new(|| {
+            Selector::parse(r#"script[type="application/ld+json" i]"#).expect("JSON-LD selector")
+        });
+        self.html
+            .select(&JSON_LD)
+            .map(|el| node_from_element(el, &self.html))
+            .collect()
+    }
+
     /// Select a node based on a selector and optional scope
-    fn select_node(
-        &self,
+    fn select_node<'a>(
+        &'a self,
         selector: &crate::spec::SelectorRef,
-        scope: Option<&Node>,
-    ) -> Result<Option<Node>, anyhow::Error> {
-        const NEXT_SIBLING_PREFIX: &str = "+ ";
-        const DIRECT_CHILD_PREFIX: char = '>';
-
-        if selector.as_str() == "$" {
-            return Ok(scope.cloned());
+        scope: Option<&Node<'a>>,
+    ) -> Result<Option<Node<'a>>, anyhow::Error> {
+        if selector.next_rows().is_some() {
+            return Ok(self.select_next_rows(selector, scope)?.into_iter().next());
         }
+        let Some(css) = selector.selector() else {
+            // Self-reference ($)
+            return Ok(scope.cloned());
+        };
 
         // Handle next sibling selector
-        if let Some(inner) = selector.as_str().strip_prefix(NEXT_SIBLING_PREFIX) {
+        if selector.is_next_sibling() {
             let Some(base) = scope else {
-                return Err(anyhow::anyhow!("Next sibling selector requires a scope"));
+                return Err(anyhow::anyhow!(
+                    "Next sibling selector '{}' requires a scope",
+                    selector.as_str()
+                ));
             };
-            let inner_sel = Selector::parse(inner)
-                .map_err(|e| anyhow::anyhow!("Invalid selector '{}': {}", inner, e))?;
-            let base_el = base.element_ref()?;
-            for sibling in base_el.next_siblings() {
-                if let Some(sib_el) = ElementRef::wrap(sibling)
-                    && let Some(first_match) = sib_el.select(&inner_sel).next()
-                {
-                    return Ok(Some(node_from_element(first_match, self.html.clone())));
-                }
-            }
-            return Ok(None);
+            return self.next_sibling_match(css, base, selector.sibling_hops());
         }
 
-        // Handle direct child selector
-        if selector.as_str().starts_with(DIRECT_CHILD_PREFIX) {
-            let effective = selector.as_str()[1..].trim();
-            return match scope {
-                Some(base) => self.query_selector_relative(base, effective),
-                None => self.query_selector(effective),
-            };
-        }
+        // Direct child (`>`) and regular selectors
+        self.first_match(css, selector.simple(), scope)
+    }
 
-        // Regular selector
-        let selector_str = selector.as_str();
-        match scope {
-            Some(base) => self.query_selector_relative(base, selector_str),
-            None => self.query_selector(selector_str),
+    /// Every node matching a selector within an optional scope, like
+    /// [`Dom::select_node`] but without stopping at the first
+    fn select_nodes<'a>(
+        &'a self,
+        selector: &crate::spec::SelectorRef,
+        scope: Option<&Node<'a>>,
+    ) -> Result<Vec<Node<'a>>, anyhow::Error> {
+        if selector.next_rows().is_some() {
+            return self.select_next_rows(selector, scope);
         }
+        let Some(css) = selector.selector() else {
+            return Ok(scope.cloned().into_iter().collect());
+        };
+        if selector.is_next_sibling() {
+            let Some(base) = scope else {
+                return Err(anyhow::anyhow!(
+                    "Next sibling selector '{}' requires a scope",
+                    selector.as_str()
+                ));
+            };
+            return Ok(base
+                .element_ref()?
+                .next_siblings()
+                .filter_map(ElementRef::wrap)
+                .skip(selector.sibling_hops().saturating_sub(1))
+                .flat_map(|sibling| sibling.select(css))
+                .map(|el| node_from_element(el, &self.html))
+                .collect());
+        }
+        self.all_matches(css, selector.simple(), scope)
     }
 
-    /// Resolve a scope selector to a Node
-    fn resolve_scope(
-        &self,
-        selector: &Option<crate::spec::SelectorRef>,
-        base: Option<&Node>,
-    ) -> Result<Option<Node>, anyhow::Error> {
-        let Some(selector) = selector else {
-            return Ok(base.cloned());
+    /// Matches of a `$next` selector in the rows after the scope, in document
+    /// order: the rows themselves for a bare `$next`
+    fn select_next_rows<'a>(
+        &'a self,
+        selector: &crate::spec::SelectorRef,
+        scope: Option<&Node<'a>>,
+    ) -> Result<Vec<Node<'a>>, anyhow::Error> {
+        let (Some(base), Some(rows @ 1..)) = (scope, selector.next_rows()) else {
+            return Err(anyhow::anyhow!(
+                "'{}' needs a collection item with \"$withNext\"",
+                selector.as_str()
+            ));
         };
+        let rows = base
+            .element_ref()?
+            .next_siblings()
+            .filter_map(ElementRef::wrap)
+            .take(rows);
+        Ok(match selector.selector() {
+            Some(css) => rows
+                .flat_map(|row| row.select(css))
+                .map(|el| node_from_element(el, &self.html))
+                .collect(),
+            None => rows.map(|el| node_from_element(el, &self.html)).collect(),
+        })
+    }
 
-        if selector.as_str() == "$" {
-            Ok(base.cloned())
-        } else if selector.as_str().starts_with('>') {
-            let effective = selector.as_str()[1..].trim();
-            match base {
-                Some(b) => self.query_selector_relative(b, effective),
-                None => self.query_selector(effective),
+    /// First match of a compiled selector among the siblings after `node`,
+    /// starting at the `hops`-th following element
+    fn next_sibling_match<'a>(
+        &'a self,
+        selector: &Selector,
+        node: &Node<'a>,
+        hops: usize,
+    ) -> Result<Option<Node<'a>>, anyhow::Error> {
+        let siblings = node
+            .element_ref()?
+            .next_siblings()
+            .filter_map(ElementRef::wrap)
+            .skip(hops.saturating_sub(1));
+        for sibling in siblings {
+            if let Some(found) = sibling.select(selector).next() {
+                return Ok(Some(node_from_element(found, &self.html)));
             }
-        } else {
-            let selector_str = selector.as_str();
-            match base {
-                Some(b) => self.query_selector_relative(b, selector_str),
-                None => self.query_selector(selector_str),
+        }
+        Ok(None)
+    }
+
+    /// Resolve an object's scope to a Node, trying fallbacks in order
+    fn resolve_scope<'a>(
+        &'a self,
+        spec: &crate::spec::ObjectSpec,
+        base: Option<&Node<'a>>,
+    ) -> Result<Option<Node<'a>>, anyhow::Error> {
+        if spec.scope_selector.is_none() {
+            return Ok(base.cloned());
+        }
+        for selector in spec.scopes() {
+            if let Some(node) = self.select_node(selector, base)? {
+                return Ok(Some(node));
             }
         }
+        Ok(None)
     }
 
     /// Convert a literal value to JSON
@@ -446,8 +1427,9 @@ impl Dom {
     fn apply_pipes_to_node(
         node: Option<Node>,
         pipes: &[crate::spec::PipeCommand],
+        ctx: &Context,
     ) -> Result<serde_json::Value, anyhow::Error> {
-        use crate::pipe::apply_pipe;
+        use crate::pipe::{PipeValue, apply_pipe_value};
         use crate::spec::PipeCommand;
 
         let Some(n) = node else {
@@ -457,27 +1439,125 @@ impl Dom {
         let (source_pipe, transform_pipes) = crate::pipe::split_source_and_transforms(pipes);
 
         let initial_value = match source_pipe {
-            Some(PipeCommand::Attr(attr_name)) => n
-                .attr(attr_name)
-                .map(|s| serde_json::Value::String(s.to_string()))
-                .unwrap_or(serde_json::Value::Null),
-            Some(PipeCommand::Void) => {
-                let text_content = n.text();
-                if text_content.is_empty() && is_void_element_from_html(n.html()) {
-                    get_void_text_from_html(n.html())
-                        .map(serde_json::Value::String)
-                        .unwrap_or(serde_json::Value::String(text_content.to_string()))
-                } else {
-                    serde_json::Value::String(text_content.to_string())
+            Some(PipeCommand::Attr(attr_name)) => match n.attr(attr_name) {
+                Some(value) => {
+                    ctx.read(value.len())?;
+                    PipeValue::borrowed(value)
                 }
+                None => PipeValue::Json(serde_json::Value::Null),
+            },
+            Some(PipeCommand::HasAttr(attr_name)) => {
+                PipeValue::Json(n.attr(attr_name).is_some().into())
+            }
+            Some(pipe @ (PipeCommand::Classes | PipeCommand::HasClass(_))) => apply_pipe_value(
+                PipeValue::borrowed(n.attr("class").unwrap_or_default()),
+                pipe,
+            )?,
+            Some(pipe @ PipeCommand::DateTime) => match n.attr("datetime") {
+                Some(datetime) => apply_pipe_value(PipeValue::borrowed(datetime), pipe)?,
+                None => apply_pipe_value(PipeValue::borrowed(n.text()), pipe)?,
+            },
+            Some(pipe @ PipeCommand::Style(..)) => apply_pipe_value(
+                PipeValue::borrowed(n.attr("style").unwrap_or_default()),
+                pipe,
+            )?,
+            // Node::text() already falls back to a void element's trailing text
+            Some(PipeCommand::Markdown) => {
+                let markdown = crate::markdown::element(n.element_ref()?);
+                ctx.read(markdown.len())?;
+                PipeValue::Text(markdown.into())
+            }
+            Some(PipeCommand::RawText) => {
+                let text = n.raw_text();
+                ctx.read(text.len())?;
+                PipeValue::Text(text.into())
+            }
+            Some(PipeCommand::Lines) => {
+                let lines = n.lines();
+                ctx.read(lines.iter().map(String::len).sum())?;
+                PipeValue::Json(lines.into())
+            }
+            Some(PipeCommand::Void) | None => {
+                // Check the size before the text is materialized into the cache
+                if ctx.limits_output() {
+                    ctx.read(n.text_len())?;
+                }
+                PipeValue::borrowed(n.text())
             }
-            None => serde_json::Value::String(n.text().to_string()),
             Some(_) => return Err(anyhow::anyhow!("Non-source pipe in source_pipe position")),
         };
 
         transform_pipes
             .into_iter()
-            .try_fold(initial_value, apply_pipe)
+            .try_fold(initial_value, |value, pipe| {
+                let pipe = Self::resolve_pipe_args(pipe, &n, ctx)?;
+                let pipe = pipe.as_ref();
+                let value = apply_pipe_value(value, pipe)
+                    .inspect_err(|e| tracing::warn!(?pipe, error = %e, "pipe failed"))?;
+                tracing::trace!(?pipe, ?value, "pipe");
+                Ok(value)
+            })
+            .map(PipeValue::into_json)
+    }
+
+    /// Fill in a pipe's `$name` arguments from the constants, and the page's
+    /// URL for a plain `absoluteUrl`
+    fn resolve_pipe_args<'p>(
+        pipe: &'p crate::spec::PipeCommand,
+        #[cfg_attr(not(feature = "url"), allow(unused_variables))] node: &Node,
+        ctx: &Context,
+    ) -> Result<Cow<'p, crate::spec::PipeCommand>, anyhow::Error> {
+        use crate::spec::{PipeArg, PipeCommand};
+
+        let constant = |name: &str| {
+            ctx.constant(name)
+                .map(|value| PipeArg::Text(value.to_string()))
+        };
+        Ok(match pipe {
+            PipeCommand::Prefix(PipeArg::Const(name)) => {
+                Cow::Owned(PipeCommand::Prefix(constant(name)?))
+            }
+            PipeCommand::AbsoluteUrl(Some(PipeArg::Const(name))) => {
+                Cow::Owned(PipeCommand::AbsoluteUrl(Some(constant(name)?)))
+            }
+            #[cfg(feature = "url")]
+            PipeCommand::AbsoluteUrl(None) => {
+                let base = ctx.page_base(|| crate::links::base(node.dom_html, ctx.base_url()));
+                Cow::Owned(PipeCommand::AbsoluteUrl(
+                    base.map(|base| PipeArg::Text(base.to_string())),
+                ))
+            }
+            pipe => Cow::Borrowed(pipe),
+        })
+    }
+
+    /// Extract every match of the first alternative that matches anything,
+    /// dropping null values
+    fn extract_all(
+        &self,
+        selectors: &[(crate::spec::SelectorRef, Vec<crate::spec::PipeCommand>)],
+        scope: Option<&Node>,
+        ctx: &Context,
+    ) -> Result<serde_json::Value, anyhow::Error> {
+        for (selector_ref, pipes) in selectors {
+            let nodes = self.select_nodes(selector_ref, scope)?;
+            if nodes.is_empty() {
+                continue;
+            }
+            ctx.matched(&nodes)?;
+            let mut values = Vec::with_capacity(nodes.len());
+            for node in nodes {
+                let id = node.node_id;
+                let value = Self::apply_pipes_to_node(Some(node), pipes, ctx)?;
+                if !value.is_null() {
+                    values.push(self.with_source(value, selector_ref.as_str(), Some(id), ctx));
+                }
+            }
+            return Ok(serde_json::Value::Array(values));
+        }
+        #[cfg(feature = "metrics")]
+        crate::metrics::selector_miss();
+        Ok(serde_json::Value::Array(Vec::new()))
     }
 
     /// Extract from fallback selectors - tries each in order until one produces a non-null result
@@ -485,16 +1565,21 @@ impl Dom {
         &self,
         selectors: &[(crate::spec::SelectorRef, Vec<crate::spec::PipeCommand>)],
         scope: Option<&Node>,
+        ctx: &Context,
     ) -> Result<serde_json::Value, anyhow::Error> {
         for (selector_ref, pipes) in selectors {
             let node = self.select_node(selector_ref, scope)?;
-            let result = Self::apply_pipes_to_node(node, pipes)?;
+            ctx.matched(node.as_slice())?;
+            let id = node.as_ref().map(|n| n.node_id);
+            let result = Self::apply_pipes_to_node(node, pipes, ctx)?;
 
             // Check if we got a meaningful result (not null, not empty string)
             match &result {
                 serde_json::Value::Null => continue,
                 serde_json::Value::String(s) if s.trim().is_empty() => continue,
-                _ => return Ok(result),
+                _ => {
+                    return Ok(self.with_source(result, selector_ref.as_str(), id, ctx));
+                }
             }
         }
 
@@ -503,7 +1588,254 @@ impl Dom {
     }
 }
 
-fn node_from_element(el: ElementRef, tree: Rc<Html>) -> Node {
+/// The plain selector fields of an item spec, matched together per item
+///
+/// Each selector field would otherwise traverse the item's subtree on its own.
+/// The set compiles the union of the field selectors (`a, b, c`) once per
+/// array, so each item needs a single walk; individual fields are only tested
+/// against elements the union matched. Sibling (`+`) and self (`$`) selectors
+/// are left to [`Dom::select_node`].
+struct FieldSelectorSet<'f> {
+    union: Selector,
+    /// Member selectors, in the iteration order of the spec's fields
+    selectors: Vec<&'f Selector>,
+}
+
+impl<'f> FieldSelectorSet<'f> {
+    /// Build a set for an item spec, or None when it has too few selector fields
+    fn new(fields: &'f HashMap<String, crate::spec::Field>) -> Option<Self> {
+        let members: Vec<_> = fields
+            .values()
+            .filter_map(|field| Self::member(&field.spec))
+            .collect();
+        if members.len() < ONE_PASS_MIN_FIELDS {
+            return None;
+        }
+        let union = members.iter().filter_map(|s| s.css()).collect::<Vec<_>>();
+        Some(Self {
+            union: Selector::parse(&union.join(", ")).ok()?,
+            selectors: members.iter().filter_map(|s| s.selector()).collect(),
+        })
+    }
+
+    /// The selector of a field that belongs in a set
+    fn member(spec: &crate::spec::FieldSpec) -> Option<&crate::spec::SelectorRef> {
+        match spec {
+            crate::spec::FieldSpec::Selector(selector, _)
+                if !selector.is_next_sibling()
+                    && selector.next_rows().is_none()
+                    && selector.selector().is_some() =>
+            {
+                Some(selector)
+            }
+            _ => None,
+        }
+    }
+
+    /// First match within `base` for every member selector, in member order
+    fn match_in<'a>(
+        &self,
+        dom: &'a Dom,
+        base: &Node<'a>,
+    ) -> Result<Vec<Option<Node<'a>>>, anyhow::Error> {
+        let mut matched = vec![None; self.selectors.len()];
+        let mut pending = self.selectors.len();
+
+        let base_el = base.element_ref()?;
+        for el in base_el.select(&self.union) {
+            for (slot, selector) in matched.iter_mut().zip(&self.selectors) {
+                if slot.is_none() && selector.matches_with_scope(&el, Some(base_el)) {
+                    *slot = Some(node_from_element(el, &dom.html));
+                    pending -= 1;
+                }
+            }
+            if pending == 0 {
+                break;
+            }
+        }
+        Ok(matched)
+    }
+}
+
+/// A collection item being extracted: its collection, its position in it,
+/// and how many `$recurse` levels down the collection is
+#[derive(Clone, Copy)]
+struct Item<'s> {
+    spec: &'s crate::spec::ArraySpec,
+    index: usize,
+    depth: usize,
+}
+
+/// Iterator returned by [`Dom::extract_iter`]
+///
+/// Yields one extracted item per matched element of a top-level array spec.
+pub struct ExtractIter<'a> {
+    dom: &'a Dom,
+    spec: Option<&'a crate::spec::ArraySpec>,
+    nodes: std::vec::IntoIter<Node<'a>>,
+    /// Position of the next item, for `$index`
+    index: usize,
+    selector_set: Option<FieldSelectorSet<'a>>,
+    ctx: Context,
+    /// Result produced up front (non-array specs or node resolution errors)
+    pending: Option<Result<serde_json::Value, anyhow::Error>>,
+}
+
+impl Iterator for ExtractIter<'_> {
+    type Item = Result<serde_json::Value, anyhow::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(result) = self.pending.take() {
+            return Some(result);
+        }
+        let spec = self.spec?;
+        let node = self.nodes.next()?;
+        let item = Item {
+            spec,
+            index: self.index,
+            depth: 0,
+        };
+        self.index += 1;
+        Some(self.dom.extract_object_from_fields(
+            &spec.item_spec.fields,
+            self.selector_set.as_ref(),
+            Some(&node),
+            Some(item),
+            &self.ctx,
+        ))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let pending = usize::from(self.pending.is_some());
+        let remaining = if self.spec.is_some() {
+            self.nodes.len()
+        } else {
+            0
+        };
+        (remaining + pending, Some(remaining + pending))
+    }
+}
+
+/// The items of one JSON-LD block, flattened and filtered by `@type`
+fn json_ld_items(text: &str, ty: Option<&str>) -> Vec<serde_json::Value> {
+    fn flatten(value: serde_json::Value, items: &mut Vec<serde_json::Value>) {
+        match value {
+            serde_json::Value::Array(values) => {
+                values.into_iter().for_each(|value| flatten(value, items))
+            }
+            serde_json::Value::Object(mut object) if object.contains_key("@graph") => {
+                if let Some(graph) = object.remove("@graph") {
+                    flatten(graph, items);
+                }
+            }
+            value => items.push(value),
+        }
+    }
+
+    let value = match serde_json::from_str(text.trim()) {
+        Ok(value) => value,
+        Err(error) => {
+            tracing::warn!(%error, "invalid JSON-LD");
+            return Vec::new();
+        }
+    };
+    let mut items = Vec::new();
+    flatten(value, &mut items);
+    if let Some(ty) = ty {
+        items.retain(|item| has_type(item, ty));
+    }
+    items
+}
+
+/// Whether a JSON-LD or microdata item's `@type` is (or includes) `ty`
+fn has_type(item: &serde_json::Value, ty: &str) -> bool {
+    // Types may be written as full IRIs like https://schema.org/Product
+    let is_type = |t: &serde_json::Value| {
+        t.as_str().is_some_and(|t| {
+            t == ty
+                || t.rsplit_once(['/', '#'])
+                    .is_some_and(|(_, name)| name == ty)
+        })
+    };
+    match item.get("@type") {
+        Some(serde_json::Value::Array(types)) => types.iter().any(is_type),
+        Some(t) => is_type(t),
+        None => false,
+    }
+}
+
+/// Move the inline sources of an extraction result into a pointer-keyed map
+///
+/// Returns `{"data": ..., "provenance": {...}}` (see [`Provenance::Sidecar`]).
+///
+/// [`Provenance::Sidecar`]: crate::Provenance::Sidecar
+fn split_provenance(value: serde_json::Value) -> serde_json::Value {
+    fn walk(
+        value: serde_json::Value,
+        pointer: &mut String,
+        sources: &mut serde_json::Map<String, serde_json::Value>,
+    ) -> serde_json::Value {
+        match value {
+            serde_json::Value::Object(mut map)
+                if map.len() == 2 && map.contains_key("value") && map.contains_key("$src") =>
+            {
+                if let Some(src) = map.remove("$src") {
+                    sources.insert(pointer.clone(), src);
+                }
+                map.remove("value").unwrap_or_default()
+            }
+            serde_json::Value::Object(map) => serde_json::Value::Object(
+                map.into_iter()
+                    .map(|(key, value)| {
+                        let len = pointer.len();
+                        pointer.push('/');
+                        pointer.push_str(&key.replace('~', "~0").replace('/', "~1"));
+                        let value = walk(value, pointer, sources);
+                        pointer.truncate(len);
+                        (key, value)
+                    })
+                    .collect(),
+            ),
+            serde_json::Value::Array(items) => serde_json::Value::Array(
+                items
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, value)| {
+                        let len = pointer.len();
+                        pointer.push('/');
+                        pointer.push_str(&i.to_string());
+                        let value = walk(value, pointer, sources);
+                        pointer.truncate(len);
+                        value
+                    })
+                    .collect(),
+            ),
+            value => value,
+        }
+    }
+
+    let mut sources = serde_json::Map::new();
+    let data = walk(value, &mut String::new(), &mut sources);
+    serde_json::json!({"data": data, "provenance": sources})
+}
+
+/// Parse an HTML document, recording metrics when enabled
+fn parse_html(source: &str) -> Html {
+    #[cfg(feature = "metrics")]
+    let start = std::time::Instant::now();
+    let html = Html::parse_fragment(source);
+    #[cfg(feature = "metrics")]
+    crate::metrics::parsed(start.elapsed());
+    html
+}
+
+/// Parse a selector string into a compiled selector
+fn parse_selector(selector_str: &str) -> Result<Selector, anyhow::Error> {
+    Selector::parse(selector_str)
+        .map_err(|e| anyhow::anyhow!("Invalid selector '{}': {}", selector_str, e))
+}
+
+fn node_from_element<'a>(el: ElementRef<'a>, tree: &'a Html) -> Node<'a> {
     let node_id = el.id();
 
     Node {
@@ -515,33 +1847,65 @@ fn node_from_element(el: ElementRef, tree: Rc<Html>) -> Node {
 }
 
 /// Get text content from void element's next sibling (for RSS/XML patterns)
-fn get_void_text_from_tree(el: ElementRef, _tree: &Rc<Html>) -> Option<String> {
-    // For void elements in RSS/XML, text often appears as next sibling
-    let node_id = el.id();
-    let tree = el.tree();
-
-    // Find parent and look for next sibling after current element
-    tree.get(node_id).and_then(|node_ref| {
-        node_ref.parent().and_then(|parent_ref| {
-            tree.get(parent_ref.id()).and_then(|parent_ref| {
-                let mut found_current = false;
-
-                for child_ref in parent_ref.children() {
-                    if found_current {
-                        // Found next sibling - check if it's a text node
-                        if let Some(text) = child_ref.value().as_text() {
-                            return Some(text.trim().to_string());
-                        }
-                        break;
-                    }
-                    if child_ref.id() == node_id {
-                        found_current = true;
-                    }
-                }
-                None
-            })
-        })
-    })
+fn get_void_text_from_tree(el: ElementRef) -> Option<String> {
+    // For void elements in RSS/XML, text often appears as the next sibling
+    el.next_sibling()?
+        .value()
+        .as_text()
+        .map(|text| text.trim().to_string())
+}
+
+/// Append an element's text with a newline for each `<br>` and around block
+/// elements; unless `raw`, the text's own line breaks become spaces
+fn push_text_lines(el: ElementRef, out: &mut String, raw: bool) {
+    for child in el.children() {
+        if let Some(text) = child.value().as_text() {
+            if raw {
+                out.push_str(text);
+            } else {
+                out.extend(text.chars().map(|c| if c == '\n' { ' ' } else { c }));
+            }
+        } else if let Some(child_el) = ElementRef::wrap(child) {
+            let name = child_el.value().name();
+            if name == "br" {
+                out.push('\n');
+                continue;
+            }
+            let block = is_block_element(name);
+            if block && !out.is_empty() && !out.ends_with('\n') {
+                out.push('\n');
+            }
+            push_text_lines(child_el, out, raw);
+            if block && !out.ends_with('\n') {
+                out.push('\n');
+            }
+        }
+    }
+}
+
+/// Check if element name is a block element that starts a new line of text
+fn is_block_element(name: &str) -> bool {
+    matches!(
+        name,
+        "address"
+            | "article"
+            | "blockquote"
+            | "div"
+            | "dd"
+            | "dt"
+            | "figure"
+            | "h1"
+            | "h2"
+            | "h3"
+            | "h4"
+            | "h5"
+            | "h6"
+            | "li"
+            | "p"
+            | "pre"
+            | "section"
+            | "tr"
+    )
 }
 
 /// Check if element name is an HTML void element
@@ -564,33 +1928,3 @@ pub fn is_void_element(name: &str) -> bool {
             | "wbr"
     )
 }
-
-/// Check if HTML represents a void element
-fn is_void_element_from_html(html: &str) -> bool {
-    if let Some(tag_end) = html.find('>') {
-        let tag = &html[1..tag_end.min(html.len())];
-        let tag_name = tag.split_whitespace().next().unwrap_or("");
-        is_void_element(tag_name)
-    } else {
-        false
-    }
-}
-
-/// Extract text content from sibling node's HTML representation
-fn get_void_text_from_html(html: &str) -> Option<String> {
-    // Find the closing of the void element
-    if let Some(close_pos) = html.find('>') {
-        let after_open = &html[close_pos + 1..];
-
-        // Look for text up to the next '<'
-        if let Some(next_tag) = after_open.find('<') {
-            let text = &after_open[..next_tag];
-            let trimmed = text.trim();
-            if !trimmed.is_empty() {
-                return Some(trimmed.to_string());
-            }
-        }
-    }
-
-    None
-}
@@ -0,0 +1,208 @@
+//! RSS and Atom feeds
+//!
+//! Available with the `feed` feature. Feeds are parsed as XML, so elements
+//! that HTML treats as void (like RSS `<link>`) keep their text. RSS 2.0,
+//! RSS 1.0 (RDF) and Atom feeds are all mapped to one [`Feed`] shape.
+//!
+//! # Example
+//!
+//! ```
+//! let xml = r#"<rss version="2.0"><channel><title>News</title>
+//!   <item><title>First</title><link>https://example.com/1</link></item>
+//! </channel></rss>"#;
+//! let feed = html2json::feed::parse(xml)?;
+//! assert_eq!(feed.title.as_deref(), Some("News"));
+//! assert_eq!(feed.items[0].link.as_deref(), Some("https://example.com/1"));
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use anyhow::Result;
+use roxmltree::{Document, Node, ParsingOptions};
+use serde::Serialize;
+
+const RDF_NS: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+
+/// A feed, whatever format it was published in
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Feed {
+    /// `rss`, `rdf` (RSS 1.0) or `atom`
+    pub format: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// The site the feed belongs to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated: Option<String>,
+    pub items: Vec<FeedItem>,
+}
+
+/// An RSS item or Atom entry
+///
+/// Dates are passed through as written: RFC 822 in RSS, RFC 3339 in Atom.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct FeedItem {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link: Option<String>,
+    /// RSS `guid` or Atom `id`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub published: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    /// RSS `description` or Atom `summary`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    /// RSS `content:encoded` or Atom `content`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub categories: Vec<String>,
+    /// URLs of enclosures (RSS) or `rel="enclosure"` links (Atom)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub enclosures: Vec<String>,
+}
+
+/// Parse an RSS or Atom document
+pub fn parse(xml: &str) -> Result<Feed> {
+    let options = ParsingOptions {
+        allow_dtd: true,
+        ..ParsingOptions::default()
+    };
+    let doc = Document::parse_with_options(xml, options)
+        .map_err(|e| anyhow::anyhow!("Invalid feed XML: {}", e))?;
+    let root = doc.root_element();
+    match root.tag_name().name() {
+        "rss" => {
+            let channel = child(root, "channel")
+                .ok_or_else(|| anyhow::anyhow!("RSS feed has no <channel>"))?;
+            Ok(Feed {
+                format: "rss",
+                items: children(channel, "item").map(rss_item).collect(),
+                ..rss_channel(channel)
+            })
+        }
+        "RDF" => {
+            let channel = child(root, "channel");
+            Ok(Feed {
+                format: "rdf",
+                items: children(root, "item").map(rss_item).collect(),
+                ..channel.map(rss_channel).unwrap_or_default()
+            })
+        }
+        "feed" => Ok(Feed {
+            format: "atom",
+            title: text(root, "title"),
+            link: atom_link(root, "alternate"),
+            description: text(root, "subtitle"),
+            updated: text(root, "updated"),
+            items: children(root, "entry").map(atom_entry).collect(),
+        }),
+        other => Err(anyhow::anyhow!(
+            "Not an RSS or Atom feed (root element <{}>)",
+            other
+        )),
+    }
+}
+
+fn rss_channel(channel: Node) -> Feed {
+    Feed {
+        title: text(channel, "title"),
+        link: text(channel, "link"),
+        description: text(channel, "description"),
+        updated: text(channel, "lastBuildDate")
+            .or_else(|| text(channel, "pubDate"))
+            .or_else(|| text(channel, "date")),
+        ..Feed::default()
+    }
+}
+
+fn rss_item(item: Node) -> FeedItem {
+    FeedItem {
+        title: text(item, "title"),
+        link: text(item, "link"),
+        // RSS 1.0 items are identified by `rdf:about`
+        id: text(item, "guid").or_else(|| item.attribute((RDF_NS, "about")).map(String::from)),
+        published: text(item, "pubDate").or_else(|| text(item, "date")),
+        updated: None,
+        author: text(item, "author").or_else(|| text(item, "creator")),
+        summary: text(item, "description"),
+        content: text(item, "encoded"),
+        categories: children(item, "category").filter_map(node_text).collect(),
+        enclosures: children(item, "enclosure")
+            .filter_map(|e| e.attribute("url").map(String::from))
+            .collect(),
+    }
+}
+
+fn atom_entry(entry: Node) -> FeedItem {
+    FeedItem {
+        title: text(entry, "title"),
+        link: atom_link(entry, "alternate"),
+        id: text(entry, "id"),
+        published: text(entry, "published"),
+        updated: text(entry, "updated"),
+        author: child(entry, "author").and_then(|author| text(author, "name")),
+        summary: text(entry, "summary"),
+        content: child(entry, "content").and_then(|content| {
+            // Inline XHTML content is kept as markup
+            if content.attribute("type") == Some("xhtml") {
+                let markup = content.first_element_child()?;
+                let source = content.document().input_text();
+                Some(source[markup.range()].to_string())
+            } else {
+                node_text(content)
+            }
+        }),
+        categories: children(entry, "category")
+            .filter_map(|c| c.attribute("term").map(String::from))
+            .collect(),
+        enclosures: children(entry, "link")
+            .filter(|link| link.attribute("rel") == Some("enclosure"))
+            .filter_map(|link| link.attribute("href").map(String::from))
+            .collect(),
+    }
+}
+
+/// The `href` of an Atom `<link>` with `rel` (a link without `rel` is `alternate`)
+fn atom_link(parent: Node, rel: &str) -> Option<String> {
+    children(parent, "link")
+        .find(|link| link.attribute("rel").unwrap_or("alternate") == rel)
+        .and_then(|link| link.attribute("href").map(String::from))
+}
+
+/// Child elements by local name, ignoring namespace prefixes
+fn children<'a, 'input>(
+    parent: Node<'a, 'input>,
+    name: &'a str,
+) -> impl Iterator<Item = Node<'a, 'input>> {
+    parent
+        .children()
+        .filter(move |node| node.is_element() && node.tag_name().name() == name)
+}
+
+fn child<'a, 'input>(parent: Node<'a, 'input>, name: &'a str) -> Option<Node<'a, 'input>> {
+    children(parent, name).next()
+}
+
+fn text(parent: Node, name: &str) -> Option<String> {
+    child(parent, name).and_then(node_text)
+}
+
+/// Trimmed text of an element (CDATA included), or `None` if empty
+fn node_text(node: Node) -> Option<String> {
+    let text: String = node
+        .descendants()
+        .filter(|n| n.is_text())
+        .filter_map(|n| n.text())
+        .collect();
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
@@ -0,0 +1,688 @@
+//! HTTP fetching for URL inputs
+//!
+//! Available with the `http` feature. Pages come from a [`Fetcher`]; the
+//! default is [`HttpFetcher`] (reqwest on tokio), and a headless browser,
+//! another cache or a test stub can stand in for it by implementing the
+//! trait. The CLI drives fetching from a single-threaded runtime.
+
+pub(crate) mod robots;
+
+use crate::Spec;
+use robots::Robots;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::sync::{Mutex, OnceCell, Semaphore};
+use tokio::time::Instant;
+
+/// Default limit on the size of a fetched page
+pub const DEFAULT_MAX_BYTES: usize = 100_000_000;
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default lifetime of cached pages
+pub const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(3600);
+
+/// Delay before the first retry; doubled for each further attempt
+const BASE_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Upper bound on any single wait between retries, including `Retry-After`
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Check whether a CLI input looks like an HTTP(S) URL rather than a path
+pub fn is_url(input: &str) -> bool {
+    let lower = input.get(..8).unwrap_or(input).to_ascii_lowercase();
+    lower.starts_with("http://") || lower.starts_with("https://")
+}
+
+/// Resolve a possibly relative `href` against the URL of the page it was
+/// found on, dropping any `#fragment`
+pub fn resolve_url(base: &str, href: &str) -> anyhow::Result<String> {
+    let base =
+        reqwest::Url::parse(base).map_err(|e| anyhow::anyhow!("Invalid URL '{}': {}", base, e))?;
+    let mut url = base
+        .join(href.trim())
+        .map_err(|e| anyhow::anyhow!("Invalid link '{}': {}", href, e))?;
+    url.set_fragment(None);
+    Ok(url.into())
+}
+
+/// A fetched page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FetchedDoc {
+    /// The URL the page was served from, after any redirects
+    pub url: String,
+    /// The decoded body
+    pub body: String,
+}
+
+/// A source of pages, used by the fetch-and-extract APIs
+///
+/// Implementations must be cheap to share across tasks; the futures they
+/// return must be `Send` so pages can be fetched on spawned tasks.
+pub trait Fetcher: Send + Sync {
+    /// Fetch a URL, failing on anything that isn't a usable page
+    fn fetch(&self, url: &str) -> impl Future<Output = Result<FetchedDoc, anyhow::Error>> + Send;
+
+    /// Fetch a URL and extract from the page with `spec`
+    ///
+    /// The page's URL, after redirects, and the fetch time are its
+    /// [`PageContext`](crate::PageContext).
+    fn fetch_and_extract(
+        &self,
+        url: &str,
+        spec: &Spec,
+    ) -> impl Future<Output = Result<serde_json::Value, anyhow::Error>> + Send {
+        async move {
+            let doc = self.fetch(url).await?;
+            let context = crate::PageContext::new()
+                .url(doc.url)
+                .fetched_at(SystemTime::now());
+            crate::extract_with_context(&doc.body, spec, &context)
+        }
+    }
+
+    /// Fetch many URLs and extract from each page, at most `concurrency` at a time
+    ///
+    /// Results are in the order of `urls`, and a page that fails doesn't stop
+    /// the others. Pages are fetched on spawned tasks, so this must run inside
+    /// a tokio runtime.
+    fn fetch_and_extract_all<I>(
+        &self,
+        urls: I,
+        spec: &Spec,
+        concurrency: usize,
+    ) -> impl Future<Output = Vec<Result<serde_json::Value, anyhow::Error>>> + Send
+    where
+        Self: Clone + 'static,
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        let urls: Vec<String> = urls.into_iter().map(Into::into).collect();
+        async move {
+            let permits = Arc::new(Semaphore::new(concurrency.max(1)));
+            let mut tasks = tokio::task::JoinSet::new();
+            for (index, url) in urls.iter().cloned().enumerate() {
+                let (fetcher, spec, permits) = (self.clone(), spec.clone(), permits.clone());
+                tasks.spawn(async move {
+                    let _permit = permits.acquire_owned().await;
+                    (index, fetcher.fetch_and_extract(&url, &spec).await)
+                });
+            }
+            let mut results: Vec<_> = urls.iter().map(|_| None).collect();
+            while let Some(joined) = tasks.join_next().await {
+                let (index, result) =
+                    joined.unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()));
+                results[index] = Some(result);
+            }
+            results.into_iter().flatten().collect()
+        }
+    }
+}
+
+/// The default [`Fetcher`]: an HTTP client with retries, caching and rate limits
+#[derive(Debug, Clone)]
+pub struct HttpFetcher {
+    client: reqwest::Client,
+    max_bytes: usize,
+    retries: u32,
+    cache: Option<Cache>,
+    throttle: Arc<Throttle>,
+}
+
+/// On-disk cache of fetched pages
+#[derive(Debug, Clone)]
+struct Cache {
+    dir: PathBuf,
+    ttl: Duration,
+    refresh: bool,
+    /// Request settings that change the response, mixed into every key
+    variant: String,
+}
+
+/// Builder for a [`HttpFetcher`] with custom request settings
+#[derive(Debug, Clone, Default)]
+pub struct HttpFetcherBuilder {
+    headers: Vec<(String, String)>,
+    cookies: Vec<String>,
+    user_agent: Option<String>,
+    proxy: Option<String>,
+    timeout: Option<Duration>,
+    max_bytes: Option<usize>,
+    retries: u32,
+    cache_dir: Option<PathBuf>,
+    cache_ttl: Option<Duration>,
+    refresh: bool,
+    delay: Duration,
+    max_rps: Option<f64>,
+    max_per_host: Option<usize>,
+    respect_robots: bool,
+}
+
+impl HttpFetcherBuilder {
+    /// Send an extra header with every request
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Send a cookie (`name=value`, or several separated by `;`) with every request
+    pub fn cookie(mut self, cookie: impl Into<String>) -> Self {
+        self.cookies.push(cookie.into());
+        self
+    }
+
+    /// Override the `User-Agent` header
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// Route all requests through a proxy (`http://` or `https://`)
+    ///
+    /// Without an explicit proxy, the `HTTP_PROXY`, `HTTPS_PROXY`, `ALL_PROXY`
+    /// and `NO_PROXY` environment variables are honored.
+    pub fn proxy(mut self, url: impl Into<String>) -> Self {
+        self.proxy = Some(url.into());
+        self
+    }
+
+    /// Set the total request timeout (default 30s)
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Retry transient failures up to `retries` times (default 0)
+    ///
+    /// Timeouts, connection errors, HTTP 429 and 5xx responses are retried
+    /// with exponential backoff and jitter. A `Retry-After` header on the
+    /// response takes precedence over the computed delay.
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Store fetched pages in `dir` and reuse them on later runs
+    ///
+    /// Entries are keyed by URL plus the configured headers, cookies and user
+    /// agent, and expire after the cache TTL. Only successful responses are
+    /// cached.
+    pub fn cache_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.cache_dir = Some(dir.into());
+        self
+    }
+
+    /// How long cached pages stay fresh (default [`DEFAULT_CACHE_TTL`])
+    pub fn cache_ttl(mut self, ttl: Duration) -> Self {
+        self.cache_ttl = Some(ttl);
+        self
+    }
+
+    /// Ignore cached pages but still store fresh responses
+    pub fn refresh(mut self, refresh: bool) -> Self {
+        self.refresh = refresh;
+        self
+    }
+
+    /// Wait at least `delay` between requests to the same host
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Send at most `rps` requests per second across all hosts
+    pub fn max_rps(mut self, rps: f64) -> Self {
+        self.max_rps = Some(rps);
+        self
+    }
+
+    /// Allow at most `n` requests in flight to the same host
+    pub fn max_per_host(mut self, n: usize) -> Self {
+        self.max_per_host = Some(n);
+        self
+    }
+
+    /// Check each host's robots.txt before fetching from it
+    ///
+    /// Disallowed URLs fail with an error, and a `Crawl-delay` longer than
+    /// [`delay`](Self::delay) is honored. A robots.txt that can't be fetched
+    /// allows everything.
+    pub fn respect_robots(mut self, respect: bool) -> Self {
+        self.respect_robots = respect;
+        self
+    }
+
+    /// Limit the size of fetched bodies (default [`DEFAULT_MAX_BYTES`])
+    pub fn max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    /// Build the fetcher, validating headers and cookies
+    pub fn build(self) -> Result<HttpFetcher, anyhow::Error> {
+        use reqwest::header::{COOKIE, HeaderMap, HeaderName, HeaderValue};
+
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers {
+            let header_name = HeaderName::from_bytes(name.trim().as_bytes())
+                .map_err(|_| anyhow::anyhow!("Invalid header name '{}'", name))?;
+            let header_value = HeaderValue::from_str(value.trim())
+                .map_err(|_| anyhow::anyhow!("Invalid value for header '{}'", name))?;
+            headers.append(header_name, header_value);
+        }
+        if !self.cookies.is_empty() {
+            let cookies = self
+                .cookies
+                .iter()
+                .map(|c| c.trim().trim_end_matches(';'))
+                .collect::<Vec<_>>()
+                .join("; ");
+            let value = HeaderValue::from_str(&cookies)
+                .map_err(|_| anyhow::anyhow!("Invalid cookie '{}'", cookies))?;
+            headers.insert(COOKIE, value);
+        }
+
+        let user_agent = self
+            .user_agent
+            .clone()
+            .unwrap_or_else(|| concat!("html2json/", env!("CARGO_PKG_VERSION")).to_string());
+        let min_interval = match self.max_rps {
+            Some(rps) if rps.is_finite() && rps > 0.0 => Some(Duration::from_secs_f64(1.0 / rps)),
+            Some(rps) => return Err(anyhow::anyhow!("Invalid request rate {}", rps)),
+            None => None,
+        };
+        if self.max_per_host == Some(0) {
+            return Err(anyhow::anyhow!("Per-host concurrency must be at least 1"));
+        }
+        let throttle = Throttle {
+            delay: self.delay,
+            min_interval,
+            max_per_host: self.max_per_host,
+            robots: self.respect_robots.then(|| user_agent.clone()),
+            ..Throttle::default()
+        };
+        let cache = self.cache_dir.clone().map(|dir| Cache {
+            dir,
+            ttl: self.cache_ttl.unwrap_or(DEFAULT_CACHE_TTL),
+            refresh: self.refresh,
+            variant: self.cache_variant(&user_agent),
+        });
+        let mut client = reqwest::Client::builder()
+            .user_agent(user_agent)
+            .default_headers(headers)
+            .timeout(self.timeout.unwrap_or(DEFAULT_TIMEOUT));
+        if let Some(proxy) = &self.proxy {
+            let proxy = reqwest::Proxy::all(proxy)
+                .map_err(|e| anyhow::anyhow!("Invalid proxy '{}': {}", proxy, e))?;
+            client = client.proxy(proxy);
+        }
+        let client = client
+            .build()
+            .map_err(|e| anyhow::anyhow!("Failed to create HTTP client: {}", e))?;
+        Ok(HttpFetcher {
+            client,
+            max_bytes: self.max_bytes.unwrap_or(DEFAULT_MAX_BYTES),
+            retries: self.retries,
+            cache,
+            throttle: Arc::new(throttle),
+        })
+    }
+
+    /// Describe the request settings that can change a response
+    fn cache_variant(&self, user_agent: &str) -> String {
+        let mut headers: Vec<_> = self
+            .headers
+            .iter()
+            .map(|(name, value)| format!("{}: {}", name.trim().to_ascii_lowercase(), value.trim()))
+            .collect();
+        headers.sort();
+        format!(
+            "user-agent: {}\n{}\ncookie: {}",
+            user_agent,
+            headers.join("\n"),
+            self.cookies.join("; ")
+        )
+    }
+}
+
+impl HttpFetcher {
+    /// Create a fetcher with default settings
+    pub fn new() -> Result<Self, anyhow::Error> {
+        Self::builder().build()
+    }
+
+    /// Start configuring a fetcher
+    pub fn builder() -> HttpFetcherBuilder {
+        HttpFetcherBuilder::default()
+    }
+
+    /// The file a page would be cached in, if caching is enabled
+    pub fn cache_path(&self, url: &str) -> Option<PathBuf> {
+        self.cache.as_ref().map(|cache| cache.path(url))
+    }
+
+    /// Fetch and parse `origin`'s robots.txt
+    async fn fetch_robots(&self, origin: &str, host: &Host, user_agent: &str) -> Robots {
+        self.throttle.wait(host, self.throttle.delay).await;
+        let url = format!("{}/robots.txt", origin);
+        let response = match self.client.get(&url).send().await {
+            Ok(response) if response.status().is_success() => response,
+            _ => return Robots::allow_all(),
+        };
+        match response.text().await {
+            Ok(text) => Robots::parse(&text, user_agent),
+            Err(_) => Robots::allow_all(),
+        }
+    }
+
+    async fn fetch_with_retries(
+        &self,
+        url: &str,
+        host: &Host,
+        delay: Duration,
+    ) -> Result<FetchedDoc, anyhow::Error> {
+        let mut attempt = 0;
+        loop {
+            self.throttle.wait(host, delay).await;
+            match self.fetch_once(url).await {
+                Ok(doc) => return Ok(doc),
+                Err(Failure::Transient { error, retry_after }) if attempt < self.retries => {
+                    let delay = retry_after.unwrap_or_else(|| backoff(attempt));
+                    tracing::warn!(url, error = %error, retry_in = ?delay.min(MAX_BACKOFF), "retrying");
+                    tokio::time::sleep(delay.min(MAX_BACKOFF)).await;
+                    attempt += 1;
+                }
+                Err(Failure::Transient { error, .. }) | Err(Failure::Fatal(error)) => {
+                    return Err(error);
+                }
+            }
+        }
+    }
+
+    async fn fetch_once(&self, url: &str) -> Result<FetchedDoc, Failure> {
+        let mut response = self.client.get(url).send().await.map_err(|e| {
+            let error = anyhow::anyhow!("Failed to fetch '{}': {}", url, e);
+            if e.is_timeout() || e.is_connect() {
+                Failure::Transient {
+                    error,
+                    retry_after: None,
+                }
+            } else {
+                Failure::Fatal(error)
+            }
+        })?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error = anyhow::anyhow!("Failed to fetch '{}': HTTP {}", url, status);
+            if status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error() {
+                return Err(Failure::Transient {
+                    error,
+                    retry_after: retry_after(response.headers()),
+                });
+            }
+            return Err(Failure::Fatal(error));
+        }
+        if response
+            .content_length()
+            .is_some_and(|len| len > self.max_bytes as u64)
+        {
+            return Err(Failure::Fatal(self.too_large(url)));
+        }
+
+        let final_url = response.url().to_string();
+        let encoding = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(charset)
+            .and_then(|label| encoding_rs::Encoding::for_label(label.as_bytes()))
+            .unwrap_or(encoding_rs::UTF_8);
+
+        // Read in chunks so a body without a Content-Length stops at the limit
+        let mut bytes = Vec::new();
+        while let Some(chunk) = response.chunk().await.map_err(|e| {
+            let error = anyhow::anyhow!("Failed to read response from '{}': {}", url, e);
+            if e.is_timeout() {
+                Failure::Transient {
+                    error,
+                    retry_after: None,
+                }
+            } else {
+                Failure::Fatal(error)
+            }
+        })? {
+            if bytes.len() + chunk.len() > self.max_bytes {
+                return Err(Failure::Fatal(self.too_large(url)));
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+        let (body, _, _) = encoding.decode(&bytes);
+        Ok(FetchedDoc {
+            url: final_url,
+            body: body.into_owned(),
+        })
+    }
+
+    fn too_large(&self, url: &str) -> anyhow::Error {
+        anyhow::anyhow!(
+            "Response from '{}' exceeds maximum size of {} bytes",
+            url,
+            self.max_bytes
+        )
+    }
+}
+
+impl Fetcher for HttpFetcher {
+    /// Fetch a URL and decode the response body
+    ///
+    /// Redirects are followed, except that a cached page reports the URL it
+    /// was requested as. Non-success status codes and bodies over the size
+    /// limit are errors. Transient failures are retried as configured by
+    /// [`HttpFetcherBuilder::retries`].
+    async fn fetch(&self, url: &str) -> Result<FetchedDoc, anyhow::Error> {
+        if let Some(body) = self.cache.as_ref().and_then(|cache| cache.get(url)) {
+            tracing::debug!(url, "cache hit");
+            return Ok(FetchedDoc {
+                url: url.to_string(),
+                body,
+            });
+        }
+        let parsed = reqwest::Url::parse(url)
+            .map_err(|e| anyhow::anyhow!("Invalid URL '{}': {}", url, e))?;
+        let origin = parsed.origin().ascii_serialization();
+        let host = self.throttle.host(&origin);
+
+        let robots = match &self.throttle.robots {
+            Some(user_agent) => {
+                let robots = host
+                    .robots
+                    .get_or_init(|| self.fetch_robots(&origin, &host, user_agent))
+                    .await;
+                let path = match parsed.query() {
+                    Some(query) => format!("{}?{}", parsed.path(), query),
+                    None => parsed.path().to_string(),
+                };
+                if !robots.allows(&path) {
+                    return Err(anyhow::anyhow!(
+                        "Fetching '{}' is disallowed by robots.txt",
+                        url
+                    ));
+                }
+                Some(robots)
+            }
+            None => None,
+        };
+        let delay = robots
+            .and_then(|robots| robots.crawl_delay)
+            .map_or(self.throttle.delay, |crawl_delay| {
+                crawl_delay.max(self.throttle.delay)
+            });
+
+        let _permit = match &host.permits {
+            Some(permits) => Some(permits.acquire().await?),
+            None => None,
+        };
+        let start = Instant::now();
+        let doc = self.fetch_with_retries(url, &host, delay).await?;
+        tracing::info!(url, bytes = doc.body.len(), elapsed = ?start.elapsed(), "fetched");
+        if let Some(cache) = &self.cache {
+            cache.put(url, &doc.body)?;
+        }
+        Ok(doc)
+    }
+}
+
+impl Cache {
+    fn path(&self, url: &str) -> PathBuf {
+        let mut hasher = Sha256::new();
+        hasher.update(url.as_bytes());
+        hasher.update(b"\n");
+        hasher.update(self.variant.as_bytes());
+        let key: String = hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        self.dir.join(format!("{}.html", key))
+    }
+
+    /// A fresh cached body for `url`; unreadable entries count as misses
+    fn get(&self, url: &str) -> Option<String> {
+        if self.refresh {
+            return None;
+        }
+        let path = self.path(url);
+        let modified = std::fs::metadata(&path).ok()?.modified().ok()?;
+        let age = SystemTime::now()
+            .duration_since(modified)
+            .unwrap_or_default();
+        if age > self.ttl {
+            return None;
+        }
+        std::fs::read_to_string(path).ok()
+    }
+
+    fn put(&self, url: &str, body: &str) -> Result<(), anyhow::Error> {
+        std::fs::create_dir_all(&self.dir).map_err(|e| {
+            anyhow::anyhow!(
+                "Failed to create cache directory '{}': {}",
+                self.dir.display(),
+                e
+            )
+        })?;
+        // Write then rename so concurrent runs never see a partial entry
+        let path = self.path(url);
+        let tmp = path.with_extension(format!("{}.tmp", std::process::id()));
+        std::fs::write(&tmp, body)
+            .and_then(|()| std::fs::rename(&tmp, &path))
+            .map_err(|e| anyhow::anyhow!("Failed to write cache entry '{}': {}", path.display(), e))
+    }
+}
+
+/// Rate limits and robots.txt rules shared by clones of a [`HttpFetcher`]
+#[derive(Debug, Default)]
+struct Throttle {
+    /// Minimum time between requests to one host
+    delay: Duration,
+    /// Minimum time between any two requests, from `max_rps`
+    min_interval: Option<Duration>,
+    max_per_host: Option<usize>,
+    /// User agent to match robots.txt groups against, when checking robots.txt
+    robots: Option<String>,
+    next_request: Mutex<Option<Instant>>,
+    hosts: std::sync::Mutex<HashMap<String, Arc<Host>>>,
+}
+
+/// Per-origin throttling state
+#[derive(Debug)]
+struct Host {
+    permits: Option<Semaphore>,
+    next_request: Mutex<Option<Instant>>,
+    robots: OnceCell<Robots>,
+}
+
+impl Throttle {
+    fn host(&self, origin: &str) -> Arc<Host> {
+        let mut hosts = self.hosts.lock().unwrap_or_else(|e| e.into_inner());
+        hosts
+            .entry(origin.to_string())
+            .or_insert_with(|| {
+                Arc::new(Host {
+                    permits: self.max_per_host.map(Semaphore::new),
+                    next_request: Mutex::new(None),
+                    robots: OnceCell::new(),
+                })
+            })
+            .clone()
+    }
+
+    /// Wait for both the global and the per-host request slots
+    async fn wait(&self, host: &Host, delay: Duration) {
+        if let Some(interval) = self.min_interval {
+            wait_turn(&self.next_request, interval).await;
+        }
+        if !delay.is_zero() {
+            wait_turn(&host.next_request, delay).await;
+        }
+    }
+}
+
+/// Sleep until the slot's next request time, then book the following one
+///
+/// The lock is held while sleeping, so waiters are released one per interval.
+async fn wait_turn(slot: &Mutex<Option<Instant>>, interval: Duration) {
+    let mut next = slot.lock().await;
+    if let Some(at) = *next {
+        tokio::time::sleep_until(at).await;
+    }
+    *next = Some(Instant::now() + interval);
+}
+
+/// Why a single fetch attempt failed
+enum Failure {
+    /// Worth retrying, optionally after a server-requested delay
+    Transient {
+        error: anyhow::Error,
+        retry_after: Option<Duration>,
+    },
+    Fatal(anyhow::Error),
+}
+
+/// Exponential backoff for retry number `attempt`, jittered to 50-100%
+pub(crate) fn backoff(attempt: u32) -> Duration {
+    let ceiling = BASE_BACKOFF
+        .saturating_mul(1 << attempt.min(16))
+        .min(MAX_BACKOFF);
+    ceiling.mul_f64(0.5 + fastrand::f64() / 2.0)
+}
+
+/// Parse a `Retry-After` header given in seconds or as an HTTP date
+pub(crate) fn retry_after(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    let value = headers
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = httpdate::parse_http_date(value).ok()?;
+    Some(
+        date.duration_since(std::time::SystemTime::now())
+            .unwrap_or_default(),
+    )
+}
+
+/// The `charset` parameter of a `Content-Type` header
+fn charset(content_type: &str) -> Option<&str> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        name.trim()
+            .eq_ignore_ascii_case("charset")
+            .then(|| value.trim().trim_matches('"'))
+    })
+}
@@ -0,0 +1,140 @@
+//! Minimal robots.txt support
+//!
+//! Implements the parts of RFC 9309 that matter for a scraper: user-agent
+//! groups, `Allow`/`Disallow` with `*` and `$` wildcards (longest match wins,
+//! ties go to `Allow`), plus the non-standard `Crawl-delay`.
+
+use std::time::Duration;
+
+/// Rules from one robots.txt that apply to our user agent
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct Robots {
+    rules: Vec<Rule>,
+    pub(crate) crawl_delay: Option<Duration>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Rule {
+    allow: bool,
+    pattern: String,
+}
+
+impl Robots {
+    /// Rules allowing everything, used when robots.txt is missing
+    pub(crate) fn allow_all() -> Self {
+        Self::default()
+    }
+
+    /// Parse robots.txt, keeping the group for `user_agent` or else `*`
+    pub(crate) fn parse(content: &str, user_agent: &str) -> Self {
+        let product = user_agent
+            .split('/')
+            .next()
+            .unwrap_or(user_agent)
+            .trim()
+            .to_ascii_lowercase();
+
+        let mut specific = None;
+        let mut wildcard = None;
+        // Agents of the group being read, and whether its rules have started
+        let mut agents: Vec<String> = Vec::new();
+        let mut in_rules = false;
+        let mut group = Robots::default();
+
+        let mut finish = |agents: &[String], group: Robots| {
+            // Both sides are lowercased, so this is a case-insensitive match
+            if agents.contains(&product) {
+                specific.get_or_insert(group);
+            } else if agents.iter().any(|a| a == "*") {
+                wildcard.get_or_insert(group);
+            }
+        };
+
+        for line in content.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            match key.trim().to_ascii_lowercase().as_str() {
+                "user-agent" => {
+                    if in_rules {
+                        finish(&agents, std::mem::take(&mut group));
+                        agents.clear();
+                        in_rules = false;
+                    }
+                    agents.push(value.to_ascii_lowercase());
+                }
+                "allow" | "disallow" if !agents.is_empty() => {
+                    in_rules = true;
+                    // An empty Disallow allows everything
+                    if !value.is_empty() {
+                        group.rules.push(Rule {
+                            allow: key.trim().eq_ignore_ascii_case("allow"),
+                            pattern: value.to_string(),
+                        });
+                    }
+                }
+                "crawl-delay" if !agents.is_empty() => {
+                    in_rules = true;
+                    group.crawl_delay = value
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|secs| secs.is_finite() && *secs >= 0.0)
+                        .map(Duration::from_secs_f64);
+                }
+                _ => {}
+            }
+        }
+        if !agents.is_empty() {
+            finish(&agents, group);
+        }
+
+        specific.or(wildcard).unwrap_or_default()
+    }
+
+    /// Whether `path` (path plus query) may be fetched
+    pub(crate) fn allows(&self, path: &str) -> bool {
+        let mut best: Option<&Rule> = None;
+        for rule in &self.rules {
+            if !matches(&rule.pattern, path) {
+                continue;
+            }
+            best = match best {
+                Some(b)
+                    if b.pattern.len() > rule.pattern.len()
+                        || (b.pattern.len() == rule.pattern.len() && b.allow) =>
+                {
+                    Some(b)
+                }
+                _ => Some(rule),
+            };
+        }
+        best.is_none_or(|rule| rule.allow)
+    }
+}
+
+/// Match a robots.txt path pattern: a prefix match with `*` wildcards and an
+/// optional `$` end anchor
+fn matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(p) => (p, true),
+        None => (pattern, false),
+    };
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    let Some(mut rest) = path.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    for (i, part) in parts.iter().enumerate() {
+        if anchored && i == parts.len() - 1 {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(pos) => rest = &rest[pos + part.len()..],
+            None => return false,
+        }
+    }
+    !anchored || rest.is_empty()
+}
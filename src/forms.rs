@@ -0,0 +1,123 @@
+//! HTML forms as JSON, for scripting submissions
+//!
+//! A form becomes `{"action", "method", "enctype", "fields"}`, where each
+//! named control is `{"name", "type", "value"}`, plus `"checked"` for
+//! checkboxes and radio buttons and `"options"` for selects. `action` is
+//! resolved against the page's base URL and defaults to the page itself.
+
+use scraper::{ElementRef, Selector};
+use serde_json::{Map, Value, json};
+use std::sync::LazyLock;
+use url::Url;
+
+static CONTROLS: LazyLock<Selector> = LazyLock::new(|| {
+    Selector::parse("input[name], select[name], textarea[name], button[name]")
+        .expect("form control selector")
+});
+static OPTIONS: LazyLock<Selector> =
+    LazyLock::new(|| Selector::parse("option").expect("option selector"));
+
+/// A form element as a JSON object
+pub(crate) fn form(form: ElementRef, base: Option<&Url>) -> Value {
+    let attr = |name: &str| form.value().attr(name).map(str::trim);
+    let action = match (attr("action").filter(|a| !a.is_empty()), base) {
+        (Some(action), Some(base)) => base.join(action).map_or(action.to_string(), String::from),
+        (Some(action), None) => action.to_string(),
+        (None, Some(base)) => base.to_string(),
+        (None, None) => String::new(),
+    };
+    let method = attr("method")
+        .map(str::to_ascii_lowercase)
+        .filter(|method| matches!(method.as_str(), "get" | "post" | "dialog"))
+        .unwrap_or_else(|| "get".to_string());
+    let enctype = attr("enctype").unwrap_or("application/x-www-form-urlencoded");
+    let fields: Vec<Value> = form.select(&CONTROLS).map(control).collect();
+    json!({
+        "action": action,
+        "method": method,
+        "enctype": enctype,
+        "fields": fields,
+    })
+}
+
+fn control(element: ElementRef) -> Value {
+    let el = element.value();
+    let mut field = Map::new();
+    field.insert("name".into(), el.attr("name").unwrap_or_default().into());
+    match el.name() {
+        "select" => {
+            let multiple = el.attr("multiple").is_some();
+            let kind = if multiple {
+                "select-multiple"
+            } else {
+                "select-one"
+            };
+            field.insert("type".into(), kind.into());
+            let options: Vec<(String, String, bool)> = element
+                .select(&OPTIONS)
+                .map(|option| {
+                    let text = text(option);
+                    let value = option
+                        .value()
+                        .attr("value")
+                        .map_or(text.clone(), String::from);
+                    (value, text, option.value().attr("selected").is_some())
+                })
+                .collect();
+            let selected: Vec<&str> = options
+                .iter()
+                .filter(|(_, _, selected)| *selected)
+                .map(|(value, _, _)| value.as_str())
+                .collect();
+            let value = if multiple {
+                json!(selected)
+            } else {
+                // A single select shows its first option until one is selected
+                selected
+                    .last()
+                    .copied()
+                    .or_else(|| options.first().map(|(value, _, _)| value.as_str()))
+                    .map_or(Value::Null, Value::from)
+            };
+            field.insert("value".into(), value);
+            let options = options
+                .iter()
+                .map(|(value, text, selected)| {
+                    json!({"value": value, "text": text, "selected": selected})
+                })
+                .collect();
+            field.insert("options".into(), Value::Array(options));
+        }
+        "textarea" => {
+            field.insert("type".into(), "textarea".into());
+            let value: String = element.text().collect();
+            field.insert("value".into(), value.into());
+        }
+        name => {
+            let default = if name == "button" { "submit" } else { "text" };
+            let kind = el
+                .attr("type")
+                .map_or(default.to_string(), str::to_ascii_lowercase);
+            let checkable = matches!(kind.as_str(), "checkbox" | "radio");
+            let value = match el.attr("value") {
+                Some(value) => value,
+                None if checkable => "on",
+                None => "",
+            };
+            field.insert("type".into(), kind.into());
+            field.insert("value".into(), value.into());
+            if checkable {
+                field.insert("checked".into(), el.attr("checked").is_some().into());
+            }
+        }
+    }
+    Value::Object(field)
+}
+
+fn text(element: ElementRef) -> String {
+    element
+        .text()
+        .flat_map(str::split_whitespace)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
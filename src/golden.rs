@@ -0,0 +1,158 @@
+//! Golden tests for spec repositories
+//!
+//! A case is a `NAME.html` page with a `NAME.spec.json` spec next to it; the
+//! spec's output must equal `NAME.expected.json`. `--update` rewrites the
+//! expected files from the current output instead of comparing.
+
+use crate::{BOLD, GREEN, RED, RESET};
+use anyhow::Result;
+use html2json::{Dom, Spec};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+/// One page/spec/expected triple
+struct Case {
+    name: String,
+    html: PathBuf,
+    spec: PathBuf,
+    expected: PathBuf,
+}
+
+enum Outcome {
+    Pass,
+    Updated,
+    /// Output differs; holds the expected and actual JSON
+    Differs(String, String),
+    Missing,
+    Error(anyhow::Error),
+}
+
+/// Run every case in `dir` and print a summary; returns whether all passed
+pub fn run(dir: &Path, update: bool) -> Result<bool> {
+    let cases = discover(dir)?;
+    if cases.is_empty() {
+        return Err(anyhow::anyhow!(
+            "No test cases in '{}' (expected NAME.html with NAME.spec.json)",
+            dir.display()
+        ));
+    }
+
+    let (mut passed, mut updated, mut failed) = (0, 0, 0);
+    for case in &cases {
+        match run_case(case, update) {
+            Outcome::Pass => {
+                passed += 1;
+                println!("{}✓{} {}", GREEN, RESET, case.name);
+            }
+            Outcome::Updated => {
+                updated += 1;
+                println!("{}↻{} {} (updated)", GREEN, RESET, case.name);
+            }
+            Outcome::Differs(expected, actual) => {
+                failed += 1;
+                println!("{}✗{} {}", RED, RESET, case.name);
+                crate::print_diff(&expected, &actual);
+            }
+            Outcome::Missing => {
+                failed += 1;
+                println!(
+                    "{}✗{} {}: no {} (run with --update to create it)",
+                    RED,
+                    RESET,
+                    case.name,
+                    case.expected.display()
+                );
+            }
+            Outcome::Error(e) => {
+                failed += 1;
+                println!("{}✗{} {}: {:#}", RED, RESET, case.name, e);
+            }
+        }
+    }
+
+    let mut summary = format!("{} passed, {} failed", passed, failed);
+    if updated > 0 {
+        summary.push_str(&format!(", {} updated", updated));
+    }
+    println!("\n{}{}{}", BOLD, summary, RESET);
+    Ok(failed == 0)
+}
+
+/// Cases in `dir`, sorted by name; pages without a spec are skipped
+fn discover(dir: &Path) -> Result<Vec<Case>> {
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| anyhow::anyhow!("Failed to read '{}': {}", dir.display(), e))?;
+    let mut cases = Vec::new();
+    for entry in entries {
+        let html = entry?.path();
+        if html.extension().is_none_or(|ext| ext != "html") {
+            continue;
+        }
+        let Some(name) = html.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        let spec = dir.join(format!("{}.spec.json", name));
+        if !spec.is_file() {
+            continue;
+        }
+        cases.push(Case {
+            name: name.to_string(),
+            expected: dir.join(format!("{}.expected.json", name)),
+            html,
+            spec,
+        });
+    }
+    cases.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(cases)
+}
+
+fn run_case(case: &Case, update: bool) -> Outcome {
+    let result = match extract(case) {
+        Ok(result) => result,
+        Err(e) => return Outcome::Error(e),
+    };
+    let actual = match serde_json::to_string_pretty(&result) {
+        Ok(json) => json,
+        Err(e) => return Outcome::Error(e.into()),
+    };
+
+    let expected = match std::fs::read_to_string(&case.expected) {
+        Ok(content) => match serde_json::from_str::<Value>(&content) {
+            Ok(expected) => Some(expected),
+            // A corrupt expected file is replaced by --update
+            Err(_) if update => None,
+            Err(e) => {
+                return Outcome::Error(anyhow::anyhow!(
+                    "Failed to parse '{}': {}",
+                    case.expected.display(),
+                    e
+                ));
+            }
+        },
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Outcome::Error(e.into()),
+    };
+
+    match expected {
+        Some(expected) if expected == result => Outcome::Pass,
+        _ if update => match std::fs::write(&case.expected, actual + "\n") {
+            Ok(()) => Outcome::Updated,
+            Err(e) => Outcome::Error(anyhow::anyhow!(
+                "Failed to write '{}': {}",
+                case.expected.display(),
+                e
+            )),
+        },
+        Some(expected) => match serde_json::to_string_pretty(&expected) {
+            Ok(expected) => Outcome::Differs(expected, actual),
+            Err(e) => Outcome::Error(e.into()),
+        },
+        None => Outcome::Missing,
+    }
+}
+
+fn extract(case: &Case) -> Result<Value> {
+    let spec = Spec::from_json(&crate::load_spec(&case.spec.to_string_lossy())?)?;
+    let html = crate::read_file(&case.html.to_string_lossy())?;
+    Dom::parse(&html)?.extract(&spec)
+}
@@ -0,0 +1,72 @@
+//! Images (`<img>`, `<source>`) as JSON objects
+//!
+//! Each image becomes `{"src", "alt", "width", "height", "srcset"}`, leaving
+//! out missing attributes. URLs are resolved against the document's
+//! `<base href>` and [`ExtractOptions::base_url`](crate::ExtractOptions::base_url);
+//! without either they are kept as written.
+
+use scraper::{ElementRef, Html, Selector};
+use serde_json::{Map, Value, json};
+use std::sync::LazyLock;
+use url::Url;
+
+static IMAGES: LazyLock<Selector> =
+    LazyLock::new(|| Selector::parse("img, source").expect("image selector"));
+
+/// The images at or inside `scope` (the whole document if `None`)
+pub(crate) fn images<'a>(html: &'a Html, scope: Option<ElementRef<'a>>) -> Vec<ElementRef<'a>> {
+    match scope {
+        Some(scope) if matches!(scope.value().name(), "img" | "source") => vec![scope],
+        Some(scope) => scope.select(&IMAGES).collect(),
+        None => html.select(&IMAGES).collect(),
+    }
+}
+
+/// An image element as a JSON object
+///
+/// `src` falls back to the lazy-loading `data-src`, and `srcset` (or
+/// `data-srcset`) becomes a list of `{"url", "descriptor"}` candidates.
+pub(crate) fn image(element: ElementRef, base: Option<&Url>) -> Value {
+    let attr = |name: &str| {
+        element
+            .value()
+            .attr(name)
+            .or_else(|| element.value().attr(&format!("data-{}", name)))
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+    };
+    let resolve = |href: &str| match base.and_then(|base| base.join(href).ok()) {
+        Some(url) => url.to_string(),
+        None => href.to_string(),
+    };
+    let dimension =
+        |name: &str| attr(name).and_then(|value| value.trim_end_matches("px").parse::<u64>().ok());
+
+    let mut image = Map::new();
+    if let Some(src) = attr("src") {
+        image.insert("src".into(), resolve(src).into());
+    }
+    if let Some(alt) = element.value().attr("alt") {
+        image.insert("alt".into(), alt.into());
+    }
+    for name in ["width", "height"] {
+        if let Some(size) = dimension(name) {
+            image.insert(name.into(), size.into());
+        }
+    }
+    if let Some(srcset) = attr("srcset") {
+        let candidates = srcset
+            .split(',')
+            .filter_map(|candidate| {
+                let mut parts = candidate.split_whitespace();
+                let url = parts.next()?;
+                Some(match parts.next() {
+                    Some(descriptor) => json!({"url": resolve(url), "descriptor": descriptor}),
+                    None => json!({"url": resolve(url)}),
+                })
+            })
+            .collect::<Vec<_>>();
+        image.insert("srcset".into(), candidates.into());
+    }
+    Value::Object(image)
+}
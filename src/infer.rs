@@ -0,0 +1,334 @@
+//! Draft spec inference from sample HTML
+//!
+//! Heuristics only: repeated sibling elements become collections, headings
+//! become titles, links and images become `attr:` fields, and classed leaf
+//! elements become fields named after their class. The result is meant as a
+//! starting point to edit by hand.
+
+use scraper::{ElementRef, Html, Selector};
+use serde_json::{Map, Value};
+use std::collections::{HashMap, HashSet};
+
+/// Fewest repeated siblings that count as a collection
+const MIN_ITEMS: usize = 3;
+/// Most collections proposed per page
+const MAX_COLLECTIONS: usize = 3;
+/// Most fields proposed per collection item
+const MAX_FIELDS: usize = 10;
+/// Items inspected when choosing an item's fields
+const SAMPLE_ITEMS: usize = 5;
+
+/// Elements that never hold extractable content
+const SKIPPED: &[&str] = &[
+    "script", "style", "noscript", "template", "head", "meta", "link", "br", "hr", "option",
+];
+
+/// Propose a spec for `html`
+pub fn infer(html: &str) -> Value {
+    let doc = Html::parse_document(html);
+    let mut spec = Map::new();
+
+    if first(&doc, "title").is_some_and(|el| !text(&el).is_empty()) {
+        spec.insert("page_title".into(), "title | trim".into());
+    }
+    if first(&doc, "h1").is_some_and(|el| !text(&el).is_empty()) {
+        spec.insert("heading".into(), "h1 | trim".into());
+    }
+
+    for group in collections(&doc) {
+        let mut item = Map::new();
+        item.insert("$".into(), Value::String(group.selector.clone()));
+        for (name, expr) in item_fields(&group.items) {
+            item.insert(name, Value::String(expr));
+        }
+        let name = unique_name(&spec, &group.name);
+        spec.insert(name, Value::Array(vec![Value::Object(item)]));
+    }
+
+    Value::Object(spec)
+}
+
+/// A run of similar sibling elements
+struct Group<'a> {
+    items: Vec<ElementRef<'a>>,
+    selector: String,
+    name: String,
+    score: usize,
+}
+
+/// The highest scoring groups of repeated siblings, outermost first
+fn collections(doc: &Html) -> Vec<Group<'_>> {
+    let mut groups = Vec::new();
+    for parent in doc
+        .root_element()
+        .descendants()
+        .filter_map(ElementRef::wrap)
+    {
+        let mut by_signature: HashMap<(String, Vec<String>), Vec<ElementRef>> = HashMap::new();
+        for child in parent.children().filter_map(ElementRef::wrap) {
+            let name = child.value().name();
+            if SKIPPED.contains(&name) {
+                continue;
+            }
+            by_signature
+                .entry((name.to_string(), classes(&child)))
+                .or_default()
+                .push(child);
+        }
+        for ((tag, classes), items) in by_signature {
+            if items.len() < MIN_ITEMS {
+                continue;
+            }
+            // Descendant shapes every item shares: high for list entries,
+            // low for layout rows that merely have the same tag
+            let shapes: Vec<HashSet<String>> = items.iter().map(shapes).collect();
+            let common = shapes[0]
+                .iter()
+                .filter(|shape| shapes[1..].iter().all(|s| s.contains(*shape)))
+                .count();
+            let text_len: usize = items.iter().map(|el| text(el).len()).sum();
+            // Repeated structure with real content, not a row of bare icons
+            if common < 2 || text_len < items.len() * 10 {
+                continue;
+            }
+            let selector = item_selector(doc, &parent, &tag, &classes, items.len());
+            let name = plural(classes.first().map(String::as_str).unwrap_or(&tag));
+            groups.push(Group {
+                score: items.len() * common,
+                items,
+                selector,
+                name,
+            });
+        }
+    }
+
+    groups.sort_by_key(|group| std::cmp::Reverse(group.score));
+    let mut chosen: Vec<Group> = Vec::new();
+    for group in groups {
+        let first = group.items[0];
+        let overlaps = chosen.iter().any(|c| {
+            c.items
+                .iter()
+                .any(|item| is_ancestor(item, &first) || is_ancestor(&first, item))
+        });
+        if !overlaps {
+            chosen.push(group);
+        }
+        if chosen.len() == MAX_COLLECTIONS {
+            break;
+        }
+    }
+    chosen
+}
+
+/// A selector for a group's items, qualified by the parent if it would
+/// otherwise match elements outside the group
+fn item_selector(
+    doc: &Html,
+    parent: &ElementRef,
+    tag: &str,
+    classes: &[String],
+    count: usize,
+) -> String {
+    let own = compound(tag, classes);
+    if matches(doc, &own) == count {
+        return own;
+    }
+    let parent_selector = match parent.value().id().filter(|id| is_ident(id)) {
+        Some(id) => format!("#{}", id),
+        None => compound(parent.value().name(), &self::classes(parent)),
+    };
+    format!("{} > {}", parent_selector, own)
+}
+
+/// Fields for a collection item, from the elements common to its samples
+fn item_fields(items: &[ElementRef]) -> Vec<(String, String)> {
+    let samples = &items[..items.len().min(SAMPLE_ITEMS)];
+    let mut fields: Vec<(String, String)> = Vec::new();
+    let mut seen = HashSet::new();
+    let mut add =
+        |fields: &mut Vec<(String, String)>, name: &str, selector: String, pipes: &str| {
+            if fields.len() >= MAX_FIELDS || !seen.insert(format!("{}{}", selector, pipes)) {
+                return;
+            }
+            let present = samples
+                .iter()
+                .filter(|item| select_in(item, &selector).is_some())
+                .count();
+            if present == 0 {
+                return;
+            }
+            let mut name = name.to_string();
+            let mut n = 1;
+            while fields.iter().any(|(f, _)| f.trim_end_matches('?') == name) {
+                n += 1;
+                name = format!(
+                    "{}_{}",
+                    name.trim_end_matches(char::is_numeric)
+                        .trim_end_matches('_'),
+                    n
+                );
+            }
+            if present < samples.len() {
+                name.push('?');
+            }
+            fields.push((name, format!("{}{}", selector, pipes)));
+        };
+
+    let first = samples[0];
+    for el in first.descendants().filter_map(ElementRef::wrap).skip(1) {
+        let tag = el.value().name();
+        if SKIPPED.contains(&tag) {
+            continue;
+        }
+        let selector = field_selector(&first, &el);
+        match tag {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                add(&mut fields, "title", selector, " | trim")
+            }
+            // Links without text are usually icons or vote buttons
+            "a" if el.value().attr("href").is_some() && !text(&el).is_empty() => {
+                add(&mut fields, "link_text", selector.clone(), " | trim");
+                add(&mut fields, "url", selector, " | attr:href");
+            }
+            "img" if el.value().attr("src").is_some() => {
+                add(&mut fields, "image", selector, " | attr:src")
+            }
+            "time" if el.value().attr("datetime").is_some() => {
+                add(&mut fields, "date", selector, " | attr:datetime")
+            }
+            _ => {
+                let is_leaf = el.children().filter_map(ElementRef::wrap).next().is_none();
+                if let Some(class) = classes(&el).first()
+                    && is_leaf
+                    && !text(&el).is_empty()
+                {
+                    add(&mut fields, &snake_case(class), selector, " | trim");
+                }
+            }
+        }
+    }
+
+    if fields.is_empty() {
+        fields.push(("text".into(), "$ | trim".into()));
+    }
+    fields
+}
+
+/// A selector for `el` within `item`, qualified by its nearest classed
+/// ancestor when its own tag and classes would match an earlier element
+fn field_selector(item: &ElementRef, el: &ElementRef) -> String {
+    let own = compound(el.value().name(), &classes(el));
+    if select_in(item, &own).is_some_and(|found| found.id() == el.id()) {
+        return own;
+    }
+    let ancestor = el
+        .ancestors()
+        .take_while(|node| node.id() != item.id())
+        .filter_map(ElementRef::wrap)
+        .find(|ancestor| !classes(ancestor).is_empty());
+    match ancestor {
+        Some(ancestor) => format!(
+            "{} {}",
+            compound(ancestor.value().name(), &classes(&ancestor)),
+            own
+        ),
+        None => own,
+    }
+}
+
+/// The `tag.class` shapes of an element's descendants
+fn shapes(el: &ElementRef) -> HashSet<String> {
+    el.descendants()
+        .skip(1)
+        .filter_map(ElementRef::wrap)
+        .map(|el| compound(el.value().name(), &classes(&el)))
+        .collect()
+}
+
+fn first<'a>(doc: &'a Html, selector: &str) -> Option<ElementRef<'a>> {
+    let selector = Selector::parse(selector).ok()?;
+    doc.select(&selector).next()
+}
+
+fn matches(doc: &Html, selector: &str) -> usize {
+    Selector::parse(selector)
+        .map(|s| doc.select(&s).count())
+        .unwrap_or(0)
+}
+
+fn select_in<'a>(el: &ElementRef<'a>, selector: &str) -> Option<ElementRef<'a>> {
+    let selector = Selector::parse(selector).ok()?;
+    el.select(&selector).next()
+}
+
+fn text(el: &ElementRef) -> String {
+    el.text().collect::<String>().trim().to_string()
+}
+
+/// Classes usable in a selector, sorted
+fn classes(el: &ElementRef) -> Vec<String> {
+    let mut classes: Vec<String> = el
+        .value()
+        .classes()
+        .filter(|class| is_ident(class))
+        .map(String::from)
+        .collect();
+    classes.sort();
+    classes
+}
+
+fn compound(tag: &str, classes: &[String]) -> String {
+    let mut selector = tag.to_string();
+    for class in classes {
+        selector.push('.');
+        selector.push_str(class);
+    }
+    selector
+}
+
+fn is_ancestor(ancestor: &ElementRef, el: &ElementRef) -> bool {
+    el.ancestors().any(|node| node.id() == ancestor.id())
+}
+
+/// Whether `s` can be used as a class or id in a selector without escaping
+fn is_ident(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '-')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+fn snake_case(s: &str) -> String {
+    let mut out = String::new();
+    for c in s.chars() {
+        if c.is_ascii_uppercase() && !out.is_empty() && !out.ends_with('_') {
+            out.push('_');
+        }
+        match c {
+            '-' => out.push('_'),
+            c => out.push(c.to_ascii_lowercase()),
+        }
+    }
+    out.trim_matches('_').to_string()
+}
+
+fn plural(name: &str) -> String {
+    let name = snake_case(name);
+    match name.as_str() {
+        "li" | "div" | "tr" | "article" | "section" => "items".to_string(),
+        n if n.ends_with('s') => n.to_string(),
+        n => format!("{}s", n),
+    }
+}
+
+fn unique_name(spec: &Map<String, Value>, name: &str) -> String {
+    let mut candidate = name.to_string();
+    let mut n = 1;
+    while spec.contains_key(&candidate) {
+        n += 1;
+        candidate = format!("{}_{}", name, n);
+    }
+    candidate
+}
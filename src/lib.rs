@@ -21,140 +21,2785 @@
 //! # Ok::<(), anyhow::Error>(())
 //! ```
 
+#[cfg(all(target_arch = "wasm32", feature = "http"))]
+compile_error!(
+    "the `http` feature does not build for wasm32; fetch pages in JavaScript and pass the HTML in"
+);
+
+#[cfg(feature = "article")]
+pub mod article;
+#[cfg(feature = "url")]
+mod breadcrumbs;
+pub mod builder;
+pub mod changes;
 pub mod dom;
+#[cfg(feature = "feed")]
+pub mod feed;
+#[cfg(feature = "http")]
+pub mod fetch;
+#[cfg(feature = "url")]
+mod forms;
+#[cfg(feature = "url")]
+mod images;
+#[cfg(feature = "url")]
+mod links;
+pub mod markdown;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+mod microdata;
+pub mod options;
+mod outline;
+#[cfg(feature = "url")]
+mod pagination;
 pub mod pipe;
+pub mod pool;
+pub mod presets;
+#[cfg(feature = "render")]
+pub mod render;
+#[cfg(feature = "schema")]
+pub mod schema;
+mod snapshot;
 pub mod spec;
+pub mod stats;
+
+pub use builder::SpecBuilder;
+pub use changes::{Change, ChangeKind};
+pub use dom::{Dom, ExtractIter};
+pub use options::{ExtractOptions, Limit, LimitExceeded, PageContext, Provenance};
+pub use pool::SpecSet;
+#[cfg(feature = "schema")]
+pub use schema::{Schema, SchemaViolations, Violation};
+pub use spec::Spec;
+pub use stats::{ExtractStats, FieldStats};
+
+#[cfg(feature = "derive")]
+pub use html2json_derive::HtmlExtract;
+
+use anyhow::Result;
+
+/// Extract JSON from HTML using a spec
+///
+/// # Arguments
+///
+/// * `html` - The HTML source to parse
+/// * `spec` - The extraction specification
+///
+/// # Example
+///
+/// ```
+/// use html2json::{extract, Spec};
+///
+/// let html = r#"<div class="item"><span>Price: $25.00</span></div>"#;
+/// let spec_json = r#"{"price": ".item span | substr:8 | parseAs:float"}"#;
+/// let spec: Spec = serde_json::from_str(spec_json)?;
+/// let result = extract(html, &spec)?;
+/// assert_eq!(result["price"], 25.0);
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn extract(html: &str, spec: &Spec) -> Result<serde_json::Value> {
+    let dom = Dom::parse(html)?;
+    dom.extract(spec)
+}
+
+/// Extract JSON from HTML using a spec, enforcing resource limits
+///
+/// Returns a [`LimitExceeded`] error (reachable via `downcast_ref`) when the
+/// extraction reads too much text or visits too many nodes.
+///
+/// # Example
+///
+/// ```
+/// use html2json::{extract_with_options, ExtractOptions, LimitExceeded, Spec};
+///
+/// let html = r#"<ul><li>a</li><li>b</li><li>c</li></ul>"#;
+/// let spec: Spec = serde_json::from_str(r#"[{"$": "li", "name": "$"}]"#)?;
+/// let options = ExtractOptions::new().max_nodes(2);
+/// let err = extract_with_options(html, &spec, &options).unwrap_err();
+/// assert!(err.downcast_ref::<LimitExceeded>().is_some());
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn extract_with_options(
+    html: &str,
+    spec: &Spec,
+    options: &ExtractOptions,
+) -> Result<serde_json::Value> {
+    let dom = Dom::parse(html)?;
+    dom.extract_with_options(spec, options)
+}
+
+/// Extract JSON from HTML using a spec, with what is known about the page
+///
+/// `{"$context": KEY}` fields read the context, and its URL resolves relative
+/// URLs (`absoluteUrl`, `$links`, `$images`).
+///
+/// # Example
+///
+/// ```
+/// use html2json::{extract_with_context, PageContext, Spec};
+///
+/// # #[cfg(feature = "url")] {
+/// let html = r#"<a href="/next">Next</a>"#;
+/// let spec: Spec = serde_json::from_str(
+///     r#"{"url": {"$context": "url"}, "next": "a | attr:href | absoluteUrl"}"#,
+/// )?;
+/// let context = PageContext::new().url("https://example.com/list");
+/// let result = extract_with_context(html, &spec, &context)?;
+/// assert_eq!(result["url"], "https://example.com/list");
+/// assert_eq!(result["next"], "https://example.com/next");
+/// # }
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn extract_with_context(
+    html: &str,
+    spec: &Spec,
+    context: &PageContext,
+) -> Result<serde_json::Value> {
+    let options = ExtractOptions::new().context(context.clone());
+    extract_with_options(html, spec, &options)
+}
+
+/// Extract JSON from HTML using a spec and check it against a JSON Schema
+///
+/// Returns a [`SchemaViolations`] error (reachable via `downcast_ref`) listing
+/// each violation with a JSON pointer to the offending value. To check many
+/// results against one schema, compile it once with [`Schema::new`].
+///
+/// # Example
+///
+/// ```
+/// use html2json::{extract_validated, SchemaViolations, Spec};
+///
+/// let spec: Spec = serde_json::from_str(r#"{"price": ".price | parseAs:float"}"#)?;
+/// let schema = serde_json::json!({
+///     "type": "object",
+///     "properties": {"price": {"type": "number"}},
+///     "required": ["price"]
+/// });
+/// let result = extract_validated(r#"<b class="price">9.50</b>"#, &spec, &schema)?;
+/// assert_eq!(result["price"], 9.5);
+///
+/// // The price element is gone after a redesign
+/// let err = extract_validated(r#"<b class="cost">9.50</b>"#, &spec, &schema).unwrap_err();
+/// let violations = err.downcast_ref::<SchemaViolations>().unwrap();
+/// assert_eq!(violations.violations[0].pointer, "/price");
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+#[cfg(feature = "schema")]
+pub fn extract_validated(
+    html: &str,
+    spec: &Spec,
+    schema: &serde_json::Value,
+) -> Result<serde_json::Value> {
+    let schema = Schema::new(schema)?;
+    let result = extract(html, spec)?;
+    schema.validate(&result)?;
+    Ok(result)
+}
+
+/// Extract JSON from HTML using a spec, writing the result to `writer`
+///
+/// Unlike [`extract`], top-level array results are streamed one item at a time
+/// instead of being collected into a single `Value` first.
+///
+/// # Example
+///
+/// ```
+/// use html2json::{extract_to_writer, Spec};
+///
+/// let html = r#"<ul><li>a</li><li>b</li></ul>"#;
+/// let spec: Spec = serde_json::from_str(r#"[{"$": "li", "name": "$"}]"#)?;
+/// let mut out = Vec::new();
+/// extract_to_writer(html, &spec, &mut out)?;
+/// assert_eq!(String::from_utf8(out)?, r#"[{"name":"a"},{"name":"b"}]"#);
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn extract_to_writer<W: std::io::Write>(html: &str, spec: &Spec, writer: W) -> Result<()> {
+    let dom = Dom::parse(html)?;
+    dom.extract_to_writer(spec, writer)
+}
+
+/// Extract JSON from HTML using a spec, extracting collection items in parallel
+///
+/// See [`Dom::extract_par`] for when this is faster than [`extract`].
+#[cfg(feature = "parallel")]
+pub fn extract_par(html: &str, spec: &Spec) -> Result<serde_json::Value> {
+    let dom = Dom::parse(html)?;
+    dom.extract_par(spec)
+}
+
+/// Extract JSON from HTML using a spec and deserialize it into `T`
+///
+/// Deserialization errors report the path of the offending field
+/// (e.g. `items[3].price`), which makes spec/struct mismatches easy to find.
+///
+/// # Example
+///
+/// ```
+/// use html2json::{extract_as, Spec};
+///
+/// #[derive(serde::Deserialize)]
+/// struct Page {
+///     title: String,
+/// }
+///
+/// let html = r#"<h1>Hello</h1>"#;
+/// let spec: Spec = serde_json::from_str(r#"{"title": "h1"}"#)?;
+/// let page: Page = extract_as(html, &spec)?;
+/// assert_eq!(page.title, "Hello");
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn extract_as<T: serde::de::DeserializeOwned>(html: &str, spec: &Spec) -> Result<T> {
+    let dom = Dom::parse(html)?;
+    dom.extract_as(spec)
+}
+
+/// Extract JSON from HTML and compare it with a previous result
+///
+/// Returns the new result and the [`Change`]s from `previous` to it, none if
+/// nothing changed (see [`changes::diff`]).
+///
+/// # Example
+///
+/// ```
+/// use html2json::{extract_changes, ChangeKind, Spec};
+///
+/// let spec: Spec = serde_json::from_str(r#"{"name": "h1", "price": ".price | parseAs:float"}"#)?;
+/// let previous = serde_json::json!({"name": "Arc Lamp", "price": 120.0});
+/// let html = r#"<h1>Arc Lamp</h1><span class="price">99.5</span>"#;
+/// let (result, changes) = extract_changes(html, &spec, &previous)?;
+/// assert_eq!(result["price"], 99.5);
+/// assert_eq!(changes.len(), 1);
+/// assert_eq!(changes[0].path, ".price");
+/// assert_eq!(changes[0].kind, ChangeKind::Changed);
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn extract_changes(
+    html: &str,
+    spec: &Spec,
+    previous: &serde_json::Value,
+) -> Result<(serde_json::Value, Vec<Change>)> {
+    let result = extract(html, spec)?;
+    let changes = changes::diff(previous, &result);
+    Ok((result, changes))
+}
+
+/// Extract from several documents, each with its own spec, and deep-merge
+/// the results with [`merge_json`]
+///
+/// For data spread over several responses, such as a product page and the
+/// reviews fragment it loads separately.
+///
+/// # Example
+///
+/// ```
+/// use html2json::{extract_merged, Spec};
+///
+/// let page = r#"<h1>Arc Lamp</h1><p class="review">Bright</p>"#;
+/// let fragment = r#"<p class="review">Sturdy</p><span class="count">2</span>"#;
+/// let product: Spec = serde_json::from_str(r#"{"name": "h1", "reviews": [".review"]}"#)?;
+/// let reviews: Spec = serde_json::from_str(
+///     r#"{"reviews": [".review"], "reviewCount": ".count | parseAs:int"}"#,
+/// )?;
+/// let result = extract_merged(&[(page, &product), (fragment, &reviews)])?;
+/// assert_eq!(
+///     result,
+///     serde_json::json!({
+///         "name": "Arc Lamp",
+///         "reviews": ["Bright", "Sturdy"],
+///         "reviewCount": 2
+///     })
+/// );
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn extract_merged(docs: &[(&str, &Spec)]) -> Result<serde_json::Value> {
+    let mut merged = serde_json::Value::Null;
+    for (i, (html, spec)) in docs.iter().enumerate() {
+        let value = extract(html, spec).map_err(|e| e.context(format!("Document {}", i)))?;
+        merge_json(&mut merged, value);
+    }
+    Ok(merged)
+}
+
+/// Deep-merge `value` into `merged`
+///
+/// Objects are merged field by field and arrays are concatenated; any other
+/// value only replaces a `null`, so the first non-null value wins.
+pub fn merge_json(merged: &mut serde_json::Value, value: serde_json::Value) {
+    use serde_json::Value;
+    match (merged, value) {
+        (Value::Object(fields), Value::Object(more)) => {
+            for (key, value) in more {
+                merge_json(fields.entry(key).or_insert(Value::Null), value);
+            }
+        }
+        (Value::Array(items), Value::Array(more)) => items.extend(more),
+        (merged @ Value::Null, value) => *merged = value,
+        _ => {}
+    }
+}
+
+/// Fetch a page and extract JSON from it using a spec
+///
+/// Redirects are followed and the body is decoded using the charset the
+/// server declares. Uses an [`HttpFetcher`](fetch::HttpFetcher) with default
+/// settings; build one with [`HttpFetcher::builder`](fetch::HttpFetcher::builder)
+/// for headers, retries, caching or rate limits, or implement
+/// [`Fetcher`](fetch::Fetcher) for another source of pages, and call its
+/// methods instead.
+///
+/// # Example
+///
+/// ```no_run
+/// # async fn run() -> anyhow::Result<()> {
+/// let spec: html2json::Spec = serde_json::from_str(r#"{"title": "h1"}"#)?;
+/// let result = html2json::fetch_and_extract("https://example.com/", &spec).await?;
+/// println!("{}", result["title"]);
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "http")]
+pub async fn fetch_and_extract(url: &str, spec: &Spec) -> Result<serde_json::Value> {
+    use fetch::Fetcher;
+    fetch::HttpFetcher::new()?
+        .fetch_and_extract(url, spec)
+        .await
+}
+
+/// Fetch many pages and extract from each, at most `concurrency` at a time
+///
+/// Results are in the order of `urls`, one per URL. Must run inside a tokio
+/// runtime; see [`Fetcher::fetch_and_extract_all`](fetch::Fetcher::fetch_and_extract_all).
+#[cfg(feature = "http")]
+pub async fn fetch_and_extract_all<I>(
+    urls: I,
+    spec: &Spec,
+    concurrency: usize,
+) -> Result<Vec<Result<serde_json::Value>>>
+where
+    I: IntoIterator,
+    I::Item: Into<String>,
+{
+    use fetch::Fetcher;
+    let fetcher = fetch::HttpFetcher::new()?;
+    Ok(fetcher.fetch_and_extract_all(urls, spec, concurrency).await)
+}
+
+/// Types that know their own extraction spec
+///
+/// Usually implemented with `#[derive(HtmlExtract)]` (requires the `derive`
+/// feature), which builds the spec from `#[selector(...)]` field attributes.
+pub trait HtmlExtract: serde::de::DeserializeOwned {
+    /// The spec for this type as JSON
+    fn spec_value() -> serde_json::Value;
+
+    /// The parsed extraction spec for this type
+    fn spec() -> Result<Spec> {
+        Spec::from_json(&Self::spec_value())
+    }
+
+    /// Extract an instance of this type from HTML
+    fn from_html(html: &str) -> Result<Self> {
+        extract_as(html, &Self::spec()?)
+    }
+}
+
+/// Support code for `#[derive(HtmlExtract)]`; not public API
+#[doc(hidden)]
+pub mod __private {
+    pub use serde_json;
+
+    /// Set the `$` scope of an object spec value
+    pub fn scoped_spec(spec: serde_json::Value, scope: &str) -> serde_json::Value {
+        let mut spec = match spec {
+            serde_json::Value::Object(map) => map,
+            _ => serde_json::Map::new(),
+        };
+        spec.insert(
+            "$".to_string(),
+            serde_json::Value::String(scope.to_string()),
+        );
+        serde_json::Value::Object(spec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::extract;
+    use crate::spec::Spec;
+    const HTML: &str = include_str!("../examples/hn.html");
+
+    #[test]
+    fn basic_text_extraction() {
+        let spec: Spec = serde_json::from_str(
+            r##"{
+                "$": "title",
+                "title": "$"
+            }"##,
+        )
+        .unwrap();
+        let result = extract(HTML, &spec).unwrap();
+        assert_eq!(result["title"], "Hacker News");
+    }
+
+    #[test]
+    fn attribute_extraction() {
+        let spec: Spec = serde_json::from_str(
+            r##"{
+                "rss_link": "link[rel=alternate] | attr:href"
+            }"##,
+        )
+        .unwrap();
+        let result = extract(HTML, &spec).unwrap();
+        assert_eq!(result["rss_link"], "rss");
+    }
+
+    #[test]
+    fn scoping_with_dollar() {
+        let spec: Spec = serde_json::from_str(
+            r##"{
+                "$": ".pagetop",
+                "first_link": "a"
+            }"##,
+        )
+        .unwrap();
+        let result = extract(HTML, &spec).unwrap();
+        assert_eq!(result["first_link"], "Hacker News");
+    }
+
+    #[test]
+    fn nested_scoping() {
+        let spec: Spec = serde_json::from_str(
+            r##"{
+                "$": "head",
+                "head_element": {
+                    "$": "link",
+                    "href": "$ | attr:href",
+                    "rel": "$ | attr:rel"
+                }
+            }"##,
+        )
+        .unwrap();
+        let result = extract(HTML, &spec).unwrap();
+        assert_eq!(
+            result["head_element"]["href"],
+            "news.css?fFlkMoHAedK8lfBWEYBd"
+        );
+        assert_eq!(result["head_element"]["rel"], "stylesheet");
+    }
+
+    #[test]
+    fn collection_extraction() {
+        let spec: Spec = serde_json::from_str(
+            r##"{
+                "ranks": [{
+                    "$": ".rank",
+                    "value": "$"
+                }]
+            }"##,
+        )
+        .unwrap();
+        let result = extract(HTML, &spec).unwrap();
+        let arr = result["ranks"].as_array().unwrap();
+        assert!(arr.len() >= 3);
+        assert_eq!(arr[0]["value"], "1.");
+        assert_eq!(arr[1]["value"], "2.");
+        assert_eq!(arr[2]["value"], "3.");
+    }
+
+    #[test]
+    fn collection_with_nested_properties() {
+        let spec: Spec = serde_json::from_str(
+            r##"{
+                "items": [{
+                    "$": "tr.athing",
+                    "id": "$ | attr:id",
+                    "title": ".titleline a"
+                }]
+            }"##,
+        )
+        .unwrap();
+        let result = extract(HTML, &spec).unwrap();
+        let arr = result["items"].as_array().unwrap();
+        assert!(arr.len() >= 2);
+        assert_eq!(arr[0]["id"], "46446815");
+        assert_eq!(arr[0]["title"], "I canceled my book deal");
+    }
+
+    #[test]
+    fn extract_iter_yields_array_items_lazily() {
+        let spec: Spec = serde_json::from_str(
+            r##"[{
+                "$": "tr.athing",
+                "id": "$ | attr:id",
+                "title": ".titleline a"
+            }]"##,
+        )
+        .unwrap();
+        let dom = crate::Dom::parse(HTML).unwrap();
+        let mut iter = dom.extract_iter(&spec);
+        let expected = extract(HTML, &spec).unwrap();
+        assert_eq!(iter.size_hint().0, expected.as_array().unwrap().len());
+
+        let first = iter.next().unwrap().unwrap();
+        assert_eq!(first["id"], "46446815");
+        assert_eq!(first["title"], "I canceled my book deal");
+
+        let rest = iter.collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(rest.as_slice(), &expected.as_array().unwrap()[1..]);
+    }
+
+    #[test]
+    fn extract_iter_object_spec_yields_single_result() {
+        let spec: Spec = serde_json::from_str(r##"{"title": "title"}"##).unwrap();
+        let dom = crate::Dom::parse(HTML).unwrap();
+        let items = dom
+            .extract_iter(&spec)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0]["title"], "Hacker News");
+    }
+
+    #[test]
+    fn extract_to_writer_matches_extract() {
+        let spec_json = r##"[{
+            "$": "tr.athing",
+            "id": "$ | attr:id",
+            "title": ".titleline a"
+        }]"##;
+        let spec: Spec = serde_json::from_str(spec_json).unwrap();
+        let mut out = Vec::new();
+        crate::extract_to_writer(HTML, &spec, &mut out).unwrap();
+        let written: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(written, extract(HTML, &spec).unwrap());
+
+        let spec: Spec = serde_json::from_str(r##"{"title": "title"}"##).unwrap();
+        let mut out = Vec::new();
+        crate::extract_to_writer(HTML, &spec, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            r#"{"title":"Hacker News"}"#
+        );
+    }
+
+    #[test]
+    fn extract_as_deserializes_into_struct() {
+        #[derive(serde::Deserialize)]
+        struct Item {
+            id: String,
+            title: String,
+        }
+        #[derive(serde::Deserialize)]
+        struct Page {
+            items: Vec<Item>,
+        }
+
+        let spec: Spec = serde_json::from_str(
+            r##"{
+                "items": [{
+                    "$": "tr.athing",
+                    "id": "$ | attr:id",
+                    "title": ".titleline a"
+                }]
+            }"##,
+        )
+        .unwrap();
+        let page: Page = crate::extract_as(HTML, &spec).unwrap();
+        assert_eq!(page.items[0].id, "46446815");
+        assert_eq!(page.items[0].title, "I canceled my book deal");
+    }
+
+    #[test]
+    fn extract_as_reports_field_path() {
+        #[derive(Debug, serde::Deserialize)]
+        #[allow(dead_code)]
+        struct Item {
+            id: u64,
+        }
+        #[derive(Debug, serde::Deserialize)]
+        #[allow(dead_code)]
+        struct Page {
+            items: Vec<Item>,
+        }
+
+        let spec: Spec = serde_json::from_str(
+            r##"{
+                "items": [{
+                    "$": "tr.athing",
+                    "id": "$ | attr:id"
+                }]
+            }"##,
+        )
+        .unwrap();
+        let err = crate::extract_as::<Page>(HTML, &spec).unwrap_err();
+        assert!(err.to_string().contains("items[0].id"), "{err}");
+    }
+
+    #[test]
+    fn spec_builder_matches_json_spec() {
+        let built = Spec::builder()
+            .scope("#hnmain")
+            .field("title", "title | trim")
+            .optional_field("missing", ".nonexistent || .also-missing")
+            .literal("source", crate::spec::LiteralValue::String("hn".into()))
+            .collection("items", |item| {
+                item.scope("tr.athing")
+                    .field("id", "$ | attr:id")
+                    .field("title", ".titleline a")
+            })
+            .build()
+            .unwrap();
+        let parsed: Spec = serde_json::from_str(
+            r##"{
+                "$": "#hnmain",
+                "title": "title | trim",
+                "missing?": ".nonexistent || .also-missing",
+                "source": "'hn'",
+                "items": [{
+                    "$": "tr.athing",
+                    "id": "$ | attr:id",
+                    "title": ".titleline a"
+                }]
+            }"##,
+        )
+        .unwrap();
+        assert_eq!(built.to_json(), parsed.to_json());
+        assert_eq!(
+            extract(HTML, &built).unwrap(),
+            extract(HTML, &parsed).unwrap()
+        );
+
+        let round_tripped = Spec::from_json(&built.to_json()).unwrap();
+        assert_eq!(round_tripped.to_json(), built.to_json());
+    }
+
+    #[test]
+    fn spec_builder_reports_invalid_pipe() {
+        let err = Spec::builder()
+            .field("title", "h1 | nope")
+            .build()
+            .unwrap_err();
+        assert!(format!("{err:#}").contains("Unknown pipe command"));
+    }
+
+    #[test]
+    fn dom_can_move_across_threads() {
+        fn assert_send<T: Send>() {}
+        assert_send::<crate::Dom>();
+        assert_send::<Spec>();
+
+        let dom = crate::Dom::parse(HTML).unwrap();
+        let spec: Spec = serde_json::from_str(r##"{"title": "title"}"##).unwrap();
+        let result = std::thread::spawn(move || dom.extract(&spec).unwrap())
+            .join()
+            .unwrap();
+        assert_eq!(result["title"], "Hacker News");
+    }
+
+    #[test]
+    fn spec_is_shareable_across_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Spec>();
+        assert_send_sync::<crate::SpecSet>();
+        assert_send_sync::<crate::pool::Extractor<'static>>();
+
+        let spec: Spec = serde_json::from_str(r##"{"title": "title | upper"}"##).unwrap();
+        let handles = (0..4)
+            .map(|_| {
+                let spec = spec.clone();
+                std::thread::spawn(move || extract(HTML, &spec).unwrap())
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap()["title"], "HACKER NEWS");
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_extraction_matches_sequential() {
+        let spec: Spec = serde_json::from_str(
+            r##"[{
+                "$": "tr.athing",
+                "id": "$ | attr:id",
+                "title": ".titleline a",
+                "score": "+ .subtext .score"
+            }]"##,
+        )
+        .unwrap();
+        assert_eq!(
+            crate::extract_par(HTML, &spec).unwrap(),
+            extract(HTML, &spec).unwrap()
+        );
+    }
+
+    #[test]
+    fn invalid_selectors_and_regexes_fail_at_parse_time() {
+        let err = serde_json::from_str::<Spec>(r##"{"title": "h1[[ | trim"}"##).unwrap_err();
+        assert!(err.to_string().contains("Invalid selector"), "{err}");
+
+        let err =
+            serde_json::from_str::<Spec>(r##"{"items": [{"$": "li::", "v": "$"}]}"##).unwrap_err();
+        assert!(err.to_string().contains("Invalid selector"), "{err}");
+
+        let err = serde_json::from_str::<Spec>(r##"{"n": "h1 | regex:(\\d+"}"##).unwrap_err();
+        #[cfg(feature = "regex")]
+        assert!(err.to_string().contains("Invalid or unsafe regex"), "{err}");
+        #[cfg(not(feature = "regex"))]
+        assert!(err.to_string().contains("Regex support disabled"), "{err}");
+    }
+
+    #[cfg(not(feature = "url"))]
+    #[test]
+    fn url_constructs_need_the_url_feature() {
+        for spec in [
+            r#"{"l": "$links"}"#,
+            r#"{"l": "a | attr:href | absoluteUrl"}"#,
+        ] {
+            let err = serde_json::from_str::<Spec>(spec).unwrap_err();
+            assert!(err.to_string().contains("`url` feature"), "{err}");
+        }
+    }
+
+    #[test]
+    fn indexed_dom_matches_unindexed() {
+        let spec: Spec = serde_json::from_str(include_str!("../examples/hn.json")).unwrap();
+        let plain = crate::Dom::parse(HTML).unwrap();
+        let indexed = crate::Dom::parse_indexed(HTML).unwrap();
+        assert_eq!(
+            indexed.extract(&spec).unwrap(),
+            plain.extract(&spec).unwrap()
+        );
+
+        for selector in [
+            "#hnmain",
+            ".titleline",
+            "tr",
+            "td.title",
+            "#missing",
+            ".nope",
+        ] {
+            assert_eq!(
+                indexed.select(selector).unwrap(),
+                plain.select(selector).unwrap(),
+                "{selector}"
+            );
+            assert_eq!(
+                indexed.select_one(selector).unwrap(),
+                plain.select_one(selector).unwrap(),
+                "{selector}"
+            );
+        }
+
+        // Repeated ids match every element, as without the index
+        let html = r#"<div id="x">a</div><p><span id="x">b</span></p><div id="y">c</div>"#;
+        let plain = crate::Dom::parse(html).unwrap();
+        let indexed = crate::Dom::parse_indexed(html).unwrap();
+        assert_eq!(plain.select("#x").unwrap().len(), 2);
+        assert_eq!(indexed.select("#x").unwrap(), plain.select("#x").unwrap());
+        assert_eq!(
+            indexed.select_one("#x").unwrap(),
+            plain.select_one("#x").unwrap()
+        );
+        let spec: Spec = serde_json::from_str(r##"{"first": "#x", "all": ["#x"]}"##).unwrap();
+        assert_eq!(
+            indexed.extract(&spec).unwrap(),
+            serde_json::json!({"first": "a", "all": ["a", "b"]})
+        );
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn regex_cache_is_bounded() {
+        use crate::pipe::{
+            DEFAULT_REGEX_CACHE_CAPACITY, regex_cache_stats, set_regex_cache_capacity,
+        };
+
+        set_regex_cache_capacity(4).unwrap();
+        for i in 0..20 {
+            let spec = format!(r#"{{"n": "title | regex:bounded-{i}-(\\w+)"}}"#);
+            serde_json::from_str::<Spec>(&spec).unwrap();
+        }
+        let stats = regex_cache_stats().unwrap();
+        assert_eq!(stats.capacity, 4);
+        assert!(stats.len <= 4);
+        assert!(stats.evictions >= 16);
+
+        let before = regex_cache_stats().unwrap();
+        serde_json::from_str::<Spec>(r#"{"n": "title | regex:bounded-19-(\\w+)"}"#).unwrap();
+        let after = regex_cache_stats().unwrap();
+        assert!(after.hits + after.misses > before.hits + before.misses);
+
+        set_regex_cache_capacity(DEFAULT_REGEX_CACHE_CAPACITY).unwrap();
+    }
+
+    #[test]
+    fn scoped_first_match_is_first_of_all() {
+        let html = include_str!("../examples/hn.html");
+        let dom = crate::Dom::parse(html).unwrap();
+        let row = dom.select_one("tr.athing").unwrap().unwrap();
+
+        let first = dom.select_one_in(&row, "a").unwrap();
+        let all = dom.select_in(&row, "a").unwrap();
+        assert!(first.is_some());
+        assert_eq!(first.as_ref(), all.first());
+    }
+
+    #[test]
+    fn next_sibling_selection_matches_spec_plus() {
+        let html = "<dl><dt>Size</dt><dd><b>Large</b></dd><dt>Color</dt><dd><b>Red</b></dd></dl>";
+        let dom = crate::Dom::parse(html).unwrap();
+        let term = dom.select("dt").unwrap().pop().unwrap();
+        let value = dom.select_next_sibling(&term, "b").unwrap().unwrap();
+        assert_eq!(value.text(), "Red");
+        assert!(dom.select_next_sibling(&value, "b").unwrap().is_none());
+
+        let spec: Spec = serde_json::from_str(r#"[{"$": "dt", "v": "+ b"}]"#).unwrap();
+        let values = dom.extract(&spec).unwrap();
+        assert_eq!(values[1]["v"], "Red");
+    }
+
+    #[test]
+    fn spec_set_shares_parsed_specs() {
+        let specs = crate::SpecSet::builder()
+            .spec_json("title", r#"{"title": "h1"}"#)
+            .unwrap()
+            .spec(
+                "items",
+                serde_json::from_str(r#"[{"$": "li", "name": "$"}]"#).unwrap(),
+            )
+            .options(crate::ExtractOptions::new().max_nodes(2))
+            .build();
+        assert_eq!(specs.names().collect::<Vec<_>>(), ["items", "title"]);
+
+        let html = "<h1>Hi</h1><ul><li>a</li><li>b</li><li>c</li></ul>";
+        let shared = specs.clone();
+        let title = std::thread::spawn(move || shared.extract("title", html).unwrap())
+            .join()
+            .unwrap();
+        assert_eq!(title["title"], "Hi");
+
+        let err = specs.extractor("items").unwrap().extract(html).unwrap_err();
+        assert!(err.downcast_ref::<crate::LimitExceeded>().is_some());
+
+        let err = specs.extractor("nope").unwrap_err();
+        assert!(err.to_string().contains("known: items, title"));
+        let err = crate::SpecSet::builder()
+            .spec_json("bad", r#"{"x": "h1 | nosuchpipe"}"#)
+            .unwrap_err();
+        assert!(err.to_string().contains("Invalid spec 'bad'"));
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn metrics_count_fields_and_misses() {
+        use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        let spec: Spec =
+            serde_json::from_str(r#"[{"$": "li", "name": "$", "price": ".price", "n?": "b"}]"#)
+                .unwrap();
+        metrics::with_local_recorder(&recorder, || {
+            extract("<ul><li>a</li><li>b <i class=price>2</i></li></ul>", &spec).unwrap();
+        });
+
+        let mut counters = std::collections::HashMap::new();
+        let mut histograms = 0;
+        for (key, _, _, value) in snapshotter.snapshot().into_vec() {
+            match value {
+                DebugValue::Counter(n) => {
+                    counters.insert(key.key().name().to_string(), n);
+                }
+                DebugValue::Histogram(samples) => histograms += samples.len(),
+                DebugValue::Gauge(_) => {}
+            }
+        }
+        assert_eq!(counters[crate::metrics::DOCUMENTS], 1);
+        assert_eq!(counters[crate::metrics::FIELDS], 6);
+        assert_eq!(counters[crate::metrics::NULL_FIELDS], 3);
+        assert_eq!(counters[crate::metrics::SELECTOR_MISSES], 3);
+        assert!(!counters.contains_key(crate::metrics::ERRORS));
+        assert_eq!(histograms, 2);
+    }
+
+    #[test]
+    fn provenance_inline_and_sidecar() {
+        use crate::{ExtractOptions, Provenance, extract_with_options};
+
+        let html = r#"<h1>Shop</h1><ul><li>a <b>1</b></li><li>b</li></ul>"#;
+        let spec: Spec = serde_json::from_str(
+            r#"{"title": "h1", "version": 2, "items": [{"$": "li", "price?": "b || i"}]}"#,
+        )
+        .unwrap();
+
+        let options = ExtractOptions::new().provenance(Provenance::Inline);
+        let inline = extract_with_options(html, &spec, &options).unwrap();
+        assert_eq!(
+            inline["title"],
+            serde_json::json!({"value": "Shop", "$src": {"selector": "h1", "index": 1}})
+        );
+        assert_eq!(inline["version"], 2.0);
+        assert_eq!(inline["items"][0]["price"]["$src"]["selector"], "b");
+        assert_eq!(inline["items"][0]["price"]["$src"]["index"], 4);
+        assert!(inline["items"][1].get("price").is_none());
+
+        let options = ExtractOptions::new().provenance(Provenance::Sidecar);
+        let sidecar = extract_with_options(html, &spec, &options).unwrap();
+        assert_eq!(sidecar["data"], extract(html, &spec).unwrap());
+        assert_eq!(
+            sidecar["provenance"],
+            serde_json::json!({
+                "/title": {"selector": "h1", "index": 1},
+                "/items/0/price": {"selector": "b", "index": 4},
+            })
+        );
+    }
+
+    #[test]
+    fn lint_flags_fragile_patterns() {
+        use crate::spec::{Lint, lint};
+
+        let spec = serde_json::json!({
+            "title": "html > body > div > div > h1",
+            "n?": 3,
+            "alt": "h1 || h2 || h1",
+            "rows": [{"$": "tr", "cell": "td:nth-child(2) > a:nth-of-type(1)"}],
+            "all": [{"$": "div > *", "x": "$"}],
+            "fine": [{"$": "li.item", "name": "a.title", "price?": ".price || .cost"}]
+        });
+        let found: Vec<_> = lint(&spec)
+            .unwrap()
+            .into_iter()
+            .map(|w| (w.path, w.lint))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("all.$".to_string(), Lint::UniversalScope),
+                ("alt".to_string(), Lint::UnreachableFallback),
+                ("n".to_string(), Lint::OptionalLiteral),
+                ("rows.cell".to_string(), Lint::PositionalSelector),
+                ("title".to_string(), Lint::PositionalSelector),
+            ]
+        );
+
+        #[cfg(feature = "regex")]
+        assert_eq!(
+            lint(&serde_json::json!({"p": ".price | regex:\\d+"})).unwrap()[0].lint,
+            Lint::RegexWithoutGroup
+        );
+        assert!(lint(&serde_json::json!({"p": "h1 | nosuchpipe"})).is_err());
+    }
+
+    #[test]
+    fn split_pipes_ignores_quoted_bars() {
+        use crate::spec::split_pipes;
+        assert_eq!(split_pipes(" a[href] "), ("a[href]", None));
+        assert_eq!(
+            split_pipes("a[href] | attr:href | trim"),
+            ("a[href]", Some("attr:href | trim"))
+        );
+        assert_eq!(
+            split_pipes("a[title='x|y'] | attr:href"),
+            ("a[title='x|y']", Some("attr:href"))
+        );
+    }
+
+    #[test]
+    fn duplicate_fields_are_spec_errors() {
+        let err = Spec::from_json(&serde_json::json!({"title": "h1", "title?": "h2"})).unwrap_err();
+        assert!(err.to_string().contains("Duplicate field 'title'"));
+        let err =
+            Spec::from_json(&serde_json::json!([{"$": "li", "n?": "a", "n": "b"}])).unwrap_err();
+        assert!(err.to_string().contains("Duplicate field 'n'"));
+        let err = Spec::from_json(&serde_json::json!({"t": "h1", "t*": "h2"})).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Duplicate field 't': 't' and 't*' are both given"
+        );
+        let err = Spec::from_json(&serde_json::json!({"t*": "h1", "t?": "h2"})).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Duplicate field 't': 't*' and 't?' are both given"
+        );
+        let err = Spec::from_json(&serde_json::json!({"t*?": "h1", "t": "h2"})).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Duplicate field 't': 't' and 't*?' are both given"
+        );
+
+        let err =
+            serde_json::from_str::<Spec>(r#"{"$": "main", "t": "h1", "$": "body"}"#).unwrap_err();
+        assert!(err.to_string().contains("Duplicate key '$'"));
+        let err =
+            crate::spec::parse_json(r#"{"items": [{"name": "a", "name": "b"}]}"#).unwrap_err();
+        assert!(err.to_string().contains("Duplicate key 'name'"));
+
+        let err = Spec::builder()
+            .field("t", "h1")
+            .optional_field("t", "h2")
+            .build();
+        assert!(err.is_err());
+    }
+
+    #[test]
+    fn collections_need_a_scope_or_all() {
+        let err = Spec::from_json(&serde_json::json!({"items": [{"name": "$"}]})).unwrap_err();
+        assert!(err.to_string().contains("Collection has no \"$\""));
+
+        // `$` as the scope means the enclosing element, and the document has none
+        let spec = Spec::from_json(&serde_json::json!([{"$": "$", "name": "$"}])).unwrap();
+        let err = extract("<p>a</p>", &spec).unwrap_err();
+        assert!(err.to_string().contains("Invalid selector '$'"), "{err}");
+        assert!(Spec::from_json(&serde_json::json!([{"$": "li", "$all": true}])).is_err());
+        assert!(Spec::from_json(&serde_json::json!({"$all": true, "t": "h1"})).is_err());
+
+        let spec =
+            serde_json::json!({"$": "ul", "items": [{"$all": true, "tag": "$ | attr:class"}]});
+        let parsed = Spec::from_json(&spec).unwrap();
+        assert_eq!(parsed.to_json(), spec);
+        let result = extract(
+            r#"<ul><li class="a">1</li><li class="b"><b class="c">2</b></li></ul>"#,
+            &parsed,
+        )
+        .unwrap();
+        assert_eq!(
+            result["items"],
+            serde_json::json!([{"tag": "a"}, {"tag": "b"}, {"tag": "c"}])
+        );
+
+        assert!(
+            Spec::builder()
+                .collection("items", |item| item.field("n", "$"))
+                .build()
+                .is_err()
+        );
+        assert!(
+            Spec::builder()
+                .all()
+                .collection("items", |item| item.all().field("n", "$"))
+                .build()
+                .is_err()
+        );
+        let built = Spec::builder()
+            .scope("ul")
+            .collection("items", |item| item.all().field("tag", "$ | attr:class"))
+            .build()
+            .unwrap();
+        assert_eq!(built.to_json(), spec);
+    }
+
+    #[test]
+    fn document_level_sibling_selectors() {
+        let html = include_str!("../examples/hn.html");
+        let spec: Spec = serde_json::from_str(r#"{"score": "tr.athing + tr .score"}"#).unwrap();
+        assert_eq!(extract(html, &spec).unwrap()["score"], "156 points");
+
+        for spec in [
+            serde_json::json!({"score": "+ .score"}),
+            serde_json::json!({"meta": {"score": "h1 || + .score"}}),
+        ] {
+            let err = Spec::from_json(&spec).unwrap_err().to_string();
+            assert!(err.contains("'+ .score' has no scope"), "{err}");
+        }
+        assert!(Spec::builder().field("score", "+ .score").build().is_err());
+        assert!(
+            Spec::from_json(&serde_json::json!({"$": "tr.athing", "score": "+ .score"})).is_ok()
+        );
+    }
+
+    #[test]
+    fn root_breaks_out_of_scope() {
+        let html = r#"<h1>Blog</h1>
+            <div class="post"><span class="tag">a</span><span class="tag">b</span></div>
+            <div class="post"><span class="tag">c</span></div>"#;
+        let spec: Spec = serde_json::from_str(
+            r#"[{"$": ".post",
+                "tags": [{"$": ".tag", "name": "$"}],
+                "site": {"$root": true, "name": "h1"},
+                "every_tag": [{"$": ".tag", "$root": true, "name": "$"}]}]"#,
+        )
+        .unwrap();
+        let result = extract(html, &spec).unwrap();
+        assert_eq!(result[1]["tags"], serde_json::json!([{"name": "c"}]));
+        assert_eq!(result[1]["site"]["name"], "Blog");
+        assert_eq!(result[1]["every_tag"].as_array().unwrap().len(), 3);
+        assert_eq!(spec.to_json()[0]["site"]["$root"], true);
+
+        let err = Spec::from_json(&serde_json::json!({"x": {"$root": true, "s": "+ .a"}}))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("no scope"), "{err}");
+        assert!(Spec::from_json(&serde_json::json!({"x": {"$root": 1}})).is_err());
+    }
+
+    #[test]
+    fn scope_fallbacks() {
+        let old =
+            r#"<ul class="old"><li>a</li><li>b</li></ul><div class="old-card"><h2>Old</h2></div>"#;
+        let new = r#"<div class="new"><p>c</p></div><ul class="old"><li>a</li></ul>"#;
+        let spec: Spec = serde_json::from_str(
+            r#"{"items": [{"$": ".new p || .old li", "name": "$"}], "card": {"$": ".new-card || .old-card", "title": "h2"}}"#,
+        )
+        .unwrap();
+
+        let result = extract(old, &spec).unwrap();
+        assert_eq!(
+            result["items"],
+            serde_json::json!([{"name": "a"}, {"name": "b"}])
+        );
+        assert_eq!(result["card"]["title"], "Old");
+
+        let result = extract(new, &spec).unwrap();
+        assert_eq!(result["items"], serde_json::json!([{"name": "c"}]));
+        assert_eq!(result["card"]["title"], serde_json::Value::Null);
+
+        let json = spec.to_json();
+        assert_eq!(json["items"][0]["$"], ".new p || .old li");
+        assert_eq!(Spec::from_json(&json).unwrap().to_json(), json);
+        let built = Spec::builder()
+            .scope(".a || .b")
+            .field("t", "h2")
+            .build()
+            .unwrap();
+        assert_eq!(built.to_json()["$"], ".a || .b");
+    }
+
+    #[test]
+    fn chained_sibling_hops() {
+        let html = include_str!("../examples/hn.html");
+        let spec: Spec = serde_json::from_str(
+            r#"[{"$": "tr.athing", "own": "+ .score", "skip": "+2 .score", "chained": "+ + .score", "next": "+3 .rank"}]"#,
+        )
+        .unwrap();
+        let result = extract(html, &spec).unwrap();
+        assert_eq!(result[0]["own"], "156 points");
+        assert_eq!(result[0]["skip"], result[1]["own"]);
+        assert_eq!(result[0]["chained"], result[1]["own"]);
+        assert_eq!(result[0]["next"], "2.");
+
+        assert_eq!(
+            crate::spec::SelectorRef::parse("+ + td")
+                .unwrap()
+                .sibling_hops(),
+            2
+        );
+        assert_eq!(
+            crate::spec::SelectorRef::parse("+2 + td")
+                .unwrap()
+                .sibling_hops(),
+            3
+        );
+        assert_eq!(
+            crate::spec::SelectorRef::parse("td")
+                .unwrap()
+                .sibling_hops(),
+            0
+        );
+        assert!(crate::spec::SelectorRef::parse("+0 td").is_err());
+    }
+
+    #[test]
+    fn one_pass_field_matching_matches_per_field() {
+        let html = include_str!("../examples/hn.html");
+        let fields = [
+            ("title", "span.titleline > a"),
+            ("url", "span.titleline > a | attr:href"),
+            ("site", "span.titleline > span.sitebit | trim"),
+            ("rank", ".rank"),
+            ("vote", ".votelinks a | attr:href"),
+            ("missing", ".does-not-exist"),
+            ("points", "+ .subtext .score"),
+        ];
+
+        let mut wide = serde_json::Map::new();
+        wide.insert("$".into(), "tr.athing".into());
+        for (key, selector) in fields {
+            wide.insert(key.into(), selector.into());
+        }
+        let wide: Spec = serde_json::from_value(serde_json::json!([wide])).unwrap();
+        let items = extract(html, &wide).unwrap();
+        let items = items.as_array().unwrap();
+        assert!(!items.is_empty());
+
+        for (key, selector) in fields {
+            let narrow: Spec =
+                serde_json::from_value(serde_json::json!([{"$": "tr.athing", key: selector}]))
+                    .unwrap();
+            let expected = extract(html, &narrow).unwrap();
+            for (item, expected) in items.iter().zip(expected.as_array().unwrap()) {
+                assert_eq!(item[key], expected[key], "field '{}'", key);
+            }
+        }
+    }
+
+    #[test]
+    fn extraction_limits() {
+        use crate::{ExtractOptions, Limit, LimitExceeded, extract_with_options};
+
+        let html = include_str!("../examples/hn.html");
+        let spec: Spec = serde_json::from_str(r#"[{"$": "*", "text": "$"}]"#).unwrap();
+
+        let options = ExtractOptions::new().max_output_bytes(64 * 1024);
+        let err = extract_with_options(html, &spec, &options).unwrap_err();
+        let limit = err.downcast_ref::<LimitExceeded>().unwrap();
+        assert_eq!(limit.limit, Limit::OutputBytes);
+        assert_eq!(limit.max, 64 * 1024);
+
+        let options = ExtractOptions::new().max_nodes(10);
+        let err = extract_with_options(html, &spec, &options).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<LimitExceeded>().map(|e| e.limit),
+            Some(Limit::Nodes)
+        );
+        assert_eq!(
+            err.to_string(),
+            "Extraction limit exceeded: more than 10 matched nodes"
+        );
+
+        // Generous limits behave like a plain extraction
+        let spec: Spec = serde_json::from_str(include_str!("../examples/hn.json")).unwrap();
+        let options = ExtractOptions::new()
+            .max_output_bytes(1 << 20)
+            .max_nodes(10_000);
+        assert_eq!(
+            extract_with_options(html, &spec, &options).unwrap(),
+            extract(html, &spec).unwrap()
+        );
+    }
+
+    #[test]
+    fn dom_snapshot_round_trip() {
+        let html = include_str!("../examples/hn.html");
+        let spec: Spec = serde_json::from_str(include_str!("../examples/hn.json")).unwrap();
+        let dom = crate::Dom::parse(html).unwrap();
+
+        let mut snapshot = Vec::new();
+        dom.write_snapshot(&mut snapshot).unwrap();
+        let loaded = crate::Dom::read_snapshot(snapshot.as_slice()).unwrap();
+
+        assert_eq!(loaded.extract(&spec).unwrap(), dom.extract(&spec).unwrap());
+        let root = |dom: &crate::Dom| dom.select_one("html").unwrap().unwrap().html().to_string();
+        assert_eq!(root(&loaded), root(&dom));
+
+        assert!(crate::Dom::read_snapshot(&b"<html></html>"[..]).is_err());
+        assert!(crate::Dom::read_snapshot(&snapshot[..snapshot.len() / 2]).is_err());
+    }
+
+    #[test]
+    fn extract_with_stats_reports_fields() {
+        let html = include_str!("../examples/hn.html");
+        let spec: Spec = serde_json::from_str(include_str!("../examples/hn.json")).unwrap();
+        let dom = crate::Dom::parse(html).unwrap();
+
+        let (value, stats) = dom.extract_with_stats(&spec).unwrap();
+        assert_eq!(value, dom.extract(&spec).unwrap());
+
+        let stories = value["stories"].as_array().unwrap().len();
+        let field = |path: &str| stats.fields.iter().find(|f| f.path == path).unwrap();
+        assert_eq!(field("stories").matched, stories);
+        assert_eq!(field("stories.title").calls, stories);
+        assert_eq!(field("stories.title").matched, stories);
+        assert_eq!(field("stories.title").selector, "span.titleline > a");
+        assert!(field("stories").elapsed >= field("stories.title").elapsed);
+        assert!(stats.nodes_matched > stories);
+        assert_eq!(stats.slowest_fields()[0].path, "stories");
+
+        let first_title = field("stories.title").first_match.as_deref().unwrap();
+        assert!(first_title.starts_with("<a href=") && first_title.contains("book deal"));
+        assert!(field("stories").first_match.as_ref().unwrap().len() <= 200);
+
+        // The sample of a huge element stops at a character boundary
+        let big = format!("<main>{}</main>", "é".repeat(1_000_000));
+        let spec: Spec = serde_json::from_str(r#"{"main": "main"}"#).unwrap();
+        let (_, stats) = crate::Dom::parse(&big)
+            .unwrap()
+            .extract_with_stats(&spec)
+            .unwrap();
+        let sample = stats.fields[0].first_match.as_deref().unwrap();
+        assert!(sample.starts_with("<main>é") && sample.len() <= 200 && sample.len() >= 198);
+
+        let missing: Spec = serde_json::from_str(r#"{"x": ".does-not-exist"}"#).unwrap();
+        let (_, stats) = dom.extract_with_stats(&missing).unwrap();
+        assert_eq!(stats.fields[0].matched, 0);
+        assert_eq!(stats.fields[0].first_match, None);
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn url_inputs_are_detected() {
+        use crate::fetch::is_url;
+
+        assert!(is_url("https://example.com"));
+        assert!(is_url("HTTP://example.com/page"));
+        assert!(!is_url("examples/hn.html"));
+        assert!(!is_url("http"));
+        assert!(!is_url("ftp://example.com"));
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn fetcher_builder_validates_headers() {
+        use crate::fetch::HttpFetcher;
+
+        assert!(
+            HttpFetcher::builder()
+                .header("Accept-Language", "en")
+                .cookie("session=abc")
+                .cookie("theme=dark;")
+                .user_agent("test-agent/1.0")
+                .build()
+                .is_ok()
+        );
+        assert!(
+            HttpFetcher::builder()
+                .header("Bad Header", "x")
+                .build()
+                .is_err()
+        );
+        assert!(
+            HttpFetcher::builder()
+                .header("X-Ok", "line\nbreak")
+                .build()
+                .is_err()
+        );
+        assert!(
+            HttpFetcher::builder()
+                .proxy("http://127.0.0.1:3128")
+                .build()
+                .is_ok()
+        );
+        assert!(HttpFetcher::builder().proxy("not a url").build().is_err());
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn http_cache_serves_fresh_entries() {
+        use crate::fetch::{Fetcher, HttpFetcher};
+
+        let dir = std::env::temp_dir().join(format!("html2json-cache-{}", std::process::id()));
+        // Nothing listens on port 9, so only a cache hit can succeed
+        let url = "http://127.0.0.1:9/page";
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let fetcher = HttpFetcher::builder().cache_dir(&dir).build().unwrap();
+        assert!(runtime.block_on(fetcher.fetch(url)).is_err());
+
+        let path = fetcher.cache_path(url).unwrap();
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(&path, "<p>cached</p>").unwrap();
+        assert_eq!(
+            runtime.block_on(fetcher.fetch(url)).unwrap().body,
+            "<p>cached</p>"
+        );
+
+        // Different headers or --refresh bypass the entry
+        let other = HttpFetcher::builder()
+            .cache_dir(&dir)
+            .header("Accept-Language", "de")
+            .build()
+            .unwrap();
+        assert_ne!(other.cache_path(url).unwrap(), path);
+        let refresh = HttpFetcher::builder()
+            .cache_dir(&dir)
+            .refresh(true)
+            .build()
+            .unwrap();
+        assert!(runtime.block_on(refresh.fetch(url)).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn fetch_and_extract_many() {
+        use crate::fetch::{Fetcher, HttpFetcher};
+
+        let dir = std::env::temp_dir().join(format!("html2json-batch-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let fetcher = HttpFetcher::builder().cache_dir(&dir).build().unwrap();
+        // Served from the cache; nothing listens on port 9
+        let urls = [
+            "http://127.0.0.1:9/a",
+            "http://127.0.0.1:9/missing",
+            "http://127.0.0.1:9/b",
+        ];
+        for (url, title) in [(urls[0], "A"), (urls[2], "B")] {
+            let html = format!("<h1>{}</h1>", title);
+            std::fs::write(fetcher.cache_path(url).unwrap(), html).unwrap();
+        }
+
+        let spec: Spec = serde_json::from_str(r#"{"title": "h1"}"#).unwrap();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let results = runtime.block_on(fetcher.fetch_and_extract_all(urls, &spec, 2));
+        assert_eq!(results.len(), 3);
+        assert_eq!(
+            results[0].as_ref().unwrap(),
+            &serde_json::json!({"title": "A"})
+        );
+        assert!(results[1].is_err());
+        assert_eq!(
+            results[2].as_ref().unwrap(),
+            &serde_json::json!({"title": "B"})
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn custom_fetcher() {
+        use crate::fetch::{FetchedDoc, Fetcher};
+
+        #[derive(Clone)]
+        struct Stub;
+
+        impl Fetcher for Stub {
+            async fn fetch(&self, url: &str) -> anyhow::Result<FetchedDoc> {
+                let page = url.rsplit('/').next().unwrap_or_default();
+                Ok(FetchedDoc {
+                    url: url.to_string(),
+                    body: format!("<h1>{}</h1>", page),
+                })
+            }
+        }
+
+        let spec: Spec = serde_json::from_str(r#"{"title": "h1"}"#).unwrap();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let result = runtime.block_on(Stub.fetch_and_extract("stub://pages/one", &spec));
+        assert_eq!(result.unwrap(), serde_json::json!({"title": "one"}));
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn robots_txt_rules() {
+        use crate::fetch::robots::Robots;
+        use std::time::Duration;
+
+        let txt = "User-agent: *\nDisallow: /private/\nAllow: /private/ok\nDisallow: /*.pdf$\n\n\
+                   # Our own group\nUser-agent: html2json\nDisallow: /search\nCrawl-delay: 2\n";
+        let ours = Robots::parse(txt, "html2json/0.5");
+        assert!(!ours.allows("/search?q=x"));
+        assert!(ours.allows("/private/page"));
+        assert_eq!(ours.crawl_delay, Some(Duration::from_secs(2)));
+
+        let other = Robots::parse(txt, "Mozilla/5.0");
+        assert!(!other.allows("/private/page"));
+        assert!(other.allows("/private/ok"));
+        assert!(!other.allows("/docs/a.pdf"));
+        assert!(other.allows("/docs/a.pdf?download"));
+        assert!(other.allows("/search"));
+
+        assert!(Robots::parse("User-agent: *\nDisallow:\n", "x").allows("/"));
+
+        // Only the whole product token matches, in any case
+        let txt = "User-agent: json\nUser-agent: h\nDisallow: /\n\n\
+                   User-agent: *\nDisallow: /private/\n";
+        let ours = Robots::parse(txt, "html2json/0.5");
+        assert!(ours.allows("/page"));
+        assert!(!ours.allows("/private/page"));
+        let ours = Robots::parse("User-agent: HTML2JSON\nDisallow: /\n", "html2json/0.5");
+        assert!(!ours.allows("/page"));
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn retry_delays() {
+        use crate::fetch::{backoff, retry_after};
+        use reqwest::header::{HeaderMap, HeaderValue, RETRY_AFTER};
+        use std::time::Duration;
+
+        let first = backoff(0);
+        assert!(first >= Duration::from_millis(250) && first <= Duration::from_millis(500));
+        assert!(backoff(3) >= Duration::from_secs(2));
+        assert!(backoff(30) <= Duration::from_secs(60));
+
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after(&headers), None);
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("7"));
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(7)));
+        headers.insert(
+            RETRY_AFTER,
+            HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"),
+        );
+        assert_eq!(retry_after(&headers), Some(Duration::ZERO));
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn resolves_relative_links() {
+        use crate::fetch::resolve_url;
+
+        let base = "https://example.com/news/page/1?sort=new";
+        assert_eq!(
+            resolve_url(base, "2").unwrap(),
+            "https://example.com/news/page/2"
+        );
+        assert_eq!(
+            resolve_url(base, "/news?p=2#top").unwrap(),
+            "https://example.com/news?p=2"
+        );
+        assert_eq!(
+            resolve_url(base, "https://other.org/x").unwrap(),
+            "https://other.org/x"
+        );
+        assert!(resolve_url("not a url", "x").is_err());
+    }
+
+    #[test]
+    fn pipe_text_stays_borrowed() {
+        use crate::pipe::{PipeValue, apply_pipe_value};
+        use crate::spec::PipeCommand;
+        use std::borrow::Cow;
+
+        let input = "  Hello World  ";
+        let value = [PipeCommand::Trim, PipeCommand::Substr(0, Some(5))]
+            .iter()
+            .try_fold(PipeValue::borrowed(input), apply_pipe_value)
+            .unwrap();
+        assert!(matches!(value, PipeValue::Text(Cow::Borrowed("Hello"))));
+
+        let value = apply_pipe_value(value, &PipeCommand::Upper).unwrap();
+        assert_eq!(value.into_json(), serde_json::json!("HELLO"));
+    }
+
+    #[test]
+    fn json_ld_items() {
+        let html = r#"<html><head>
+            <script type="application/ld+json">
+                {"@context": "https://schema.org", "@graph": [
+                    {"@type": "WebSite", "name": "Shop"},
+                    {"@type": ["Product", "Thing"], "name": "Lamp"}
+                ]}
+            </script>
+            <script type="application/ld+json">[{"@type": "https://schema.org/Product", "name": "Desk"}]</script>
+            <script type="application/ld+json">{not json</script>
+        </head><body><div class="item"></div></body></html>"#;
+        let spec: Spec = serde_json::from_str(
+            r##"{
+                "all": "$jsonld",
+                "products": "$jsonld:Product",
+                "item": {"$": ".item", "products": "$jsonld:Product"}
+            }"##,
+        )
+        .unwrap();
+        let result = extract(html, &spec).unwrap();
+        assert_eq!(result["all"].as_array().unwrap().len(), 3);
+        assert_eq!(result["products"][0]["name"], "Lamp");
+        assert_eq!(result["products"][1]["name"], "Desk");
+        assert_eq!(result["item"]["products"], result["products"]);
+        assert_eq!(spec.to_json()["products"], "$jsonld:Product");
+
+        let dom = crate::Dom::parse(html).unwrap();
+        assert_eq!(dom.json_ld(None).len(), 3);
+        assert_eq!(dom.json_ld(Some("WebSite"))[0]["name"], "Shop");
+        assert!(Spec::from_json(&serde_json::json!({"x": "$jsonld:"})).is_err());
+    }
+
+    #[test]
+    fn microdata_items() {
+        let html = r#"
+            <div class="card" itemscope itemtype="https://schema.org/Product" itemid="lamp" itemref="extra">
+                <h2 itemprop="name"> Lamp </h2>
+                <img itemprop="image" src="lamp.png">
+                <span itemprop="color">red</span><span itemprop="color">blue</span>
+                <div itemprop="offers" itemscope itemtype="https://schema.org/Offer">
+                    <meta itemprop="price" content="25.00">
+                    <span itemprop="name">Offer name</span>
+                </div>
+            </div>
+            <p id="extra" itemprop="description">Bright</p>
+            <div itemscope itemtype="https://schema.org/Person"><span itemprop="name">Ann</span></div>"#;
+        let spec: Spec = serde_json::from_str(
+            r##"{
+                "all": "$microdata",
+                "products": "$microdata:Product",
+                "card": {"$": ".card", "items": "$microdata"}
+            }"##,
+        )
+        .unwrap();
+        let result = extract(html, &spec).unwrap();
+        assert_eq!(result["all"].as_array().unwrap().len(), 2);
+        assert_eq!(
+            result["products"],
+            serde_json::json!([{
+                "@type": "Product",
+                "@id": "lamp",
+                "name": "Lamp",
+                "image": "lamp.png",
+                "color": ["red", "blue"],
+                "offers": {"@type": "Offer", "price": "25.00", "name": "Offer name"},
+                "description": "Bright"
+            }])
+        );
+        assert_eq!(result["card"]["items"], result["products"]);
+        assert_eq!(spec.to_json()["products"], "$microdata:Product");
+
+        let dom = crate::Dom::parse(html).unwrap();
+        assert_eq!(dom.microdata(Some("Person"))[0]["name"], "Ann");
+    }
+
+    #[test]
+    fn metadata_preset() {
+        let html = r#"<head><title> Lamp </title>
+            <meta name="description" content="A lamp">
+            <link rel="shortcut icon" href="/favicon.ico">
+            <meta property="og:title" content="Lamp!">
+            <meta name="twitter:card" content="summary">
+        </head>"#;
+        let meta = crate::Dom::parse(html).unwrap().metadata().unwrap();
+        assert_eq!(
+            meta,
+            serde_json::json!({
+                "title": "Lamp",
+                "description": "A lamp",
+                "favicon": "/favicon.ico",
+                "og": {"title": "Lamp!"},
+                "twitter": {"card": "summary"}
+            })
+        );
+        assert_eq!(
+            extract(HTML, &crate::presets::metadata()).unwrap(),
+            serde_json::json!({"title": "Hacker News", "favicon": "y18.svg"})
+        );
+    }
+
+    #[cfg(feature = "feed")]
+    #[test]
+    fn feeds() {
+        let rss = r#"<?xml version="1.0"?>
+            <rss version="2.0" xmlns:dc="http://purl.org/dc/elements/1.1/"><channel>
+                <title>News</title><link>https://example.com/</link>
+                <item>
+                    <title>First</title><link>https://example.com/1</link>
+                    <dc:creator>Ann</dc:creator>
+                    <description><![CDATA[<p>Short</p>]]></description>
+                    <category>a</category><category>b</category>
+                    <enclosure url="https://example.com/1.mp3" type="audio/mpeg"/>
+                </item>
+            </channel></rss>"#;
+        let feed = crate::feed::parse(rss).unwrap();
+        assert_eq!(
+            serde_json::to_value(&feed).unwrap(),
+            serde_json::json!({
+                "format": "rss",
+                "title": "News",
+                "link": "https://example.com/",
+                "items": [{
+                    "title": "First",
+                    "link": "https://example.com/1",
+                    "author": "Ann",
+                    "summary": "<p>Short</p>",
+                    "categories": ["a", "b"],
+                    "enclosures": ["https://example.com/1.mp3"]
+                }]
+            })
+        );
+
+        let atom = r#"<feed xmlns="http://www.w3.org/2005/Atom">
+            <title>Blog</title>
+            <link rel="self" href="https://example.com/feed"/><link href="https://example.com/"/>
+            <entry>
+                <title>Post</title><id>urn:1</id><link href="https://example.com/p"/>
+                <author><name>Bo</name></author>
+                <content type="xhtml"><div xmlns="http://www.w3.org/1999/xhtml">Hi</div></content>
+            </entry>
+        </feed>"#;
+        let feed = crate::feed::parse(atom).unwrap();
+        assert_eq!(feed.format, "atom");
+        assert_eq!(feed.link.as_deref(), Some("https://example.com/"));
+        let entry = &feed.items[0];
+        assert_eq!(entry.link.as_deref(), Some("https://example.com/p"));
+        assert_eq!(entry.author.as_deref(), Some("Bo"));
+        assert_eq!(
+            entry.content.as_deref(),
+            Some(r#"<div xmlns="http://www.w3.org/1999/xhtml">Hi</div>"#)
+        );
+
+        assert!(crate::feed::parse("<html><body></body></html>").is_err());
+    }
+
+    #[cfg(feature = "article")]
+    #[test]
+    fn article_content() {
+        let html = r#"<head><title>Lamps | Blog</title>
+            <meta name="author" content="Ann">
+            <meta property="article:published_time" content="2026-10-01">
+        </head>
+        <nav><a href="/">Home</a> <a href="/blog">All the posts on this blog, by date</a></nav>
+        <div class="post">
+            <h1>Lamps</h1>
+            <p class="byline">By Ann</p>
+            <p>Lamps turn electricity into light, in homes, offices and streets.</p>
+            <div class="share"><a href="/share">Share this post with your friends</a></div>
+            <p>LEDs emit light from a semiconductor, so they stay cool for years.</p>
+            <script>track()</script>
+        </div>
+        <aside><p>Subscribe to the newsletter, it is free, and it is weekly.</p></aside>"#;
+        let dom = crate::Dom::parse(html).unwrap();
+        let article = dom.article();
+        assert_eq!(article.title.as_deref(), Some("Lamps"));
+        assert_eq!(article.byline.as_deref(), Some("Ann"));
+        assert_eq!(article.published.as_deref(), Some("2026-10-01"));
+        assert_eq!(
+            article.text,
+            "Lamps\n\nLamps turn electricity into light, in homes, offices and streets.\n\n\
+             LEDs emit light from a semiconductor, so they stay cool for years."
+        );
+        assert!(article.html.contains("<h1>Lamps</h1>"));
+        assert!(!article.html.contains("share") && !article.html.contains("script"));
+
+        // Article fields alongside ordinary ones
+        let spec =
+            Spec::from_json(&serde_json::json!({"title": "$article:title", "home": "nav a"}))
+                .unwrap();
+        assert_eq!(
+            dom.extract(&spec).unwrap(),
+            serde_json::json!({"title": "Lamps", "home": "Home"})
+        );
+        assert!(Spec::from_json(&serde_json::json!({"x": "$article:bogus"})).is_err());
+    }
+
+    #[test]
+    fn raw_text_pipe_keeps_code_whitespace() {
+        let html = "<pre>  if x {\n    <span>y</span>();<br>  }</pre>\
+                    <code class=hl><div class=line>a = 1</div><div class=line>  b = 2</div></code>";
+        let spec: Spec = serde_json::from_str(
+            r#"{"pre": "pre | rawText", "lines": "code | rawText", "text": "code"}"#,
+        )
+        .unwrap();
+        let result = extract(html, &spec).unwrap();
+        assert_eq!(result["pre"], "  if x {\n    y();\n  }");
+        assert_eq!(result["lines"], "a = 1\n  b = 2\n");
+        assert_eq!(result["text"], "a = 1  b = 2");
+        assert_eq!(spec.to_json()["pre"], "pre | rawText");
+
+        // Written after another pipe, rawText keeps the value so far
+        let spec: Spec = serde_json::from_str(r#"{"code": "code | upper | rawText"}"#).unwrap();
+        assert_eq!(extract(html, &spec).unwrap()["code"], "A = 1  B = 2");
+    }
+
+    #[test]
+    fn lines_pipe_splits_at_breaks() {
+        let html = r#"<p class="address">1 Main
+            St<br><br>  Springfield <b>IL</b><br></p>
+            <ul class="hours"><li>Mon 9-5</li><li>Tue 9-5</li></ul>
+            <span data-lines="a
+b"></span>"#;
+        let spec: Spec = serde_json::from_str(
+            r#"{"address": ".address | lines", "hours": ".hours | lines", "attr": "span | attr:data-lines | lines"}"#,
+        )
+        .unwrap();
+        let result = extract(html, &spec).unwrap();
+        assert_eq!(
+            result["address"],
+            serde_json::json!(["1 Main St", "Springfield IL"])
+        );
+        assert_eq!(result["hours"], serde_json::json!(["Mon 9-5", "Tue 9-5"]));
+        assert_eq!(result["attr"], serde_json::json!(["a", "b"]));
+
+        // Written after another pipe, lines splits the text so far, where
+        // the <br> no longer shows
+        let spec: Spec = serde_json::from_str(r#"{"d": ".a | upper | lines"}"#).unwrap();
+        let result = extract(r#"<div class="a">Line 1<br>Line 2</div>"#, &spec).unwrap();
+        assert_eq!(result["d"], serde_json::json!(["LINE 1LINE 2"]));
+    }
+
+    #[cfg(feature = "url")]
+    #[test]
+    fn images_construct() {
+        use crate::{Dom, ExtractOptions, extract_with_options};
+
+        let html = r#"<img src="/logo.png" alt="Logo">
+            <figure class="g"><picture>
+                <source srcset="a.webp 1x, a@2x.webp 2x" type="image/webp">
+                <img data-src="a.jpg" width="640" height="480px" alt="">
+            </picture></figure>"#;
+        let spec: Spec =
+            serde_json::from_str(r#"{"gallery": [{"$": ".g", "images": "$images"}]}"#).unwrap();
+        let options = ExtractOptions::new().base_url("https://example.com/photos/");
+        let result = extract_with_options(html, &spec, &options).unwrap();
+        assert_eq!(
+            result["gallery"][0]["images"],
+            serde_json::json!([
+                {"srcset": [
+                    {"url": "https://example.com/photos/a.webp", "descriptor": "1x"},
+                    {"url": "https://example.com/photos/a@2x.webp", "descriptor": "2x"},
+                ]},
+                {"src": "https://example.com/photos/a.jpg", "alt": "", "width": 640, "height": 480},
+            ])
+        );
+
+        let dom = Dom::parse(r#"<base href="https://cdn.example/"><img src="x.png">"#).unwrap();
+        assert_eq!(dom.images(None)[0]["src"], "https://cdn.example/x.png");
+        let dom = Dom::parse(html).unwrap();
+        assert_eq!(dom.images(None)[0]["src"], "/logo.png");
+        assert_eq!(spec.to_json()["gallery"][0]["images"], "$images");
+    }
+
+    #[cfg(feature = "url")]
+    #[test]
+    fn links_construct() {
+        use crate::spec::LinkFilter;
+        use crate::{Dom, ExtractOptions, extract_with_options};
+
+        let html = r#"<nav><a href="/about">About
+            us</a></nav>
+            <main><a href="https://other.org/x" rel="nofollow ugc">Other</a>
+            <a href="mailto:me@example.com">Mail</a><a name="anchor">No href</a></main>"#;
+        let spec: Spec = serde_json::from_str(
+            r#"{"all": "$links", "internal": "$links:internal", "main": {"$": "main", "external": "$links:external"}}"#,
+        )
+        .unwrap();
+        let options = ExtractOptions::new().base_url("https://example.com/blog/");
+        let result = extract_with_options(html, &spec, &options).unwrap();
+        assert_eq!(result["all"].as_array().unwrap().len(), 3);
+        assert_eq!(
+            result["internal"],
+            serde_json::json!([{"href": "https://example.com/about", "text": "About us", "nofollow": false}])
+        );
+        assert_eq!(
+            result["main"]["external"],
+            serde_json::json!([{"href": "https://other.org/x", "text": "Other", "rel": "nofollow ugc", "nofollow": true}])
+        );
+        assert_eq!(spec.to_json()["internal"], "$links:internal");
+        assert!(Spec::from_json(&serde_json::json!({"l": "$links:outbound"})).is_err());
+
+        let dom = Dom::parse(html).unwrap();
+        let internal = dom.links(None, Some(LinkFilter::Internal));
+        assert_eq!(internal.len(), 1);
+        assert_eq!(internal[0]["href"], "/about");
+    }
+
+    #[test]
+    fn outline_construct() {
+        let html = r#"<h1 id="top">Guide</h1><h2>Install</h2><h4>From source</h4>
+            <h2 id="use">Use</h2><h3>CLI</h3><h1>Appendix</h1>"#;
+        let spec: Spec = serde_json::from_str(r#"{"toc": "$outline"}"#).unwrap();
+        let result = extract(html, &spec).unwrap();
+        let entry = |level: u8, text: &str, children: serde_json::Value| serde_json::json!({"level": level, "text": text, "children": children});
+        let mut guide = entry(
+            1,
+            "Guide",
+            serde_json::json!([
+                entry(
+                    2,
+                    "Install",
+                    serde_json::json!([entry(4, "From source", serde_json::json!([]))])
+                ),
+                entry(
+                    2,
+                    "Use",
+                    serde_json::json!([entry(3, "CLI", serde_json::json!([]))])
+                ),
+            ]),
+        );
+        guide["id"] = "top".into();
+        guide["children"][1]["id"] = "use".into();
+        assert_eq!(
+            result["toc"],
+            serde_json::json!([guide, entry(1, "Appendix", serde_json::json!([]))])
+        );
+        assert_eq!(crate::Dom::parse(html).unwrap().outline(), result["toc"]);
+    }
+
+    #[cfg(feature = "url")]
+    #[test]
+    fn breadcrumbs_construct() {
+        let spec: Spec = serde_json::from_str(r#"{"crumbs": "$breadcrumbs"}"#).unwrap();
+        let trail = serde_json::json!([
+            {"name": "Home", "url": "https://shop.example/"},
+            {"name": "Lamps", "url": "https://shop.example/lamps"},
+            {"name": "Arc lamp"},
+        ]);
+
+        let json_ld = r#"<script type="application/ld+json">{"@type": "BreadcrumbList", "itemListElement": [
+            {"@type": "ListItem", "position": 2, "name": "Lamps", "item": "https://shop.example/lamps"},
+            {"@type": "ListItem", "position": 1, "name": "Home", "item": {"@id": "https://shop.example/"}},
+            {"@type": "ListItem", "position": 3, "name": "Arc lamp"}]}</script>
+            <nav aria-label="Breadcrumb"><a href="/">Ignored</a></nav>"#;
+        assert_eq!(extract(json_ld, &spec).unwrap()["crumbs"], trail);
+
+        let microdata = r#"<base href="https://shop.example/"><ol itemscope itemtype="https://schema.org/BreadcrumbList">
+            <li itemprop="itemListElement" itemscope itemtype="https://schema.org/ListItem">
+              <a itemprop="item" href="/"><span itemprop="name">Home</span></a><meta itemprop="position" content="1"></li>
+            <li itemprop="itemListElement" itemscope itemtype="https://schema.org/ListItem">
+              <a itemprop="item" href="lamps"><span itemprop="name">Lamps</span></a><meta itemprop="position" content="2"></li>
+            <li itemprop="itemListElement" itemscope itemtype="https://schema.org/ListItem">
+              <span itemprop="name">Arc lamp</span><meta itemprop="position" content="3"></li></ol>"#;
+        assert_eq!(extract(microdata, &spec).unwrap()["crumbs"], trail);
+
+        let markup = r#"<nav aria-label="Breadcrumb"><ol class="crumbs">
+            <li><a href="/">Home</a></li><li><a href="/lamps">Lamps</a></li><li>Arc
+            lamp</li></ol></nav>"#;
+        let options = crate::ExtractOptions::new().base_url("https://shop.example/lamps/arc");
+        let result = crate::extract_with_options(markup, &spec, &options).unwrap();
+        assert_eq!(result["crumbs"], trail);
+        assert_eq!(
+            extract("<p>none</p>", &spec).unwrap()["crumbs"],
+            serde_json::json!([])
+        );
+    }
+
+    #[cfg(feature = "url")]
+    #[test]
+    fn pagination_construct() {
+        let spec: Spec = serde_json::from_str(r#"{"pages": "$pagination"}"#).unwrap();
+        let html = r#"<link rel="canonical" href="/list?page=2"><link rel="prev" href="/list?page=1">
+            <nav aria-label="Pagination"><a href="?page=1">1</a> <span aria-current="page">2</span>
+            <a href="?page=3">3</a> <a href="?page=3" aria-label="Next page">›</a></nav>
+            <p class="comments"><a href="/c">12</a></p>"#;
+        let options = crate::ExtractOptions::new().base_url("https://shop.example/list?page=2");
+        let result = crate::extract_with_options(html, &spec, &options).unwrap();
+        assert_eq!(
+            result["pages"],
+            serde_json::json!({
+                "next": "https://shop.example/list?page=3",
+                "prev": "https://shop.example/list?page=1",
+                "canonical": "https://shop.example/list?page=2",
+                "current": 2,
+                "pages": [
+                    {"number": 1, "url": "https://shop.example/list?page=1"},
+                    {"number": 3, "url": "https://shop.example/list?page=3"},
+                ],
+            })
+        );
+
+        let numbered = r#"<div class="pager"><a href="/p/1">1</a><b class="current">2</b><a href="/p/3">3</a></div>"#;
+        let pagination = crate::Dom::parse(numbered).unwrap().pagination(None);
+        assert_eq!(pagination["next"], "/p/3");
+        assert_eq!(pagination["prev"], "/p/1");
+        assert_eq!(
+            crate::Dom::parse("<p>x</p>").unwrap().pagination(None),
+            serde_json::json!({"pages": []})
+        );
+    }
+
+    #[cfg(feature = "url")]
+    #[test]
+    fn form_construct() {
+        let html = r#"<form id="login" method="post" action="/login"><input name="user"></form>
+            <form id="search" action="search">
+              <input type="hidden" name="csrf" value="t0k">
+              <input name="q" value="lamps" required>
+              <input type="checkbox" name="stock" checked>
+              <select name="sort"><option value="new">Newest</option><option>Price</option></select>
+              <textarea name="note">hi</textarea>
+              <button>Go</button>
+            </form>"#;
+        let spec: Spec =
+            serde_json::from_str(r#"{"search": "$form:form#search", "first": "$form"}"#).unwrap();
+        let options = crate::ExtractOptions::new().base_url("https://shop.example/lamps/");
+        let result = crate::extract_with_options(html, &spec, &options).unwrap();
+        assert_eq!(
+            result["search"],
+            serde_json::json!({
+                "action": "https://shop.example/lamps/search",
+                "method": "get",
+                "enctype": "application/x-www-form-urlencoded",
+                "fields": [
+                    {"name": "csrf", "type": "hidden", "value": "t0k"},
+                    {"name": "q", "type": "text", "value": "lamps"},
+                    {"name": "stock", "type": "checkbox", "value": "on", "checked": true},
+                    {"name": "sort", "type": "select-one", "value": "new", "options": [
+                        {"value": "new", "text": "Newest", "selected": false},
+                        {"value": "Price", "text": "Price", "selected": false},
+                    ]},
+                    {"name": "note", "type": "textarea", "value": "hi"},
+                ],
+            })
+        );
+        assert_eq!(result["first"]["method"], "post");
+        assert_eq!(spec.to_json()["search"], "$form:form#search");
+        assert_eq!(
+            crate::Dom::parse(html).unwrap().forms(None)[0]["action"],
+            "/login"
+        );
+    }
+
+    #[test]
+    fn script_contents_parse_as_json() {
+        let html = r#"<!DOCTYPE html><html><head>
+            <script id="__NEXT_DATA__" type="application/json">
+              {"props": {"page": "<b>home</b>", "ids": [1, 2]}}
+            </script><style>p > a { color: red }</style></head><body></body></html>"#;
+        let spec: Spec = serde_json::from_str(
+            r#"{"config": "script#__NEXT_DATA__ | parseAs:json", "css": "style"}"#,
+        )
+        .unwrap();
+        let result = extract(html, &spec).unwrap();
+        assert_eq!(
+            result["config"],
+            serde_json::json!({"props": {"page": "<b>home</b>", "ids": [1, 2]}})
+        );
+        assert_eq!(result["css"], "p > a { color: red }");
+        assert_eq!(
+            spec.to_json()["config"],
+            "script#__NEXT_DATA__ | parseAs:json"
+        );
+
+        let bad: Spec = serde_json::from_str(r#"{"x": "p | parseAs:json"}"#).unwrap();
+        assert!(extract("<p>{oops</p>", &bad).is_err());
+    }
+
+    #[test]
+    fn has_attr_pipe() {
+        let html = r#"<button class="buy" disabled>Buy</button><input class="q" value="">"#;
+        let spec: Spec = serde_json::from_str(
+            r#"{"disabled": ".buy | hasAttr:disabled", "hidden": ".buy | hasAttr:hidden",
+                "empty": ".q | attr:value", "missing": ".q | attr:placeholder",
+                "gone": ".sold-out | hasAttr:disabled"}"#,
+        )
+        .unwrap();
+        let result = extract(html, &spec).unwrap();
+        assert_eq!(result["disabled"], true);
+        assert_eq!(result["hidden"], false);
+        assert_eq!(result["empty"], "");
+        assert_eq!(result["missing"], serde_json::Value::Null);
+        assert_eq!(result["gone"], serde_json::Value::Null);
+        assert_eq!(spec.to_json()["disabled"], ".buy | hasAttr:disabled");
+
+        // Only the first pipe sees the element
+        for late in [".q | attr:value | hasAttr:x", ".q | trim | hasAttr:x"] {
+            let err = Spec::from_json(&serde_json::json!({ "x": late })).unwrap_err();
+            assert!(err.to_string().contains("must be the first pipe"), "{err}");
+        }
+    }
+
+    #[test]
+    fn class_pipes() {
+        let html = r#"<li class="product  in-stock featured">Lamp</li><li class="product">Rug</li>
+            <p data-tags="a b">x</p>"#;
+        let spec: Spec = serde_json::from_str(
+            r#"[{"$": "li", "classes": "$ | classes", "in_stock": "$ | hasClass:in-stock",
+                 "stock": "$ | hasClass:in"}]"#,
+        )
+        .unwrap();
+        let result = extract(html, &spec).unwrap();
+        assert_eq!(
+            result[0]["classes"],
+            serde_json::json!(["product", "in-stock", "featured"])
+        );
+        assert_eq!(result[0]["in_stock"], true);
+        assert_eq!(result[0]["stock"], false);
+        assert_eq!(result[1]["in_stock"], false);
+
+        let tags: Spec =
+            serde_json::from_str(r#"{"tags": "p | attr:data-tags | classes"}"#).unwrap();
+        assert_eq!(
+            extract(html, &tags).unwrap()["tags"],
+            serde_json::json!(["a", "b"])
+        );
+        assert_eq!(spec.to_json()[0]["in_stock"], "$ | hasClass:in-stock");
+
+        // Written after another pipe, they split the value so far
+        let spec: Spec = serde_json::from_str(
+            r#"{"words": "li | upper | classes", "lamp": "li | lower | hasClass:lamp"}"#,
+        )
+        .unwrap();
+        let result = extract(html, &spec).unwrap();
+        assert_eq!(result["words"], serde_json::json!(["LAMP"]));
+        assert_eq!(result["lamp"], true);
+    }
+
+    #[test]
+    fn style_pipe() {
+        let html = r#"<div class="hero" style="color: red; Background-Image: url('/img/a;b.jpg') !important">x</div>
+            <div class="plain" style="background-image: none" data-css="width: 10px">y</div>"#;
+        let spec: Spec = serde_json::from_str(
+            r#"{"image": ".hero | style:background-image:url", "raw": ".hero | style:background-image",
+                "color": ".hero | style:color", "margin": ".hero | style:margin",
+                "none": ".plain | style:background-image:url", "width": ".plain | attr:data-css | style:width"}"#,
+        )
+        .unwrap();
+        let result = extract(html, &spec).unwrap();
+        assert_eq!(result["image"], "/img/a;b.jpg");
+        assert_eq!(result["raw"], "url('/img/a;b.jpg')");
+        assert_eq!(result["color"], "red");
+        assert_eq!(result["margin"], serde_json::Value::Null);
+        assert_eq!(result["none"], serde_json::Value::Null);
+        assert_eq!(result["width"], "10px");
+        assert_eq!(
+            spec.to_json()["image"],
+            ".hero | style:background-image:url"
+        );
 
-pub use dom::Dom;
-pub use spec::Spec;
+        // Written after another pipe, style reads the value so far
+        let spec: Spec = serde_json::from_str(r#"{"width": "p | lower | style:width"}"#).unwrap();
+        let result = extract("<p>Width: 10PX</p>", &spec).unwrap();
+        assert_eq!(result["width"], "10px");
+    }
 
-use anyhow::Result;
+    #[test]
+    fn datetime_pipe() {
+        use crate::pipe::normalize_datetime;
 
-/// Extract JSON from HTML using a spec
-///
-/// # Arguments
-///
-/// * `html` - The HTML source to parse
-/// * `spec` - The extraction specification
-///
-/// # Example
-///
-/// ```
-/// use html2json::{extract, Spec};
-///
-/// let html = r#"<div class="item"><span>Price: $25.00</span></div>"#;
-/// let spec_json = r#"{"price": ".item span | regex:\\$(\\d+\\.\\d+)"}"#;
-/// let spec: Spec = serde_json::from_str(spec_json)?;
-/// let result = extract(html, &spec)?;
-/// assert_eq!(result["price"], "25.00");
-/// # Ok::<(), anyhow::Error>(())
-/// ```
-pub fn extract(html: &str, spec: &Spec) -> Result<serde_json::Value> {
-    let dom = Dom::parse(html)?;
-    dom.extract(spec)
-}
+        let html = r#"<time datetime="2024-03-05T10:00:00+01:00">5 March</time>
+            <span class="posted">March 5, 2024</span><span class="never">soon</span>"#;
+        let spec: Spec = serde_json::from_str(
+            r#"{"time": "time | datetime", "text": "time | trim", "posted": ".posted | datetime",
+                "never": ".never | datetime"}"#,
+        )
+        .unwrap();
+        let result = extract(html, &spec).unwrap();
+        assert_eq!(result["time"], "2024-03-05T10:00:00+01:00");
+        assert_eq!(result["text"], "5 March");
+        assert_eq!(result["posted"], "2024-03-05");
+        assert_eq!(result["never"], serde_json::Value::Null);
 
-#[cfg(test)]
-mod tests {
-    use crate::extract;
-    use crate::spec::Spec;
-    const HTML: &str = include_str!("../examples/hn.html");
+        // Written after another pipe, datetime parses the text so far
+        #[cfg(feature = "regex")]
+        {
+            let spec: Spec = serde_json::from_str(
+                r#"{"d": "p | regex:(\\d{4}-\\d\\d-\\d\\d) | datetime",
+                    "t": "time | substr:0:7 | datetime"}"#,
+            )
+            .unwrap();
+            let html = "<p>Posted: 2024-03-05</p><time datetime=\"2024-03-05\">5 March</time>";
+            let result = extract(html, &spec).unwrap();
+            assert_eq!(result["d"], "2024-03-05");
+            assert_eq!(result["t"], serde_json::Value::Null);
+        }
+
+        for (input, expected) in [
+            ("2024-03-05T09:00:00.5Z", "2024-03-05T09:00:00.500Z"),
+            ("2024-03-05 10:00", "2024-03-05T10:00:00"),
+            ("2024-03-05 10:00 +0100", "2024-03-05T10:00:00+01:00"),
+            ("2024-03-05T10:00Z", "2024-03-05T10:00:00Z"),
+            ("Tue, 05 Mar 2024 10:00:00 GMT", "2024-03-05T10:00:00Z"),
+            ("5 Mar 2024", "2024-03-05"),
+            ("2024/03/05", "2024-03-05"),
+        ] {
+            assert_eq!(
+                normalize_datetime(input).as_deref(),
+                Some(expected),
+                "{input}"
+            );
+        }
+    }
 
     #[test]
-    fn basic_text_extraction() {
+    fn digits_pipe() {
+        use crate::pipe::apply_pipes;
+        use crate::spec::PipeCommand;
+
+        let int = [PipeCommand::Digits, PipeCommand::ParseAsInt];
+        let float = [PipeCommand::Digits, PipeCommand::ParseAsFloat];
+        assert_eq!(apply_pipes("≈ 12\u{202f}345 users", &int).unwrap(), 12345);
+        assert_eq!(apply_pipes("1,234,567", &int).unwrap(), 1234567);
+        assert_eq!(apply_pipes("\u{2212}4.5 °C", &float).unwrap(), -4.5);
+        assert_eq!(apply_pipes("+3 votes", &int).unwrap(), 3);
+        assert_eq!(
+            apply_pipes("no data", &[PipeCommand::Digits]).unwrap(),
+            serde_json::Value::Null
+        );
+
+        let spec: Spec = serde_json::from_str(r#"{"users": "p | digits | parseAs:int"}"#).unwrap();
+        assert_eq!(
+            extract("<p>12 345 users</p>", &spec).unwrap()["users"],
+            12345
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "regex")]
+    fn regex_flags() {
+        let html = "<p>PRICE: 5</p><pre>a\nb</pre>";
         let spec: Spec = serde_json::from_str(
-            r##"{
-                "$": "title",
-                "title": "$"
-            }"##,
+            r#"{"price": "p | regexi:price: (\\d+)", "span": "pre | regex/s:(a.b)", "none": "pre | regex:(a.b)"}"#,
         )
         .unwrap();
-        let result = extract(HTML, &spec).unwrap();
-        assert_eq!(result["title"], "Hacker News");
+        assert_eq!(
+            extract(html, &spec).unwrap(),
+            serde_json::json!({"price": "5", "span": "a\nb", "none": null})
+        );
+
+        let spec: Spec =
+            serde_json::from_str(r#"{"a": "p | regexi:(\\d+)", "b": "p | regex/ms:^(x)"}"#)
+                .unwrap();
+        assert_eq!(
+            spec.to_json(),
+            serde_json::json!({"a": "p | regexi:(\\d+)", "b": "p | regex/ms:^(x)"})
+        );
+        let err = serde_json::from_str::<Spec>(r#"{"n": "h1 | regex/q:x"}"#).unwrap_err();
+        assert!(err.to_string().contains("Unknown regex flag"), "{err}");
     }
 
     #[test]
-    fn attribute_extraction() {
+    fn quoted_pipe_arguments() {
+        let html = r#"<a title="x|y" href="/a" data-k="1">A</a><p style="--a:b: 1">it's 5 kg</p>"#;
         let spec: Spec = serde_json::from_str(
-            r##"{
-                "rss_link": "link[rel=alternate] | attr:href"
-            }"##,
+            r#"{
+                "href": "a[title='x|y'] | attr:'href'",
+                "fallback": "b[title='a||b'] || a[title=\"x|y\"] | text",
+                "flag": "a | hasAttr:\"data-k\"",
+                "sub": "p | substr:'0':'4'"
+            }"#,
         )
         .unwrap();
-        let result = extract(HTML, &spec).unwrap();
-        assert_eq!(result["rss_link"], "rss");
+        assert_eq!(
+            extract(html, &spec).unwrap(),
+            serde_json::json!({"href": "/a", "fallback": "A", "flag": true, "sub": "it's"})
+        );
     }
 
     #[test]
-    fn scoping_with_dollar() {
+    #[cfg(feature = "regex")]
+    fn quoted_regex_alternation() {
+        let html = "<p>it's 5 kg</p>";
         let spec: Spec = serde_json::from_str(
-            r##"{
-                "$": ".pagetop",
-                "first_link": "a"
-            }"##,
+            r#"{"unit": "p | regex:'(kg|lb)' | upper", "n": "p | regex:it's (\\d+)", "q": "p | regex:'(it''s)'"}"#,
         )
         .unwrap();
-        let result = extract(HTML, &spec).unwrap();
-        assert_eq!(result["first_link"], "Hacker News");
+        assert_eq!(
+            extract(html, &spec).unwrap(),
+            serde_json::json!({"unit": "KG", "n": "5", "q": "it's"})
+        );
+        assert_eq!(
+            spec.to_json(),
+            serde_json::json!({"unit": "p | regex:'(kg|lb)' | upper", "n": "p | regex:it's (\\d+)", "q": "p | regex:(it's)"})
+        );
     }
 
     #[test]
-    fn nested_scoping() {
+    fn pipe_characters_in_selectors_and_regexes() {
+        let html = r#"<p lang="en-GB">Hi</p><p lang="fr">Salut</p>"#;
         let spec: Spec = serde_json::from_str(
-            r##"{
-                "$": "head",
-                "head_element": {
-                    "$": "link",
-                    "href": "$ | attr:href",
-                    "rel": "$ | attr:rel"
-                }
-            }"##,
+            r#"{"en": "[lang|=en] | upper", "fr": "p[lang|=de] || p[lang|=fr]"}"#,
         )
         .unwrap();
-        let result = extract(HTML, &spec).unwrap();
         assert_eq!(
-            result["head_element"]["href"],
-            "news.css?fFlkMoHAedK8lfBWEYBd"
+            extract(html, &spec).unwrap(),
+            serde_json::json!({"en": "HI", "fr": "Salut"})
         );
-        assert_eq!(result["head_element"]["rel"], "stylesheet");
     }
 
     #[test]
-    fn collection_extraction() {
+    #[cfg(feature = "regex")]
+    fn regex_alternation_in_groups() {
+        let html = "<p>[5] kg|lb</p>";
         let spec: Spec = serde_json::from_str(
-            r##"{
-                "ranks": [{
-                    "$": ".rank",
-                    "value": "$"
-                }]
-            }"##,
+            r#"{
+                "unit": "p | regex:(kg|lb) | upper",
+                "class": "p | regex:([^|]+)$ | trim",
+                "escaped": "p | regex:(kg\\|lb)",
+                "unbalanced": "p | regex:\\[(\\d+)] | parseAs:int"
+            }"#,
         )
         .unwrap();
-        let result = extract(HTML, &spec).unwrap();
-        let arr = result["ranks"].as_array().unwrap();
-        assert!(arr.len() >= 3);
-        assert_eq!(arr[0]["value"], "1.");
-        assert_eq!(arr[1]["value"], "2.");
-        assert_eq!(arr[2]["value"], "3.");
+        assert_eq!(
+            extract(html, &spec).unwrap(),
+            serde_json::json!({"unit": "KG", "class": "lb", "escaped": "kg|lb", "unbalanced": 5})
+        );
     }
 
     #[test]
-    fn collection_with_nested_properties() {
+    fn all_matches_fields() {
+        let html = r#"<article><h1>T</h1><span class="author">Ann</span>
+            <span class="author">Bo</span><a href="/x">x</a><a>no href</a><p>a</p><p>b</p></article>"#;
         let spec: Spec = serde_json::from_str(
-            r##"{
-                "items": [{
-                    "$": "tr.athing",
-                    "id": "$ | attr:id",
-                    "title": ".titleline a"
-                }]
-            }"##,
+            r#"{
+                "$": "article",
+                "authors*": ".author | upper",
+                "hrefs*": "a | attr:href",
+                "paragraphs": ["p"],
+                "tags*?": ".tag || h1",
+                "none*": ".missing"
+            }"#,
         )
         .unwrap();
-        let result = extract(HTML, &spec).unwrap();
-        let arr = result["items"].as_array().unwrap();
-        assert!(arr.len() >= 2);
-        assert_eq!(arr[0]["id"], "46446815");
-        assert_eq!(arr[0]["title"], "I canceled my book deal");
+        assert_eq!(
+            extract(html, &spec).unwrap(),
+            serde_json::json!({
+                "authors": ["ANN", "BO"],
+                "hrefs": ["/x"],
+                "paragraphs": ["a", "b"],
+                "tags": ["T"],
+                "none": []
+            })
+        );
+        assert_eq!(
+            spec.to_json()["authors"],
+            serde_json::json!([".author | upper"])
+        );
+
+        let built = crate::SpecBuilder::new()
+            .all_field("paragraphs", "p")
+            .build()
+            .unwrap();
+        assert_eq!(
+            extract(html, &built).unwrap(),
+            serde_json::json!({"paragraphs": ["a", "b"]})
+        );
+
+        let err = serde_json::from_str::<Spec>(r#"{"x*": "$jsonld"}"#).unwrap_err();
+        assert!(err.to_string().contains("Field 'x*'"), "{err}");
+    }
+
+    #[test]
+    fn index_and_rank_fields() {
+        let html = "<ol><li>a</li><li>b</li><li>c</li></ol>";
+        let spec: Spec = serde_json::from_str(
+            r#"[{"$": "li", "name": "$ | text", "index": "$index", "rank": "$rank"}]"#,
+        )
+        .unwrap();
+        let expected = serde_json::json!([
+            {"name": "a", "index": 0, "rank": 1},
+            {"name": "b", "index": 1, "rank": 2},
+            {"name": "c", "index": 2, "rank": 3}
+        ]);
+        assert_eq!(extract(html, &spec).unwrap(), expected);
+        let dom = crate::Dom::parse(html).unwrap();
+        let items: Vec<_> = dom.extract_iter(&spec).collect::<Result<_, _>>().unwrap();
+        assert_eq!(serde_json::Value::Array(items), expected);
+        #[cfg(feature = "parallel")]
+        assert_eq!(crate::extract_par(html, &spec).unwrap(), expected);
+
+        let err = serde_json::from_str::<Spec>(r#"{"rank": "$rank"}"#).unwrap_err();
+        assert!(
+            err.to_string().contains("only apply to collection items"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn with_next_rows() {
+        let html = r#"<table>
+            <tr class="athing"><td class="title">A</td></tr>
+            <tr><td><span class="score">10</span></td></tr>
+            <tr class="spacer"></tr>
+            <tr class="athing"><td class="title">B</td></tr>
+            <tr><td>no score</td></tr>
+            <tr class="spacer"></tr>
+            <tr class="athing"><td class="title">C</td></tr>
+            <tr><td><span class="score">30</span></td></tr>
+        </table>"#;
+        let spec: Spec = serde_json::from_str(
+            r#"[{
+                "$": "tr.athing",
+                "$withNext": 1,
+                "title": ".title",
+                "score": "$next .score | parseAs:int",
+                "meta": {"$": "$next", "text": "$ | text"}
+            }]"#,
+        )
+        .unwrap();
+        // `+ .score` would have found C's score for B
+        assert_eq!(
+            extract(html, &spec).unwrap(),
+            serde_json::json!([
+                {"title": "A", "score": 10, "meta": {"text": "10"}},
+                {"title": "B", "score": null, "meta": {"text": "no score"}},
+                {"title": "C", "score": 30, "meta": {"text": "30"}}
+            ])
+        );
+        assert_eq!(spec.to_json()[0]["$withNext"], 1);
+
+        let err =
+            serde_json::from_str::<Spec>(r#"[{"$": "tr", "s": "$next .score"}]"#).unwrap_err();
+        assert!(err.to_string().contains("needs \"$withNext\""), "{err}");
+        let err = serde_json::from_str::<Spec>(r#"{"$withNext": 1, "s": "b"}"#).unwrap_err();
+        assert!(
+            err.to_string().contains("only applies to collections"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn recursive_collections() {
+        let html = r#"<ul id="menu">
+            <li><a>Home</a></li>
+            <li><a>Docs</a><ul>
+                <li><a>Guide</a><ul><li><a>Install</a></li></ul></li>
+                <li><a>API</a></li>
+            </ul></li>
+        </ul>"#;
+        let spec: Spec = serde_json::from_str(
+            r##"{"menu": {"$": "#menu", "items": [{
+                "$": ":scope > li",
+                "label": ":scope > a",
+                "children": {"$": ":scope > ul", "$recurse": 5}
+            }]}}"##,
+        )
+        .unwrap();
+        assert_eq!(
+            extract(html, &spec).unwrap(),
+            serde_json::json!({"menu": {"items": [
+                {"label": "Home", "children": []},
+                {"label": "Docs", "children": [
+                    {"label": "Guide", "children": [{"label": "Install", "children": []}]},
+                    {"label": "API", "children": []}
+                ]}
+            ]}})
+        );
+
+        // Levels past the maximum depth are left empty
+        let shallow: Spec = serde_json::from_str(
+            &serde_json::to_string(&spec.to_json())
+                .unwrap()
+                .replace("5", "1"),
+        )
+        .unwrap();
+        let items = &extract(html, &shallow).unwrap()["menu"]["items"];
+        assert_eq!(items[1]["children"][0]["children"], serde_json::json!([]));
+
+        let err = serde_json::from_str::<Spec>(r#"{"r": {"$recurse": 2}}"#).unwrap_err();
+        assert!(
+            err.to_string().contains("only applies to collection items"),
+            "{err}"
+        );
+    }
+
+    #[cfg(feature = "url")]
+    #[test]
+    fn spec_constants() {
+        let html = r#"
+            <div class="price">12.50</div>
+            <a class="more" href="/lamps/arc">Arc</a>
+            <img src="arc.jpg">
+        "#;
+        let spec = serde_json::from_str::<Spec>(
+            r#"{
+                "$consts": {"currency": "€", "baseUrl": "https://shop.example/", "cdn": "https://cdn.example/img/"},
+                "price": ".price | prefix:$currency",
+                "link": "a.more | attr:href | absoluteUrl",
+                "image": "img | attr:src | absoluteUrl:$cdn",
+                "literal": ".price | prefix:'$'"
+            }"#,
+        )
+        .unwrap();
+        let result = extract(html, &spec).unwrap();
+        assert_eq!(
+            result,
+            serde_json::json!({
+                "price": "€12.50",
+                "link": "https://shop.example/lamps/arc",
+                "image": "https://cdn.example/img/arc.jpg",
+                "literal": "$12.50"
+            })
+        );
+
+        // Options override the spec's constants
+        let options = crate::ExtractOptions::new()
+            .constant("currency", "EUR ")
+            .constant("baseUrl", "https://staging.example/");
+        let result = crate::extract_with_options(html, &spec, &options).unwrap();
+        assert_eq!(result["price"], "EUR 12.50");
+        assert_eq!(result["link"], "https://staging.example/lamps/arc");
+
+        // The spec survives a round trip
+        let reparsed = serde_json::from_value::<Spec>(spec.to_json()).unwrap();
+        assert_eq!(extract(html, &reparsed).unwrap()["image"], result["image"]);
+
+        let spec =
+            serde_json::from_str::<Spec>(r#"{"price": ".price | prefix:$currency"}"#).unwrap();
+        let err = extract(html, &spec).unwrap_err();
+        assert!(
+            err.to_string().contains("Unknown constant '$currency'"),
+            "{err}"
+        );
+
+        let err = serde_json::from_str::<Spec>(r#"{"item": {"$": "div", "$consts": {"a": "b"}}}"#)
+            .unwrap_err();
+        assert!(err.to_string().contains("top level"), "{err}");
+    }
+
+    #[test]
+    fn now_and_env_fields() {
+        let spec: Spec = serde_json::from_str(
+            r#"{
+                "title": "h1",
+                "scrapedAt": {"$now": "iso"},
+                "day": {"$now": "date"},
+                "unix": {"$now": "unix"},
+                "package": {"$env": "CARGO_PKG_NAME"},
+                "missing": {"$env": "HTML2JSON_TEST_UNSET"}
+            }"#,
+        )
+        .unwrap();
+        let result = extract("<h1>Hello</h1>", &spec).unwrap();
+        let scraped_at = result["scrapedAt"].as_str().unwrap();
+        let time = chrono::DateTime::parse_from_rfc3339(scraped_at).unwrap();
+        assert!(scraped_at.starts_with(result["day"].as_str().unwrap()));
+        assert_eq!(result["unix"], time.timestamp());
+        assert_eq!(
+            result["package"],
+            std::env::var("CARGO_PKG_NAME").map_or(serde_json::Value::Null, Into::into)
+        );
+        assert_eq!(result["missing"], serde_json::Value::Null);
+
+        // The spec survives a round trip
+        let reparsed: Spec = serde_json::from_value(spec.to_json()).unwrap();
+        assert_eq!(reparsed.to_json(), spec.to_json());
+
+        let err = serde_json::from_str::<Spec>(r#"{"at": {"$now": "rfc"}}"#).unwrap_err();
+        assert!(err.to_string().contains("Unknown $now format"), "{err}");
+        let err =
+            serde_json::from_str::<Spec>(r#"{"at": {"$env": "RUN_ID", "$": "h1"}}"#).unwrap_err();
+        assert!(err.to_string().contains("no other keys"), "{err}");
+    }
+
+    #[cfg(feature = "url")]
+    #[test]
+    fn page_context_fields() {
+        let html = r#"<h1>Lamp</h1><a href="arc">Arc</a><img src="/img/arc.jpg">"#;
+        let spec: Spec = serde_json::from_str(
+            r#"{
+                "title": "h1",
+                "url": {"$context": "url"},
+                "fetchedAt": {"$context": "fetchedAt"},
+                "job": {"$context": "job"},
+                "missing?": {"$context": "missing"},
+                "link": "a | attr:href | absoluteUrl",
+                "images": "$images"
+            }"#,
+        )
+        .unwrap();
+        let fetched_at = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000);
+        let context = crate::PageContext::new()
+            .url("https://shop.example/lamps/")
+            .fetched_at(fetched_at)
+            .value("job", 7);
+        let result = crate::extract_with_context(html, &spec, &context).unwrap();
+        assert_eq!(
+            result,
+            serde_json::json!({
+                "title": "Lamp",
+                "url": "https://shop.example/lamps/",
+                "fetchedAt": "2023-11-14T22:13:20Z",
+                "job": 7,
+                "link": "https://shop.example/lamps/arc",
+                "images": [{"src": "https://shop.example/img/arc.jpg"}]
+            })
+        );
+
+        // An explicit base URL wins over the context's
+        let options = crate::ExtractOptions::new()
+            .base_url("https://cdn.example/")
+            .context(context);
+        let result = crate::extract_with_options(html, &spec, &options).unwrap();
+        assert_eq!(result["url"], "https://shop.example/lamps/");
+        assert_eq!(result["link"], "https://cdn.example/arc");
+
+        // Without a context, every value is unknown
+        let result = extract(html, &spec).unwrap();
+        assert_eq!(result["url"], serde_json::Value::Null);
+        assert_eq!(result["link"], "arc");
+
+        let reparsed: Spec = serde_json::from_value(spec.to_json()).unwrap();
+        assert_eq!(reparsed.to_json(), spec.to_json());
+        let err = serde_json::from_str::<Spec>(r#"{"url": {"$context": ""}}"#).unwrap_err();
+        assert!(err.to_string().contains("name of a context value"), "{err}");
+    }
+
+    #[test]
+    fn merged_documents() {
+        let page = r#"
+            <h1>Arc Lamp</h1>
+            <div class="price">120</div>
+            <ul><li class="review">Bright</li></ul>
+        "#;
+        let fragment = r#"
+            <div class="summary"><span class="avg">4.5</span></div>
+            <ul><li class="review">Sturdy</li><li class="review">Tall</li></ul>
+        "#;
+        let product: Spec = serde_json::from_str(
+            r#"{
+                "name": "h1",
+                "price": ".price | parseAs:int",
+                "rating": {"count": ".count"},
+                "reviews": [{"$": ".review", "text": "$"}]
+            }"#,
+        )
+        .unwrap();
+        let reviews: Spec = serde_json::from_str(
+            r#"{
+                "name": "h2",
+                "price": ".price",
+                "rating": {"$": ".summary", "average": ".avg | parseAs:float"},
+                "reviews": [{"$": ".review", "text": "$"}]
+            }"#,
+        )
+        .unwrap();
+        let result = crate::extract_merged(&[(page, &product), (fragment, &reviews)]).unwrap();
+        assert_eq!(
+            result,
+            serde_json::json!({
+                "name": "Arc Lamp",
+                "price": 120,
+                "rating": {"average": 4.5},
+                "reviews": [{"text": "Bright"}, {"text": "Sturdy"}, {"text": "Tall"}]
+            })
+        );
+
+        assert_eq!(crate::extract_merged(&[]).unwrap(), serde_json::Value::Null);
+
+        let broken: Spec = serde_json::from_str(r#"{"price": ".price | parseAs:int"}"#).unwrap();
+        let err = crate::extract_merged(&[(page, &product), ("<p class=price>n/a</p>", &broken)])
+            .unwrap_err();
+        assert!(err.to_string().contains("Document 1"), "{err}");
+    }
+
+    #[test]
+    fn extract_changes_since_previous() {
+        let spec: Spec = serde_json::from_str(
+            r#"{
+                "name": "h1",
+                "price": ".price | parseAs:int",
+                "sale?": ".sale",
+                "badge?": ".badge",
+                "sizes": ["li"]
+            }"#,
+        )
+        .unwrap();
+        let before = r#"<h1>Arc</h1><b class="price">120</b><i class="sale">-10%</i>
+            <ul><li>S</li><li>M</li><li>L</li></ul>"#;
+        let after = r#"<h1>Arc</h1><b class="price">99</b><i class="badge">New</i>
+            <ul><li>S</li><li>XL</li></ul>"#;
+        let previous = extract(before, &spec).unwrap();
+
+        let (result, changes) = crate::extract_changes(after, &spec, &previous).unwrap();
+        assert_eq!(result, extract(after, &spec).unwrap());
+        let changes: Vec<String> = changes.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            changes,
+            [
+                "~ .price: 120 → 99",
+                r#"- .sale: "-10%""#,
+                r#"~ .sizes[1]: "M" → "XL""#,
+                r#"- .sizes[2]: "L""#,
+                r#"+ .badge: "New""#,
+            ]
+        );
+
+        let (_, changes) = crate::extract_changes(before, &spec, &previous).unwrap();
+        assert!(changes.is_empty());
+
+        // Changes serialize for alerts and logs
+        let changes = crate::changes::diff(&serde_json::json!(1), &serde_json::json!([1]));
+        assert_eq!(
+            serde_json::to_value(&changes).unwrap(),
+            serde_json::json!([{"path": ".", "kind": "changed", "old": 1, "new": [1]}])
+        );
+
+        // Paths read like jq's
+        let changes = crate::changes::diff(
+            &serde_json::json!([{"unit price": 1, "a.b": [1], "x[0]": 1, "ok_1": 1}]),
+            &serde_json::json!([{"unit price": 2, "a.b": [2], "x[0]": 2, "ok_1": 2}, 3]),
+        );
+        let mut paths: Vec<&str> = changes.iter().map(|c| c.path.as_str()).collect();
+        paths.sort_unstable();
+        assert_eq!(
+            paths,
+            [
+                r#".[0]."a.b"[0]"#,
+                r#".[0]."unit price""#,
+                r#".[0]."x[0]""#,
+                ".[0].ok_1",
+                ".[1]"
+            ]
+        );
+    }
+
+    #[test]
+    fn markdown_pipe() {
+        let html = r#"<div class="post">
+            <h2>Lamps &amp; lights</h2>
+            <p>Lamps are <strong>bright</strong>, <em>warm</em> and use <code>230V</code>.<br>
+            See <a href="/docs">the docs</a> or <a href="/a b">this</a>.</p>
+            <ul>
+                <li>LED</li>
+                <li>Halogen
+                    <ol start="3"><li>old</li><li>hot</li></ol>
+                </li>
+            </ul>
+            <blockquote><p>Let there be light</p></blockquote>
+            <pre><code class="language-rust">fn main() {
+    println!("*");
+}</code></pre>
+            <img src="/lamp.png" alt="A lamp">
+            <table><tr><th>Type</th><th>Watts</th></tr><tr><td>LED</td><td>5</td></tr></table>
+            <script>track()</script>
+        </div>"#;
+        let spec: Spec = serde_json::from_str(r#"{"body": ".post | markdown"}"#).unwrap();
+        let result = extract(html, &spec).unwrap();
+        assert_eq!(
+            result["body"],
+            "## Lamps & lights\n\n\
+             Lamps are **bright**, *warm* and use `230V`.  \n\
+             See [the docs](/docs) or [this](</a b>).\n\n\
+             - LED\n\
+             - Halogen\n  3. old\n  4. hot\n\n\
+             > Let there be light\n\n\
+             ```rust\nfn main() {\n    println!(\"*\");\n}\n```\n\n\
+             ![A lamp](/lamp.png)\n\n\
+             | Type | Watts |\n| --- | --- |\n| LED | 5 |"
+        );
+
+        // After a source pipe, the text is converted as HTML
+        let spec: Spec =
+            serde_json::from_str(r#"{"note": "meta | attr:content | markdown"}"#).unwrap();
+        let html = r#"<meta content="<b>snake_case</b> names">"#;
+        assert_eq!(
+            extract(html, &spec).unwrap()["note"],
+            "**snake\\_case** names"
+        );
+
+        // Written after another pipe, markdown converts the value so far
+        let spec: Spec = serde_json::from_str(r#"{"name": "p | upper | markdown"}"#).unwrap();
+        let html = "<p>snake_<b>case</b></p>";
+        assert_eq!(extract(html, &spec).unwrap()["name"], "SNAKE\\_CASE");
+    }
+
+    #[cfg(feature = "schema")]
+    #[test]
+    fn schema_validation() {
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": {
+                "stories": {
+                    "type": "array",
+                    "minItems": 1,
+                    "items": {
+                        "type": "object",
+                        "required": ["title"],
+                        "properties": {"title": {"type": "string"}}
+                    }
+                }
+            }
+        });
+        let spec: Spec =
+            serde_json::from_str(r#"{"stories": [{"$": ".athing", "title": ".titleline > a"}]}"#)
+                .unwrap();
+        let result = crate::extract_validated(HTML, &spec, &schema).unwrap();
+        assert_eq!(result["stories"].as_array().unwrap().len(), 30);
+
+        // A renamed class leaves the stories empty
+        let spec: Spec =
+            serde_json::from_str(r#"{"stories": [{"$": ".athing", "title": ".storylink"}]}"#)
+                .unwrap();
+        let err = crate::extract_validated(HTML, &spec, &schema).unwrap_err();
+        let violations = &err
+            .downcast_ref::<crate::SchemaViolations>()
+            .unwrap()
+            .violations;
+        assert_eq!(violations.len(), 30);
+        assert_eq!(violations[0].pointer, "/stories/0");
+        assert_eq!(
+            violations[0].to_string(),
+            "/stories/0: null is not of type \"object\""
+        );
+
+        assert!(crate::Schema::new(&serde_json::json!({"type": 5})).is_err());
     }
 
     #[test]
@@ -223,6 +2868,7 @@ mod tests {
         assert_eq!(result["partial"], "Hacker");
     }
 
+    #[cfg(feature = "regex")]
     #[test]
     fn parse_as_number_pipe() {
         let spec: Spec = serde_json::from_str(
@@ -236,6 +2882,7 @@ mod tests {
         assert_eq!(result["table_width"], 85);
     }
 
+    #[cfg(feature = "regex")]
     #[test]
     fn regex_pipe() {
         let spec: Spec = serde_json::from_str(
@@ -307,7 +2954,7 @@ mod tests {
         .unwrap();
         let result = extract(HTML, &spec).unwrap();
         let items = result["submissions"].as_array().unwrap();
-        assert!(items.len() >= 1);
+        assert!(!items.is_empty());
         assert_eq!(items[0]["id"], "46446815");
         assert_eq!(items[0]["title"], "I canceled my book deal");
     }
@@ -344,7 +2991,7 @@ mod tests {
         .unwrap();
         let result = extract(HTML, &spec).unwrap();
         let items = result["items"].as_array().unwrap();
-        assert!(items.len() >= 1);
+        assert!(!items.is_empty());
         assert_eq!(items[0]["title"], "I canceled my book deal");
         assert_eq!(items[0]["score"], "156 points");
     }
@@ -701,6 +3348,12 @@ mod tests {
 // WASM bindings for JavaScript/TypeScript usage
 #[cfg(feature = "wasm")]
 pub mod wasm {
+    //! JavaScript bindings, for browsers, Node and edge runtimes
+    //!
+    //! Build with `wasm-pack build --target web --features wasm` (or
+    //! `just wasm`). Only the extraction core is compiled; pages are fetched
+    //! in JavaScript and passed in as strings.
+
     use wasm_bindgen::prelude::*;
 
     /// Extract JSON from HTML using a spec
@@ -708,15 +3361,15 @@ pub mod wasm {
     /// # Arguments
     ///
     /// * `html` - The HTML source to parse
-    /// * `spec_json` - The extraction specification as JSON string
+    /// * `spec` - The extraction specification, as a JSON string or an object
     ///
     /// # Returns
     ///
-    /// A JSON string with the extracted data
+    /// The extracted data as a plain JavaScript value
     ///
     /// # Errors
     ///
-    /// Returns a JsValue error if the HTML parsing or extraction fails
+    /// Throws an `Error` if the spec is invalid or extraction fails
     ///
     /// # Example
     ///
@@ -724,22 +3377,41 @@ pub mod wasm {
     /// import { extract } from 'html2json';
     ///
     /// const html = '<div class="item"><span>Price: $25.00</span></div>';
-    /// const spec = '{"price": ".item span | regex:\\\\$(\\\\d+\\\\.\\\\d+)"}';
-    /// const result = extract(html, spec);
-    /// console.log(result); // {"price":"25.00"}
+    /// const result = extract(html, { price: ".item span | regex:\\$(\\d+\\.\\d+)" });
+    /// console.log(result.price); // "25.00"
     /// ```
-    #[wasm_bindgen(js_name = extract)]
-    pub fn extract(html: &str, spec_json: &str) -> Result<String, JsValue> {
-        use crate::extract;
-        use crate::spec::Spec;
-
-        let spec: Spec = serde_json::from_str(spec_json)
-            .map_err(|e| JsValue::from_str(&format!("Invalid spec JSON: {}", e)))?;
+    #[wasm_bindgen(js_name = extract, unchecked_return_type = "any")]
+    pub fn extract(
+        html: &str,
+        #[wasm_bindgen(unchecked_param_type = "string | object")] spec: JsValue,
+    ) -> Result<JsValue, JsError> {
+        let result = extract_value(html, spec)?;
+        let json = serde_json::to_string(&result)?;
+        js_sys::JSON::parse(&json).map_err(|_| JsError::new("JSON conversion failed"))
+    }
 
-        let result = extract(html, &spec)
-            .map_err(|e| JsValue::from_str(&format!("Extraction failed: {}", e)))?;
+    /// Extract JSON from HTML using a spec, returning the result as a JSON string
+    ///
+    /// Useful where the result is passed on as text anyway, e.g. as a response
+    /// body in a worker.
+    #[wasm_bindgen(js_name = extractJson)]
+    pub fn extract_json(
+        html: &str,
+        #[wasm_bindgen(unchecked_param_type = "string | object")] spec: JsValue,
+    ) -> Result<String, JsError> {
+        Ok(serde_json::to_string(&extract_value(html, spec)?)?)
+    }
 
-        serde_json::to_string_pretty(&result)
-            .map_err(|e| JsValue::from_str(&format!("JSON serialization failed: {}", e)))
+    fn extract_value(html: &str, spec: JsValue) -> Result<serde_json::Value, JsError> {
+        let spec_json = match spec.as_string() {
+            Some(json) => json,
+            None => js_sys::JSON::stringify(&spec)
+                .ok()
+                .and_then(|json| json.as_string())
+                .ok_or_else(|| JsError::new("Spec must be a JSON string or an object"))?,
+        };
+        let spec: crate::Spec = serde_json::from_str(&spec_json)
+            .map_err(|e| JsError::new(&format!("Invalid spec: {}", e)))?;
+        crate::extract(html, &spec).map_err(|e| JsError::new(&format!("Extraction failed: {}", e)))
     }
 }
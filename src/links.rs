@@ -0,0 +1,97 @@
+//! Links (`<a href>`) as JSON objects, and resolving URLs found in a page
+//!
+//! Each link becomes `{"href", "text", "rel", "nofollow"}`, with `href`
+//! resolved like a browser would and `rel` left out when missing.
+
+use crate::spec::LinkFilter;
+use scraper::{ElementRef, Html, Selector};
+use serde_json::{Map, Value};
+use std::sync::LazyLock;
+use url::Url;
+
+static LINKS: LazyLock<Selector> =
+    LazyLock::new(|| Selector::parse("a[href]").expect("link selector"));
+
+/// The URL relative URLs in a page resolve against: the document's
+/// `<base href>` (itself resolved against `base_url`), or `base_url`
+pub(crate) fn base(html: &Html, base_url: Option<&str>) -> Option<Url> {
+    static BASE: LazyLock<Selector> =
+        LazyLock::new(|| Selector::parse("base[href]").expect("base selector"));
+    let page = base_url.and_then(|url| Url::parse(url).ok());
+    let href = html
+        .select(&BASE)
+        .next()
+        .and_then(|el| el.value().attr("href"));
+    match (href, &page) {
+        (Some(href), Some(page)) => page.join(href.trim()).ok().or(Some(page.clone())),
+        (Some(href), None) => Url::parse(href.trim()).ok(),
+        (None, _) => page,
+    }
+}
+
+/// The links at or inside `scope` (the whole document if `None`)
+pub(crate) fn links<'a>(html: &'a Html, scope: Option<ElementRef<'a>>) -> Vec<ElementRef<'a>> {
+    match scope {
+        Some(scope) if scope.value().name() == "a" && scope.value().attr("href").is_some() => {
+            vec![scope]
+        }
+        Some(scope) => scope.select(&LINKS).collect(),
+        None => html.select(&LINKS).collect(),
+    }
+}
+
+/// A link element as a JSON object, or `None` if `filter` excludes it
+///
+/// Links are internal when they point at the base URL's host, or are
+/// relative and there is no base URL; external when they point at another
+/// host. Other links (`mailto:`, `javascript:`) are neither.
+pub(crate) fn link(
+    element: ElementRef,
+    base: Option<&Url>,
+    filter: Option<LinkFilter>,
+) -> Option<Value> {
+    let href = element.value().attr("href")?.trim();
+    let resolved = match base {
+        Some(base) => base.join(href).ok(),
+        None => Url::parse(href).ok(),
+    };
+    if let Some(filter) = filter {
+        let internal = match (&resolved, base) {
+            (Some(url), Some(base)) => {
+                url.host_str().is_some() && url.host_str() == base.host_str()
+            }
+            (Some(_), None) => false,
+            (None, _) => !href.is_empty() && !href.contains(':'),
+        };
+        let external = !internal
+            && resolved
+                .as_ref()
+                .is_some_and(|url| url.host_str().is_some());
+        let keep = match filter {
+            LinkFilter::Internal => internal,
+            LinkFilter::External => external,
+        };
+        if !keep {
+            return None;
+        }
+    }
+
+    let rel = element.value().attr("rel");
+    let mut link = Map::new();
+    let href = resolved.map_or_else(|| href.to_string(), String::from);
+    link.insert("href".into(), href.into());
+    let text = element
+        .text()
+        .flat_map(str::split_whitespace)
+        .collect::<Vec<_>>();
+    link.insert("text".into(), text.join(" ").into());
+    if let Some(rel) = rel {
+        link.insert("rel".into(), rel.into());
+    }
+    let nofollow = rel.is_some_and(|rel| {
+        rel.split_whitespace()
+            .any(|r| r.eq_ignore_ascii_case("nofollow"))
+    });
+    link.insert("nofollow".into(), nofollow.into());
+    Some(Value::Object(link))
+}
@@ -1,66 +1,892 @@
 use anyhow::Result;
-use clap::Parser;
-use html2json::Spec;
+use clap::{CommandFactory, FromArgMatches, Parser};
+use html2json::fetch::{self, Fetcher};
+use html2json::{ExtractOptions, PageContext, Spec};
+use output::Format;
 use similar::{ChangeTag, TextDiff};
 use std::io::Read;
 
+mod archive;
+mod bench;
+mod config;
+mod crawl;
+mod diff;
+mod golden;
+mod infer;
+mod output;
+mod query;
+mod repl;
+mod sitemap;
+mod specs;
+
 // ANSI color codes
 const RED: &str = "\x1b[31m";
 const GREEN: &str = "\x1b[32m";
 const RESET: &str = "\x1b[0m";
 const BOLD: &str = "\x1b[1m";
 
+/// Command name for completion scripts and the man page
+const BIN_NAME: &str = "html2json";
+/// `--follow-next` without an expression follows the `$pagination` next link
+const PAGINATION: &str = "$pagination";
+
 /// html2json - Extract JSON from HTML using CSS selectors
 #[derive(Parser, Debug)]
 #[command(name = env!("CARGO_PKG_VERSION"))]
 #[command(author = "html2json")]
 #[command(version = env!("CARGO_PKG_VERSION"))]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
-    /// Input: path to HTML file (reads from stdin if not provided)
-    #[arg(value_name = "FILE")]
-    input: Option<String>,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Inputs: HTML files, glob patterns or http(s) URLs (reads from stdin if not provided)
+    ///
+    /// With more than one input, runs in batch mode and prints one JSON line
+    /// per document. `.gz` and `.br` files are decompressed, and the page saved
+    /// in a `.har` or `.mhtml` file is used.
+    #[arg(value_name = "FILE|URL")]
+    input: Vec<String>,
+
+    /// Read inputs from FILE, one path or URL per line (batch mode)
+    #[arg(long, value_name = "FILE")]
+    input_list: Option<String>,
+
+    /// Extract every page listed in a sitemap.xml URL or file (batch mode)
+    ///
+    /// Sitemap indexes are followed into their child sitemaps.
+    #[arg(long, value_name = "FILE|URL")]
+    sitemap: Option<String>,
+
+    /// Only extract sitemap URLs matching a glob pattern, repeatable
+    #[arg(long, value_name = "PATTERN", requires = "sitemap")]
+    url_filter: Vec<String>,
+
+    /// Number of inputs to read or fetch concurrently in batch mode
+    #[arg(short, long, value_name = "N", default_value_t = 4, global = true)]
+    jobs: usize,
 
     /// Path to JSON extractor spec file
     #[arg(short, long, value_name = "SPEC")]
-    spec: String,
+    spec: Option<String>,
+
+    /// Use a built-in spec, adding the fields of --spec if given
+    ///
+    /// metadata: title, description, canonical URL, favicon, and OpenGraph
+    /// and Twitter card properties.
+    ///
+    /// article: the main content as text and cleaned HTML, with its title,
+    /// byline and publication date.
+    ///
+    /// rss (or atom): the channel and items of an RSS or Atom feed, read as
+    /// XML.
+    #[arg(long, value_enum, value_name = "NAME", conflicts_with = "select")]
+    preset: Option<Preset>,
+
+    /// Print the values matched by a CSS selector instead of running a spec (repeatable)
+    #[arg(long, value_name = "SELECTOR", conflicts_with_all = ["spec", "check"])]
+    select: Vec<String>,
+
+    /// Pipes applied to each --select match, e.g. "attr:href | trim"
+    #[arg(long, value_name = "PIPES", requires = "select")]
+    pipe: Option<String>,
 
     /// Check output matches expected JSON file (shows diff if different)
     #[arg(short, long, value_name = "FILE")]
     check: Option<String>,
+
+    /// Output format [default: json] (batch mode always prints one line per document)
+    #[arg(short, long, value_enum)]
+    format: Option<Format>,
+
+    /// jq filter applied to each result before output, e.g.
+    /// '.products[] | select(.price < 100)'
+    #[arg(short, long, value_name = "FILTER", global = true)]
+    query: Option<String>,
+
+    /// Exit with status 1 if any value in the result is null
+    #[arg(long)]
+    fail_on_null: bool,
+
+    /// Exit with status 1 if any array in the result is empty
+    #[arg(long)]
+    fail_on_empty_array: bool,
+
+    /// Exit with status 1 if the result's collections (a top-level array, or
+    /// the array fields of a top-level object) have fewer than N items
+    #[arg(long, value_name = "N")]
+    min_items: Option<usize>,
+
+    /// Exit with status 1 if the result does not match the JSON Schema in
+    /// FILE, listing each violation with a JSON pointer to the value
+    #[arg(long, value_name = "FILE")]
+    validate: Option<String>,
+
+    /// The compiled --validate schema
+    #[arg(skip)]
+    schema: Option<std::sync::Arc<html2json::Schema>>,
+
+    /// Render results through a Jinja template FILE instead of a --format
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["format", "compact", "select", "explain", "check"])]
+    template: Option<String>,
+
+    /// Print JSON on a single line
+    #[arg(long)]
+    compact: bool,
+
+    /// Add the input's path or URL to the result as FIELD [default: source],
+    /// or to each object of a top-level array ("-" for stdin)
+    #[arg(
+        long,
+        value_name = "FIELD",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "source"
+    )]
+    add_source: Option<String>,
+
+    /// Write output to PATH instead of stdout (a directory in batch mode)
+    #[arg(short, long, value_name = "PATH")]
+    output: Option<String>,
+
+    /// Extra request header for URL input ("Name: Value"), repeatable
+    #[arg(short = 'H', long = "header", value_name = "HEADER", global = true)]
+    headers: Vec<String>,
+
+    /// Cookie for URL input ("name=value"), repeatable
+    #[arg(long = "cookie", value_name = "COOKIE", global = true)]
+    cookies: Vec<String>,
+
+    /// User-Agent for URL input
+    #[arg(short = 'A', long, value_name = "UA", global = true)]
+    user_agent: Option<String>,
+
+    /// Proxy for URL input (default: HTTP_PROXY/HTTPS_PROXY from the environment)
+    #[arg(long, value_name = "URL", global = true)]
+    proxy: Option<String>,
+
+    /// Retry URL fetches on timeouts, HTTP 429 and 5xx up to N times
+    #[arg(long, value_name = "N", default_value_t = 0, global = true)]
+    retries: u32,
+
+    /// Cache fetched pages in DIR and reuse them across runs
+    #[arg(long, value_name = "DIR", global = true)]
+    cache_dir: Option<String>,
+
+    /// Seconds before a cached page is fetched again
+    #[arg(long, value_name = "SECONDS", default_value_t = fetch::DEFAULT_CACHE_TTL.as_secs(), global = true)]
+    cache_ttl: u64,
+
+    /// Disable the HTTP cache even if --cache-dir is set
+    #[arg(long, global = true)]
+    no_cache: bool,
+
+    /// Fetch again and overwrite cached pages
+    #[arg(long, global = true)]
+    refresh: bool,
+
+    /// Seconds to wait between requests to the same host
+    #[arg(long, value_name = "SECONDS", global = true)]
+    delay: Option<f64>,
+
+    /// Maximum requests per second across all hosts
+    #[arg(long, value_name = "N", global = true)]
+    max_rps: Option<f64>,
+
+    /// Maximum concurrent requests to the same host
+    #[arg(long, value_name = "N", global = true)]
+    max_per_host: Option<usize>,
+
+    /// Skip URLs disallowed by robots.txt and honor its Crawl-delay
+    #[arg(long, global = true)]
+    respect_robots: bool,
+
+    /// Load URL input in a headless browser and extract from the rendered
+    /// page, through the Chrome DevTools endpoint at URL
+    /// [default: http://127.0.0.1:9222] (needs the `render` feature)
+    #[arg(long, value_name = "URL", num_args = 0..=1, require_equals = true, default_missing_value = RENDER_ENDPOINT, global = true)]
+    render: Option<String>,
+
+    /// With --render, wait until an element matches SELECTOR before reading the page
+    #[arg(long, value_name = "SELECTOR", requires = "render", global = true)]
+    wait_for: Option<String>,
+
+    /// With --render, seconds to allow each page for loading and rendering
+    #[arg(long, value_name = "SECONDS", requires = "render", global = true)]
+    render_timeout: Option<f64>,
+
+    /// Print per-field timings and match counts to stderr
+    #[arg(long, global = true)]
+    stats: bool,
+
+    /// Instead of JSON, print each field's selector, match count and first match
+    #[arg(long, conflicts_with = "check")]
+    explain: bool,
+
+    /// Follow pagination: after each page, fetch the link this selector and
+    /// pipes extract, e.g. "a.morelink | attr:href"; without EXPR, the
+    /// page's rel="next" or pagination "Next" link ($pagination)
+    #[arg(
+        long,
+        value_name = "EXPR",
+        num_args = 0..=1,
+        default_missing_value = PAGINATION,
+        conflicts_with_all = ["explain", "cache_dom", "select"]
+    )]
+    follow_next: Option<String>,
+
+    /// Maximum pages to extract with --follow-next
+    #[arg(long, value_name = "N", default_value_t = 10, requires = "follow_next")]
+    max_pages: usize,
+
+    /// Log to stderr: -v for progress and warnings such as fields that
+    /// matched nothing, -vv to debug selectors, -vvv to trace pipes
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
+
+    /// Format of -v log lines
+    #[arg(long, value_enum, default_value_t = LogFormat::Text, global = true)]
+    log_format: LogFormat,
+
+    /// Read default options and jobs from FILE [default: html2json.toml if present]
+    #[arg(long, value_name = "FILE", global = true)]
+    config: Option<String>,
+
+    /// Cache the parsed DOM in FILE and reuse it while the input file is unchanged
+    #[arg(long, value_name = "FILE", requires = "input")]
+    cache_dom: Option<String>,
+
+    /// Print a man page for html2json and exit
+    #[arg(long, exclusive = true)]
+    man: bool,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Preset {
+    Metadata,
+    Article,
+    #[value(alias = "atom")]
+    #[serde(alias = "atom")]
+    Rss,
+}
+
+impl Preset {
+    /// The preset's spec, or `None` for presets that don't run a spec
+    fn spec(self) -> Option<Spec> {
+        match self {
+            Preset::Metadata => Some(html2json::presets::metadata()),
+            Preset::Article => Some(html2json::presets::article()),
+            Preset::Rss => None,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum LogFormat {
+    Text,
+    Json,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Command {
+    /// Interactively try selectors and build up a spec
+    Repl {
+        /// HTML file or http(s) URL
+        #[arg(value_name = "FILE|URL")]
+        input: String,
+    },
+    /// Run golden tests: every NAME.html with a NAME.spec.json in DIR must
+    /// produce NAME.expected.json
+    Test {
+        /// Directory holding the test cases
+        #[arg(value_name = "DIR", default_value = "tests")]
+        dir: std::path::PathBuf,
+
+        /// Write the current output to the expected files instead of comparing
+        #[arg(long)]
+        update: bool,
+    },
+    /// Follow links from start URLs, extracting each visited page
+    ///
+    /// Prints one JSON line per page: {"url": ..., "depth": N, "data": ...}.
+    Crawl(crawl::CrawlArgs),
+    /// Extract a page with two specs, or two pages with one spec, and show
+    /// how the results differ
+    ///
+    /// Exits with 1 if they differ.
+    Diff(diff::DiffArgs),
+    /// Run a job from the config file, or list the jobs
+    Run {
+        /// Name of a [jobs.NAME] table in html2json.toml
+        job: Option<String>,
+    },
+    /// Time parsing and per-field extraction of a page with a spec
+    Bench(bench::BenchArgs),
+    /// Work with spec files
+    Spec {
+        #[command(subcommand)]
+        command: specs::SpecCommand,
+    },
+    /// Check a spec for fragile patterns, with suggested fixes
+    ///
+    /// Exits with 1 if there are warnings.
+    Lint {
+        /// Spec file
+        #[arg(value_name = "SPEC")]
+        spec: String,
+    },
+    /// Print a shell completion script
+    ///
+    /// For bash: html2json completions bash > /etc/bash_completion.d/html2json
+    Completions {
+        /// Shell to complete for
+        #[arg(value_enum)]
+        shell: clap_complete::Shell,
+    },
+    /// Propose a draft spec for a page, to be edited by hand
+    Infer {
+        /// HTML file or http(s) URL
+        #[arg(value_name = "FILE|URL")]
+        input: String,
+    },
 }
 
 fn main() -> Result<()> {
-    let args = Args::parse();
+    let matches = Args::command().get_matches();
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    init_logging(args.verbose, args.log_format);
+    if args.man {
+        // The about line already starts with the name, which NAME repeats
+        let command = Args::command()
+            .name(BIN_NAME)
+            .about("Extract JSON from HTML using CSS selectors");
+        let man = clap_mangen::Man::new(command);
+        man.render(&mut std::io::stdout())?;
+        return Ok(());
+    }
+    if let Some(Command::Completions { shell }) = &args.command {
+        clap_complete::generate(
+            *shell,
+            &mut Args::command(),
+            BIN_NAME,
+            &mut std::io::stdout(),
+        );
+        return Ok(());
+    }
+    let config = config::Config::load(args.config.as_deref())?;
 
-    let html = read_html(args.input.as_deref())?;
-    let spec_value = load_spec(&args.spec)?;
-    let spec = Spec::from_json(&spec_value)?;
-    let dom = html2json::Dom::parse(&html)?;
-    let result = dom.extract(&spec)?;
+    let job = match &args.command {
+        Some(Command::Run { job: Some(job) }) => Some(job.clone()),
+        Some(Command::Run { job: None }) => {
+            for name in config.iter().flat_map(|config| config.job_names()) {
+                println!("{}", name);
+            }
+            return Ok(());
+        }
+        _ => None,
+    };
+    match &config {
+        Some(config) => config.apply(&mut args, &matches, job.as_deref())?,
+        None if job.is_some() => {
+            return Err(anyhow::anyhow!(
+                "No config file: create {} or pass --config",
+                config::DEFAULT_PATH
+            ));
+        }
+        None => {}
+    }
+    if job.is_some() {
+        args.command = None;
+    }
+    if let Some(path) = &args.validate {
+        let schema = html2json::Schema::new(&load_spec(path)?)
+            .map_err(|e| anyhow::anyhow!("{}: {}", path, e))?;
+        args.schema = Some(std::sync::Arc::new(schema));
+    }
 
-    if let Some(check_path) = args.check {
+    if let Some(Command::Repl { input }) = &args.command {
+        let dom = html2json::Dom::parse(&read_html(&args, Some(input))?)?;
+        return repl::run(&dom);
+    }
+    if let Some(Command::Test { dir, update }) = &args.command {
+        if !golden::run(dir, *update)? {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    if let Some(Command::Crawl(crawl)) = &args.command {
+        return crawl::run(&args, crawl);
+    }
+    if let Some(Command::Diff(diff)) = &args.command {
+        if !diff::run(&args, diff)? {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    if let Some(Command::Bench(bench)) = &args.command {
+        return bench::run(&args, bench);
+    }
+    if let Some(Command::Spec { command }) = &args.command {
+        return specs::run(command);
+    }
+    if let Some(Command::Lint { spec }) = &args.command {
+        if !specs::lint(spec)? {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    if let Some(Command::Infer { input }) = &args.command {
+        let draft = infer::infer(&read_html(&args, Some(input))?);
+        // Only ever propose specs that load
+        Spec::from_json(&draft)?;
+        println!("{}", serde_json::to_string_pretty(&draft)?);
+        return Ok(());
+    }
+
+    if args.query.is_some() && (!args.select.is_empty() || args.explain) {
+        // Checked here because a global argument can't conflict with the
+        // top-level-only --select and --explain
+        Args::command()
+            .error(
+                clap::error::ErrorKind::ArgumentConflict,
+                "--query cannot be used with --select or --explain",
+            )
+            .exit();
+    }
+    if args.spec.is_none() && args.preset.is_none() && args.select.is_empty() {
+        Args::command()
+            .error(
+                clap::error::ErrorKind::MissingRequiredArgument,
+                "--spec <SPEC> is required unless --preset or --select is used",
+            )
+            .exit();
+    }
+
+    let inputs = collect_inputs(&args)?;
+    let batch = args.input_list.is_some()
+        || args.sitemap.is_some()
+        || inputs.len() > 1
+        || args.input.iter().any(|i| is_glob(i));
+    let spec = match (args.preset, &args.spec) {
+        (Some(preset), path) => match (preset.spec(), path) {
+            (Some(spec), Some(path)) => Some(with_fields(&spec, &load_spec(path)?)?),
+            (Some(spec), None) => Some(spec),
+            (None, Some(_)) => {
+                return Err(anyhow::anyhow!(
+                    "--preset rss reads feeds as XML and cannot take --spec fields"
+                ));
+            }
+            (None, None) => return run_feed(&args, inputs, batch),
+        },
+        (None, Some(path)) => Some(Spec::from_json(&load_spec(path)?)?),
+        (None, None) => None,
+    };
+    let Some(spec) = spec else {
+        if batch {
+            return Err(anyhow::anyhow!(
+                "--select takes a single input, not a batch"
+            ));
+        }
+        let dom = html2json::Dom::parse(&read_html(&args, inputs.first().map(String::as_str))?)?;
+        return run_select(&args, &dom);
+    };
+    if batch {
+        if args.check.is_some() || args.cache_dom.is_some() || args.follow_next.is_some() {
+            return Err(anyhow::anyhow!(
+                "--check, --cache-dom and --follow-next take a single input, not a batch"
+            ));
+        }
+        return run_batch(&args, inputs, &|html, page| {
+            extract_html(&args, &spec, html, page)
+        });
+    }
+    if let Some(next) = &args.follow_next {
+        let input = inputs.first().map(String::as_str);
+        let mut result = follow_pages(&args, &spec, input, next)?;
+        add_source(&args, input, &mut result);
+        return finish(&args, result);
+    }
+
+    let input = inputs.first().map(String::as_str);
+    let dom = match (&args.cache_dom, input) {
+        (Some(cache_path), Some(input)) => load_cached_dom(&args, input, cache_path)?,
+        _ => html2json::Dom::parse(&read_html(&args, input)?)?,
+    };
+    if args.explain {
+        let (_, stats) = dom.extract_with_stats(&spec)?;
+        print_explain(&stats);
+        return Ok(());
+    }
+    let page = page_context(input, input.filter(|input| fetch::is_url(input)));
+    let mut result = extract_dom(&args, &spec, &dom, &page)?;
+    add_source(&args, input, &mut result);
+    finish(&args, result)
+}
+
+/// Add the input to each record of a result, for --add-source
+fn add_source(args: &Args, input: Option<&str>, result: &mut serde_json::Value) {
+    use serde_json::Value;
+    let Some(field) = &args.add_source else {
+        return;
+    };
+    let source = Value::from(input.unwrap_or("-"));
+    let records: Vec<_> = match result {
+        Value::Object(record) => vec![record],
+        Value::Array(items) => items.iter_mut().filter_map(Value::as_object_mut).collect(),
+        _ => Vec::new(),
+    };
+    for record in records {
+        record.insert(field.clone(), source.clone());
+    }
+}
+
+/// Send this crate's tracing events to stderr at the level `-v` asks for
+fn init_logging(verbose: u8, format: LogFormat) {
+    use tracing_subscriber::filter::{LevelFilter, Targets};
+    use tracing_subscriber::prelude::*;
+
+    let level = match verbose {
+        0 => return,
+        1 => LevelFilter::INFO,
+        2 => LevelFilter::DEBUG,
+        _ => LevelFilter::TRACE,
+    };
+    // Only our own events: dependencies like hyper are very chatty
+    let targets = Targets::new().with_target(env!("CARGO_CRATE_NAME"), level);
+    let layer = tracing_subscriber::fmt::layer().with_writer(std::io::stderr);
+    match format {
+        LogFormat::Text => tracing_subscriber::registry()
+            .with(layer.with_filter(targets))
+            .init(),
+        LogFormat::Json => tracing_subscriber::registry()
+            .with(layer.json().with_filter(targets))
+            .init(),
+    }
+}
+
+/// Check or print the result of a single-input run
+fn finish(args: &Args, result: serde_json::Value) -> Result<()> {
+    let result = &match &args.query {
+        Some(query) => query::Query::compile(query)?.apply(result)?,
+        None => result,
+    };
+    let problems = suspicious(args, result);
+    if let Some(check_path) = &args.check {
         // Compare against expected output
         let expected_value: serde_json::Value =
-            serde_json::from_str(&std::fs::read_to_string(&check_path)?)?;
-        let actual_json = serde_json::to_string_pretty(&result)?;
+            serde_json::from_str(&std::fs::read_to_string(check_path)?)?;
+        let actual_json = serde_json::to_string_pretty(result)?;
         let expected_json = serde_json::to_string_pretty(&expected_value)?;
 
-        if result == expected_value {
+        if *result == expected_value {
             eprintln!("✓ Output matches {}", check_path);
-            std::process::exit(0);
         } else {
             eprintln!("✗ Output differs from {}\n", check_path);
             print_diff(&expected_json, &actual_json);
             std::process::exit(1);
         }
     } else {
-        // Print output to stdout
-        println!("{}", serde_json::to_string_pretty(&result)?);
+        write_output(args, result, &*serializer(args)?)?;
     }
 
+    if !problems.is_empty() {
+        report_suspicious(None, &problems);
+        std::process::exit(1);
+    }
+    if args.check.is_some() {
+        std::process::exit(0);
+    }
     Ok(())
 }
 
+/// Most problems listed for one result before the rest are counted
+const MAX_PROBLEMS: usize = 10;
+
+/// What `--fail-on-null`, `--fail-on-empty-array`, `--min-items` and
+/// `--validate` find wrong with a result, as messages naming the offending paths
+fn suspicious(args: &Args, result: &serde_json::Value) -> Vec<String> {
+    use serde_json::Value;
+
+    fn walk(value: &Value, path: &str, visit: &mut impl FnMut(&str, &Value)) {
+        visit(if path.is_empty() { "." } else { path }, value);
+        match value {
+            Value::Object(fields) => {
+                for (key, value) in fields {
+                    walk(value, &format!("{}.{}", path, key), visit);
+                }
+            }
+            Value::Array(items) => {
+                for (i, value) in items.iter().enumerate() {
+                    walk(value, &format!("{}[{}]", path, i), visit);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut problems = Vec::new();
+    if args.fail_on_null || args.fail_on_empty_array {
+        walk(result, "", &mut |path, value| match value {
+            Value::Null if args.fail_on_null => problems.push(format!("{} is null", path)),
+            Value::Array(items) if items.is_empty() && args.fail_on_empty_array => {
+                problems.push(format!("{} is an empty array", path))
+            }
+            _ => {}
+        });
+    }
+    if let Some(min) = args.min_items {
+        let collections: Vec<(String, usize)> = match result {
+            Value::Array(items) => vec![(".".to_string(), items.len())],
+            Value::Object(fields) => fields
+                .iter()
+                .filter_map(|(key, value)| Some((format!(".{}", key), value.as_array()?.len())))
+                .collect(),
+            _ => Vec::new(),
+        };
+        if collections.is_empty() {
+            problems.push("no collection to count for --min-items".to_string());
+        }
+        for (path, len) in collections {
+            if len < min {
+                problems.push(format!(
+                    "{} has {} items, expected at least {}",
+                    path, len, min
+                ));
+            }
+        }
+    }
+    if let Some(schema) = &args.schema
+        && let Err(violations) = schema.validate(result)
+    {
+        problems.extend(violations.violations.iter().map(ToString::to_string));
+    }
+    problems
+}
+
+/// Print problems found by [`suspicious`] to stderr
+fn report_suspicious(source: Option<&str>, problems: &[String]) {
+    let prefix = source.map(|s| format!("{}: ", s)).unwrap_or_default();
+    for problem in problems.iter().take(MAX_PROBLEMS) {
+        eprintln!("{}{}✗{} {}", prefix, RED, RESET, problem);
+    }
+    if problems.len() > MAX_PROBLEMS {
+        eprintln!("{}... and {} more", prefix, problems.len() - MAX_PROBLEMS);
+    }
+}
+
+/// Extract from `input` and each following page linked by `next`
+///
+/// Stops after `--max-pages`, when a page has no next link, or when the link
+/// points back to a page already seen.
+fn follow_pages(
+    args: &Args,
+    spec: &Spec,
+    input: Option<&str>,
+    next: &str,
+) -> Result<serde_json::Value> {
+    // A one-field spec, so quoted `|`, attribute selectors like `[lang|=en]`
+    // and `||` fallbacks all work as in a spec
+    let next_spec = if next.trim() == PAGINATION {
+        None
+    } else {
+        Some(Spec::from_json(&serde_json::json!({ "next": next }))?)
+    };
+    // One client for every page so --delay and the cache apply across them
+    let fetcher = build_fetcher(args)?;
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+
+    let mut pages = Vec::new();
+    let mut seen = std::collections::HashSet::new();
+    let mut current = input.map(String::from);
+    while pages.len() < args.max_pages {
+        // Links are relative to where the page was served from, after redirects
+        let (html, base, page) = match &current {
+            Some(url) if fetch::is_url(url) => {
+                let doc = runtime.block_on(fetcher.fetch(url))?;
+                let page = page_context(Some(url), Some(&doc.url));
+                (check_html_size(doc.body)?, Some(doc.url), page)
+            }
+            _ => (
+                read_html(args, current.as_deref())?,
+                current.clone(),
+                page_context(current.as_deref(), None),
+            ),
+        };
+        let dom = html2json::Dom::parse(&html)?;
+        pages.push(dom.extract_with_options(spec, &ExtractOptions::new().context(page))?);
+
+        let mut found = match &next_spec {
+            Some(next_spec) => dom.extract(next_spec)?,
+            None => dom.pagination(None),
+        };
+        let href = match found["next"].take() {
+            serde_json::Value::String(s) if !s.trim().is_empty() => s,
+            _ => break,
+        };
+        let url = match &base {
+            Some(base) if fetch::is_url(base) => fetch::resolve_url(base, &href)?,
+            Some(base) if !fetch::is_url(&href) => resolve_file_link(base, &href)?,
+            _ => href,
+        };
+        if let Some(current) = current.take() {
+            seen.insert(current);
+        }
+        if !seen.insert(url.clone()) {
+            break;
+        }
+        current = Some(url);
+    }
+
+    Ok(merge_pages(pages))
+}
+
+/// Resolve a link on a local page against its `file://` URL, so `?page=2`,
+/// `../b.html` and `/c.html` resolve like in a browser
+fn resolve_file_link(page: &str, href: &str) -> Result<String> {
+    let invalid = || anyhow::anyhow!("Cannot resolve link '{}' from '{}'", href, page);
+    let base = std::path::absolute(page)
+        .ok()
+        .and_then(|path| reqwest::Url::from_file_path(path).ok())
+        .ok_or_else(invalid)?;
+    let url = reqwest::Url::parse(&fetch::resolve_url(base.as_str(), href)?)?;
+    let path = url.to_file_path().map_err(|()| invalid())?;
+    Ok(path.to_string_lossy().into_owned())
+}
+
+/// Concatenate per-page results: arrays are joined, as are array fields of
+/// objects; other fields keep their first non-null value
+fn merge_pages(pages: Vec<serde_json::Value>) -> serde_json::Value {
+    use serde_json::Value;
+    let mut pages = pages.into_iter();
+    let Some(mut merged) = pages.next() else {
+        return Value::Null;
+    };
+    for page in pages {
+        match (&mut merged, page) {
+            (Value::Array(items), Value::Array(more)) => items.extend(more),
+            (Value::Object(fields), Value::Object(more)) => {
+                for (key, value) in more {
+                    match (fields.get_mut(&key), value) {
+                        (Some(Value::Array(items)), Value::Array(more)) => items.extend(more),
+                        (Some(existing), value) if existing.is_null() => *existing = value,
+                        (Some(_), _) => {}
+                        (None, value) => {
+                            fields.insert(key, value);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    merged
+}
+
+/// The serializer chosen by `--template` or `--format`
+fn serializer(args: &Args) -> Result<Box<dyn output::Serializer>> {
+    match &args.template {
+        Some(path) => Ok(Box::new(output::Template::load(path.as_ref())?)),
+        None => Ok(args.format.unwrap_or_default().serializer(args.compact)),
+    }
+}
+
+/// Write a result to `--output`, or stdout
+fn write_output(
+    args: &Args,
+    result: &serde_json::Value,
+    serializer: &dyn output::Serializer,
+) -> Result<()> {
+    match &args.output {
+        Some(path) => output::write_file(path.as_ref(), result, serializer),
+        None => output::print(result, serializer),
+    }
+}
+
+/// Values of every element matching `selector`, after applying `pipes`
+fn select_values(
+    dom: &html2json::Dom,
+    selector: &str,
+    pipes: Option<&str>,
+) -> Result<Vec<serde_json::Value>> {
+    let field = match pipes {
+        Some(pipes) => format!("$ | {}", pipes),
+        None => "$".to_string(),
+    };
+    let spec = Spec::from_json(&serde_json::json!([{ "$": selector, "value": field }]))?;
+    Ok(match dom.extract(&spec)? {
+        serde_json::Value::Array(items) => items
+            .into_iter()
+            .map(|mut item| item["value"].take())
+            .collect(),
+        _ => Vec::new(),
+    })
+}
+
+/// Print the values matched by each `--select` selector
+///
+/// Without `--format`, values are printed one per line like `htmlq`; with it,
+/// the result is an object mapping each selector to its values.
+fn run_select(args: &Args, dom: &html2json::Dom) -> Result<()> {
+    let mut matches = serde_json::Map::new();
+    for selector in &args.select {
+        let values = select_values(dom, selector, args.pipe.as_deref())?;
+        matches.insert(selector.clone(), serde_json::Value::Array(values));
+    }
+
+    match args.format {
+        Some(format) => write_output(
+            args,
+            &serde_json::Value::Object(matches),
+            &*format.serializer(args.compact),
+        ),
+        None => {
+            // Keep command-line order; the map is sorted by selector
+            let values = args
+                .select
+                .iter()
+                .filter_map(|selector| matches.remove(selector))
+                .flat_map(|values| match values {
+                    serde_json::Value::Array(values) => values,
+                    other => vec![other],
+                })
+                .collect();
+            write_output(args, &serde_json::Value::Array(values), &output::Lines)
+        }
+    }
+}
+
+/// Print what each spec field matched, flagging fields that matched nothing
+fn print_explain(stats: &html2json::ExtractStats) {
+    let mut fields: Vec<_> = stats.fields.iter().collect();
+    fields.sort_by(|a, b| a.path.cmp(&b.path));
+    let width = fields.iter().map(|f| f.path.len()).max().unwrap_or(0);
+
+    for field in fields {
+        let calls = match field.calls {
+            1 => String::new(),
+            n => format!(" in {} evaluations", n),
+        };
+        let matched = if field.matched == 0 {
+            format!("{}{}no match{}", RED, BOLD, RESET)
+        } else {
+            format!("{} matched", field.matched)
+        };
+        println!(
+            "{:<width$}  {}  {}{}",
+            field.path, field.selector, matched, calls
+        );
+        if let Some(html) = &field.first_match {
+            let html = html.split_whitespace().collect::<Vec<_>>().join(" ");
+            let end = html.floor_char_boundary(120);
+            let more = if end < html.len() { "…" } else { "" };
+            println!("{:<width$}    {}{}", "", &html[..end], more);
+        }
+    }
+}
+
 fn print_diff(expected: &str, actual: &str) {
     let diff = TextDiff::from_lines(expected, actual);
 
@@ -81,15 +907,253 @@ fn print_diff(expected: &str, actual: &str) {
     }
 }
 
+/// Expand glob patterns, `--input-list` and `--sitemap` into the list of inputs
+///
+/// URLs and paths without glob characters are passed through untouched.
+fn collect_inputs(args: &Args) -> Result<Vec<String>> {
+    let mut patterns = args.input.clone();
+    if let Some(list) = &args.input_list {
+        let content = std::fs::read_to_string(list)
+            .map_err(|e| anyhow::anyhow!("Failed to read input list '{}': {}", list, e))?;
+        patterns.extend(
+            content
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(String::from),
+        );
+    }
+
+    let mut inputs = Vec::new();
+    if let Some(location) = &args.sitemap {
+        inputs.extend(sitemap::urls(args, location)?);
+    }
+    for pattern in patterns {
+        if !is_glob(&pattern) {
+            inputs.push(pattern);
+            continue;
+        }
+        let paths = glob::glob(&pattern)
+            .map_err(|e| anyhow::anyhow!("Invalid glob pattern '{}': {}", pattern, e))?;
+        let start = inputs.len();
+        for path in paths {
+            let path =
+                path.map_err(|e| anyhow::anyhow!("Failed to expand '{}': {}", pattern, e))?;
+            inputs.push(path.to_string_lossy().into_owned());
+        }
+        if inputs.len() == start {
+            return Err(anyhow::anyhow!("No files match '{}'", pattern));
+        }
+    }
+    Ok(inputs)
+}
+
+fn is_glob(input: &str) -> bool {
+    !fetch::is_url(input) && input.contains(['*', '?', '['])
+}
+
+/// Run `extract` over every input, printing one JSON line per document
+///
+/// Inputs are read or fetched `--jobs` at a time but reported in order. A
+/// failed input is reported as an `error` line and does not stop the batch;
+/// the exit status is non-zero if any input failed. With `--output DIR`, each
+/// result is written to its own file and the line names that file instead.
+fn run_batch(
+    args: &Args,
+    inputs: Vec<String>,
+    extract: &dyn Fn(&str, &PageContext) -> Result<serde_json::Value>,
+) -> Result<()> {
+    let query = args
+        .query
+        .as_deref()
+        .map(query::Query::compile)
+        .transpose()?;
+    if args.template.is_some() && args.output.is_none() {
+        return Err(anyhow::anyhow!(
+            "--template in batch mode needs --output DIR to write the rendered files to"
+        ));
+    }
+    let fetcher = build_fetcher(args)?;
+    let serializer = serializer(args)?;
+    let mut files = args
+        .output
+        .as_ref()
+        .map(|dir| output::OutputDir::create(dir.as_ref(), serializer.extension()))
+        .transpose()?;
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    let permits = std::sync::Arc::new(tokio::sync::Semaphore::new(args.jobs.max(1)));
+
+    let total = inputs.len();
+    let mut failed = 0;
+    runtime.block_on(async {
+        let mut tasks = tokio::task::JoinSet::new();
+        for (index, input) in inputs.iter().cloned().enumerate() {
+            let fetcher = fetcher.clone();
+            let permits = permits.clone();
+            tasks.spawn(async move {
+                let _permit = permits.acquire_owned().await;
+                let html = if fetch::is_url(&input) {
+                    fetcher.fetch(&input).await.and_then(|doc| {
+                        let page = page_context(Some(&input), Some(&doc.url));
+                        Ok((check_html_size(doc.body)?, page))
+                    })
+                } else {
+                    read_file(&input).map(|html| (html, page_context(Some(&input), None)))
+                };
+                (index, html)
+            });
+        }
+
+        // Emit results in input order as soon as each is available
+        let mut pending = std::collections::BTreeMap::new();
+        let mut next = 0;
+        while let Some(joined) = tasks.join_next().await {
+            let (index, html) = joined?;
+            pending.insert(index, html);
+            while let Some(html) = pending.remove(&next) {
+                let source = &inputs[next];
+                let result = html.and_then(|(html, page)| {
+                    let mut data = extract(&html, &page)?;
+                    add_source(args, Some(source), &mut data);
+                    match &query {
+                        Some(query) => query.apply(data),
+                        None => Ok(data),
+                    }
+                });
+                if let Ok(data) = &result {
+                    let problems = suspicious(args, data);
+                    if !problems.is_empty() {
+                        failed += 1;
+                        report_suspicious(Some(source), &problems);
+                    }
+                }
+                let written = match (result, &mut files) {
+                    (Ok(data), Some(files)) => files
+                        .write(source, &data, &*serializer)
+                        .map(|path| ("output", path.display().to_string().into())),
+                    (Ok(data), None) => Ok(("data", data)),
+                    (Err(e), _) => Err(e),
+                };
+                let (key, value) = match written {
+                    Ok(line) => line,
+                    Err(e) => {
+                        failed += 1;
+                        eprintln!("{}: {}", source, e);
+                        ("error", serde_json::Value::String(e.to_string()))
+                    }
+                };
+                // Written by hand so `source` leads each line
+                println!(
+                    "{{\"source\":{},\"{}\":{}}}",
+                    serde_json::Value::from(source.as_str()),
+                    key,
+                    value
+                );
+                next += 1;
+            }
+        }
+        Ok::<_, anyhow::Error>(())
+    })?;
+
+    if failed > 0 {
+        eprintln!("{} of {} inputs failed", failed, total);
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// A preset spec with the fields of a user spec added (or replaced)
+fn with_fields(preset: &Spec, extra: &serde_json::Value) -> Result<Spec> {
+    let mut merged = preset.to_json();
+    match (merged.as_object_mut(), extra.as_object()) {
+        (Some(fields), Some(extra)) if !extra.contains_key("$") => {
+            for (key, value) in extra {
+                // `title` replaces the preset's `title?` and vice versa
+                let name = key.strip_suffix('?').unwrap_or(key);
+                fields.remove(name);
+                fields.remove(&format!("{}?", name));
+                fields.insert(key.clone(), value.clone());
+            }
+        }
+        _ => {
+            return Err(anyhow::anyhow!(
+                "--spec used with --preset must be an object without a \"$\" scope"
+            ));
+        }
+    }
+    Spec::from_json(&merged)
+}
+
+/// Read feeds for `--preset rss`
+fn run_feed(args: &Args, inputs: Vec<String>, batch: bool) -> Result<()> {
+    if args.explain || args.stats || args.cache_dom.is_some() || args.follow_next.is_some() {
+        return Err(anyhow::anyhow!(
+            "--explain, --stats, --cache-dom and --follow-next run a spec, \
+             which --preset rss does not"
+        ));
+    }
+    let feed = |xml: &str, _: &PageContext| Ok(serde_json::to_value(html2json::feed::parse(xml)?)?);
+    if batch {
+        if args.check.is_some() {
+            return Err(anyhow::anyhow!("--check takes a single input, not a batch"));
+        }
+        return run_batch(args, inputs, &feed);
+    }
+    let input = inputs.first().map(String::as_str);
+    let mut result = feed(&read_html(args, input)?, &PageContext::new())?;
+    add_source(args, input, &mut result);
+    finish(args, result)
+}
+
+/// Parse and extract one document, printing stats if requested
+fn extract_html(
+    args: &Args,
+    spec: &Spec,
+    html: &str,
+    page: &PageContext,
+) -> Result<serde_json::Value> {
+    extract_dom(args, spec, &html2json::Dom::parse(html)?, page)
+}
+
+/// Extract from a parsed document, printing stats if requested
+fn extract_dom(
+    args: &Args,
+    spec: &Spec,
+    dom: &html2json::Dom,
+    page: &PageContext,
+) -> Result<serde_json::Value> {
+    let options = ExtractOptions::new().context(page.clone());
+    if args.stats {
+        let (result, stats) = dom.extract_with_stats_and_options(spec, &options)?;
+        eprint!("{}", stats);
+        Ok(result)
+    } else {
+        dom.extract_with_options(spec, &options)
+    }
+}
+
+/// What `{"$context": KEY}` fields see of an input: `source` is the path or
+/// URL as given (`-` for stdin), and a fetched page also has its `url`, after
+/// any redirects, and `fetchedAt`
+fn page_context(source: Option<&str>, fetched_url: Option<&str>) -> PageContext {
+    let page = PageContext::new().value("source", source.unwrap_or("-"));
+    match fetched_url {
+        Some(url) => page.url(url).fetched_at(std::time::SystemTime::now()),
+        None => page,
+    }
+}
+
 // Maximum sizes for security
 const MAX_HTML_SIZE: usize = 100_000_000; // 100MB
 const MAX_SPEC_SIZE: usize = 1_048_576; // 1MB
 
-/// Read HTML from a file path or stdin
-fn read_html(path: Option<&str>) -> Result<String> {
+/// Read HTML from a URL, a file path, or stdin
+fn read_html(args: &Args, path: Option<&str>) -> Result<String> {
     let content = match path {
-        Some(file_path) => std::fs::read_to_string(file_path)
-            .map_err(|e| anyhow::anyhow!("Failed to read file '{file_path}': {e}"))?,
+        Some(url) if fetch::is_url(url) => fetch_url(args, url)?,
+        Some(file_path) => read_file(file_path)?,
         None => {
             // Read from stdin
             let mut buffer = String::new();
@@ -100,18 +1164,236 @@ fn read_html(path: Option<&str>) -> Result<String> {
         }
     };
 
+    check_html_size(content)
+}
+
+/// Read a file, decompressing `.gz` and `.br` files and taking the page out
+/// of `.har` and `.mhtml` archives
+fn read_file(path: &str) -> Result<String> {
+    let failed = |e: std::io::Error| anyhow::anyhow!("Failed to read file '{path}': {e}");
+    let file = std::fs::File::open(path).map_err(failed)?;
+    let mut name = path.to_ascii_lowercase();
+    let reader: Box<dyn Read> = if let Some(stem) = name.strip_suffix(".gz") {
+        name = stem.to_string();
+        Box::new(flate2::read::MultiGzDecoder::new(file))
+    } else if let Some(stem) = name.strip_suffix(".br") {
+        name = stem.to_string();
+        Box::new(brotli_decompressor::Decompressor::new(file, 4096))
+    } else {
+        Box::new(file)
+    };
+
+    // Stop reading just past the limit, so a compression bomb is rejected
+    // without being inflated in full
+    let mut content = Vec::new();
+    reader
+        .take(MAX_HTML_SIZE as u64 + 1)
+        .read_to_end(&mut content)
+        .map_err(failed)?;
+    let content = if name.ends_with(".mhtml") || name.ends_with(".mht") {
+        archive::mhtml_html(&content)?
+    } else {
+        let content = String::from_utf8(content).map_err(|e| {
+            anyhow::anyhow!(
+                "Failed to read file '{path}': not UTF-8 ({})",
+                e.utf8_error()
+            )
+        })?;
+        if name.ends_with(".har") {
+            archive::har_html(&content)?
+        } else {
+            content
+        }
+    };
+    check_html_size(content)
+}
+
+fn check_html_size(content: String) -> Result<String> {
     if content.len() > MAX_HTML_SIZE {
         return Err(anyhow::anyhow!(
             "HTML input exceeds maximum size of {} bytes",
             MAX_HTML_SIZE
         ));
     }
-
     Ok(content)
 }
 
-/// Load spec from a JSON file
+/// Fetch a URL on a single-threaded runtime
+fn fetch_url(args: &Args, url: &str) -> Result<String> {
+    let fetcher = build_fetcher(args)?;
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?
+        .block_on(fetcher.fetch(url))
+        .map(|doc| doc.body)
+}
+
+#[cfg(feature = "render")]
+const RENDER_ENDPOINT: &str = html2json::render::DEFAULT_ENDPOINT;
+#[cfg(not(feature = "render"))]
+const RENDER_ENDPOINT: &str = "http://127.0.0.1:9222";
+
+/// Where URL input is fetched from
+#[derive(Debug, Clone)]
+enum PageFetcher {
+    Http(fetch::HttpFetcher),
+    #[cfg(feature = "render")]
+    Browser(html2json::render::BrowserFetcher),
+}
+
+impl Fetcher for PageFetcher {
+    async fn fetch(&self, url: &str) -> Result<fetch::FetchedDoc> {
+        match self {
+            Self::Http(fetcher) => fetcher.fetch(url).await,
+            #[cfg(feature = "render")]
+            Self::Browser(fetcher) => fetcher.fetch(url).await,
+        }
+    }
+}
+
+/// Configure fetching from the command line
+fn build_fetcher(args: &Args) -> Result<PageFetcher> {
+    let Some(endpoint) = &args.render else {
+        return build_http_fetcher(args).map(PageFetcher::Http);
+    };
+    // The browser makes its own requests
+    let http_options = [
+        ("--header", !args.headers.is_empty()),
+        ("--cookie", !args.cookies.is_empty()),
+        ("--user-agent", args.user_agent.is_some()),
+        ("--proxy", args.proxy.is_some()),
+    ];
+    if let Some((option, _)) = http_options.iter().find(|(_, set)| *set) {
+        return Err(anyhow::anyhow!(
+            "{} can't be combined with --render; configure the browser instead",
+            option
+        ));
+    }
+    build_browser_fetcher(args, endpoint)
+}
+
+/// Configure the headless browser from the command line
+#[cfg(feature = "render")]
+fn build_browser_fetcher(args: &Args, endpoint: &str) -> Result<PageFetcher> {
+    let mut fetcher = html2json::render::BrowserFetcher::new(endpoint);
+    if let Some(selector) = &args.wait_for {
+        fetcher = fetcher.wait_for(selector);
+    }
+    if let Some(timeout) = args.render_timeout {
+        let timeout = std::time::Duration::try_from_secs_f64(timeout)
+            .map_err(|_| anyhow::anyhow!("Invalid --render-timeout {}", timeout))?;
+        fetcher = fetcher.timeout(timeout);
+    }
+    Ok(PageFetcher::Browser(fetcher))
+}
+
+#[cfg(not(feature = "render"))]
+fn build_browser_fetcher(_args: &Args, _endpoint: &str) -> Result<PageFetcher> {
+    Err(anyhow::anyhow!(
+        "--render needs html2json to be built with the `render` feature"
+    ))
+}
+
+/// Configure the HTTP client from the command line
+fn build_http_fetcher(args: &Args) -> Result<fetch::HttpFetcher> {
+    let mut builder = fetch::HttpFetcher::builder();
+    for header in &args.headers {
+        let (name, value) = header.split_once(':').ok_or_else(|| {
+            anyhow::anyhow!("Invalid header '{}': expected 'Name: Value'", header)
+        })?;
+        builder = builder.header(name, value);
+    }
+    for cookie in &args.cookies {
+        builder = builder.cookie(cookie);
+    }
+    if let Some(user_agent) = &args.user_agent {
+        builder = builder.user_agent(user_agent);
+    }
+    if let Some(proxy) = &args.proxy {
+        builder = builder.proxy(proxy);
+    }
+    builder = builder
+        .retries(args.retries)
+        .respect_robots(args.respect_robots);
+    if let Some(delay) = args.delay {
+        let delay = std::time::Duration::try_from_secs_f64(delay)
+            .map_err(|_| anyhow::anyhow!("Invalid --delay {}", delay))?;
+        builder = builder.delay(delay);
+    }
+    if let Some(rps) = args.max_rps {
+        builder = builder.max_rps(rps);
+    }
+    if let Some(n) = args.max_per_host {
+        builder = builder.max_per_host(n);
+    }
+    if let Some(dir) = args.cache_dir.as_ref().filter(|_| !args.no_cache) {
+        builder = builder
+            .cache_dir(dir)
+            .cache_ttl(std::time::Duration::from_secs(args.cache_ttl))
+            .refresh(args.refresh);
+    }
+    builder.build()
+}
+
+/// Load a DOM snapshot from `cache_path`, or parse `input` and write one
+///
+/// The snapshot starts with a line naming the input's path, size and
+/// modification time, and is only reused while they all still match.
+fn load_cached_dom(args: &Args, input: &str, cache_path: &str) -> Result<html2json::Dom> {
+    use std::io::{BufRead, Write};
+
+    let header = dom_cache_header(input);
+    if let Some(header) = &header
+        && let Ok(file) = std::fs::File::open(cache_path)
+    {
+        let mut reader = std::io::BufReader::new(file);
+        let mut line = String::new();
+        // A snapshot of another input, or a stale or corrupt file, is not
+        // fatal; fall through and reparse
+        if reader.read_line(&mut line).is_ok()
+            && line == *header
+            && let Ok(dom) = html2json::Dom::read_snapshot(reader)
+        {
+            return Ok(dom);
+        }
+    }
+
+    let dom = html2json::Dom::parse(&read_html(args, Some(input))?)?;
+    let mut file = std::fs::File::create(cache_path)
+        .map_err(|e| anyhow::anyhow!("Failed to create DOM cache '{}': {}", cache_path, e))?;
+    // Without a header (the input is not a file) the snapshot never matches
+    file.write_all(header.as_deref().unwrap_or("\n").as_bytes())?;
+    dom.write_snapshot(file)?;
+    Ok(dom)
+}
+
+/// The first line of a DOM snapshot of `input`, identifying the file it was
+/// parsed from
+fn dom_cache_header(input: &str) -> Option<String> {
+    let metadata = std::fs::metadata(input).ok()?;
+    let modified = metadata
+        .modified()
+        .ok()?
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()?;
+    let path = std::fs::canonicalize(input).ok()?;
+    let path = path.to_str().filter(|path| !path.contains('\n'))?;
+    Some(format!(
+        "html2json-dom {} {}.{:09} {}\n",
+        metadata.len(),
+        modified.as_secs(),
+        modified.subsec_nanos(),
+        path
+    ))
+}
+
+/// Load a spec file, parsed by its extension (JSON unless JSON5, YAML or JS)
 fn load_spec(path: &str) -> Result<serde_json::Value> {
+    let format = specs::SpecFormat::from_path(path).unwrap_or(specs::SpecFormat::Json);
+    format.parse(&read_spec_file(path)?)
+}
+
+fn read_spec_file(path: &str) -> Result<String> {
     let content = std::fs::read_to_string(path)
         .map_err(|e| anyhow::anyhow!("Failed to read spec file '{}': {}", path, e))?;
 
@@ -122,8 +1404,85 @@ fn load_spec(path: &str) -> Result<serde_json::Value> {
         ));
     }
 
-    let value: serde_json::Value = serde_json::from_str(&content)
-        .map_err(|e| anyhow::anyhow!("Failed to parse spec JSON: {}", e))?;
+    Ok(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dom_cache_is_tied_to_its_input() {
+        let dir = std::env::temp_dir().join(format!("html2json-dom-cache-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let first = dir.join("first.html");
+        let second = dir.join("second.html");
+        let cache = dir.join("page.dom");
+        std::fs::write(&first, "<h1>First</h1>").unwrap();
+        std::fs::write(&second, "<h1>Second</h1>").unwrap();
+        // Same size and mtime, so only the path tells them apart
+        let mtime = std::fs::metadata(&first).unwrap().modified().unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(&second)
+            .unwrap()
+            .set_modified(mtime)
+            .unwrap();
+
+        let args = Args::parse_from(["html2json"]);
+        let spec = Spec::from_json(&serde_json::json!({"title": "h1"})).unwrap();
+        let title = |input: &std::path::Path| {
+            let dom =
+                load_cached_dom(&args, input.to_str().unwrap(), cache.to_str().unwrap()).unwrap();
+            dom.extract(&spec).unwrap()["title"].clone()
+        };
+        let results = [title(&first), title(&second), title(&second), title(&first)];
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(results, ["First", "Second", "Second", "First"]);
+    }
+
+    #[test]
+    fn follow_next_resolves_local_links() {
+        let dir = std::env::temp_dir().join(format!("html2json-follow-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("more")).unwrap();
+        let first = dir.join("index.html");
+        std::fs::write(
+            &first,
+            r#"<h1>One</h1><a hreflang="en-GB" href="more/two.html#top">Next</a>"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("more/two.html"),
+            r#"<h1>Two</h1><a hreflang="en" href="../three.html?page=3">Next</a>"#,
+        )
+        .unwrap();
+        // A query-only link points back to the same file
+        std::fs::write(
+            dir.join("three.html"),
+            r#"<h1>Three</h1><a hreflang="en" href="?page=4">Next</a>"#,
+        )
+        .unwrap();
+
+        let args = Args::parse_from(["html2json"]);
+        let spec = Spec::from_json(&serde_json::json!([{"$": "h1", "title": "$"}])).unwrap();
+        let result = follow_pages(
+            &args,
+            &spec,
+            first.to_str(),
+            "a[hreflang|=en] | attr:href || a[rel=next] | attr:href",
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            result.unwrap(),
+            serde_json::json!([{"title": "One"}, {"title": "Two"}, {"title": "Three"}])
+        );
+    }
 
-    Ok(value)
+    #[cfg(not(feature = "render"))]
+    #[test]
+    fn render_needs_the_render_feature() {
+        let args = Args::parse_from(["html2json", "--render"]);
+        let err = build_fetcher(&args).unwrap_err();
+        assert!(err.to_string().contains("`render` feature"), "{err}");
+    }
 }
@@ -0,0 +1,410 @@
+//! HTML to Markdown conversion
+//!
+//! Backs the `markdown` pipe. Headings, paragraphs, emphasis, links, images,
+//! inline code, fenced code blocks, block quotes, nested lists and simple
+//! tables are converted; scripts, styles and other non-content elements are
+//! dropped, and any other element contributes its content.
+//!
+//! # Example
+//!
+//! ```
+//! let md = html2json::markdown::from_html(
+//!     r#"<h2>Intro</h2><p>Read <a href="/docs">the <em>docs</em></a>.</p><ul><li>one</li><li>two</li></ul>"#,
+//! );
+//! assert_eq!(md, "## Intro\n\nRead [the *docs*](/docs).\n\n- one\n- two");
+//! ```
+
+use scraper::{ElementRef, Html, Node};
+
+/// Convert an HTML fragment to Markdown
+pub fn from_html(html: &str) -> String {
+    element(Html::parse_fragment(html).root_element())
+}
+
+/// Markdown for the content of an element (not the element's own tag)
+pub(crate) fn element(el: ElementRef) -> String {
+    join(&blocks(el), false)
+}
+
+/// A rendered block and whether it is a list (lists nest tightly in list items)
+struct Block {
+    text: String,
+    list: bool,
+}
+
+fn join(blocks: &[Block], tight_lists: bool) -> String {
+    let mut out = String::new();
+    for (i, block) in blocks.iter().enumerate() {
+        if i > 0 {
+            out.push_str(if tight_lists && block.list {
+                "\n"
+            } else {
+                "\n\n"
+            });
+        }
+        out.push_str(&block.text);
+    }
+    out
+}
+
+/// The children of `parent` as blocks, with runs of inline content as paragraphs
+fn blocks(parent: ElementRef) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut inline = String::new();
+    for child in parent.children() {
+        match child.value() {
+            Node::Text(text) => push_text(&mut inline, text),
+            Node::Element(element) if is_block(element.name()) => {
+                flush_paragraph(&mut inline, &mut blocks);
+                if let Some(el) = ElementRef::wrap(child) {
+                    let list = matches!(element.name(), "ul" | "ol");
+                    let text = block(el);
+                    if !text.trim().is_empty() {
+                        blocks.push(Block { text, list });
+                    }
+                }
+            }
+            Node::Element(_) => {
+                if let Some(el) = ElementRef::wrap(child) {
+                    push_inline(&mut inline, &inline_element(el));
+                }
+            }
+            _ => {}
+        }
+    }
+    flush_paragraph(&mut inline, &mut blocks);
+    blocks
+}
+
+fn flush_paragraph(inline: &mut String, blocks: &mut Vec<Block>) {
+    let text = paragraph(inline);
+    if !text.is_empty() {
+        blocks.push(Block { text, list: false });
+    }
+    inline.clear();
+}
+
+/// Trim a paragraph and the start of each of its lines (hard breaks end in two spaces)
+fn paragraph(inline: &str) -> String {
+    inline
+        .trim()
+        .lines()
+        .map(str::trim_start)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn block(el: ElementRef) -> String {
+    let name = el.value().name();
+    match name {
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+            let level = usize::from(name.as_bytes()[1] - b'0');
+            let text = single_line(&inline_children(el));
+            if text.is_empty() {
+                String::new()
+            } else {
+                format!("{} {}", "#".repeat(level), text)
+            }
+        }
+        "p" => paragraph(&inline_children(el)),
+        "ul" | "ol" => list(el),
+        "pre" => code_block(el),
+        "blockquote" => element(el)
+            .lines()
+            .map(|line| {
+                if line.is_empty() {
+                    ">".to_string()
+                } else {
+                    format!("> {}", line)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        "hr" => "---".to_string(),
+        "table" => table(el),
+        name if is_skipped(name) => String::new(),
+        _ => element(el),
+    }
+}
+
+fn list(el: ElementRef) -> String {
+    let ordered = el.value().name() == "ol";
+    let start: usize = el
+        .value()
+        .attr("start")
+        .and_then(|start| start.trim().parse().ok())
+        .unwrap_or(1);
+    let mut items = Vec::new();
+    for (i, item) in el
+        .children()
+        .filter_map(ElementRef::wrap)
+        .filter(|child| child.value().name() == "li")
+        .enumerate()
+    {
+        let marker = if ordered {
+            format!("{}. ", start + i)
+        } else {
+            "- ".to_string()
+        };
+        let indent = " ".repeat(marker.len());
+        let content = join(&blocks(item), true);
+        let mut lines = content.lines();
+        let mut text = format!("{}{}", marker, lines.next().unwrap_or_default());
+        for line in lines {
+            text.push('\n');
+            if !line.is_empty() {
+                text.push_str(&indent);
+                text.push_str(line);
+            }
+        }
+        items.push(text.trim_end().to_string());
+    }
+    items.join("\n")
+}
+
+fn code_block(el: ElementRef) -> String {
+    // The language is conventionally a `language-*` class on the inner `<code>`
+    let language = std::iter::once(el)
+        .chain(el.children().filter_map(ElementRef::wrap))
+        .filter_map(|el| el.value().attr("class"))
+        .flat_map(str::split_whitespace)
+        .find_map(|class| {
+            class
+                .strip_prefix("language-")
+                .or_else(|| class.strip_prefix("lang-"))
+        })
+        .unwrap_or_default();
+    let code: String = el.text().collect();
+    let code = code.strip_prefix('\n').unwrap_or(&code).trim_end();
+    let fence = "`".repeat(longest_run(code, '`').max(2) + 1);
+    format!("{}{}\n{}\n{}", fence, language, code, fence)
+}
+
+fn table(el: ElementRef) -> String {
+    let rows: Vec<Vec<String>> = el
+        .children()
+        .filter_map(ElementRef::wrap)
+        .flat_map(|section| match section.value().name() {
+            "thead" | "tbody" | "tfoot" => {
+                section.children().filter_map(ElementRef::wrap).collect()
+            }
+            _ => vec![section],
+        })
+        .filter(|row| row.value().name() == "tr")
+        .map(|row| {
+            row.children()
+                .filter_map(ElementRef::wrap)
+                .filter(|cell| matches!(cell.value().name(), "td" | "th"))
+                .map(|cell| single_line(&inline_children(cell)).replace('|', "\\|"))
+                .collect()
+        })
+        .filter(|cells: &Vec<String>| !cells.is_empty())
+        .collect();
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    if columns == 0 {
+        return String::new();
+    }
+
+    let line = |cells: &[String]| {
+        let mut line = String::from("|");
+        for i in 0..columns {
+            line.push(' ');
+            line.push_str(cells.get(i).map(String::as_str).unwrap_or_default());
+            line.push_str(" |");
+        }
+        line
+    };
+    // Markdown tables need a header row, so the first row serves as one
+    let mut lines = vec![line(&rows[0]), format!("|{}", " --- |".repeat(columns))];
+    lines.extend(rows[1..].iter().map(|row| line(row)));
+    lines.join("\n")
+}
+
+/// Markdown for an inline element, including its own markup
+fn inline_element(el: ElementRef) -> String {
+    let element = el.value();
+    match element.name() {
+        "strong" | "b" => wrap(&inline_children(el), "**"),
+        "em" | "i" => wrap(&inline_children(el), "*"),
+        "del" | "s" | "strike" => wrap(&inline_children(el), "~~"),
+        "code" | "kbd" | "samp" => {
+            let code: String = el.text().collect();
+            let code = code.split_whitespace().collect::<Vec<_>>().join(" ");
+            if code.is_empty() {
+                return String::new();
+            }
+            let fence = "`".repeat(longest_run(&code, '`') + 1);
+            let pad = if code.starts_with('`') || code.ends_with('`') {
+                " "
+            } else {
+                ""
+            };
+            format!("{}{}{}{}{}", fence, pad, code, pad, fence)
+        }
+        "a" => {
+            let text = inline_children(el);
+            match element.attr("href").map(str::trim) {
+                Some(href) if !href.is_empty() && !href.starts_with("javascript:") => {
+                    let text = single_line(&text);
+                    if text.is_empty() {
+                        format!("<{}>", href)
+                    } else {
+                        format!("[{}]({})", text, destination(href))
+                    }
+                }
+                _ => text,
+            }
+        }
+        "img" => match element.attr("src") {
+            Some(src) if !src.trim().is_empty() => format!(
+                "![{}]({})",
+                escape(element.attr("alt").unwrap_or_default().trim()),
+                destination(src.trim())
+            ),
+            _ => String::new(),
+        },
+        "br" => "  \n".to_string(),
+        name if is_skipped(name) => String::new(),
+        _ => inline_children(el),
+    }
+}
+
+/// Inline Markdown for everything inside `el`, block structure flattened
+fn inline_children(el: ElementRef) -> String {
+    let mut out = String::new();
+    for child in el.children() {
+        match child.value() {
+            Node::Text(text) => push_text(&mut out, text),
+            Node::Element(_) => {
+                if let Some(child) = ElementRef::wrap(child) {
+                    push_inline(&mut out, &inline_element(child));
+                }
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+/// Append text with whitespace collapsed and Markdown syntax escaped
+fn push_text(out: &mut String, text: &str) {
+    if text.starts_with(char::is_whitespace) {
+        push_inline(out, " ");
+    }
+    for (i, word) in text.split_whitespace().enumerate() {
+        if i > 0 {
+            out.push(' ');
+        }
+        out.push_str(&escape(word));
+    }
+    if text.ends_with(char::is_whitespace) && !text.trim().is_empty() {
+        out.push(' ');
+    }
+}
+
+/// Append inline Markdown without doubling spaces between pieces
+fn push_inline(out: &mut String, markdown: &str) {
+    if markdown.starts_with(' ') && (out.is_empty() || out.ends_with([' ', '\n'])) {
+        out.push_str(markdown.trim_start_matches(' '));
+    } else {
+        out.push_str(markdown);
+    }
+}
+
+/// Surround text with emphasis markers, keeping its outer spaces outside them
+fn wrap(text: &str, marker: &str) -> String {
+    let inner = text.trim();
+    if inner.is_empty() {
+        return text.to_string();
+    }
+    let before = if text.starts_with(char::is_whitespace) {
+        " "
+    } else {
+        ""
+    };
+    let after = if text.ends_with(char::is_whitespace) {
+        " "
+    } else {
+        ""
+    };
+    format!("{}{}{}{}{}", before, marker, inner, marker, after)
+}
+
+/// A link destination, bracketed if it would otherwise end the link early
+fn destination(url: &str) -> String {
+    if url.contains([' ', '(', ')']) {
+        format!("<{}>", url)
+    } else {
+        url.to_string()
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '*' | '_' | '`' | '[' | ']') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+fn single_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn longest_run(text: &str, c: char) -> usize {
+    let (mut longest, mut run) = (0, 0);
+    for ch in text.chars() {
+        run = if ch == c { run + 1 } else { 0 };
+        longest = longest.max(run);
+    }
+    longest
+}
+
+fn is_block(name: &str) -> bool {
+    matches!(
+        name,
+        "address"
+            | "article"
+            | "aside"
+            | "blockquote"
+            | "body"
+            | "dd"
+            | "details"
+            | "div"
+            | "dl"
+            | "dt"
+            | "figcaption"
+            | "figure"
+            | "footer"
+            | "h1"
+            | "h2"
+            | "h3"
+            | "h4"
+            | "h5"
+            | "h6"
+            | "header"
+            | "hr"
+            | "html"
+            | "li"
+            | "main"
+            | "nav"
+            | "ol"
+            | "p"
+            | "pre"
+            | "section"
+            | "summary"
+            | "table"
+            | "ul"
+    ) || is_skipped(name)
+}
+
+/// Elements with no content worth converting
+fn is_skipped(name: &str) -> bool {
+    matches!(
+        name,
+        "head" | "link" | "meta" | "noscript" | "script" | "style" | "template" | "title"
+    )
+}
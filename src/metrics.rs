@@ -0,0 +1,80 @@
+//! Counters and histograms for the [`metrics`](https://docs.rs/metrics) facade
+//!
+//! Available with the `metrics` feature. Every parse and extraction reports
+//! to whichever recorder the application has installed (for example
+//! `metrics-exporter-prometheus`), so a scraping fleet gets throughput,
+//! miss rates and latency without wrapping each call. Without a recorder
+//! the calls do nothing.
+//!
+//! Metrics are global and unlabelled. [`describe`] registers their units
+//! and help text with the recorder.
+//!
+//! # Example
+//!
+//! ```no_run
+//! // After installing a recorder
+//! html2json::metrics::describe();
+//! let spec: html2json::Spec = serde_json::from_str(r#"{"title": "h1"}"#)?;
+//! html2json::extract("<h1>Hello</h1>", &spec)?;
+//! // html2json_documents_total is now 1, html2json_fields_total 1, ...
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use metrics::{Unit, counter, describe_counter, describe_histogram, histogram};
+use std::time::Duration;
+
+/// Documents parsed into a [`Dom`](crate::Dom)
+pub const DOCUMENTS: &str = "html2json_documents_total";
+/// Fields evaluated, counting each collection item's fields separately
+pub const FIELDS: &str = "html2json_fields_total";
+/// Fields that produced `null`, optional or not
+pub const NULL_FIELDS: &str = "html2json_null_fields_total";
+/// Selector fields whose selector matched no element
+pub const SELECTOR_MISSES: &str = "html2json_selector_misses_total";
+/// Extractions that failed with an error, including exceeded limits
+pub const ERRORS: &str = "html2json_errors_total";
+/// Time to parse a document, in seconds
+pub const PARSE_SECONDS: &str = "html2json_parse_seconds";
+/// Time to extract a spec from a parsed document, in seconds
+///
+/// Not recorded for [`Dom::extract_iter`](crate::Dom::extract_iter), whose
+/// items are extracted as the caller asks for them.
+pub const EXTRACT_SECONDS: &str = "html2json_extract_seconds";
+
+/// Register descriptions of all html2json metrics with the installed recorder
+pub fn describe() {
+    describe_counter!(DOCUMENTS, Unit::Count, "Documents parsed");
+    describe_counter!(FIELDS, Unit::Count, "Fields evaluated");
+    describe_counter!(NULL_FIELDS, Unit::Count, "Fields that produced null");
+    describe_counter!(
+        SELECTOR_MISSES,
+        Unit::Count,
+        "Selector fields that matched no element"
+    );
+    describe_counter!(ERRORS, Unit::Count, "Extractions that failed");
+    describe_histogram!(PARSE_SECONDS, Unit::Seconds, "Time to parse a document");
+    describe_histogram!(EXTRACT_SECONDS, Unit::Seconds, "Time to extract a spec");
+}
+
+pub(crate) fn parsed(elapsed: Duration) {
+    counter!(DOCUMENTS).increment(1);
+    histogram!(PARSE_SECONDS).record(elapsed);
+}
+
+pub(crate) fn extracted<T>(elapsed: Duration, result: &Result<T, anyhow::Error>) {
+    histogram!(EXTRACT_SECONDS).record(elapsed);
+    if result.is_err() {
+        counter!(ERRORS).increment(1);
+    }
+}
+
+pub(crate) fn field(null: bool) {
+    counter!(FIELDS).increment(1);
+    if null {
+        counter!(NULL_FIELDS).increment(1);
+    }
+}
+
+pub(crate) fn selector_miss() {
+    counter!(SELECTOR_MISSES).increment(1);
+}
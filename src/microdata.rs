@@ -0,0 +1,126 @@
+//! Microdata (`itemscope`/`itemprop`/`itemtype`) items as JSON
+//!
+//! Items come out shaped like JSON-LD, so both sources of structured data
+//! read the same: `{"@type": "Product", "name": "Lamp", "offers": {...}}`.
+//! Types are shortened to their last path segment, `itemid` becomes `@id`,
+//! and a property given more than once becomes an array.
+
+use ego_tree::NodeId;
+use scraper::{ElementRef, Html, Selector};
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+use std::sync::LazyLock;
+
+/// The top-level items at or inside `scope` (the whole document if `None`)
+pub(crate) fn items<'a>(html: &'a Html, scope: Option<ElementRef<'a>>) -> Vec<ElementRef<'a>> {
+    static TOP_LEVEL: LazyLock<Selector> = LazyLock::new(|| {
+        Selector::parse("[itemscope]:not([itemprop])").expect("microdata selector")
+    });
+    match scope {
+        Some(scope) if scope.value().attr("itemscope").is_some() => vec![scope],
+        Some(scope) => scope.select(&TOP_LEVEL).collect(),
+        None => html.select(&TOP_LEVEL).collect(),
+    }
+}
+
+/// An item element as a JSON object
+pub(crate) fn item(html: &Html, element: ElementRef) -> Value {
+    let mut visited = HashSet::new();
+    item_value(html, element, &mut visited)
+}
+
+fn item_value(html: &Html, element: ElementRef, visited: &mut HashSet<NodeId>) -> Value {
+    let mut object = Map::new();
+    if let Some(types) = element.value().attr("itemtype") {
+        let types: Vec<Value> = types
+            .split_whitespace()
+            .map(|t| Value::from(t.rsplit(['/', '#']).next().unwrap_or(t)))
+            .collect();
+        match types.len() {
+            0 => {}
+            1 => {
+                object.insert("@type".to_string(), types[0].clone());
+            }
+            _ => {
+                object.insert("@type".to_string(), Value::Array(types));
+            }
+        }
+    }
+    if let Some(id) = element.value().attr("itemid") {
+        object.insert("@id".to_string(), Value::from(id));
+    }
+    visited.insert(element.id());
+
+    // The item's own subtree, then the elements named by `itemref`
+    let mut pending: Vec<ElementRef> = element.children().filter_map(ElementRef::wrap).collect();
+    for id in element
+        .value()
+        .attr("itemref")
+        .unwrap_or_default()
+        .split_whitespace()
+    {
+        if let Some(referenced) = by_id(html, id) {
+            pending.push(referenced);
+        }
+    }
+    pending.reverse();
+
+    while let Some(el) = pending.pop() {
+        // Guards against itemref cycles
+        if !visited.insert(el.id()) {
+            continue;
+        }
+        let is_item = el.value().attr("itemscope").is_some();
+        if let Some(names) = el.value().attr("itemprop") {
+            let value = if is_item {
+                item_value(html, el, visited)
+            } else {
+                property_value(el)
+            };
+            for name in names.split_whitespace() {
+                add_property(&mut object, name, value.clone());
+            }
+        }
+        // A nested item's properties belong to it
+        if !is_item {
+            let children: Vec<ElementRef> = el.children().filter_map(ElementRef::wrap).collect();
+            pending.extend(children.into_iter().rev());
+        }
+    }
+    Value::Object(object)
+}
+
+/// The value of a non-item property element, per the microdata spec
+fn property_value(el: ElementRef) -> Value {
+    let element = el.value();
+    let attr = |name| Value::from(element.attr(name).unwrap_or_default());
+    match element.name() {
+        "meta" => attr("content"),
+        "audio" | "embed" | "iframe" | "img" | "source" | "track" | "video" => attr("src"),
+        "a" | "area" | "link" => attr("href"),
+        "object" => attr("data"),
+        "data" | "meter" => attr("value"),
+        "time" if element.attr("datetime").is_some() => attr("datetime"),
+        _ => Value::from(el.text().collect::<String>().trim()),
+    }
+}
+
+fn add_property(object: &mut Map<String, Value>, name: &str, value: Value) {
+    match object.get_mut(name) {
+        None => {
+            object.insert(name.to_string(), value);
+        }
+        Some(Value::Array(values)) => values.push(value),
+        Some(existing) => {
+            let first = existing.take();
+            *existing = Value::Array(vec![first, value]);
+        }
+    }
+}
+
+fn by_id<'a>(html: &'a Html, id: &str) -> Option<ElementRef<'a>> {
+    html.tree
+        .nodes()
+        .filter_map(ElementRef::wrap)
+        .find(|el| el.value().id() == Some(id))
+}
@@ -0,0 +1,455 @@
+//! Extraction options and resource limits
+
+use crate::dom::Node;
+use crate::spec::FieldSpec;
+use crate::stats::StatsCollector;
+use ego_tree::NodeId;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Instant, SystemTime};
+
+/// Constant giving the page's URL when [`ExtractOptions::base_url`] isn't set
+#[cfg(feature = "url")]
+const BASE_URL_CONST: &str = "baseUrl";
+
+/// Options controlling a single extraction
+///
+/// The defaults impose no limits. Set limits when running untrusted specs or
+/// documents, so a spec like `[{"$": "*", "html": "$"}]` can't exhaust memory.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExtractOptions {
+    /// Maximum total bytes of node text and attribute values read while
+    /// extracting
+    pub max_output_bytes: Option<usize>,
+    /// Maximum number of nodes matched by scope, collection and field selectors
+    pub max_nodes: Option<usize>,
+    /// Whether to report which element each value was read from
+    pub provenance: Provenance,
+    /// URL of the page, for resolving relative URLs (`$images`, `$links`)
+    pub base_url: Option<String>,
+    /// Constants for `$name` pipe arguments, overriding the spec's `"$consts"`
+    pub consts: HashMap<String, String>,
+    /// What is known about the document, for `{"$context": KEY}` fields
+    pub context: PageContext,
+}
+
+impl ExtractOptions {
+    /// Options with no limits
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cap the total bytes of extracted text
+    pub fn max_output_bytes(mut self, bytes: usize) -> Self {
+        self.max_output_bytes = Some(bytes);
+        self
+    }
+
+    /// Cap the number of nodes matched by selectors
+    pub fn max_nodes(mut self, nodes: usize) -> Self {
+        self.max_nodes = Some(nodes);
+        self
+    }
+
+    /// Report where each value came from (see [`Provenance`])
+    pub fn provenance(mut self, provenance: Provenance) -> Self {
+        self.provenance = provenance;
+        self
+    }
+
+    /// Resolve relative URLs against the page's URL
+    ///
+    /// A `<base href>` in the document is resolved against it and takes
+    /// precedence, as in a browser.
+    pub fn base_url(mut self, url: impl Into<String>) -> Self {
+        self.base_url = Some(url.into());
+        self
+    }
+
+    /// Set a constant for `$name` pipe arguments, overriding the spec's
+    /// `"$consts"` (so one spec can serve several sites or environments)
+    pub fn constant(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.consts.insert(name.into(), value.into());
+        self
+    }
+
+    /// Describe the document for `{"$context": KEY}` fields; its URL also
+    /// resolves relative URLs if [`base_url`](Self::base_url) isn't set
+    pub fn context(mut self, context: PageContext) -> Self {
+        self.context = context;
+        self
+    }
+}
+
+/// What is known about a document besides its HTML
+///
+/// Specs read it with `{"$context": "url"}`, `{"$context": "fetchedAt"}`, or
+/// the name of one of the [`values`](Self::values); anything unknown is `null`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PageContext {
+    /// Where the document came from
+    pub url: Option<String>,
+    /// When the document was fetched
+    pub fetched_at: Option<SystemTime>,
+    /// Other values by name, such as a crawl's job ID
+    pub values: serde_json::Map<String, serde_json::Value>,
+}
+
+impl PageContext {
+    /// A context knowing nothing about the document
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the document's URL
+    pub fn url(mut self, url: impl Into<String>) -> Self {
+        self.url = Some(url.into());
+        self
+    }
+
+    /// Set when the document was fetched
+    pub fn fetched_at(mut self, time: SystemTime) -> Self {
+        self.fetched_at = Some(time);
+        self
+    }
+
+    /// Add a named value
+    pub fn value(mut self, name: impl Into<String>, value: impl Into<serde_json::Value>) -> Self {
+        self.values.insert(name.into(), value.into());
+        self
+    }
+
+    /// The value a `{"$context": KEY}` field gives
+    pub(crate) fn get(&self, key: &str) -> serde_json::Value {
+        match key {
+            "url" => self.url.clone().into(),
+            "fetchedAt" => self
+                .fetched_at
+                .map(|time| {
+                    chrono::DateTime::<chrono::Utc>::from(time)
+                        .to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+                })
+                .into(),
+            _ => self.values.get(key).cloned().unwrap_or_default(),
+        }
+    }
+}
+
+/// How to report the source of extracted values
+///
+/// A source is recorded for every selector field that matched an element and
+/// produced a non-null value, as `{"selector": ..., "index": ...}`: the
+/// field's selector as written in the spec, and the position of the matched
+/// element among all elements of the document in document order (0 is the
+/// root `<html>`). Literals and `$jsonld`, `$microdata` and `$article` fields
+/// have no source.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Provenance {
+    /// Plain values (the default)
+    #[default]
+    Off,
+    /// Replace each sourced value with `{"value": ..., "$src": {...}}`
+    Inline,
+    /// Return `{"data": ..., "provenance": {...}}`, where `data` is the plain
+    /// result and `provenance` maps the JSON pointer of each sourced value
+    /// (e.g. `/items/2/price`) to its source
+    Sidecar,
+}
+
+/// The kind of limit that was exceeded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    /// [`ExtractOptions::max_output_bytes`]
+    OutputBytes,
+    /// [`ExtractOptions::max_nodes`]
+    Nodes,
+}
+
+/// Error returned when an extraction exceeds one of its [`ExtractOptions`] limits
+///
+/// Extraction errors are `anyhow::Error`s; use `downcast_ref::<LimitExceeded>()`
+/// to tell a limit violation apart from other failures.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LimitExceeded {
+    /// Which limit was hit
+    pub limit: Limit,
+    /// The configured maximum
+    pub max: usize,
+}
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let what = match self.limit {
+            Limit::OutputBytes => "output bytes",
+            Limit::Nodes => "matched nodes",
+        };
+        write!(
+            f,
+            "Extraction limit exceeded: more than {} {}",
+            self.max, what
+        )
+    }
+}
+
+impl std::error::Error for LimitExceeded {}
+
+/// Running totals checked against the limits of one extraction
+///
+/// Counters are atomic so parallel workers can share a budget.
+#[derive(Debug, Default)]
+pub(crate) struct Budget {
+    max_output_bytes: Option<usize>,
+    max_nodes: Option<usize>,
+    output_bytes: AtomicUsize,
+    nodes: AtomicUsize,
+}
+
+impl Budget {
+    pub(crate) fn new(options: &ExtractOptions) -> Self {
+        Self {
+            max_output_bytes: options.max_output_bytes,
+            max_nodes: options.max_nodes,
+            ..Self::default()
+        }
+    }
+
+    /// Whether extracted text needs to be measured at all
+    pub(crate) fn limits_output(&self) -> bool {
+        self.max_output_bytes.is_some()
+    }
+
+    /// Account for `count` matched nodes
+    pub(crate) fn visit(&self, count: usize) -> Result<(), anyhow::Error> {
+        Self::charge(&self.nodes, count, self.max_nodes, Limit::Nodes)
+    }
+
+    /// Account for `bytes` of text read from the document
+    pub(crate) fn read(&self, bytes: usize) -> Result<(), anyhow::Error> {
+        Self::charge(
+            &self.output_bytes,
+            bytes,
+            self.max_output_bytes,
+            Limit::OutputBytes,
+        )
+    }
+
+    fn charge(
+        counter: &AtomicUsize,
+        amount: usize,
+        max: Option<usize>,
+        limit: Limit,
+    ) -> Result<(), anyhow::Error> {
+        let Some(max) = max else {
+            return Ok(());
+        };
+        let total = counter
+            .fetch_add(amount, Ordering::Relaxed)
+            .saturating_add(amount);
+        if total > max {
+            return Err(LimitExceeded { limit, max }.into());
+        }
+        Ok(())
+    }
+}
+
+/// Per-extraction state threaded through the DOM walk
+///
+/// Holds the limit [`Budget`] and, for [`Dom::extract_with_stats`], the
+/// statistics being collected.
+///
+/// [`Dom::extract_with_stats`]: crate::Dom::extract_with_stats
+#[derive(Debug, Default)]
+pub(crate) struct Context {
+    budget: Budget,
+    stats: Option<Mutex<StatsCollector>>,
+    provenance: Provenance,
+    /// Document-order position of each element, built on first use
+    positions: OnceLock<HashMap<NodeId, usize>>,
+    #[cfg(feature = "url")]
+    base_url: Option<String>,
+    /// The options' constants over the spec's
+    consts: HashMap<String, String>,
+    /// The page's URL combined with its `<base href>`, found on first use
+    #[cfg(feature = "url")]
+    page_base: OnceLock<Option<url::Url>>,
+    /// What the options say about the document
+    page: PageContext,
+    /// When the extraction first asked for the time (`$now`)
+    now: OnceLock<chrono::DateTime<chrono::Utc>>,
+}
+
+impl Context {
+    pub(crate) fn new(options: &ExtractOptions) -> Self {
+        Self {
+            budget: Budget::new(options),
+            stats: None,
+            provenance: options.provenance,
+            positions: OnceLock::new(),
+            #[cfg(feature = "url")]
+            base_url: options
+                .base_url
+                .clone()
+                .or_else(|| options.context.url.clone()),
+            consts: options.consts.clone(),
+            #[cfg(feature = "url")]
+            page_base: OnceLock::new(),
+            page: options.context.clone(),
+            now: OnceLock::new(),
+        }
+    }
+
+    /// Add the spec's constants, where the options don't override them
+    pub(crate) fn with_consts(mut self, consts: Option<&HashMap<String, String>>) -> Self {
+        for (name, value) in consts.into_iter().flatten() {
+            self.consts
+                .entry(name.clone())
+                .or_insert_with(|| value.clone());
+        }
+        self
+    }
+
+    /// Value of a constant named by a `$name` pipe argument
+    pub(crate) fn constant(&self, name: &str) -> Result<&str, anyhow::Error> {
+        self.consts.get(name).map(String::as_str).ok_or_else(|| {
+            anyhow::anyhow!(
+                "Unknown constant '${}': define it in the spec's \"$consts\" or with \
+                 ExtractOptions::constant",
+                name
+            )
+        })
+    }
+
+    /// A context that also collects statistics
+    pub(crate) fn with_stats(options: &ExtractOptions) -> Self {
+        Self {
+            stats: Some(Mutex::default()),
+            ..Self::new(options)
+        }
+    }
+
+    /// URL of the page, if known: the options' `base_url`, else the
+    /// `baseUrl` constant
+    #[cfg(feature = "url")]
+    pub(crate) fn base_url(&self) -> Option<&str> {
+        self.base_url
+            .as_deref()
+            .or_else(|| self.consts.get(BASE_URL_CONST).map(String::as_str))
+    }
+
+    /// The URL relative URLs resolve against, computing it with `find` the
+    /// first time
+    #[cfg(feature = "url")]
+    pub(crate) fn page_base(&self, find: impl FnOnce() -> Option<url::Url>) -> Option<&url::Url> {
+        self.page_base.get_or_init(find).as_ref()
+    }
+
+    /// What is known about the document
+    pub(crate) fn page(&self) -> &PageContext {
+        &self.page
+    }
+
+    /// The time of the extraction, read from the clock on first use so every
+    /// `$now` field agrees
+    pub(crate) fn now(&self) -> chrono::DateTime<chrono::Utc> {
+        *self.now.get_or_init(|| {
+            // wasm32 has no system clock of its own, so ask JavaScript
+            #[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+            return chrono::DateTime::from_timestamp_millis(js_sys::Date::now() as i64)
+                .unwrap_or_default();
+            #[cfg(not(all(target_arch = "wasm32", feature = "wasm")))]
+            std::time::SystemTime::now().into()
+        })
+    }
+
+    /// Whether extracted text needs to be measured at all
+    pub(crate) fn limits_output(&self) -> bool {
+        self.budget.limits_output()
+    }
+
+    /// Account for `count` matched nodes
+    fn visit(&self, count: usize) -> Result<(), anyhow::Error> {
+        self.budget.visit(count)?;
+        if let Some(stats) = &self.stats {
+            lock(stats).matched(count);
+        }
+        Ok(())
+    }
+
+    /// Account for matched nodes, sampling the first for statistics
+    pub(crate) fn matched(&self, nodes: &[Node]) -> Result<(), anyhow::Error> {
+        tracing::debug!(count = nodes.len(), "matched");
+        self.visit(nodes.len())?;
+        if let (Some(stats), Some(first)) = (&self.stats, nodes.first()) {
+            lock(stats).sample(|max| first.html_prefix(max));
+        }
+        Ok(())
+    }
+
+    /// Account for `bytes` of text read from the document
+    pub(crate) fn read(&self, bytes: usize) -> Result<(), anyhow::Error> {
+        self.budget.read(bytes)
+    }
+
+    pub(crate) fn provenance(&self) -> Provenance {
+        self.provenance
+    }
+
+    /// Document-order position of an element, computing all positions with
+    /// `build` the first time
+    pub(crate) fn position(
+        &self,
+        id: NodeId,
+        build: impl FnOnce() -> HashMap<NodeId, usize>,
+    ) -> Option<usize> {
+        self.positions.get_or_init(build).get(&id).copied()
+    }
+
+    /// Evaluate field `key`, timing it when statistics are being collected
+    ///
+    /// The evaluation runs in a `field` tracing span, so log lines from
+    /// nested fields carry the path to them.
+    pub(crate) fn field<T>(
+        &self,
+        key: &str,
+        spec: &FieldSpec,
+        f: impl FnOnce() -> Result<T, anyhow::Error>,
+    ) -> Result<T, anyhow::Error> {
+        let span = tracing::info_span!("field", name = key);
+        let _entered = span.enter();
+        tracing::debug!(selector = %describe(spec), "resolving");
+
+        let Some(stats) = &self.stats else {
+            return f();
+        };
+        lock(stats).enter(key);
+        let start = Instant::now();
+        let result = f();
+        lock(stats).exit(|| describe(spec), start.elapsed());
+        result
+    }
+
+    /// Take the collected statistics, if any
+    pub(crate) fn into_stats(self) -> Option<StatsCollector> {
+        self.stats
+            .map(|stats| stats.into_inner().unwrap_or_else(|e| e.into_inner()))
+    }
+}
+
+/// The selector a field spec evaluates, for statistics and logs
+fn describe(spec: &FieldSpec) -> String {
+    let scope = |obj: &crate::spec::ObjectSpec| obj.scope_str().unwrap_or_default();
+    match spec {
+        FieldSpec::Nested(obj) => format!("{{{}}}", scope(obj)),
+        FieldSpec::NestedArray(arr) => format!("[{}]", scope(&arr.item_spec)),
+        _ => match spec.to_json() {
+            serde_json::Value::String(s) => s,
+            other => other.to_string(),
+        },
+    }
+}
+
+fn lock(stats: &Mutex<StatsCollector>) -> std::sync::MutexGuard<'_, StatsCollector> {
+    stats.lock().unwrap_or_else(|e| e.into_inner())
+}
@@ -0,0 +1,88 @@
+//! The heading (`h1`–`h6`) outline of a document as a tree
+//!
+//! Each heading becomes `{"level", "text", "id", "children"}`, where
+//! `children` holds the headings of lower rank that follow it up to the next
+//! heading of the same or higher rank. `id` is left out when missing. Levels
+//! may skip (an `h2` followed by an `h4` nests the `h4` under the `h2`).
+
+use scraper::{ElementRef, Html, Selector};
+use serde_json::{Map, Value};
+use std::sync::LazyLock;
+
+static HEADINGS: LazyLock<Selector> =
+    LazyLock::new(|| Selector::parse("h1, h2, h3, h4, h5, h6").expect("heading selector"));
+
+/// The headings at or inside `scope` (the whole document if `None`), in
+/// document order
+pub(crate) fn headings<'a>(html: &'a Html, scope: Option<ElementRef<'a>>) -> Vec<ElementRef<'a>> {
+    match scope {
+        Some(scope) if level(scope).is_some() => vec![scope],
+        Some(scope) => scope.select(&HEADINGS).collect(),
+        None => html.select(&HEADINGS).collect(),
+    }
+}
+
+/// Nest headings into a tree by their level
+pub(crate) fn outline(headings: &[ElementRef]) -> Value {
+    let mut roots = Vec::new();
+    // Open headings, outermost first, each with the level it was opened at
+    let mut open: Vec<(u8, Map<String, Value>)> = Vec::new();
+    for &heading in headings {
+        let Some(level) = level(heading) else {
+            continue;
+        };
+        while open
+            .last()
+            .is_some_and(|(open_level, _)| *open_level >= level)
+        {
+            close(&mut open, &mut roots);
+        }
+        open.push((level, entry(heading, level)));
+    }
+    while !open.is_empty() {
+        close(&mut open, &mut roots);
+    }
+    Value::Array(roots)
+}
+
+/// Close the innermost open heading, adding it to its parent or the roots
+fn close(open: &mut Vec<(u8, Map<String, Value>)>, roots: &mut Vec<Value>) {
+    let Some((_, entry)) = open.pop() else {
+        return;
+    };
+    match open.last_mut() {
+        Some((_, parent)) => {
+            if let Some(Value::Array(children)) = parent.get_mut("children") {
+                children.push(Value::Object(entry));
+            }
+        }
+        None => roots.push(Value::Object(entry)),
+    }
+}
+
+fn entry(heading: ElementRef, level: u8) -> Map<String, Value> {
+    let mut entry = Map::new();
+    entry.insert("level".into(), level.into());
+    let text = heading
+        .text()
+        .flat_map(str::split_whitespace)
+        .collect::<Vec<_>>();
+    entry.insert("text".into(), text.join(" ").into());
+    if let Some(id) = heading.value().id() {
+        entry.insert("id".into(), id.into());
+    }
+    entry.insert("children".into(), Value::Array(Vec::new()));
+    entry
+}
+
+fn level(element: ElementRef) -> Option<u8> {
+    match element.value().name() {
+        "h1" => Some(1),
+        "h2" => Some(2),
+        "h3" => Some(3),
+        "h4" => Some(4),
+        "h5" => Some(5),
+        "h6" => Some(6),
+        _ => None,
+    }
+}
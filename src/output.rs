@@ -0,0 +1,591 @@
+//! Output formats for the CLI
+//!
+//! Each [`Format`] maps to a [`Serializer`] that renders an extraction result
+//! to a writer. Adding a format means adding a variant and a serializer.
+//! `--template` bypasses formats and renders through a user [`Template`].
+
+use anyhow::Result;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Output format for extraction results
+#[derive(clap::ValueEnum, serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    /// Pretty-printed JSON
+    #[default]
+    Json,
+    /// One compact JSON value per line; top-level arrays are split into lines
+    Ndjson,
+    /// Comma-separated rows with dotted column names for nested fields
+    Csv,
+    /// Tab-separated rows with dotted column names for nested fields
+    Tsv,
+    /// YAML document
+    Yaml,
+    /// TOML document; nulls are omitted and non-table results are wrapped in `items`
+    Toml,
+    /// Markdown document; a top-level `title` becomes the heading, short
+    /// values a `**key:** value` list, and text and nested values sections
+    Markdown,
+}
+
+impl Format {
+    /// The serializer for this format; `compact` only affects JSON
+    pub fn serializer(self, compact: bool) -> Box<dyn Serializer> {
+        match self {
+            Format::Json => Box::new(Json { compact }),
+            Format::Ndjson => Box::new(Ndjson),
+            Format::Csv => Box::new(Table { delimiter: b',' }),
+            Format::Tsv => Box::new(Table { delimiter: b'\t' }),
+            Format::Yaml => Box::new(Yaml),
+            Format::Toml => Box::new(Toml),
+            Format::Markdown => Box::new(Markdown),
+        }
+    }
+}
+
+/// Renders an extraction result
+pub trait Serializer {
+    fn write(&self, w: &mut dyn Write, result: &Value) -> Result<()>;
+
+    /// File extension for files written by this serializer
+    fn extension(&self) -> &str;
+}
+
+/// Print an extraction result to stdout in the requested format
+///
+/// A closed pipe (e.g. `| head`) ends output quietly.
+pub fn print(result: &Value, serializer: &dyn Serializer) -> Result<()> {
+    let mut out = std::io::BufWriter::new(std::io::stdout().lock());
+    let written = serializer
+        .write(&mut out, result)
+        .and_then(|()| Ok(out.flush()?));
+    match written {
+        Err(e)
+            if e.downcast_ref::<std::io::Error>()
+                .is_some_and(|e| e.kind() == std::io::ErrorKind::BrokenPipe) =>
+        {
+            Ok(())
+        }
+        other => other,
+    }
+}
+
+/// Write `result` to the file at `path`
+pub fn write_file(path: &Path, result: &Value, serializer: &dyn Serializer) -> Result<()> {
+    let file = std::fs::File::create(path)
+        .map_err(|e| anyhow::anyhow!("Failed to create '{}': {}", path.display(), e))?;
+    let mut out = std::io::BufWriter::new(file);
+    serializer.write(&mut out, result)?;
+    out.flush()
+        .map_err(|e| anyhow::anyhow!("Failed to write '{}': {}", path.display(), e))
+}
+
+/// A directory receiving one output file per batch input
+pub struct OutputDir {
+    dir: PathBuf,
+    extension: String,
+    used: HashSet<PathBuf>,
+}
+
+impl OutputDir {
+    pub fn create(dir: &Path, extension: &str) -> Result<Self> {
+        std::fs::create_dir_all(dir).map_err(|e| {
+            anyhow::anyhow!(
+                "Failed to create output directory '{}': {}",
+                dir.display(),
+                e
+            )
+        })?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            extension: extension.to_string(),
+            used: HashSet::new(),
+        })
+    }
+
+    /// Write the result for `source`, returning the file's path
+    ///
+    /// Files are named after the input's file stem, or its host and path for
+    /// URLs; repeated names get a numeric suffix.
+    pub fn write(
+        &mut self,
+        source: &str,
+        result: &Value,
+        serializer: &dyn Serializer,
+    ) -> Result<PathBuf> {
+        let stem = file_stem(source);
+        let mut path = self.dir.join(format!("{}.{}", stem, self.extension));
+        let mut n = 1;
+        while !self.used.insert(path.clone()) {
+            n += 1;
+            path = self.dir.join(format!("{}-{}.{}", stem, n, self.extension));
+        }
+        write_file(&path, result, serializer)?;
+        Ok(path)
+    }
+}
+
+/// A file name for an input path or URL
+fn file_stem(source: &str) -> String {
+    let name = match source.split_once("://") {
+        Some((_, rest)) => rest.trim_end_matches('/').to_string(),
+        None => Path::new(source)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+    };
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if name.is_empty() {
+        "output".to_string()
+    } else {
+        name
+    }
+}
+
+struct Json {
+    compact: bool,
+}
+
+impl Serializer for Json {
+    fn write(&self, w: &mut dyn Write, result: &Value) -> Result<()> {
+        if self.compact {
+            serde_json::to_writer(&mut *w, result)?;
+        } else {
+            serde_json::to_writer_pretty(&mut *w, result)?;
+        }
+        writeln!(w)?;
+        Ok(())
+    }
+
+    fn extension(&self) -> &str {
+        "json"
+    }
+}
+
+/// Plain lines for ad-hoc selections: strings as-is, other values as JSON
+pub struct Lines;
+
+impl Serializer for Lines {
+    fn write(&self, w: &mut dyn Write, result: &Value) -> Result<()> {
+        let values = match result {
+            Value::Array(values) => values.as_slice(),
+            other => std::slice::from_ref(other),
+        };
+        for value in values {
+            match value {
+                Value::String(s) => writeln!(w, "{}", s)?,
+                Value::Null => {}
+                other => writeln!(w, "{}", other)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn extension(&self) -> &str {
+        "txt"
+    }
+}
+
+struct Ndjson;
+
+impl Serializer for Ndjson {
+    fn write(&self, w: &mut dyn Write, result: &Value) -> Result<()> {
+        match result {
+            Value::Array(items) => {
+                for item in items {
+                    writeln!(w, "{}", item)?;
+                }
+            }
+            _ => writeln!(w, "{}", result)?,
+        }
+        Ok(())
+    }
+
+    fn extension(&self) -> &str {
+        "ndjson"
+    }
+}
+
+/// Delimited rows: one per element of a top-level array (or of an object's
+/// only field), or a single row otherwise
+///
+/// Nested objects become dotted columns (`author.name`); arrays inside a row
+/// are written as JSON. Columns are the union of all rows' fields; an object
+/// that is null in some rows leaves its columns empty there.
+struct Table {
+    delimiter: u8,
+}
+
+impl Serializer for Table {
+    fn write(&self, w: &mut dyn Write, result: &Value) -> Result<()> {
+        // A lone collection field, like `{"stories": [...]}`, is the table itself
+        let table = match result {
+            Value::Object(map) if map.len() == 1 => match map.values().next() {
+                Some(array @ Value::Array(_)) => array,
+                _ => result,
+            },
+            _ => result,
+        };
+        let items = match table {
+            Value::Array(items) => items.as_slice(),
+            other => std::slice::from_ref(other),
+        };
+        let rows: Vec<Vec<(String, String)>> = items
+            .iter()
+            .map(|item| {
+                let mut row = Vec::new();
+                flatten(item, String::new(), &mut row);
+                row
+            })
+            .collect();
+
+        let mut columns: Vec<&str> = Vec::new();
+        let mut seen = HashSet::new();
+        for (column, _) in rows.iter().flatten() {
+            if seen.insert(column.as_str()) {
+                columns.push(column);
+            }
+        }
+        // An object that is null in some rows leaves its dotted columns empty
+        // there, rather than adding a column of its own
+        let filled: HashSet<&str> = rows
+            .iter()
+            .flatten()
+            .filter(|(_, cell)| !cell.is_empty())
+            .map(|(column, _)| column.as_str())
+            .collect();
+        let nested = |column: &str| {
+            seen.iter().any(|other| {
+                other
+                    .strip_prefix(column)
+                    .is_some_and(|rest| rest.starts_with('.'))
+            })
+        };
+        columns.retain(|column| filled.contains(column) || !nested(column));
+
+        let mut writer = csv::WriterBuilder::new()
+            .delimiter(self.delimiter)
+            .from_writer(w);
+        writer.write_record(&columns)?;
+        for row in &rows {
+            let cells: HashMap<&str, &str> =
+                row.iter().map(|(c, v)| (c.as_str(), v.as_str())).collect();
+            writer.write_record(columns.iter().map(|c| cells.get(c).copied().unwrap_or("")))?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    fn extension(&self) -> &str {
+        if self.delimiter == b'\t' {
+            "tsv"
+        } else {
+            "csv"
+        }
+    }
+}
+
+/// Flatten `value` into `(column, cell)` pairs under `prefix`
+fn flatten(value: &Value, prefix: String, row: &mut Vec<(String, String)>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                let column = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                flatten(value, column, row);
+            }
+        }
+        _ => {
+            let column = if prefix.is_empty() {
+                "value".to_string()
+            } else {
+                prefix
+            };
+            let cell = match value {
+                Value::Null => String::new(),
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            row.push((column, cell));
+        }
+    }
+}
+
+struct Yaml;
+
+impl Serializer for Yaml {
+    fn write(&self, w: &mut dyn Write, result: &Value) -> Result<()> {
+        serde_yaml_ng::to_writer(w, result)?;
+        Ok(())
+    }
+
+    fn extension(&self) -> &str {
+        "yaml"
+    }
+}
+
+struct Toml;
+
+impl Serializer for Toml {
+    fn write(&self, w: &mut dyn Write, result: &Value) -> Result<()> {
+        // TOML has no null, and a document must be a table
+        let table = match without_nulls(result) {
+            Some(Value::Object(map)) => Value::Object(map),
+            Some(other) => serde_json::json!({ "items": other }),
+            None => serde_json::json!({}),
+        };
+        let text = toml::to_string_pretty(&table)
+            .map_err(|e| anyhow::anyhow!("Result can't be written as TOML: {}", e))?;
+        w.write_all(text.as_bytes())?;
+        Ok(())
+    }
+
+    fn extension(&self) -> &str {
+        "toml"
+    }
+}
+
+/// Results as documents to read or archive, e.g. with `markdown` pipe fields
+struct Markdown;
+
+/// Longest string shown in a field list rather than in its own section
+const MAX_INLINE_TEXT: usize = 120;
+
+impl Serializer for Markdown {
+    fn write(&self, w: &mut dyn Write, result: &Value) -> Result<()> {
+        let mut out = String::new();
+        match result {
+            Value::Object(fields) => markdown_object(fields, 1, &mut out),
+            other => markdown_value(other, 1, &mut out),
+        }
+        writeln!(w, "{}", out.trim_end())?;
+        Ok(())
+    }
+
+    fn extension(&self) -> &str {
+        "md"
+    }
+}
+
+/// An object with its `title` (if any) as a heading at `level`, then its
+/// short fields as a list, then a section per remaining field
+fn markdown_object(fields: &serde_json::Map<String, Value>, level: usize, out: &mut String) {
+    let title = fields.get("title").and_then(Value::as_str);
+    if let Some(title) = title {
+        out.push_str(&format!("{} {}\n\n", heading(level), title.trim()));
+    }
+    let level = if title.is_some() { level + 1 } else { level };
+    let fields = fields
+        .iter()
+        .filter(|(key, _)| title.is_none() || *key != "title");
+
+    let (short, long): (Vec<_>, Vec<_>) =
+        fields
+            .filter(|(_, value)| !value.is_null())
+            .partition(|(_, value)| match value {
+                Value::String(s) => !s.contains('\n') && s.len() <= MAX_INLINE_TEXT,
+                Value::Array(_) | Value::Object(_) => false,
+                _ => true,
+            });
+    for (key, value) in &short {
+        match value {
+            Value::String(s) => out.push_str(&format!("- **{}:** {}\n", key, s)),
+            other => out.push_str(&format!("- **{}:** {}\n", key, other)),
+        }
+    }
+    if !short.is_empty() {
+        out.push('\n');
+    }
+    for (key, value) in long {
+        out.push_str(&format!("{} {}\n\n", heading(level), key));
+        markdown_value(value, level + 1, out);
+    }
+}
+
+fn markdown_value(value: &Value, level: usize, out: &mut String) {
+    match value {
+        Value::Null => {}
+        Value::String(s) => {
+            out.push_str(s.trim());
+            out.push_str("\n\n");
+        }
+        Value::Object(fields) => markdown_object(fields, level, out),
+        Value::Array(items)
+            if items
+                .iter()
+                .all(|item| !item.is_object() && !item.is_array()) =>
+        {
+            for item in items.iter().filter(|item| !item.is_null()) {
+                match item {
+                    Value::String(s) => out.push_str(&format!("- {}\n", s.trim())),
+                    other => out.push_str(&format!("- {}\n", other)),
+                }
+            }
+            out.push('\n');
+        }
+        Value::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                // Items without a title heading need a rule to tell them apart
+                let titled = item.get("title").is_some_and(Value::is_string);
+                if i > 0 && !titled {
+                    out.push_str("---\n\n");
+                }
+                markdown_value(item, level, out);
+            }
+        }
+        other => {
+            out.push_str(&other.to_string());
+            out.push_str("\n\n");
+        }
+    }
+}
+
+fn heading(level: usize) -> String {
+    "#".repeat(level.min(6))
+}
+
+/// Drop null values from objects and arrays, recursively
+fn without_nulls(value: &Value) -> Option<Value> {
+    match value {
+        Value::Null => None,
+        Value::Object(map) => Some(Value::Object(
+            map.iter()
+                .filter_map(|(k, v)| Some((k.clone(), without_nulls(v)?)))
+                .collect(),
+        )),
+        Value::Array(items) => Some(Value::Array(
+            items.iter().filter_map(without_nulls).collect(),
+        )),
+        other => Some(other.clone()),
+    }
+}
+
+/// A user template rendered with the result as its context
+///
+/// Templates use Jinja syntax. An object result's fields are top-level
+/// variables, and the whole result is also available as `data`. Output is
+/// HTML-escaped when the template file ends in `.html`.
+pub struct Template {
+    env: minijinja::Environment<'static>,
+    name: String,
+    extension: String,
+}
+
+impl Template {
+    pub fn load(path: &Path) -> Result<Self> {
+        let source = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read template '{}': {}", path.display(), e))?;
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let mut env = minijinja::Environment::new();
+        env.set_keep_trailing_newline(true);
+        env.add_template_owned(name.clone(), source)
+            .map_err(|e| anyhow::anyhow!("Invalid template '{}': {:#}", path.display(), e))?;
+
+        // `report.md.j2` writes `.md` files; `report.md` writes `.md` too
+        let stem = Path::new(&name);
+        let extension = match stem.extension().and_then(|ext| ext.to_str()) {
+            Some("j2" | "jinja" | "jinja2" | "tmpl" | "tpl") => {
+                Path::new(stem.file_stem().unwrap_or_default())
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .unwrap_or("txt")
+            }
+            Some(ext) => ext,
+            None => "txt",
+        }
+        .to_string();
+
+        Ok(Self {
+            env,
+            name,
+            extension,
+        })
+    }
+}
+
+impl Serializer for Template {
+    fn write(&self, w: &mut dyn Write, result: &Value) -> Result<()> {
+        let mut context = serde_json::Map::new();
+        context.insert("data".to_string(), result.clone());
+        if let Value::Object(fields) = result {
+            context.extend(fields.clone());
+        }
+        self.env
+            .get_template(&self.name)?
+            .render_captured_to(Value::Object(context), w)
+            .map_err(|e| anyhow::anyhow!("Failed to render template '{}': {:#}", self.name, e))?;
+        Ok(())
+    }
+
+    fn extension(&self) -> &str {
+        &self.extension
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn table(delimiter: u8, result: &Value) -> String {
+        let mut out = Vec::new();
+        Table { delimiter }.write(&mut out, result).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn table_flattens_nested_objects() {
+        let result = json!({"stories": [
+            {"title": "A", "author": {"name": "Ann", "links": ["x", "y"]}},
+            {"title": "B", "author": null},
+        ]});
+        assert_eq!(
+            table(b',', &result),
+            "author.links,author.name,title\n\
+             \"[\"\"x\"\",\"\"y\"\"]\",Ann,A\n\
+             ,,B\n"
+        );
+        let result = json!([{"a": null, "n": 1}, {"a": {"b": 2}, "n": 3}]);
+        assert_eq!(table(b',', &result), "n,a.b\n1,\n3,2\n");
+        // Never an object, so the column stays
+        assert_eq!(table(b',', &json!([{"a": null}, {"a": 1}])), "a\n\"\"\n1\n");
+    }
+
+    #[test]
+    fn table_columns_are_the_union_of_rows() {
+        let result = json!([{"a": 1, "b": true}, {"c": "x"}, {"b": false, "a": 2}]);
+        assert_eq!(table(b',', &result), "a,b,c\n1,true,\n,,x\n2,false,\n");
+        assert_eq!(table(b',', &json!("lone")), "value\nlone\n");
+    }
+
+    #[test]
+    fn table_quotes_special_cells() {
+        let result = json!([{"text": "a,b", "quote": "say \"hi\"", "lines": "one\ntwo"}]);
+        assert_eq!(
+            table(b',', &result),
+            "lines,quote,text\n\"one\ntwo\",\"say \"\"hi\"\"\",\"a,b\"\n"
+        );
+        let result = json!([{"text": "a\tb", "plain": "a,b"}]);
+        assert_eq!(table(b'\t', &result), "plain\ttext\na,b\t\"a\tb\"\n");
+    }
+}
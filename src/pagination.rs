@@ -0,0 +1,122 @@
+//! Pagination links as `{"next", "prev", "canonical", "current", "pages"}`
+//!
+//! `next` and `prev` come from `rel="next"`/`rel="prev"` links, falling back
+//! to "Next"/"Previous" links in pagination markup (`.pagination`, `.pager`,
+//! `nav[aria-label=pagination]`) and then to the numbered page after or
+//! before the current one. `pages` lists the numbered page links of that
+//! markup as `{"number", "url"}`. Missing values are left out.
+
+use scraper::{ElementRef, Html, Selector};
+use serde_json::{Map, Value, json};
+use std::sync::LazyLock;
+use url::Url;
+
+fn selector(css: &str) -> Selector {
+    Selector::parse(css).expect("pagination selector")
+}
+
+static NEXT: LazyLock<Selector> =
+    LazyLock::new(|| selector("link[rel~=next][href], a[rel~=next][href]"));
+static PREV: LazyLock<Selector> = LazyLock::new(|| {
+    selector(
+        "link[rel~=prev][href], a[rel~=prev][href], link[rel~=previous][href], a[rel~=previous][href]",
+    )
+});
+static CANONICAL: LazyLock<Selector> = LazyLock::new(|| selector("link[rel~=canonical][href]"));
+static CONTAINER: LazyLock<Selector> = LazyLock::new(|| {
+    selector("[class*=pagination], [class*=pager], nav[aria-label*=pagination i]")
+});
+static LINKS: LazyLock<Selector> = LazyLock::new(|| selector("a[href]"));
+static CURRENT: LazyLock<Selector> =
+    LazyLock::new(|| selector("[aria-current=page], .current, .active"));
+
+/// The page's pagination links, resolved against `base`
+pub(crate) fn pagination(html: &Html, base: Option<&Url>) -> Value {
+    let resolve = |href: &str| match base.and_then(|base| base.join(href.trim()).ok()) {
+        Some(url) => url.to_string(),
+        None => href.trim().to_string(),
+    };
+    let first_href = |selector: &Selector| {
+        html.select(selector)
+            .find_map(|el| el.value().attr("href"))
+            .filter(|href| !href.trim().is_empty())
+    };
+
+    let container = html.select(&CONTAINER).next();
+    let links: Vec<ElementRef> = container
+        .map(|container| container.select(&LINKS).collect())
+        .unwrap_or_default();
+    let mut pages: Vec<(u64, &str)> = Vec::new();
+    for link in &links {
+        if let (Some(number), Some(href)) = (number(*link), link.value().attr("href"))
+            && !pages.iter().any(|(n, _)| *n == number)
+        {
+            pages.push((number, href));
+        }
+    }
+    pages.sort_by_key(|(number, _)| *number);
+    let current = container.and_then(|container| container.select(&CURRENT).find_map(number));
+    let labelled = |words: &[&str]| {
+        links.iter().find_map(|link| {
+            let text = text(*link).to_lowercase();
+            let label = link
+                .value()
+                .attr("aria-label")
+                .unwrap_or_default()
+                .to_lowercase();
+            words
+                .iter()
+                .any(|word| text.starts_with(word) || label.starts_with(word))
+                .then(|| link.value().attr("href"))
+                .flatten()
+        })
+    };
+    let numbered = |number: Option<u64>| {
+        number.and_then(|number| {
+            pages
+                .iter()
+                .find(|(n, _)| *n == number)
+                .map(|(_, href)| *href)
+        })
+    };
+
+    let next = first_href(&NEXT)
+        .or_else(|| labelled(&["next", "›", "»", ">"]))
+        .or_else(|| numbered(current.map(|c| c + 1)));
+    let prev = first_href(&PREV)
+        .or_else(|| labelled(&["prev", "‹", "«", "<"]))
+        .or_else(|| numbered(current.and_then(|c| c.checked_sub(1))));
+
+    let mut pagination = Map::new();
+    if let Some(next) = next {
+        pagination.insert("next".into(), resolve(next).into());
+    }
+    if let Some(prev) = prev {
+        pagination.insert("prev".into(), resolve(prev).into());
+    }
+    if let Some(canonical) = first_href(&CANONICAL) {
+        pagination.insert("canonical".into(), resolve(canonical).into());
+    }
+    if let Some(current) = current {
+        pagination.insert("current".into(), current.into());
+    }
+    let pages = pages
+        .into_iter()
+        .map(|(number, href)| json!({"number": number, "url": resolve(href)}))
+        .collect();
+    pagination.insert("pages".into(), Value::Array(pages));
+    Value::Object(pagination)
+}
+
+fn text(element: ElementRef) -> String {
+    element
+        .text()
+        .flat_map(str::split_whitespace)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The page number an element's text names, if it is just a number
+fn number(element: ElementRef) -> Option<u64> {
+    text(element).parse().ok()
+}
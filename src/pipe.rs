@@ -1,143 +1,584 @@
 //! Pipe transformation module
 
-use crate::spec::PipeCommand;
+use crate::spec::{PipeArg, PipeCommand};
+#[cfg(feature = "regex")]
 use regex::{Regex, RegexBuilder};
 use serde_json::Value;
+use std::borrow::Cow;
+#[cfg(feature = "regex")]
 use std::collections::HashMap;
-use std::sync::{LazyLock, RwLock};
+#[cfg(feature = "regex")]
+use std::sync::{LazyLock, Mutex};
 
-static REGEX_CACHE: LazyLock<RwLock<HashMap<String, Regex>>> =
-    LazyLock::new(|| RwLock::new(HashMap::new()));
+#[cfg(feature = "regex")]
+static REGEX_CACHE: LazyLock<Mutex<RegexCache>> =
+    LazyLock::new(|| Mutex::new(RegexCache::new(DEFAULT_REGEX_CACHE_CAPACITY)));
+
+/// Default number of compiled regexes kept in the process-wide cache
+pub const DEFAULT_REGEX_CACHE_CAPACITY: usize = 256;
 
 // ReDoS protection limits
+#[cfg(feature = "regex")]
 const REGEX_SIZE_LIMIT: usize = 1_000_000;
+#[cfg(feature = "regex")]
 const REGEX_DFA_SIZE_LIMIT: usize = 1_000_000;
 
-fn get_cached_regex(pattern: &str) -> Result<Regex, anyhow::Error> {
-    {
-        let cache = REGEX_CACHE
-            .read()
-            .map_err(|_| anyhow::anyhow!("Regex cache lock poisoned"))?;
-        if let Some(re) = cache.get(pattern) {
-            return Ok(re.clone());
+/// Snapshot of the regex cache counters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RegexCacheStats {
+    /// Lookups answered from the cache
+    pub hits: u64,
+    /// Lookups that had to compile the pattern
+    pub misses: u64,
+    /// Entries dropped to stay within capacity
+    pub evictions: u64,
+    /// Entries currently cached
+    pub len: usize,
+    /// Maximum number of cached entries
+    pub capacity: usize,
+}
+
+#[cfg(feature = "regex")]
+/// Least-recently-used cache of compiled regexes
+///
+/// Regexes are compiled once per spec parse, so the cache only needs to
+/// dedupe patterns shared across specs; eviction scans for the oldest entry.
+struct RegexCache {
+    entries: HashMap<String, (Regex, u64)>,
+    capacity: usize,
+    tick: u64,
+    stats: RegexCacheStats,
+}
+
+#[cfg(feature = "regex")]
+impl RegexCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            capacity,
+            tick: 0,
+            stats: RegexCacheStats::default(),
         }
     }
 
+    fn get(&mut self, pattern: &str) -> Option<Regex> {
+        self.tick += 1;
+        let tick = self.tick;
+        match self.entries.get_mut(pattern) {
+            Some((re, last_used)) => {
+                *last_used = tick;
+                self.stats.hits += 1;
+                Some(re.clone())
+            }
+            None => {
+                self.stats.misses += 1;
+                None
+            }
+        }
+    }
+
+    fn insert(&mut self, pattern: &str, re: Regex) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.evict_to(self.capacity - 1);
+        self.entries.insert(pattern.to_string(), (re, self.tick));
+    }
+
+    /// Drop least-recently-used entries until at most `len` remain
+    fn evict_to(&mut self, len: usize) {
+        while self.entries.len() > len {
+            let Some(oldest) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(k, _)| k.clone())
+            else {
+                break;
+            };
+            self.entries.remove(&oldest);
+            self.stats.evictions += 1;
+        }
+    }
+}
+
+#[cfg(feature = "regex")]
+fn lock_regex_cache() -> Result<std::sync::MutexGuard<'static, RegexCache>, anyhow::Error> {
+    REGEX_CACHE
+        .lock()
+        .map_err(|_| anyhow::anyhow!("Regex cache lock poisoned"))
+}
+
+#[cfg(feature = "regex")]
+/// Set the maximum number of compiled regexes kept in the cache
+///
+/// Shrinking the cache evicts least-recently-used entries immediately.
+/// A capacity of 0 disables caching.
+pub fn set_regex_cache_capacity(capacity: usize) -> Result<(), anyhow::Error> {
+    let mut cache = lock_regex_cache()?;
+    cache.capacity = capacity;
+    cache.evict_to(capacity);
+    Ok(())
+}
+
+/// Get the current regex cache counters
+///
+/// All zero without the `regex` feature.
+pub fn regex_cache_stats() -> Result<RegexCacheStats, anyhow::Error> {
+    #[cfg(feature = "regex")]
+    {
+        let cache = lock_regex_cache()?;
+        Ok(RegexCacheStats {
+            len: cache.entries.len(),
+            capacity: cache.capacity,
+            ..cache.stats
+        })
+    }
+    #[cfg(not(feature = "regex"))]
+    Ok(RegexCacheStats::default())
+}
+
+#[cfg(feature = "regex")]
+/// Remove all cached regexes (counters are kept)
+pub fn clear_regex_cache() -> Result<(), anyhow::Error> {
+    lock_regex_cache()?.entries.clear();
+    Ok(())
+}
+
+#[cfg(feature = "regex")]
+pub(crate) fn get_cached_regex(pattern: &str) -> Result<Regex, anyhow::Error> {
+    if let Some(re) = lock_regex_cache()?.get(pattern) {
+        return Ok(re);
+    }
+
     let re = RegexBuilder::new(pattern)
         .size_limit(REGEX_SIZE_LIMIT)
         .dfa_size_limit(REGEX_DFA_SIZE_LIMIT)
         .build()
         .map_err(|e| anyhow::anyhow!("Invalid or unsafe regex '{}': {}", pattern, e))?;
 
-    let mut cache = REGEX_CACHE
-        .write()
-        .map_err(|_| anyhow::anyhow!("Regex cache lock poisoned"))?;
-    cache.insert(pattern.to_string(), re.clone());
+    lock_regex_cache()?.insert(pattern, re.clone());
     Ok(re)
 }
 
+/// A value flowing through a pipe chain
+///
+/// Text stays borrowed from the DOM (or the caller's input) until a pipe
+/// actually has to change it, so chains like `trim`, `substr` and `regex`
+/// don't allocate. Pipes that produce non-text values switch to `Json`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PipeValue<'a> {
+    Text(Cow<'a, str>),
+    Json(Value),
+}
+
+impl<'a> PipeValue<'a> {
+    /// Wrap borrowed text
+    pub fn borrowed(s: &'a str) -> Self {
+        PipeValue::Text(Cow::Borrowed(s))
+    }
+
+    /// Convert into a JSON value (allocating text only now)
+    pub fn into_json(self) -> Value {
+        match self {
+            PipeValue::Text(s) => Value::String(s.into_owned()),
+            PipeValue::Json(v) => v,
+        }
+    }
+
+    /// Extract string with consistent error messaging
+    fn as_text(&self) -> Result<&str, anyhow::Error> {
+        match self {
+            PipeValue::Text(s) => Ok(s),
+            PipeValue::Json(Value::String(s)) => Ok(s),
+            PipeValue::Json(_) => Err(anyhow::anyhow!("Expected string value")),
+        }
+    }
+
+    /// Take the text out of the value, failing for non-string values
+    fn into_text(self) -> Result<Cow<'a, str>, anyhow::Error> {
+        match self {
+            PipeValue::Text(s) => Ok(s),
+            PipeValue::Json(Value::String(s)) => Ok(Cow::Owned(s)),
+            PipeValue::Json(_) => Err(anyhow::anyhow!("Expected string value")),
+        }
+    }
+}
+
+impl From<Value> for PipeValue<'_> {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::String(s) => PipeValue::Text(Cow::Owned(s)),
+            v => PipeValue::Json(v),
+        }
+    }
+}
+
 pub fn apply_pipes(value: &str, pipes: &[PipeCommand]) -> Result<Value, anyhow::Error> {
     pipes
         .iter()
-        .try_fold(Value::String(value.to_string()), |current, pipe| {
-            apply_pipe(current, pipe)
+        .try_fold(PipeValue::borrowed(value), |current, pipe| {
+            apply_pipe_value(current, pipe)
         })
+        .map(PipeValue::into_json)
 }
 
 pub fn apply_pipe(value: Value, pipe: &PipeCommand) -> Result<Value, anyhow::Error> {
+    apply_pipe_value(value.into(), pipe).map(PipeValue::into_json)
+}
+
+/// Apply a single pipe to a (possibly borrowed) pipe value
+pub fn apply_pipe_value<'a>(
+    value: PipeValue<'a>,
+    pipe: &PipeCommand,
+) -> Result<PipeValue<'a>, anyhow::Error> {
     match pipe {
-        PipeCommand::Trim => string_transform(value, |s| s.trim().to_string()),
+        PipeCommand::Trim => apply_trim(value),
         PipeCommand::Lower => string_transform(value, |s| s.to_lowercase()),
         PipeCommand::Upper => string_transform(value, |s| s.to_uppercase()),
         PipeCommand::Substr(start, end) => apply_substring(value, *start, *end),
         PipeCommand::ParseAsNumber | PipeCommand::ParseAsFloat => apply_parse_number(value),
         PipeCommand::ParseAsInt => apply_parse_int(value),
-        PipeCommand::Regex(pattern) => apply_regex(value, pattern),
+        PipeCommand::Digits => apply_digits(value),
+        PipeCommand::ParseAsJson => apply_parse_json(value),
+        PipeCommand::DateTime => {
+            let normalized = normalize_datetime(value.as_text()?);
+            Ok(normalized.map_or(PipeValue::Json(Value::Null), |s| {
+                PipeValue::Text(Cow::Owned(s))
+            }))
+        }
+        #[cfg(feature = "regex")]
+        PipeCommand::Regex(pattern) => apply_regex(value, pattern.regex()),
         PipeCommand::Attr(_) => Ok(value),
+        // After another pipe, the text is a class list (e.g. `attr:class`)
+        PipeCommand::Classes => {
+            let classes: Vec<&str> = value.as_text()?.split_whitespace().collect();
+            Ok(PipeValue::Json(classes.into()))
+        }
+        PipeCommand::HasClass(name) => {
+            let has = value.as_text()?.split_whitespace().any(|c| c == name);
+            Ok(PipeValue::Json(has.into()))
+        }
+        // After another pipe, the text is a declaration list (e.g. `attr:style`)
+        PipeCommand::Style(property, url) => {
+            let value = style_property(value.as_text()?, property)
+                .and_then(|value| if *url { css_url(value) } else { Some(value) });
+            Ok(value.map_or(PipeValue::Json(Value::Null), |value| {
+                PipeValue::Text(Cow::Owned(value.to_string()))
+            }))
+        }
+        PipeCommand::HasAttr(name) => Err(anyhow::anyhow!(
+            "hasAttr:{} reads the element, so it must be the first pipe",
+            name
+        )),
         PipeCommand::Void => Ok(value),
+        // Text from an attribute or earlier pipes has no markup to keep
+        // whitespace around
+        PipeCommand::RawText => Ok(value),
+        PipeCommand::Lines => {
+            let lines = split_lines(value.as_text()?);
+            Ok(PipeValue::Json(lines.into()))
+        }
+        // After another pipe, the text is HTML (e.g. from an attribute)
+        PipeCommand::Markdown => string_transform(value, crate::markdown::from_html),
+        PipeCommand::Prefix(PipeArg::Const(name))
+        | PipeCommand::AbsoluteUrl(Some(PipeArg::Const(name))) => Err(anyhow::anyhow!(
+            "Constant '${}' is only known while extracting a spec",
+            name
+        )),
+        PipeCommand::Prefix(PipeArg::Text(_)) | PipeCommand::AbsoluteUrl(_)
+            if matches!(value, PipeValue::Json(Value::Null)) =>
+        {
+            Ok(value)
+        }
+        PipeCommand::Prefix(PipeArg::Text(text)) => {
+            string_transform(value, |s| format!("{}{}", text, s))
+        }
+        PipeCommand::AbsoluteUrl(None) => Ok(value),
+        #[cfg(feature = "url")]
+        PipeCommand::AbsoluteUrl(Some(PipeArg::Text(base))) => {
+            let href = value.as_text()?.trim();
+            let resolved = url::Url::parse(base).and_then(|base| base.join(href)).ok();
+            Ok(match resolved {
+                Some(url) => PipeValue::Text(Cow::Owned(url.into())),
+                None => value,
+            })
+        }
+        #[cfg(not(feature = "url"))]
+        PipeCommand::AbsoluteUrl(Some(PipeArg::Text(_))) => Err(url_disabled("absoluteUrl")),
     }
 }
 
-/// Helper to apply a string-to-string transformation
-fn string_transform<F>(value: Value, f: F) -> Result<Value, anyhow::Error>
+/// The error for URL handling without the `url` feature
+#[cfg(not(feature = "url"))]
+pub(crate) fn url_disabled(what: &str) -> anyhow::Error {
+    anyhow::anyhow!(
+        "{} needs html2json to be built with the `url` feature",
+        what
+    )
+}
+
+/// Split text into lines with whitespace collapsed, dropping blank ones
+pub(crate) fn split_lines(text: &str) -> Vec<String> {
+    text.lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+/// Helper to apply an allocating string-to-string transformation
+fn string_transform<'a, F>(value: PipeValue<'a>, f: F) -> Result<PipeValue<'a>, anyhow::Error>
 where
     F: FnOnce(&str) -> String,
 {
-    let s = as_string(&value)?;
-    Ok(Value::String(f(s)))
+    let s = value.as_text()?;
+    Ok(PipeValue::Text(Cow::Owned(f(s))))
+}
+
+/// Trim whitespace, re-slicing borrowed text instead of copying it
+fn apply_trim(value: PipeValue<'_>) -> Result<PipeValue<'_>, anyhow::Error> {
+    Ok(PipeValue::Text(match value.into_text()? {
+        Cow::Borrowed(s) => Cow::Borrowed(s.trim()),
+        Cow::Owned(s) => Cow::Owned(s.trim().to_string()),
+    }))
 }
 
-/// Apply substring transformation
-fn apply_substring(value: Value, start: usize, end: Option<usize>) -> Result<Value, anyhow::Error> {
-    let s = as_string(&value)?;
-    let result = match end {
-        Some(e) => s.chars().take(e).skip(start).collect(),
-        None => s.chars().skip(start).collect(),
+/// Apply substring transformation (character offsets)
+fn apply_substring(
+    value: PipeValue<'_>,
+    start: usize,
+    end: Option<usize>,
+) -> Result<PipeValue<'_>, anyhow::Error> {
+    let text = value.into_text()?;
+    let byte_offset = |s: &str, chars: usize| {
+        s.char_indices()
+            .nth(chars)
+            .map(|(i, _)| i)
+            .unwrap_or(s.len())
     };
-    Ok(Value::String(result))
+    let from = byte_offset(&text, start);
+    let to = end
+        .map(|e| byte_offset(&text, e))
+        .unwrap_or(text.len())
+        .max(from);
+    Ok(PipeValue::Text(match text {
+        Cow::Borrowed(s) => Cow::Borrowed(&s[from..to]),
+        Cow::Owned(s) => Cow::Owned(s[from..to].to_string()),
+    }))
+}
+
+/// Keep digits, decimal points and a sign before the first digit, so
+/// `≈ 12 345 users` becomes `12345` and `−4.5 °C` becomes `-4.5`
+///
+/// Text without digits becomes `null`.
+fn apply_digits(value: PipeValue<'_>) -> Result<PipeValue<'_>, anyhow::Error> {
+    let mut digits = String::new();
+    for c in value.as_text()?.chars() {
+        match c {
+            '0'..='9' | '.' => digits.push(c),
+            '-' | '\u{2212}' | '\u{2013}' | '\u{fe63}' | '\u{ff0d}' if digits.is_empty() => {
+                digits.push('-')
+            }
+            '+' if digits.is_empty() => digits.push('+'),
+            _ => {}
+        }
+    }
+    if !digits.contains(|c: char| c.is_ascii_digit()) {
+        return Ok(PipeValue::Json(Value::Null));
+    }
+    Ok(PipeValue::Text(Cow::Owned(digits)))
 }
 
 /// Parse string as floating-point number
-fn apply_parse_number(value: Value) -> Result<Value, anyhow::Error> {
-    let s = as_string(&value)?;
+fn apply_parse_number(value: PipeValue<'_>) -> Result<PipeValue<'_>, anyhow::Error> {
+    let s = value.as_text()?;
     let n: f64 = s
         .trim()
         .parse()
         .map_err(|_| anyhow::anyhow!("Cannot parse '{}' as number", s))?;
-    Ok(Value::from(n))
+    Ok(PipeValue::Json(Value::from(n)))
 }
 
 /// Parse string as integer
-fn apply_parse_int(value: Value) -> Result<Value, anyhow::Error> {
-    let s = as_string(&value)?;
+fn apply_parse_int(value: PipeValue<'_>) -> Result<PipeValue<'_>, anyhow::Error> {
+    let s = value.as_text()?;
     let n: i64 = s
         .trim()
         .parse()
         .map_err(|_| anyhow::anyhow!("Cannot parse '{}' as int", s))?;
-    Ok(Value::from(n))
+    Ok(PipeValue::Json(Value::from(n)))
 }
 
-/// Apply regex extraction with optional capture group
-fn apply_regex(value: Value, pattern: &str) -> Result<Value, anyhow::Error> {
-    let s = as_string(&value)?;
-    let re = get_cached_regex(pattern)?;
+/// The value of the last `property` declaration in an inline style, without
+/// `!important`
+fn style_property<'a>(style: &'a str, property: &str) -> Option<&'a str> {
+    let mut found = None;
+    for declaration in split_declarations(style) {
+        if let Some((name, value)) = declaration.split_once(':')
+            && name.trim().eq_ignore_ascii_case(property)
+        {
+            let value = value.trim();
+            let value = value
+                .strip_suffix("!important")
+                .map_or(value, str::trim_end);
+            found = Some(value);
+        }
+    }
+    found.filter(|value| !value.is_empty())
+}
 
-    match re.captures(s) {
-        Some(caps) => Ok(caps
-            .get(1)
-            .or_else(|| caps.get(0))
-            .map(|m| Value::String(m.as_str().to_string()))
-            .unwrap_or(Value::Null)),
-        None => Ok(Value::Null),
+/// Split declarations at `;`, except inside quotes or parentheses (`url("a;b")`)
+fn split_declarations(style: &str) -> Vec<&str> {
+    let mut declarations = Vec::new();
+    let (mut depth, mut quote, mut start) = (0usize, None, 0);
+    for (i, c) in style.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '(') => depth += 1,
+            (None, ')') => depth = depth.saturating_sub(1),
+            (None, ';') if depth == 0 => {
+                declarations.push(&style[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    declarations.push(&style[start..]);
+    declarations
+}
+
+/// The address in the first `url(...)` of a CSS value, without quotes
+fn css_url(value: &str) -> Option<&str> {
+    let start = value.to_ascii_lowercase().find("url(")? + 4;
+    let rest = &value[start..];
+    let inner = rest[..rest.rfind(')')?].trim();
+    let inner = match inner.chars().next() {
+        Some(q @ ('"' | '\'')) => inner[1..].split(q).next()?,
+        _ => inner.split(')').next()?.trim(),
+    };
+    (!inner.is_empty()).then_some(inner)
+}
+
+/// Rewrite a date or time in a common format as ISO 8601
+///
+/// Accepts RFC 3339 and looser ISO forms (`2024-03-05 10:00`, `+0100`
+/// offsets), RFC 2822 and written-out dates (`March 5, 2024`,
+/// `5 Mar 2024`). Times without an offset stay without one. Returns `None`
+/// for anything else.
+pub fn normalize_datetime(text: &str) -> Option<String> {
+    use chrono::{DateTime, NaiveDate, NaiveDateTime, SecondsFormat};
+
+    const OFFSET_FORMATS: [&str; 3] = [
+        "%Y-%m-%dT%H:%M:%S%.f%#z",
+        "%Y-%m-%dT%H:%M%#z",
+        "%Y-%m-%dT%H:%M:%S%.fZ",
+    ];
+    const NAIVE_FORMATS: [&str; 2] = ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%dT%H:%M"];
+    const DATE_FORMATS: [&str; 8] = [
+        "%Y-%m-%d",
+        "%Y/%m/%d",
+        "%B %d, %Y",
+        "%b %d, %Y",
+        "%b. %d, %Y",
+        "%d %B %Y",
+        "%d %b %Y",
+        "%A, %B %d, %Y",
+    ];
+
+    let text = text.trim();
+    if let Ok(dt) = DateTime::parse_from_rfc3339(text) {
+        return Some(dt.to_rfc3339_opts(SecondsFormat::AutoSi, true));
+    }
+    if let Ok(dt) = DateTime::parse_from_rfc2822(text) {
+        return Some(dt.to_rfc3339_opts(SecondsFormat::AutoSi, true));
     }
+    // `2024-03-05 10:00` has a space where ISO 8601 wants a `T`
+    let iso = match text.as_bytes().get(10) {
+        Some(b' ') => format!("{}T{}", &text[..10], text[11..].trim_start()).replace(" ", ""),
+        _ => text.to_string(),
+    };
+    for format in OFFSET_FORMATS {
+        if let Ok(dt) = DateTime::parse_from_str(&iso, format) {
+            return Some(dt.to_rfc3339_opts(SecondsFormat::AutoSi, true));
+        }
+        if format.ends_with('Z')
+            && let Ok(dt) = NaiveDateTime::parse_from_str(&iso, format)
+        {
+            return Some(dt.and_utc().to_rfc3339_opts(SecondsFormat::AutoSi, true));
+        }
+    }
+    for format in NAIVE_FORMATS {
+        if let Ok(dt) = NaiveDateTime::parse_from_str(&iso, format) {
+            return Some(dt.format("%Y-%m-%dT%H:%M:%S%.f").to_string());
+        }
+    }
+    let words = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    DATE_FORMATS.iter().find_map(|format| {
+        NaiveDate::parse_from_str(&words, format)
+            .ok()
+            .map(|date| date.format("%Y-%m-%d").to_string())
+    })
 }
 
-/// Extract string from JSON value with consistent error messaging
-fn as_string(value: &Value) -> Result<&str, anyhow::Error> {
-    value
-        .as_str()
-        .ok_or_else(|| anyhow::anyhow!("Expected string value"))
+/// Parse string as JSON
+fn apply_parse_json(value: PipeValue<'_>) -> Result<PipeValue<'_>, anyhow::Error> {
+    let s = value.as_text()?;
+    let json = serde_json::from_str(s.trim())
+        .map_err(|e| anyhow::anyhow!("Cannot parse text as JSON: {}", e))?;
+    Ok(PipeValue::Json(json))
+}
+
+/// Apply regex extraction with optional capture group
+#[cfg(feature = "regex")]
+fn apply_regex<'a>(value: PipeValue<'a>, re: &Regex) -> Result<PipeValue<'a>, anyhow::Error> {
+    let text = value.into_text()?;
+
+    let Some(range) = re
+        .captures(&text)
+        .and_then(|caps| caps.get(1).or_else(|| caps.get(0)))
+        .map(|m| m.range())
+    else {
+        return Ok(PipeValue::Json(Value::Null));
+    };
+
+    Ok(PipeValue::Text(match text {
+        Cow::Borrowed(s) => Cow::Borrowed(&s[range]),
+        Cow::Owned(s) => Cow::Owned(s[range].to_string()),
+    }))
 }
 
 /// Separate source pipes from transform pipes
 ///
-/// Returns (source_pipe, transform_pipes) where source_pipe is the first
-/// attr or void command found (or None for default text extraction).
+/// Returns (source_pipe, transform_pipes) where source_pipe is the first attr
+/// or void command, or a pipe reading the element (such as `datetime`) written
+/// first; None means default text extraction. Written later, a pipe reading
+/// the element transforms the value so far instead, so pipes run in order.
 pub fn split_source_and_transforms(
     pipes: &[PipeCommand],
 ) -> (Option<&PipeCommand>, Vec<&PipeCommand>) {
     let mut source_pipe = None;
     let mut transforms = Vec::new();
 
-    for pipe in pipes {
+    for (i, pipe) in pipes.iter().enumerate() {
         match pipe {
-            PipeCommand::Attr(_) | PipeCommand::Void => {
-                // First source pipe wins, subsequent ones are treated as transforms
-                if source_pipe.is_none() {
-                    source_pipe = Some(pipe);
-                } else {
-                    transforms.push(pipe);
-                }
+            // First source pipe wins, subsequent ones are treated as transforms
+            PipeCommand::Attr(_) | PipeCommand::Void if source_pipe.is_none() => {
+                source_pipe = Some(pipe);
+            }
+            // These read the element only when written first, so pipes run in order
+            PipeCommand::HasAttr(_)
+            | PipeCommand::Classes
+            | PipeCommand::HasClass(_)
+            | PipeCommand::Style(..)
+            | PipeCommand::DateTime
+            | PipeCommand::Markdown
+            | PipeCommand::RawText
+            | PipeCommand::Lines
+                if i == 0 =>
+            {
+                source_pipe = Some(pipe);
             }
             _ => {
                 transforms.push(pipe);
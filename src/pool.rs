@@ -0,0 +1,165 @@
+//! Named, pre-parsed specs for long-running services
+//!
+//! Parsing a spec compiles its selectors and regexes, work a service would
+//! otherwise repeat on every request. A [`SpecSet`] parses each spec once at
+//! startup and hands out cheap [`Extractor`] handles per request.
+//! It is `Send + Sync` and cheap to clone, so one set can be shared by every
+//! worker of a web service.
+//!
+//! # Example
+//!
+//! ```
+//! use html2json::{ExtractOptions, SpecSet};
+//!
+//! let specs = SpecSet::builder()
+//!     .spec_json("title", r#"{"title": "h1"}"#)?
+//!     .spec_json("links", r#"[{"$": "a", "href": "$ | attr:href"}]"#)?
+//!     .options(ExtractOptions::new().max_nodes(100_000))
+//!     .build();
+//!
+//! // In a request handler
+//! let result = specs.extractor("title")?.extract("<h1>Hello</h1>")?;
+//! assert_eq!(result["title"], "Hello");
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use crate::{Dom, ExtractOptions, Spec};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// A shared set of named, pre-parsed specs
+///
+/// Build one with [`SpecSet::builder`]. Clones share the specs.
+#[derive(Debug, Clone, Default)]
+pub struct SpecSet {
+    specs: Arc<BTreeMap<String, Spec>>,
+    options: ExtractOptions,
+}
+
+impl SpecSet {
+    /// Start building a set
+    pub fn builder() -> SpecSetBuilder {
+        SpecSetBuilder::default()
+    }
+
+    /// The spec registered as `name`
+    pub fn get(&self, name: &str) -> Option<&Spec> {
+        self.specs.get(name)
+    }
+
+    /// Names of the specs in the set, sorted
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.specs.keys().map(String::as_str)
+    }
+
+    /// Number of specs in the set
+    pub fn len(&self) -> usize {
+        self.specs.len()
+    }
+
+    /// Whether the set has no specs
+    pub fn is_empty(&self) -> bool {
+        self.specs.is_empty()
+    }
+
+    /// Options applied to every extraction
+    pub fn options(&self) -> &ExtractOptions {
+        &self.options
+    }
+
+    /// A handle for extracting with the spec registered as `name`
+    ///
+    /// Fails for unknown names, listing the known ones.
+    pub fn extractor(&self, name: &str) -> Result<Extractor<'_>, anyhow::Error> {
+        let (name, spec) = self.specs.get_key_value(name).ok_or_else(|| {
+            let known: Vec<&str> = self.names().collect();
+            anyhow::anyhow!("Unknown spec '{}' (known: {})", name, known.join(", "))
+        })?;
+        Ok(Extractor {
+            name,
+            spec,
+            options: &self.options,
+        })
+    }
+
+    /// Extract from HTML with the spec registered as `name`
+    pub fn extract(&self, name: &str, html: &str) -> Result<serde_json::Value, anyhow::Error> {
+        self.extractor(name)?.extract(html)
+    }
+}
+
+/// Builder for [`SpecSet`]
+#[derive(Debug, Default)]
+pub struct SpecSetBuilder {
+    specs: BTreeMap<String, Spec>,
+    options: ExtractOptions,
+}
+
+impl SpecSetBuilder {
+    /// Register a parsed spec as `name`, replacing any spec of that name
+    pub fn spec(mut self, name: impl Into<String>, spec: Spec) -> Self {
+        self.specs.insert(name.into(), spec);
+        self
+    }
+
+    /// Parse a JSON spec and register it as `name`
+    pub fn spec_json(self, name: impl Into<String>, json: &str) -> Result<Self, anyhow::Error> {
+        let name = name.into();
+        let spec = crate::spec::parse_json(json)
+            .and_then(|value| Spec::from_json(&value))
+            .map_err(|e| e.context(format!("Invalid spec '{}'", name)))?;
+        Ok(self.spec(name, spec))
+    }
+
+    /// Limits applied to every extraction (default: none)
+    pub fn options(mut self, options: ExtractOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Finish the set
+    pub fn build(self) -> SpecSet {
+        SpecSet {
+            specs: Arc::new(self.specs),
+            options: self.options,
+        }
+    }
+}
+
+/// A per-request handle on one spec of a [`SpecSet`]
+///
+/// Borrows from the set, so getting one costs a map lookup.
+#[derive(Debug, Clone, Copy)]
+pub struct Extractor<'s> {
+    name: &'s str,
+    spec: &'s Spec,
+    options: &'s ExtractOptions,
+}
+
+impl<'s> Extractor<'s> {
+    /// Name of the spec
+    pub fn name(&self) -> &'s str {
+        self.name
+    }
+
+    /// The spec
+    pub fn spec(&self) -> &'s Spec {
+        self.spec
+    }
+
+    /// Use other options for this handle's extractions, e.g. to override the
+    /// spec's constants per request with [`ExtractOptions::constant`]
+    pub fn options(self, options: &'s ExtractOptions) -> Self {
+        Self { options, ..self }
+    }
+
+    /// Parse HTML and extract from it
+    pub fn extract(&self, html: &str) -> Result<serde_json::Value, anyhow::Error> {
+        self.extract_dom(&Dom::parse(html)?)
+    }
+
+    /// Extract from an already parsed document
+    pub fn extract_dom(&self, dom: &Dom) -> Result<serde_json::Value, anyhow::Error> {
+        dom.extract_with_options(self.spec, self.options)
+    }
+}
@@ -0,0 +1,57 @@
+//! Built-in specs for data every scraper wants
+//!
+//! Presets are ordinary specs, kept as JSON next to this module, so their
+//! output can be reproduced or tweaked by copying the spec.
+
+use crate::spec::Spec;
+use std::sync::LazyLock;
+
+/// Page metadata: title, description, canonical URL, favicon, and
+/// OpenGraph (`og`) and Twitter card (`twitter`) properties
+///
+/// Missing values are left out, as are `og` and `twitter` when the page
+/// has no such tags.
+///
+/// # Example
+///
+/// ```
+/// let html = r#"<head><title>Hi</title><meta property="og:title" content="Hello"></head>"#;
+/// let meta = html2json::Dom::parse(html)?.extract(&html2json::presets::metadata())?;
+/// assert_eq!(meta["title"], "Hi");
+/// assert_eq!(meta["og"]["title"], "Hello");
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn metadata() -> Spec {
+    static METADATA: LazyLock<Spec> = LazyLock::new(|| {
+        let spec = serde_json::from_str(include_str!("presets/metadata.json"))
+            .expect("metadata preset is valid JSON");
+        Spec::from_json(&spec).expect("metadata preset is a valid spec")
+    });
+    METADATA.clone()
+}
+
+/// The page's main content: `title`, `byline`, `published`, and the content
+/// as `text` and cleaned `html`, all from `$article` fields
+///
+/// Needs the `article` feature. Add fields of your own to the spec to get
+/// them alongside the article.
+///
+/// # Example
+///
+/// ```
+/// let html = r#"<h1>Lamps</h1><div class="post"><p>Lamps give light, which is
+///   useful at night, in caves, and in rooms without windows.</p></div>"#;
+/// let article = html2json::Dom::parse(html)?.extract(&html2json::presets::article())?;
+/// assert_eq!(article["title"], "Lamps");
+/// assert!(article["text"].as_str().unwrap().starts_with("Lamps give light"));
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+#[cfg(feature = "article")]
+pub fn article() -> Spec {
+    static ARTICLE: LazyLock<Spec> = LazyLock::new(|| {
+        let spec = serde_json::from_str(include_str!("presets/article.json"))
+            .expect("article preset is valid JSON");
+        Spec::from_json(&spec).expect("article preset is a valid spec")
+    });
+    ARTICLE.clone()
+}
@@ -0,0 +1,87 @@
+//! `--query`: jq filters over extraction results
+//!
+//! Filters are run by jaq with its standard library, so most jq programs
+//! work unchanged. A filter yielding one value outputs it as is; none or
+//! several values (as from `.items[]`) are collected into an array.
+
+use anyhow::Result;
+use jaq_core::load::{Arena, File, Loader};
+use jaq_core::{Compiler, Ctx, Filter, Native, RcIter};
+use jaq_json::Val;
+use serde_json::Value;
+
+/// A compiled jq filter
+pub struct Query(Filter<Native<Val>>);
+
+impl Query {
+    /// Parse and compile `code`
+    pub fn compile(code: &str) -> Result<Self> {
+        let invalid = |reason: String| anyhow::anyhow!("Invalid query '{}': {}", code, reason);
+
+        let loader = Loader::new(jaq_std::defs().chain(jaq_json::defs()));
+        let arena = Arena::default();
+        let modules = loader
+            .load(&arena, File { code, path: () })
+            .map_err(|errors| {
+                let reasons: Vec<String> = errors
+                    .into_iter()
+                    .map(|(_, error)| match error {
+                        jaq_core::load::Error::Io(errors) => errors
+                            .into_iter()
+                            .map(|(_, e)| e)
+                            .collect::<Vec<_>>()
+                            .join(", "),
+                        jaq_core::load::Error::Lex(errors) => errors
+                            .iter()
+                            .map(|(expect, at)| unexpected(expect.as_str(), at))
+                            .collect::<Vec<_>>()
+                            .join(", "),
+                        jaq_core::load::Error::Parse(errors) => errors
+                            .iter()
+                            .map(|(expect, at)| unexpected(expect.as_str(), at))
+                            .collect::<Vec<_>>()
+                            .join(", "),
+                    })
+                    .collect();
+                invalid(reasons.join("; "))
+            })?;
+
+        let filter = Compiler::default()
+            .with_funs(jaq_std::funs().chain(jaq_json::funs()))
+            .compile(modules)
+            .map_err(|errors| {
+                let reasons: Vec<String> = errors
+                    .into_iter()
+                    .flat_map(|(_, undefined)| undefined)
+                    .map(|(name, kind)| format!("undefined {} '{}'", kind.as_str(), name))
+                    .collect();
+                invalid(reasons.join(", "))
+            })?;
+        Ok(Self(filter))
+    }
+
+    /// Run the filter over `input`
+    pub fn apply(&self, input: Value) -> Result<Value> {
+        let inputs = RcIter::new(core::iter::empty());
+        let mut outputs = self
+            .0
+            .run((Ctx::new([], &inputs), Val::from(input)))
+            .map(|output| {
+                output
+                    .map(Value::from)
+                    .map_err(|e| anyhow::anyhow!("Query failed: {}", e))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(match outputs.len() {
+            1 => outputs.remove(0),
+            _ => Value::Array(outputs),
+        })
+    }
+}
+
+fn unexpected(expected: &str, at: &str) -> String {
+    match at.chars().next() {
+        Some(_) => format!("expected {} at '{}'", expected, at),
+        None => format!("expected {} at end of query", expected),
+    }
+}
@@ -0,0 +1,143 @@
+//! Fetching pages through a headless browser
+//!
+//! Available with the `render` feature. Pages that build their content with
+//! JavaScript have little to extract from the HTML the server sends;
+//! [`BrowserFetcher`] loads them in Chrome or Chromium over the DevTools
+//! Protocol instead and returns the document as rendered. The browser must
+//! already be running with remote debugging enabled, e.g.
+//! `chromium --headless --remote-debugging-port=9222`.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use html2json::fetch::Fetcher;
+//! use html2json::render::BrowserFetcher;
+//!
+//! # async fn run(spec: &html2json::Spec) -> anyhow::Result<()> {
+//! let fetcher = BrowserFetcher::new("http://127.0.0.1:9222").wait_for(".results li");
+//! let result = fetcher.fetch_and_extract("https://example.com/search?q=rust", spec).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::fetch::{FetchedDoc, Fetcher};
+use chromiumoxide::Browser;
+use futures_util::StreamExt;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::OnceCell;
+
+/// Default debugging endpoint of a locally running browser
+pub const DEFAULT_ENDPOINT: &str = "http://127.0.0.1:9222";
+
+/// Default limit on loading and rendering one page
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How often to check for the [`wait_for`](BrowserFetcher::wait_for) selector
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A [`Fetcher`] that renders pages in a browser over the DevTools Protocol
+///
+/// Connects on the first fetch, and clones share the connection. Each page
+/// gets its own tab, closed once its content has been read. The connection
+/// is driven by a task on the tokio runtime of the first fetch, so all
+/// fetches must happen on that runtime.
+#[derive(Debug, Clone)]
+pub struct BrowserFetcher {
+    endpoint: String,
+    wait_for: Option<String>,
+    timeout: Duration,
+    browser: Arc<OnceCell<Browser>>,
+}
+
+impl BrowserFetcher {
+    /// Fetch through the browser at `endpoint`
+    ///
+    /// Either the `ws://` debugger URL or the `http://host:port` address the
+    /// browser's remote debugging listens on.
+    pub fn new(endpoint: impl Into<String>) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+            wait_for: None,
+            timeout: DEFAULT_TIMEOUT,
+            browser: Arc::new(OnceCell::new()),
+        }
+    }
+
+    /// After the page has loaded, wait until an element matches `selector`
+    ///
+    /// For pages that fill in their content after the `load` event.
+    pub fn wait_for(mut self, selector: impl Into<String>) -> Self {
+        self.wait_for = Some(selector.into());
+        self
+    }
+
+    /// Give up on a page after `timeout` (default [`DEFAULT_TIMEOUT`])
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    async fn browser(&self) -> Result<&Browser, anyhow::Error> {
+        self.browser
+            .get_or_try_init(|| async {
+                let (browser, mut handler) = Browser::connect(self.endpoint.as_str())
+                    .await
+                    .map_err(|e| {
+                        anyhow::anyhow!(
+                            "Failed to connect to browser at '{}': {}",
+                            self.endpoint,
+                            e
+                        )
+                    })?;
+                tokio::spawn(async move { while handler.next().await.is_some() {} });
+                tracing::info!(endpoint = self.endpoint, "connected to browser");
+                Ok(browser)
+            })
+            .await
+    }
+
+    async fn render(&self, url: &str) -> Result<FetchedDoc, anyhow::Error> {
+        let page = self.browser().await?.new_page("about:blank").await?;
+        let rendered = async {
+            page.goto(url).await?;
+            if let Some(selector) = &self.wait_for {
+                while page.find_element(selector.as_str()).await.is_err() {
+                    tokio::time::sleep(POLL_INTERVAL).await;
+                }
+            }
+            let body = page.content().await?;
+            let url = page.url().await?.unwrap_or_else(|| url.to_string());
+            Ok::<_, anyhow::Error>(FetchedDoc { url, body })
+        }
+        .await;
+        if let Err(e) = page.close().await {
+            tracing::warn!(url, error = %e, "failed to close tab");
+        }
+        rendered
+    }
+}
+
+impl Fetcher for BrowserFetcher {
+    /// Load a URL in a new tab and return the rendered document
+    async fn fetch(&self, url: &str) -> Result<FetchedDoc, anyhow::Error> {
+        let start = tokio::time::Instant::now();
+        let doc = tokio::time::timeout(self.timeout, self.render(url))
+            .await
+            .map_err(|_| {
+                let waiting = match &self.wait_for {
+                    Some(selector) => format!(" waiting for '{}'", selector),
+                    None => String::new(),
+                };
+                anyhow::anyhow!(
+                    "Timed out rendering '{}'{} after {:?}",
+                    url,
+                    waiting,
+                    self.timeout
+                )
+            })?
+            .map_err(|e| anyhow::anyhow!("Failed to render '{}': {}", url, e))?;
+        tracing::info!(url, bytes = doc.body.len(), elapsed = ?start.elapsed(), "rendered");
+        Ok(doc)
+    }
+}
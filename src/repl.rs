@@ -0,0 +1,254 @@
+//! Interactive prompt for trying selectors and building a spec
+//!
+//! Bare input is a query (`a.title | attr:href`) that lists what it matches.
+//! `name = expr` adds a field to the spec being built and shows its value;
+//! `:`-commands set the scope, print, run, and save the spec.
+
+use anyhow::Result;
+use html2json::{Dom, Spec};
+use rustyline::DefaultEditor;
+use rustyline::error::ReadlineError;
+use serde_json::{Map, Value};
+
+/// Matches shown for a query before eliding the rest
+const SHOW_MATCHES: usize = 10;
+
+const HELP: &str = "\
+Commands:
+  SELECTOR [| PIPES]     list what a selector matches, e.g. `h1 | trim`
+  NAME = EXPR            add or replace a spec field, e.g. `title = h1 | trim`
+  :scope [SELECTOR]      scope the spec to one element (no argument clears)
+  :each SELECTOR         make the spec a collection over matching elements
+  :rm NAME               remove a field
+  :spec                  print the spec
+  :run                   run the spec and print the result
+  :save FILE             write the spec to FILE
+  :help                  show this help
+  :quit                  exit (or Ctrl-D)";
+
+/// The spec being built
+#[derive(Debug, Default)]
+struct Draft {
+    /// `$` selector of the spec
+    scope: Option<String>,
+    /// Whether the spec is a collection over `scope`
+    each: bool,
+    /// Fields in the order they were added
+    fields: Vec<(String, String)>,
+}
+
+impl Draft {
+    fn to_json(&self) -> Value {
+        let mut object = Map::new();
+        if let Some(scope) = &self.scope {
+            object.insert("$".to_string(), Value::String(scope.clone()));
+        }
+        for (name, expr) in &self.fields {
+            object.insert(name.clone(), Value::String(expr.clone()));
+        }
+        if self.each {
+            Value::Array(vec![Value::Object(object)])
+        } else {
+            Value::Object(object)
+        }
+    }
+
+    fn run(&self, dom: &Dom) -> Result<Value> {
+        dom.extract(&Spec::from_json(&self.to_json())?)
+    }
+
+    /// Change the scope, undoing it if the spec no longer runs
+    fn set_scope(&mut self, dom: &Dom, scope: Option<String>, each: bool) -> Result<()> {
+        let backup = (std::mem::replace(&mut self.scope, scope), self.each);
+        self.each = each;
+        if let Err(e) = self.run(dom) {
+            (self.scope, self.each) = backup;
+            return Err(e);
+        }
+        Ok(())
+    }
+}
+
+/// Run the prompt over `dom` until the user quits
+pub fn run(dom: &Dom) -> Result<()> {
+    let mut editor = DefaultEditor::new()?;
+    let mut draft = Draft::default();
+    println!("Type a selector to try it, NAME = EXPR to add a field, :help for commands");
+
+    loop {
+        let line = match editor.readline("html2json> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(e.into()),
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let _ = editor.add_history_entry(line);
+
+        match eval(dom, &mut draft, line) {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(e) => println!("error: {}", e),
+        }
+    }
+    Ok(())
+}
+
+/// Evaluate one line; returns false when the user quits
+fn eval(dom: &Dom, draft: &mut Draft, line: &str) -> Result<bool> {
+    if let Some(command) = line.strip_prefix(':') {
+        let (command, arg) = match command.split_once(char::is_whitespace) {
+            Some((command, arg)) => (command, arg.trim()),
+            None => (command, ""),
+        };
+        match command {
+            "q" | "quit" | "exit" => return Ok(false),
+            "h" | "help" => println!("{}", HELP),
+            "scope" => {
+                draft.set_scope(dom, (!arg.is_empty()).then(|| arg.to_string()), false)?;
+                show_fields(dom, draft)?;
+            }
+            "each" if !arg.is_empty() => {
+                draft.set_scope(dom, Some(arg.to_string()), true)?;
+                show_fields(dom, draft)?;
+            }
+            "rm" if !arg.is_empty() => {
+                let before = draft.fields.len();
+                draft.fields.retain(|(name, _)| name != arg);
+                if draft.fields.len() == before {
+                    println!("no field '{}'", arg);
+                }
+            }
+            "spec" => println!("{}", serde_json::to_string_pretty(&draft.to_json())?),
+            "run" => println!("{}", serde_json::to_string_pretty(&draft.run(dom)?)?),
+            "save" if !arg.is_empty() => {
+                let json = serde_json::to_string_pretty(&draft.to_json())?;
+                std::fs::write(arg, json + "\n")
+                    .map_err(|e| anyhow::anyhow!("Failed to write '{}': {}", arg, e))?;
+                println!("saved {}", arg);
+            }
+            _ => println!("unknown command ':{}' (try :help)", command),
+        }
+        return Ok(true);
+    }
+
+    if let Some((name, expr)) = line.split_once('=')
+        && is_field_name(name.trim())
+    {
+        let (name, expr) = (name.trim().to_string(), expr.trim().to_string());
+        let backup = draft.fields.clone();
+        match draft.fields.iter_mut().find(|(n, _)| *n == name) {
+            Some(field) => field.1 = expr,
+            None => draft.fields.push((name.clone(), expr)),
+        }
+        // Keep the draft valid: undo the change if the spec no longer runs
+        if let Err(e) = draft.run(dom) {
+            draft.fields = backup;
+            return Err(e);
+        }
+        return show_field(dom, draft, &name).map(|()| true);
+    }
+
+    let values = query(dom, line)?;
+    for (i, value) in values.iter().take(SHOW_MATCHES).enumerate() {
+        println!("{:>3}  {}", i + 1, display(value));
+    }
+    match values.len() {
+        0 => println!("no match"),
+        n if n > SHOW_MATCHES => println!("... {} matches", n),
+        _ => {}
+    }
+    Ok(true)
+}
+
+/// Values of every element a `SELECTOR [| PIPES]` query matches
+fn query(dom: &Dom, line: &str) -> Result<Vec<Value>> {
+    let (selector, pipes) = html2json::spec::split_pipes(line);
+    crate::select_values(dom, selector, pipes)
+}
+
+/// Print the value of every field
+fn show_fields(dom: &Dom, draft: &Draft) -> Result<()> {
+    for (name, _) in &draft.fields {
+        show_field(dom, draft, name)?;
+    }
+    if draft.fields.is_empty() && draft.scope.is_some() {
+        let count = match draft.run(dom)? {
+            Value::Array(items) => items.len(),
+            Value::Null => 0,
+            _ => 1,
+        };
+        println!("scope matches {}", count);
+    }
+    Ok(())
+}
+
+/// Print a field's value, or its first values in a collection
+fn show_field(dom: &Dom, draft: &Draft, name: &str) -> Result<()> {
+    // Optional fields are output without their `?`
+    let key = name.trim_end_matches('?');
+    match draft.run(dom)? {
+        Value::Array(items) => {
+            let values: Vec<String> = items
+                .iter()
+                .take(3)
+                .map(|item| display(&item[key]))
+                .collect();
+            let more = if items.len() > 3 {
+                format!(", ... ({} items)", items.len())
+            } else {
+                String::new()
+            };
+            println!("{} = [{}{}]", name, values.join(", "), more);
+        }
+        result => println!("{} = {}", name, display(&result[key])),
+    }
+    Ok(())
+}
+
+fn display(value: &Value) -> String {
+    match value {
+        Value::String(s) => format!("{:?}", s.split_whitespace().collect::<Vec<_>>().join(" ")),
+        other => other.to_string(),
+    }
+}
+
+/// Whether `name = ...` names a field rather than being part of a selector
+/// like `a[href="x"]`
+fn is_field_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-' || c == '?')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn queries_split_at_top_level_pipes() {
+        let dom = Dom::parse(r#"<a hreflang="en-GB" href="/a">A|B</a><a href="/b">C</a>"#).unwrap();
+        assert_eq!(
+            query(&dom, "a[hreflang|=en] | attr:href").unwrap(),
+            [Value::from("/a")]
+        );
+        assert_eq!(
+            query(&dom, "a | regex:'(A|C)' | lower").unwrap(),
+            [Value::from("a"), Value::from("c")]
+        );
+    }
+
+    #[test]
+    fn bad_scope_is_rolled_back() {
+        let dom = Dom::parse("<ul><li>a</li></ul>").unwrap();
+        let mut draft = Draft::default();
+        eval(&dom, &mut draft, ":each li").unwrap();
+        assert!(eval(&dom, &mut draft, ":scope li[").is_err());
+        assert_eq!(draft.scope.as_deref(), Some("li"));
+        assert!(draft.each);
+    }
+}
@@ -0,0 +1,90 @@
+//! Checking extraction results against a JSON Schema
+//!
+//! Available with the `schema` feature. A spec that quietly stops matching
+//! after a site redesign still produces JSON, just with nulls and empty
+//! arrays where data used to be; a schema describing the expected output
+//! turns that drift into an error naming each offending value.
+
+use serde_json::Value;
+use std::fmt;
+
+/// A compiled JSON Schema
+///
+/// Compile once and reuse it for every result; remote `$ref`s are not
+/// fetched, so schemas must be self-contained.
+pub struct Schema {
+    validator: jsonschema::Validator,
+}
+
+impl Schema {
+    /// Compile a schema (drafts 4 to 2020-12, detected from `$schema`)
+    pub fn new(schema: &Value) -> Result<Self, anyhow::Error> {
+        let validator = jsonschema::validator_for(schema)
+            .map_err(|e| anyhow::anyhow!("Invalid JSON Schema: {}", e))?;
+        Ok(Self { validator })
+    }
+
+    /// Check `value`, returning every violation found
+    pub fn validate(&self, value: &Value) -> Result<(), SchemaViolations> {
+        let violations: Vec<Violation> = self
+            .validator
+            .iter_errors(value)
+            .map(|error| Violation {
+                pointer: error.instance_path().to_string(),
+                message: error.to_string(),
+            })
+            .collect();
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(SchemaViolations { violations })
+        }
+    }
+}
+
+impl fmt::Debug for Schema {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Schema").finish_non_exhaustive()
+    }
+}
+
+/// One way a result fails its schema
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// JSON pointer to the offending value (`/stories/3/points`; empty for the root)
+    pub pointer: String,
+    /// What is wrong with it
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pointer = if self.pointer.is_empty() {
+            "/"
+        } else {
+            &self.pointer
+        };
+        write!(f, "{}: {}", pointer, self.message)
+    }
+}
+
+/// The error returned when a result does not match its schema
+///
+/// Reachable via `downcast_ref` on the `anyhow::Error` from
+/// [`extract_validated`](crate::extract_validated).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaViolations {
+    pub violations: Vec<Violation>,
+}
+
+impl fmt::Display for SchemaViolations {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Result does not match the schema:")?;
+        for violation in &self.violations {
+            write!(f, "\n  {}", violation)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for SchemaViolations {}
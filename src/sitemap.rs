@@ -0,0 +1,88 @@
+//! sitemap.xml expansion
+//!
+//! Reads a sitemap from a URL or file and returns the page URLs it lists,
+//! following `<sitemapindex>` entries into child sitemaps.
+
+use crate::{Args, check_html_size, read_file, select_values};
+use anyhow::Result;
+use html2json::Dom;
+use html2json::fetch::{self, Fetcher};
+use serde_json::Value;
+use std::collections::HashSet;
+
+/// Most sitemaps read through indexes, to stop runaway or cyclic indexes
+const MAX_SITEMAPS: usize = 1000;
+
+/// Page URLs listed by the sitemap at `location` that match `--url-filter`
+pub fn urls(args: &Args, location: &str) -> Result<Vec<String>> {
+    let filters = args
+        .url_filter
+        .iter()
+        .map(|p| {
+            glob::Pattern::new(p).map_err(|e| anyhow::anyhow!("Invalid pattern '{}': {}", p, e))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let fetcher = crate::build_fetcher(args)?;
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+
+    let mut pending = vec![location.to_string()];
+    let mut read = HashSet::new();
+    let mut pages = Vec::new();
+    let mut listed = HashSet::new();
+    while let Some(sitemap) = pending.pop() {
+        if !read.insert(sitemap.clone()) {
+            continue;
+        }
+        if read.len() > MAX_SITEMAPS {
+            return Err(anyhow::anyhow!(
+                "Sitemap index lists more than {} sitemaps",
+                MAX_SITEMAPS
+            ));
+        }
+        let content = if fetch::is_url(&sitemap) {
+            runtime
+                .block_on(fetcher.fetch(&sitemap))
+                .and_then(|doc| check_html_size(doc.body))?
+        } else {
+            read_file(&sitemap)?
+        };
+        let dom = Dom::parse(&content)?;
+
+        // Child sitemaps are read in the order the index lists them
+        let children = locations(&dom, "sitemap > loc")?;
+        pending.extend(children.into_iter().rev());
+        for url in locations(&dom, "url > loc")? {
+            if (filters.is_empty() || filters.iter().any(|p| p.matches(&url)))
+                && listed.insert(url.clone())
+            {
+                pages.push(url);
+            }
+        }
+    }
+
+    if pages.is_empty() {
+        let filtered = if filters.is_empty() {
+            ""
+        } else {
+            " matching --url-filter"
+        };
+        return Err(anyhow::anyhow!(
+            "No page URLs{} in sitemap '{}'",
+            filtered,
+            location
+        ));
+    }
+    Ok(pages)
+}
+
+fn locations(dom: &Dom, selector: &str) -> Result<Vec<String>> {
+    Ok(select_values(dom, selector, Some("trim"))?
+        .into_iter()
+        .filter_map(|value| match value {
+            Value::String(url) if !url.is_empty() => Some(url),
+            _ => None,
+        })
+        .collect())
+}
@@ -0,0 +1,290 @@
+//! Compact binary snapshots of a parsed document
+//!
+//! A snapshot stores the html5ever tree in pre-order, so reloading it skips
+//! tokenizing and tree construction entirely. The format is private to this
+//! crate and versioned; snapshots from other versions are rejected.
+
+use ego_tree::Tree;
+use ego_tree::iter::Edge;
+use html5ever::tree_builder::QuirksMode;
+use html5ever::{Attribute, LocalName, Namespace, Prefix, QualName};
+use scraper::node::{Comment, Doctype, Element, ProcessingInstruction, Text};
+use scraper::{Html, Node, StrTendril};
+use std::collections::HashMap;
+use std::io::{BufReader, BufWriter, Read, Write};
+
+const MAGIC: &[u8; 6] = b"H2JDOM";
+const VERSION: u8 = 1;
+
+// Record tags
+const DOCUMENT: u8 = 0;
+const FRAGMENT: u8 = 1;
+const DOCTYPE: u8 = 2;
+const COMMENT: u8 = 3;
+const TEXT: u8 = 4;
+const ELEMENT: u8 = 5;
+const PROCESSING_INSTRUCTION: u8 = 6;
+/// Closes the most recently opened node
+const END: u8 = 0xff;
+
+/// Write `html` as a snapshot
+pub(crate) fn write<W: Write>(html: &Html, writer: W) -> Result<(), anyhow::Error> {
+    let mut w = BufWriter::new(writer);
+    w.write_all(MAGIC)?;
+    w.write_all(&[VERSION, quirks_to_u8(html.quirks_mode)])?;
+
+    let mut names = HashMap::new();
+    for edge in html.tree.root().traverse() {
+        match edge {
+            Edge::Open(node) => write_node(&mut w, &mut names, node.value())?,
+            Edge::Close(_) => w.write_all(&[END])?,
+        }
+    }
+    w.flush()?;
+    Ok(())
+}
+
+/// Read a snapshot written by [`write`]
+pub(crate) fn read<R: Read>(reader: R) -> Result<Html, anyhow::Error> {
+    let mut r = BufReader::new(reader);
+
+    let mut magic = [0u8; 6];
+    r.read_exact(&mut magic)
+        .map_err(|_| anyhow::anyhow!("Not a DOM snapshot"))?;
+    if &magic != MAGIC {
+        return Err(anyhow::anyhow!("Not a DOM snapshot"));
+    }
+    let version = read_u8(&mut r)?;
+    if version != VERSION {
+        return Err(anyhow::anyhow!(
+            "Unsupported DOM snapshot version {} (expected {})",
+            version,
+            VERSION
+        ));
+    }
+    let quirks_mode = quirks_from_u8(read_u8(&mut r)?)?;
+
+    let mut names = Vec::new();
+    let root = match read_node(&mut r, &mut names)? {
+        Some(node @ (Node::Document | Node::Fragment)) => node,
+        _ => return Err(anyhow::anyhow!("DOM snapshot has no document root")),
+    };
+    let mut tree = Tree::new(root);
+    let mut open = vec![tree.root().id()];
+
+    while let Some(&parent) = open.last() {
+        match read_node(&mut r, &mut names)? {
+            Some(node) => {
+                let mut parent = tree
+                    .get_mut(parent)
+                    .ok_or_else(|| anyhow::anyhow!("Corrupt DOM snapshot"))?;
+                open.push(parent.append(node).id());
+            }
+            None => {
+                open.pop();
+            }
+        }
+    }
+
+    let mut trailing = [0u8; 1];
+    if r.read(&mut trailing)? != 0 {
+        return Err(anyhow::anyhow!("Trailing data after DOM snapshot"));
+    }
+
+    Ok(Html {
+        errors: Vec::new(),
+        quirks_mode,
+        tree,
+    })
+}
+
+fn write_node<W: Write>(
+    w: &mut W,
+    names: &mut HashMap<String, usize>,
+    node: &Node,
+) -> Result<(), anyhow::Error> {
+    match node {
+        Node::Document => w.write_all(&[DOCUMENT])?,
+        Node::Fragment => w.write_all(&[FRAGMENT])?,
+        Node::Doctype(doctype) => {
+            w.write_all(&[DOCTYPE])?;
+            write_str(w, &doctype.name)?;
+            write_str(w, &doctype.public_id)?;
+            write_str(w, &doctype.system_id)?;
+        }
+        Node::Comment(comment) => {
+            w.write_all(&[COMMENT])?;
+            write_str(w, &comment.comment)?;
+        }
+        Node::Text(text) => {
+            w.write_all(&[TEXT])?;
+            write_str(w, &text.text)?;
+        }
+        Node::Element(element) => {
+            w.write_all(&[ELEMENT])?;
+            write_name(w, names, &element.name)?;
+            write_len(w, element.attrs.len())?;
+            for (name, value) in element.attrs.iter() {
+                write_name(w, names, name)?;
+                write_str(w, value)?;
+            }
+        }
+        Node::ProcessingInstruction(pi) => {
+            w.write_all(&[PROCESSING_INSTRUCTION])?;
+            write_str(w, &pi.target)?;
+            write_str(w, &pi.data)?;
+        }
+    }
+    Ok(())
+}
+
+/// Read the next node record, or None for an end marker
+fn read_node<R: Read>(r: &mut R, names: &mut Vec<String>) -> Result<Option<Node>, anyhow::Error> {
+    let node = match read_u8(r)? {
+        END => return Ok(None),
+        DOCUMENT => Node::Document,
+        FRAGMENT => Node::Fragment,
+        DOCTYPE => Node::Doctype(Doctype {
+            name: read_tendril(r)?,
+            public_id: read_tendril(r)?,
+            system_id: read_tendril(r)?,
+        }),
+        COMMENT => Node::Comment(Comment {
+            comment: read_tendril(r)?,
+        }),
+        TEXT => Node::Text(Text {
+            text: read_tendril(r)?,
+        }),
+        ELEMENT => {
+            let name = read_name(r, names)?;
+            let count = read_len(r)?;
+            let mut attrs = Vec::new();
+            for _ in 0..count {
+                attrs.push(Attribute {
+                    name: read_name(r, names)?,
+                    value: read_string(r)?.into(),
+                });
+            }
+            Node::Element(Element::new(name, attrs))
+        }
+        PROCESSING_INSTRUCTION => Node::ProcessingInstruction(ProcessingInstruction {
+            target: read_tendril(r)?,
+            data: read_tendril(r)?,
+        }),
+        tag => {
+            return Err(anyhow::anyhow!(
+                "Corrupt DOM snapshot: unknown record {}",
+                tag
+            ));
+        }
+    };
+    Ok(Some(node))
+}
+
+/// Write a qualified name as three interned strings
+fn write_name<W: Write>(
+    w: &mut W,
+    names: &mut HashMap<String, usize>,
+    name: &QualName,
+) -> Result<(), anyhow::Error> {
+    write_interned(w, names, name.prefix.as_deref().unwrap_or(""))?;
+    write_interned(w, names, &name.ns)?;
+    write_interned(w, names, &name.local)
+}
+
+fn read_name<R: Read>(r: &mut R, names: &mut Vec<String>) -> Result<QualName, anyhow::Error> {
+    let prefix = read_interned(r, names)?;
+    let ns = read_interned(r, names)?;
+    let local = read_interned(r, names)?;
+    let prefix = (!names[prefix].is_empty()).then(|| Prefix::from(names[prefix].as_str()));
+    Ok(QualName::new(
+        prefix,
+        Namespace::from(names[ns].as_str()),
+        LocalName::from(names[local].as_str()),
+    ))
+}
+
+/// Write a string table index; a new index is followed by the string itself
+fn write_interned<W: Write>(
+    w: &mut W,
+    names: &mut HashMap<String, usize>,
+    s: &str,
+) -> Result<(), anyhow::Error> {
+    if let Some(&index) = names.get(s) {
+        return write_len(w, index);
+    }
+    let index = names.len();
+    names.insert(s.to_string(), index);
+    write_len(w, index)?;
+    write_str(w, s)
+}
+
+/// Read a string table index, returning a valid index into `names`
+fn read_interned<R: Read>(r: &mut R, names: &mut Vec<String>) -> Result<usize, anyhow::Error> {
+    let index = read_len(r)?;
+    if index == names.len() {
+        names.push(read_string(r)?);
+    }
+    if index >= names.len() {
+        return Err(anyhow::anyhow!("Corrupt DOM snapshot: bad name index"));
+    }
+    Ok(index)
+}
+
+fn write_len<W: Write>(w: &mut W, len: usize) -> Result<(), anyhow::Error> {
+    let len = u32::try_from(len).map_err(|_| anyhow::anyhow!("DOM too large to snapshot"))?;
+    w.write_all(&len.to_le_bytes())?;
+    Ok(())
+}
+
+fn read_len<R: Read>(r: &mut R) -> Result<usize, anyhow::Error> {
+    let mut buf = [0u8; 4];
+    r.read_exact(&mut buf)
+        .map_err(|_| anyhow::anyhow!("Truncated DOM snapshot"))?;
+    Ok(u32::from_le_bytes(buf) as usize)
+}
+
+fn write_str<W: Write>(w: &mut W, s: &str) -> Result<(), anyhow::Error> {
+    write_len(w, s.len())?;
+    w.write_all(s.as_bytes())?;
+    Ok(())
+}
+
+fn read_string<R: Read>(r: &mut R) -> Result<String, anyhow::Error> {
+    let len = read_len(r)?;
+    // Grow as data arrives instead of trusting the length up front
+    let mut bytes = Vec::new();
+    r.take(len as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != len {
+        return Err(anyhow::anyhow!("Truncated DOM snapshot"));
+    }
+    String::from_utf8(bytes).map_err(|_| anyhow::anyhow!("Corrupt DOM snapshot: invalid UTF-8"))
+}
+
+fn read_tendril<R: Read>(r: &mut R) -> Result<StrTendril, anyhow::Error> {
+    read_string(r).map(StrTendril::from)
+}
+
+fn read_u8<R: Read>(r: &mut R) -> Result<u8, anyhow::Error> {
+    let mut buf = [0u8; 1];
+    r.read_exact(&mut buf)
+        .map_err(|_| anyhow::anyhow!("Truncated DOM snapshot"))?;
+    Ok(buf[0])
+}
+
+fn quirks_to_u8(mode: QuirksMode) -> u8 {
+    match mode {
+        QuirksMode::Quirks => 0,
+        QuirksMode::LimitedQuirks => 1,
+        QuirksMode::NoQuirks => 2,
+    }
+}
+
+fn quirks_from_u8(value: u8) -> Result<QuirksMode, anyhow::Error> {
+    match value {
+        0 => Ok(QuirksMode::Quirks),
+        1 => Ok(QuirksMode::LimitedQuirks),
+        2 => Ok(QuirksMode::NoQuirks),
+        _ => Err(anyhow::anyhow!("Corrupt DOM snapshot: bad quirks mode")),
+    }
+}
@@ -7,33 +7,225 @@
 //! - Literal values (strings, numbers, booleans)
 //! - Pipe transformations for data manipulation
 
-use serde::Deserialize;
+mod lint;
+mod tokens;
+
+pub use lint::{Lint, LintWarning, lint};
+
+use crate::dom::SimpleSelector;
+#[cfg(feature = "regex")]
+use regex::Regex;
+use scraper::Selector;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use tokens::{quote, split_top_level, unquote};
 
-/// A CSS selector string
-#[derive(Debug, Clone, PartialEq)]
-pub struct SelectorRef(String);
+/// Marks a sibling hop in next-sibling selectors (`+ .subtext`, `+2 .subtext`)
+const NEXT_SIBLING_PREFIX: char = '+';
+/// Prefix for direct-child selectors (`> li`)
+const DIRECT_CHILD_PREFIX: char = '>';
+/// Key of a collection item that opts into one item per element
+const ALL_KEY: &str = "$all";
+/// Suffix of a field name that collects every match (`"tags*": ".tag"`)
+const ALL_MATCHES_SUFFIX: char = '*';
+/// Field value collecting the images in scope
+const IMAGES_KEY: &str = "$images";
+/// Field value collecting the links in scope
+const LINKS_KEY: &str = "$links";
+/// Field value giving the heading outline in scope
+const OUTLINE_KEY: &str = "$outline";
+/// Field value giving the page's breadcrumb trail
+const BREADCRUMBS_KEY: &str = "$breadcrumbs";
+/// Field value giving the page's pagination links
+const PAGINATION_KEY: &str = "$pagination";
+/// Field value describing a form
+const FORM_KEY: &str = "$form";
+/// Field value giving a collection item's position, counting from 0
+const INDEX_KEY: &str = "$index";
+/// Field value giving a collection item's position, counting from 1
+const RANK_KEY: &str = "$rank";
+/// Key of a nested object or collection item that searches from the document root
+const ROOT_KEY: &str = "$root";
+/// Key of a collection item that takes in the rows after its element
+const WITH_NEXT_KEY: &str = "$withNext";
+/// Prefix of selectors searching a collection item's following rows (`$next .score`)
+const NEXT_ROWS_PREFIX: &str = "$next";
+/// Key of a field object applying the enclosing collection again (`{"$recurse": 5}`)
+const RECURSE_KEY: &str = "$recurse";
+/// Key of the top-level section of named constants for pipe arguments
+const CONSTS_KEY: &str = "$consts";
+/// Marks a pipe argument naming a constant (`prefix:$currency`)
+const CONST_PREFIX: char = '$';
+/// Key of a field object giving the time of the extraction (`{"$now": "iso"}`)
+const NOW_KEY: &str = "$now";
+/// Key of a field object reading an environment variable (`{"$env": "RUN_ID"}`)
+const ENV_KEY: &str = "$env";
+/// Key of a field object reading the extraction's page context (`{"$context": "url"}`)
+const CONTEXT_KEY: &str = "$context";
+
+/// A CSS selector string, compiled when the spec is parsed
+///
+/// The source string and compiled selector are shared behind `Arc`s, so
+/// cloning is cheap and extraction never re-parses selector strings.
+#[derive(Debug, Clone)]
+pub struct SelectorRef {
+    source: Arc<str>,
+    /// Compiled CSS part of the selector (None for `$`)
+    compiled: Option<Arc<Selector>>,
+    /// Index-friendly form of the CSS part, if it is a lone id/class/tag
+    simple: Option<SimpleSelector>,
+    /// Siblings to step over for `+` selectors (0 for others)
+    sibling_hops: usize,
+    /// Rows after the scope searched by `$next` selectors: set from the
+    /// collection item's `$withNext`, so 0 until then (None for others)
+    next_rows: Option<usize>,
+}
+
+impl PartialEq for SelectorRef {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
 
 impl SelectorRef {
+    /// Parse and compile a selector string
+    ///
+    /// Accepts `$`, `+ selector` (next siblings), `+ + selector` or
+    /// `+2 selector` (siblings after the next one), `$next` or
+    /// `$next selector` (a collection item's following rows), `> selector`
+    /// and plain CSS.
+    pub fn parse(selector: impl Into<Arc<str>>) -> Result<Self, anyhow::Error> {
+        let source: Arc<str> = selector.into();
+        let sibling_hops = match Self::sibling_prefix(&source) {
+            Some((0, _)) => {
+                return Err(anyhow::anyhow!(
+                    "Invalid selector '{}': sibling hops start at +1",
+                    source
+                ));
+            }
+            Some((hops, _)) => hops,
+            None => 0,
+        };
+        let css = Self::css_part(&source);
+        let compiled = match css {
+            Some(css) => {
+                Some(Arc::new(Selector::parse(css).map_err(|e| {
+                    anyhow::anyhow!("Invalid selector '{}': {}", css, e)
+                })?))
+            }
+            None => None,
+        };
+        let simple = css.and_then(SimpleSelector::parse);
+        let next_rows = Self::next_rows_part(&source).map(|_| 0);
+        Ok(Self {
+            source,
+            compiled,
+            simple,
+            sibling_hops,
+            next_rows,
+        })
+    }
+
+    /// The selector after a `$next` prefix (empty for a bare `$next`)
+    fn next_rows_part(source: &str) -> Option<&str> {
+        let rest = source.trim().strip_prefix(NEXT_ROWS_PREFIX)?;
+        (rest.is_empty() || rest.starts_with(char::is_whitespace)).then(|| rest.trim())
+    }
+
+    /// Split a `+ `, `+ + ` or `+N ` prefix into its hop count and the rest
+    fn sibling_prefix(source: &str) -> Option<(usize, &str)> {
+        let mut rest = source;
+        let mut hops = 0usize;
+        while let Some(after) = rest.strip_prefix(NEXT_SIBLING_PREFIX) {
+            let digits = after.len() - after.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            if !after[digits..].starts_with(char::is_whitespace) {
+                break;
+            }
+            let count = match digits {
+                0 => 1,
+                _ => after[..digits].parse().ok()?,
+            };
+            hops = hops.checked_add(count)?;
+            rest = after[digits..].trim_start();
+        }
+        (rest.len() < source.len()).then_some((hops, rest))
+    }
+
+    /// The CSS portion of a selector string, without `+ `/`>` prefixes
+    fn css_part(source: &str) -> Option<&str> {
+        if source == "$" {
+            return None;
+        }
+        if let Some(inner) = Self::next_rows_part(source) {
+            return (!inner.is_empty()).then_some(inner);
+        }
+        if let Some((_, inner)) = Self::sibling_prefix(source) {
+            return Some(inner);
+        }
+        if let Some(inner) = source.trim().strip_prefix(DIRECT_CHILD_PREFIX) {
+            return Some(inner.trim());
+        }
+        Some(source)
+    }
+
+    /// CSS text of the compiled selector, without sibling/child prefixes
+    pub(crate) fn css(&self) -> Option<&str> {
+        Self::css_part(&self.source)
+    }
+
     /// Get the selector string
     pub fn as_str(&self) -> &str {
-        &self.0
+        &self.source
+    }
+
+    /// Get the compiled CSS selector (None for `$`)
+    pub fn selector(&self) -> Option<&Selector> {
+        self.compiled.as_deref()
+    }
+
+    /// Index-friendly form of the selector, if any
+    pub(crate) fn simple(&self) -> Option<&SimpleSelector> {
+        self.simple.as_ref()
     }
 
     /// Check if this is a self-reference selector ($)
     pub fn is_self_ref(&self) -> bool {
-        self.0 == "$"
+        &*self.source == "$"
+    }
+
+    /// Check if this is a next-sibling selector (`+ selector`)
+    pub fn is_next_sibling(&self) -> bool {
+        self.sibling_hops > 0
+    }
+
+    /// How far a next-sibling selector steps: 1 for `+ selector`, 2 for
+    /// `+ + selector` or `+2 selector`, and 0 for other selectors
+    pub fn sibling_hops(&self) -> usize {
+        self.sibling_hops
+    }
+
+    /// For `$next` selectors, how many rows after the scope they search
+    /// (the collection item's `$withNext`); None for other selectors
+    pub fn next_rows(&self) -> Option<usize> {
+        self.next_rows
     }
 }
 
 /// Represents an extraction specification
+///
+/// A spec is immutable once parsed, `Send + Sync`, and cheap to clone
+/// (object and array specs are shared behind an `Arc`), so one parsed spec can
+/// be reused across worker threads.
 #[derive(Debug, Clone)]
 pub enum Spec {
     /// Extract a single value (object with key-value pairs)
-    Object(ObjectSpec),
+    Object(Arc<ObjectSpec>),
     /// Extract multiple values (array of objects)
-    Array(ArraySpec),
+    Array(Arc<ArraySpec>),
     /// A literal value
     Literal(LiteralValue),
 }
@@ -43,19 +235,162 @@ impl<'de> Deserialize<'de> for Spec {
     where
         D: serde::Deserializer<'de>,
     {
-        let value = Value::deserialize(deserializer)?;
+        let UniqueKeys(value) = UniqueKeys::deserialize(deserializer)?;
         Spec::from_json(&value).map_err(serde::de::Error::custom)
     }
 }
 
+/// Parse spec JSON text into its JSON form, rejecting repeated object keys
+///
+/// Parsing into a plain [`Value`] keeps only the last of two `"$"` or two
+/// `"title"` keys; spec text should go through this (or deserialize straight
+/// into a [`Spec`]) so the mistake is reported instead.
+pub fn parse_json(text: &str) -> Result<Value, anyhow::Error> {
+    let UniqueKeys(value) = serde_json::from_str(text)?;
+    Ok(value)
+}
+
+/// Deserialize spec text in any serde format, rejecting repeated object keys
+///
+/// [`parse_json`] for other formats, such as JSON5 or YAML.
+pub fn deserialize_unique<'de, D>(deserializer: D) -> Result<Value, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    UniqueKeys::deserialize(deserializer).map(|UniqueKeys(value)| value)
+}
+
+/// Split a `SELECTOR | PIPES` expression at its first `|`
+///
+/// A `|` inside quotes or brackets, like in `a[title='a|b']` or
+/// `regex:'a|b'`, does not split. The parts are trimmed; the pipes are `None`
+/// if there are none.
+pub fn split_pipes(expr: &str) -> (&str, Option<&str>) {
+    let selector = split_top_level(expr, "|")[0];
+    match expr[selector.len()..].strip_prefix('|') {
+        Some(pipes) => (selector.trim(), Some(pipes.trim())),
+        None => (selector.trim(), None),
+    }
+}
+
+/// A JSON value whose objects had no repeated keys
+struct UniqueKeys(Value);
+
+impl<'de> Deserialize<'de> for UniqueKeys {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer
+            .deserialize_any(UniqueKeysVisitor)
+            .map(UniqueKeys)
+    }
+}
+
+struct UniqueKeysVisitor;
+
+impl<'de> serde::de::Visitor<'de> for UniqueKeysVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a JSON value")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Value, E> {
+        Ok(Value::Bool(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Value, E> {
+        Ok(v.into())
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Value, E> {
+        Ok(v.into())
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Value, E> {
+        Ok(v.into())
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Value, E> {
+        Ok(v.into())
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Value, E> {
+        Ok(v.into())
+    }
+
+    fn visit_unit<E>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_none<E>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserialize_unique(deserializer)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Value, A::Error>
+    where
+        A: serde::de::SeqAccess<'de>,
+    {
+        let mut items = Vec::new();
+        while let Some(UniqueKeys(item)) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(Value::Array(items))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Value, A::Error>
+    where
+        A: serde::de::MapAccess<'de>,
+    {
+        let mut object = serde_json::Map::new();
+        while let Some(key) = map.next_key::<String>()? {
+            if object.contains_key(&key) {
+                return Err(serde::de::Error::custom(format!("Duplicate key '{}'", key)));
+            }
+            let UniqueKeys(value) = map.next_value()?;
+            object.insert(key, value);
+        }
+        Ok(Value::Object(object))
+    }
+}
+
+impl Serialize for Spec {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.to_json().serialize(serializer)
+    }
+}
+
 /// Object spec - map of keys to extractors
 ///
 /// The scope_selector defines the base element(s) for all field extractions.
 /// All selectors in fields are evaluated relative to this scope.
+/// `"$": "a || b"` tries each scope in turn, like a field fallback.
+///
+/// A nested object or collection searches inside its parent's scope, so a
+/// collection inside a collection item only sees that item's elements.
+/// `"$root": true` searches from the document root instead.
 #[derive(Debug, Clone, PartialEq)]
 pub struct ObjectSpec {
     pub scope_selector: Option<SelectorRef>,
+    /// Scopes tried in order when `scope_selector` matches nothing
+    pub scope_fallbacks: Vec<SelectorRef>,
+    /// Search from the document root, not the parent's scope (`"$root": true`)
+    pub root: bool,
     pub fields: HashMap<String, Field>,
+    /// Constants named by `$name` pipe arguments (`"$consts"`); only the
+    /// top-level object or collection item has them
+    pub consts: HashMap<String, String>,
 }
 
 /// A field specification with optional flag
@@ -71,9 +406,15 @@ pub struct Field {
 /// Array spec - extract all matching elements
 ///
 /// The item_spec is applied to each matched element to produce an array of results.
+/// Its scope selector picks the elements; without one, `all` must be set
+/// (`"$all": true`), and every element in the scope gets an item.
 #[derive(Debug, Clone, PartialEq)]
 pub struct ArraySpec {
     pub item_spec: ObjectSpec,
+    pub all: bool,
+    /// Rows after each item's element that belong to the item (`"$withNext"`),
+    /// searched by its `$next` selectors
+    pub with_next: usize,
 }
 
 /// Field specification
@@ -85,12 +426,128 @@ pub enum FieldSpec {
     Selector(SelectorRef, Vec<PipeCommand>),
     /// Fallback selectors - tries each in order until one produces a result
     FallbackSelector(Vec<(SelectorRef, Vec<PipeCommand>)>),
+    /// Every match of a selector, piped, as an array (`"tags*": ".tag"` or
+    /// `"tags": [".tag"]`); of fallbacks, the first that matches anything
+    All(Vec<(SelectorRef, Vec<PipeCommand>)>),
     /// Nested object
     Nested(ObjectSpec),
     /// Nested array
     NestedArray(ArraySpec),
     /// Literal value
     Literal(LiteralValue),
+    /// JSON-LD items of the document (`$jsonld`), optionally only those of
+    /// one `@type` (`$jsonld:Product`)
+    JsonLd(Option<String>),
+    /// Microdata items at or inside the scope (`$microdata`), optionally
+    /// only those of one `itemtype` (`$microdata:Product`)
+    Microdata(Option<String>),
+    /// The page's main content found by readability heuristics (`$article`),
+    /// or one of its fields (`$article:text`); needs the `article` feature
+    Article(Option<String>),
+    /// `<img>` and `<source>` elements at or inside the scope (`$images`)
+    Images,
+    /// Links at or inside the scope (`$links`), optionally only internal or
+    /// external ones (`$links:internal`)
+    Links(Option<LinkFilter>),
+    /// The heading outline at or inside the scope as a tree (`$outline`)
+    Outline,
+    /// The page's breadcrumb trail (`$breadcrumbs`)
+    Breadcrumbs,
+    /// The page's next, previous, canonical and numbered page links (`$pagination`)
+    Pagination,
+    /// A form's action, method and fields: the first form at or inside the
+    /// scope (`$form`), or the first match of a selector (`$form:form#search`)
+    Form(Option<SelectorRef>),
+    /// A collection item's position, counting from 0 (`$index`)
+    Index,
+    /// A collection item's position, counting from 1 (`$rank`)
+    Rank,
+    /// The enclosing collection applied again inside the item, or inside its
+    /// first match of a selector, at most a number of levels deep
+    /// (`{"$recurse": 5, "$": "> ul"}`), for trees of unknown depth
+    Recurse(Option<SelectorRef>, usize),
+    /// The time of the extraction (`{"$now": "iso"}`), the same for every
+    /// field of one extraction
+    Now(NowFormat),
+    /// An environment variable (`{"$env": "RUN_ID"}`), `null` if it isn't set
+    Env(String),
+    /// A value of the [`PageContext`](crate::PageContext) given to the
+    /// extraction (`{"$context": "url"}`), `null` if it isn't known
+    Context(String),
+}
+
+/// Which links `$links` keeps
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkFilter {
+    /// Links to the page's own host (`$links:internal`)
+    Internal,
+    /// Links to other hosts (`$links:external`)
+    External,
+}
+
+impl LinkFilter {
+    /// Name of the filter in specs
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Internal => "internal",
+            Self::External => "external",
+        }
+    }
+}
+
+/// How `$now` writes the time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NowFormat {
+    /// RFC 3339 in UTC, to the second (`"iso"`)
+    Iso,
+    /// The UTC date, `YYYY-MM-DD` (`"date"`)
+    Date,
+    /// Seconds since the Unix epoch, as a number (`"unix"`)
+    Unix,
+}
+
+impl NowFormat {
+    /// Name of the format in specs
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Iso => "iso",
+            Self::Date => "date",
+            Self::Unix => "unix",
+        }
+    }
+}
+
+/// A pipe argument: text as written, or the name of a constant (`$name`)
+/// looked up when extracting
+#[derive(Debug, Clone, PartialEq)]
+pub enum PipeArg {
+    Text(String),
+    Const(String),
+}
+
+impl PipeArg {
+    /// Parse an argument, where `$name` names a constant and quoting
+    /// (`'$5'`) keeps a leading `$` as text
+    fn parse(arg: &str) -> Result<Self, anyhow::Error> {
+        let arg = arg.trim();
+        match arg.strip_prefix(CONST_PREFIX) {
+            Some("") => Err(anyhow::anyhow!("Missing constant name after '$'")),
+            Some(name) => Ok(PipeArg::Const(name.to_string())),
+            None => Ok(PipeArg::Text(unquote(arg).into_owned())),
+        }
+    }
+}
+
+impl fmt::Display for PipeArg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PipeArg::Const(name) => write!(f, "{}{}", CONST_PREFIX, name),
+            PipeArg::Text(text) if text.starts_with(CONST_PREFIX) => {
+                write!(f, "'{}'", text.replace('\'', "''"))
+            }
+            PipeArg::Text(text) => write!(f, "{}", quote(text, &[])),
+        }
+    }
 }
 
 /// Pipe transformation command
@@ -99,15 +556,153 @@ pub enum FieldSpec {
 #[derive(Debug, Clone, PartialEq)]
 pub enum PipeCommand {
     Attr(String),
+    /// Whether the element has an attribute, for boolean attributes like
+    /// `disabled` (`hasAttr:disabled`)
+    HasAttr(String),
+    /// The element's class names as an array
+    Classes,
+    /// Whether the element has a class (`hasClass:sale`)
+    HasClass(String),
+    /// A property of the inline `style` (`style:background-image`), with
+    /// `url(...)` unwrapped if the flag is set (`style:background-image:url`)
+    Style(String, bool),
     Void,
+    /// The element's content as Markdown, or a string of HTML converted to Markdown
+    Markdown,
+    /// The element's text with whitespace, `<br>`s and line breaks kept
+    RawText,
+    /// The element's lines (split at `<br>` and block elements) as an array
+    Lines,
     Trim,
     Lower,
     Upper,
     Substr(usize, Option<usize>),
+    /// Keep only digits, a leading sign and decimal points, before `parseAs`
+    Digits,
     ParseAsNumber,
     ParseAsInt,
     ParseAsFloat,
-    Regex(String),
+    /// Parse text as JSON, e.g. the contents of `script#__NEXT_DATA__`
+    ParseAsJson,
+    /// A date or time as ISO 8601, read from the `datetime` attribute if
+    /// the element has one
+    DateTime,
+    /// Text put before the value (`prefix:https://example.com`, `prefix:$currency`)
+    Prefix(PipeArg),
+    /// A URL resolved against a base URL (`absoluteUrl:$cdn`), by default the
+    /// page's (`absoluteUrl`)
+    AbsoluteUrl(Option<PipeArg>),
+    #[cfg(feature = "regex")]
+    Regex(RegexPattern),
+}
+
+#[cfg(feature = "regex")]
+/// A regex pattern compiled when the spec is parsed
+///
+/// Compilation applies the ReDoS size limits, so unsafe patterns are rejected
+/// as spec errors rather than on first use.
+#[derive(Debug, Clone)]
+pub struct RegexPattern {
+    regex: Regex,
+    pattern: String,
+    flags: String,
+}
+
+#[cfg(feature = "regex")]
+impl RegexPattern {
+    /// Flags accepted by [`RegexPattern::with_flags`]
+    pub const FLAGS: &'static str = "imsx";
+
+    /// Compile a pattern (shared with other specs through the regex cache)
+    pub fn parse(pattern: &str) -> Result<Self, anyhow::Error> {
+        Self::with_flags(pattern, "")
+    }
+
+    /// Compile a pattern with flags: `i` (case-insensitive), `m` (`^` and `$`
+    /// match at line breaks), `s` (`.` matches newlines), `x` (ignore
+    /// whitespace and `#` comments)
+    pub fn with_flags(pattern: &str, flags: &str) -> Result<Self, anyhow::Error> {
+        if let Some(flag) = flags.chars().find(|c| !Self::FLAGS.contains(*c)) {
+            return Err(anyhow::anyhow!(
+                "Unknown regex flag '{}' in 'regex/{}:' (expected some of i, m, s, x)",
+                flag,
+                flags
+            ));
+        }
+        let regex = if flags.is_empty() {
+            crate::pipe::get_cached_regex(pattern)?
+        } else {
+            crate::pipe::get_cached_regex(&format!("(?{}){}", flags, pattern))?
+        };
+        Ok(Self {
+            regex,
+            pattern: pattern.to_string(),
+            flags: flags.to_string(),
+        })
+    }
+
+    /// Get the pattern string, without flags
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Get the flags the pattern was compiled with
+    pub fn flags(&self) -> &str {
+        &self.flags
+    }
+
+    /// Get the compiled regex
+    pub fn regex(&self) -> &Regex {
+        &self.regex
+    }
+}
+
+#[cfg(feature = "regex")]
+impl PartialEq for RegexPattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern == other.pattern && self.flags == other.flags
+    }
+}
+
+impl fmt::Display for PipeCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PipeCommand::Attr(name) => write!(f, "attr:{}", quote(name, &[])),
+            PipeCommand::HasAttr(name) => write!(f, "hasAttr:{}", quote(name, &[])),
+            PipeCommand::Classes => write!(f, "classes"),
+            PipeCommand::HasClass(name) => write!(f, "hasClass:{}", quote(name, &[])),
+            PipeCommand::Style(property, false) => {
+                write!(f, "style:{}", quote(property, &[':']))
+            }
+            PipeCommand::Style(property, true) => {
+                write!(f, "style:{}:url", quote(property, &[':']))
+            }
+            PipeCommand::Void => write!(f, "void"),
+            PipeCommand::Markdown => write!(f, "markdown"),
+            PipeCommand::RawText => write!(f, "rawText"),
+            PipeCommand::Lines => write!(f, "lines"),
+            PipeCommand::Trim => write!(f, "trim"),
+            PipeCommand::Lower => write!(f, "lower"),
+            PipeCommand::Upper => write!(f, "upper"),
+            PipeCommand::Substr(start, Some(end)) => write!(f, "substr:{}:{}", start, end),
+            PipeCommand::Substr(start, None) => write!(f, "substr:{}", start),
+            PipeCommand::Digits => write!(f, "digits"),
+            PipeCommand::ParseAsNumber => write!(f, "parseAs:number"),
+            PipeCommand::ParseAsInt => write!(f, "parseAs:int"),
+            PipeCommand::ParseAsFloat => write!(f, "parseAs:float"),
+            PipeCommand::ParseAsJson => write!(f, "parseAs:json"),
+            PipeCommand::DateTime => write!(f, "datetime"),
+            PipeCommand::Prefix(text) => write!(f, "prefix:{}", text),
+            PipeCommand::AbsoluteUrl(None) => write!(f, "absoluteUrl"),
+            PipeCommand::AbsoluteUrl(Some(base)) => write!(f, "absoluteUrl:{}", base),
+            #[cfg(feature = "regex")]
+            PipeCommand::Regex(pattern) => match pattern.flags() {
+                "" => write!(f, "regex:{}", quote(pattern.as_str(), &[])),
+                "i" => write!(f, "regexi:{}", quote(pattern.as_str(), &[])),
+                flags => write!(f, "regex/{}:{}", flags, quote(pattern.as_str(), &[])),
+            },
+        }
+    }
 }
 
 /// Literal values
@@ -119,64 +714,469 @@ pub enum LiteralValue {
     Null,
 }
 
+impl LiteralValue {
+    /// Convert to the JSON form used in specs (strings are quoted)
+    fn to_spec_json(&self) -> Value {
+        match self {
+            LiteralValue::String(s) => Value::String(format!("'{}'", s)),
+            LiteralValue::Number(n) => Value::from(*n),
+            LiteralValue::Boolean(b) => Value::from(*b),
+            LiteralValue::Null => Value::Null,
+        }
+    }
+}
+
 impl Spec {
+    /// Start building an object spec programmatically
+    pub fn builder() -> crate::builder::SpecBuilder {
+        crate::builder::SpecBuilder::new()
+    }
+
+    /// Convert the spec back into its JSON form
+    pub fn to_json(&self) -> Value {
+        match self {
+            Spec::Object(obj) => obj.to_json(),
+            Spec::Array(arr) => arr.to_json(),
+            Spec::Literal(lit) => lit.to_spec_json(),
+        }
+    }
+
     pub fn from_json(value: &Value) -> Result<Self, anyhow::Error> {
         match value {
             Value::Array(arr) if !arr.is_empty() => {
-                let item_spec = Self::parse_object_spec(&arr[0])?;
-                Ok(Spec::Array(ArraySpec { item_spec }))
+                Ok(Spec::Array(Arc::new(Self::parse_array_spec(&arr[0])?)))
             }
             Value::Object(_) => {
                 let spec = Self::parse_object_spec(value)?;
-                Ok(Spec::Object(spec))
+                spec.check_document_level()?;
+                Ok(Spec::Object(Arc::new(spec)))
             }
-            _ => Ok(Spec::Object(ObjectSpec {
+            _ => Ok(Spec::Object(Arc::new(ObjectSpec {
                 scope_selector: None,
+                scope_fallbacks: Vec::new(),
+                root: false,
                 fields: HashMap::new(),
-            })),
+                consts: HashMap::new(),
+            }))),
+        }
+    }
+
+    /// Constants from the spec's `$consts` section
+    pub fn consts(&self) -> Option<&HashMap<String, String>> {
+        match self {
+            Spec::Object(obj) => Some(&obj.consts),
+            Spec::Array(arr) => Some(&arr.item_spec.consts),
+            Spec::Literal(_) => None,
         }
     }
 
-    fn parse_object_spec(value: &Value) -> Result<ObjectSpec, anyhow::Error> {
+    /// Parse a `$consts` section: names mapped to strings or numbers
+    fn parse_consts(value: &Value) -> Result<HashMap<String, String>, anyhow::Error> {
+        let obj = value
+            .as_object()
+            .ok_or_else(|| anyhow::anyhow!("\"{}\" must be an object", CONSTS_KEY))?;
+        obj.iter()
+            .map(|(name, value)| {
+                let value = match value {
+                    Value::String(s) => s.clone(),
+                    Value::Number(n) => n.to_string(),
+                    _ => {
+                        return Err(anyhow::anyhow!(
+                            "Constant '{}' in \"{}\" must be a string or number",
+                            name,
+                            CONSTS_KEY
+                        ));
+                    }
+                };
+                Ok((name.clone(), value))
+            })
+            .collect()
+    }
+
+    pub(crate) fn parse_object_spec(value: &Value) -> Result<ObjectSpec, anyhow::Error> {
+        let (mut spec, _, _) = Self::parse_item_spec(value, false)?;
+        spec.check_next_rows()?;
+        Ok(spec)
+    }
+
+    /// Parse the item object of a collection (`[{...}]`)
+    fn parse_array_spec(value: &Value) -> Result<ArraySpec, anyhow::Error> {
+        let (item_spec, all, with_next) = Self::parse_item_spec(value, true)?;
+        ArraySpec::new(item_spec, all, with_next)
+    }
+
+    /// Parse an object spec, and for collection items their `$all` flag and
+    /// `$withNext` row count
+    fn parse_item_spec(
+        value: &Value,
+        collection: bool,
+    ) -> Result<(ObjectSpec, bool, usize), anyhow::Error> {
         let obj = value
             .as_object()
             .ok_or_else(|| anyhow::anyhow!("Expected object"))?;
 
         let mut scope_selector = None;
+        let mut scope_fallbacks = Vec::new();
+        let mut all = false;
+        let mut with_next = 0;
+        let mut root = false;
+        let mut consts = HashMap::new();
         let mut fields = HashMap::new();
+        // The key each field was written as, to name both in a duplicate error
+        let mut keys = HashMap::new();
 
         for (key, val) in obj {
             if key == "$" {
                 if let Some(s) = val.as_str() {
-                    scope_selector = Some(SelectorRef(s.to_string()));
+                    let (scope, fallbacks) = ObjectSpec::parse_scope(s)?;
+                    scope_selector = Some(scope);
+                    scope_fallbacks = fallbacks;
                 }
+            } else if key == ALL_KEY {
+                if !collection {
+                    return Err(anyhow::anyhow!(
+                        "\"{}\" only applies to collections ([{{...}}])",
+                        ALL_KEY
+                    ));
+                }
+                all = val
+                    .as_bool()
+                    .ok_or_else(|| anyhow::anyhow!("\"{}\" must be true or false", ALL_KEY))?;
+            } else if key == WITH_NEXT_KEY {
+                if !collection {
+                    return Err(anyhow::anyhow!(
+                        "\"{}\" only applies to collections ([{{...}}])",
+                        WITH_NEXT_KEY
+                    ));
+                }
+                with_next = val
+                    .as_u64()
+                    .filter(|n| *n > 0)
+                    .and_then(|n| usize::try_from(n).ok())
+                    .ok_or_else(|| {
+                        anyhow::anyhow!("\"{}\" must be a positive number of rows", WITH_NEXT_KEY)
+                    })?;
+            } else if key == CONSTS_KEY {
+                consts = Self::parse_consts(val)?;
+            } else if key == ROOT_KEY {
+                root = val
+                    .as_bool()
+                    .ok_or_else(|| anyhow::anyhow!("\"{}\" must be true or false", ROOT_KEY))?;
             } else {
-                // Check if field is optional (ends with ?)
-                let (field_name, optional) = if key.ends_with('?') {
-                    (&key[..key.len() - 1], true)
-                } else {
-                    (key.as_str(), false)
+                // Check if field is optional (ends with ?) and collects every
+                // match (ends with *)
+                let (field_name, optional) = match key.strip_suffix('?') {
+                    Some(name) => (name, true),
+                    None => (key.as_str(), false),
+                };
+                let (field_name, all_matches) = match field_name.strip_suffix(ALL_MATCHES_SUFFIX) {
+                    Some(name) => (name, true),
+                    None => (field_name, false),
                 };
+                if let Some(first) = keys.insert(field_name, key.as_str()) {
+                    return Err(anyhow::anyhow!(
+                        "Duplicate field '{}': '{}' and '{}' are both given",
+                        field_name,
+                        first,
+                        key
+                    ));
+                }
 
-                fields.insert(
-                    field_name.to_string(),
-                    Field {
-                        spec: FieldSpec::from_json(val)?,
-                        optional,
-                    },
-                );
+                let mut spec = FieldSpec::from_json(val)?;
+                if !collection && matches!(spec, FieldSpec::Index | FieldSpec::Rank) {
+                    return Err(anyhow::anyhow!(
+                        "Field '{}': {} and {} only apply to collection items ([{{...}}])",
+                        field_name,
+                        INDEX_KEY,
+                        RANK_KEY
+                    ));
+                }
+                if !collection && matches!(spec, FieldSpec::Recurse(..)) {
+                    return Err(anyhow::anyhow!(
+                        "Field '{}': {} only applies to collection items ([{{...}}]), \
+                         which it extracts again",
+                        field_name,
+                        RECURSE_KEY
+                    ));
+                }
+                if all_matches {
+                    spec = spec.into_all().map_err(|e| {
+                        e.context(format!("Field '{}{}'", field_name, ALL_MATCHES_SUFFIX))
+                    })?;
+                }
+                fields.insert(field_name.to_string(), Field { spec, optional });
             }
         }
 
-        Ok(ObjectSpec {
+        let spec = ObjectSpec {
             scope_selector,
+            scope_fallbacks,
+            root,
             fields,
+            consts,
+        };
+        if root {
+            spec.check_document_level()?;
+        }
+        Ok((spec, all, with_next))
+    }
+}
+
+impl ObjectSpec {
+    /// Parse a `$` value into the scope and its `||` fallbacks
+    pub(crate) fn parse_scope(
+        scope: &str,
+    ) -> Result<(SelectorRef, Vec<SelectorRef>), anyhow::Error> {
+        let mut alternatives = split_top_level(scope, "||")
+            .into_iter()
+            .map(|s| SelectorRef::parse(s.trim()))
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter();
+        let first = alternatives.next().expect("split yields at least one part");
+        Ok((first, alternatives.collect()))
+    }
+
+    /// The scope selector followed by its fallbacks, in the order tried
+    pub fn scopes(&self) -> impl Iterator<Item = &SelectorRef> {
+        self.scope_selector.iter().chain(&self.scope_fallbacks)
+    }
+
+    /// The `$` value, with fallbacks joined by ` || `
+    pub fn scope_str(&self) -> Option<String> {
+        self.scope_selector.as_ref()?;
+        Some(
+            self.scopes()
+                .map(SelectorRef::as_str)
+                .collect::<Vec<_>>()
+                .join(" || "),
+        )
+    }
+
+    /// Check that an object evaluated against the whole document doesn't use
+    /// `+ selector` fields, which start from the scope's next siblings
+    ///
+    /// Nested objects without a `$` share the document scope and are checked
+    /// too. The same layout can be reached with a full sibling selector
+    /// (`tr.athing + tr .score`), which needs no scope.
+    pub(crate) fn check_document_level(&self) -> Result<(), anyhow::Error> {
+        if self.scope_selector.is_some() {
+            return Ok(());
+        }
+        for (name, field) in &self.fields {
+            let selectors: Vec<&SelectorRef> = match &field.spec {
+                FieldSpec::Selector(selector, _) => vec![selector],
+                FieldSpec::FallbackSelector(alternatives) | FieldSpec::All(alternatives) => {
+                    alternatives.iter().map(|(selector, _)| selector).collect()
+                }
+                FieldSpec::Nested(obj) => {
+                    obj.check_document_level()?;
+                    continue;
+                }
+                _ => continue,
+            };
+            if let Some(selector) = selectors.into_iter().find(|s| s.is_next_sibling()) {
+                return Err(anyhow::anyhow!(
+                    "Field '{}': '{}' has no scope to take next siblings of; \
+                     set \"$\" on its object, or write the whole sibling selector \
+                     (e.g. 'tr.athing + tr .score')",
+                    name,
+                    selector.as_str()
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// The `$next` selectors this object hands to its collection item's
+    /// rows: those of its fields, and the scopes of nested objects
+    fn next_row_selectors(&mut self) -> impl Iterator<Item = (&str, &mut SelectorRef)> {
+        self.fields.iter_mut().flat_map(|(name, field)| {
+            let selectors: Vec<&mut SelectorRef> = match &mut field.spec {
+                FieldSpec::Selector(selector, _) => vec![selector],
+                FieldSpec::FallbackSelector(alternatives) | FieldSpec::All(alternatives) => {
+                    alternatives
+                        .iter_mut()
+                        .map(|(selector, _)| selector)
+                        .collect()
+                }
+                FieldSpec::Nested(obj) => obj
+                    .scope_selector
+                    .iter_mut()
+                    .chain(&mut obj.scope_fallbacks)
+                    .collect(),
+                _ => Vec::new(),
+            };
+            selectors
+                .into_iter()
+                .filter(|selector| selector.next_rows.is_some())
+                .map(move |selector| (name.as_str(), selector))
         })
     }
+
+    /// Check that `$next` selectors belong to a collection item with `$withNext`
+    pub(crate) fn check_next_rows(&mut self) -> Result<(), anyhow::Error> {
+        if let Some((name, selector)) = self
+            .next_row_selectors()
+            .find(|(_, selector)| selector.next_rows == Some(0))
+        {
+            return Err(anyhow::anyhow!(
+                "Field '{}': '{}' needs \"{}\" on its collection item, to say how many rows \
+                 after the item's element to search",
+                name,
+                selector.as_str(),
+                WITH_NEXT_KEY
+            ));
+        }
+        Ok(())
+    }
+
+    /// Convert the object spec back into its JSON form
+    pub fn to_json(&self) -> Value {
+        let mut map = serde_json::Map::new();
+        if let Some(scope) = self.scope_str() {
+            map.insert("$".to_string(), Value::String(scope));
+        }
+        if self.root {
+            map.insert(ROOT_KEY.to_string(), Value::Bool(true));
+        }
+        if !self.consts.is_empty() {
+            let consts = self
+                .consts
+                .iter()
+                .map(|(name, value)| (name.clone(), Value::String(value.clone())))
+                .collect();
+            map.insert(CONSTS_KEY.to_string(), Value::Object(consts));
+        }
+        for (name, field) in &self.fields {
+            let key = if field.optional {
+                format!("{}?", name)
+            } else {
+                name.clone()
+            };
+            map.insert(key, field.spec.to_json());
+        }
+        Value::Object(map)
+    }
+}
+
+impl ArraySpec {
+    /// Check that a collection says which elements it iterates over
+    ///
+    /// The item spec needs either a scope selector or `all`, but not both.
+    /// Its `$next` selectors search the `with_next` rows after each item.
+    pub(crate) fn new(
+        mut item_spec: ObjectSpec,
+        all: bool,
+        with_next: usize,
+    ) -> Result<Self, anyhow::Error> {
+        for (_, selector) in item_spec.next_row_selectors() {
+            selector.next_rows = Some(with_next);
+        }
+        item_spec.check_next_rows()?;
+        match (&item_spec.scope_selector, all) {
+            (Some(_), true) => Err(anyhow::anyhow!(
+                "A collection has either \"$\" or \"{}\": true, not both",
+                ALL_KEY
+            )),
+            (None, false) => Err(anyhow::anyhow!(
+                "Collection has no \"$\": set it to the repeating element, or set \"{}\": true \
+                 for an item per element",
+                ALL_KEY
+            )),
+            _ => Ok(Self {
+                item_spec,
+                all,
+                with_next,
+            }),
+        }
+    }
+
+    /// Convert the array spec back into its JSON form
+    pub fn to_json(&self) -> Value {
+        let mut item = self.item_spec.to_json();
+        if self.all
+            && let Value::Object(map) = &mut item
+        {
+            map.insert(ALL_KEY.to_string(), Value::Bool(true));
+        }
+        if self.with_next > 0
+            && let Value::Object(map) = &mut item
+        {
+            map.insert(WITH_NEXT_KEY.to_string(), Value::from(self.with_next));
+        }
+        Value::Array(vec![item])
+    }
 }
 
 impl FieldSpec {
-    fn from_json(value: &Value) -> Result<Self, anyhow::Error> {
+    /// Convert the field spec back into its JSON form
+    pub fn to_json(&self) -> Value {
+        match self {
+            FieldSpec::Selector(selector, pipes) => {
+                Value::String(Self::selector_to_string(selector, pipes))
+            }
+            FieldSpec::FallbackSelector(selectors) => {
+                Value::String(Self::alternatives_to_string(selectors))
+            }
+            FieldSpec::All(selectors) => {
+                Value::Array(vec![Value::String(Self::alternatives_to_string(selectors))])
+            }
+            FieldSpec::Nested(obj) => obj.to_json(),
+            FieldSpec::NestedArray(arr) => arr.to_json(),
+            FieldSpec::Literal(lit) => lit.to_spec_json(),
+            FieldSpec::JsonLd(None) => Value::String("$jsonld".to_string()),
+            FieldSpec::JsonLd(Some(ty)) => Value::String(format!("$jsonld:{}", ty)),
+            FieldSpec::Microdata(None) => Value::String("$microdata".to_string()),
+            FieldSpec::Microdata(Some(ty)) => Value::String(format!("$microdata:{}", ty)),
+            FieldSpec::Article(None) => Value::String("$article".to_string()),
+            FieldSpec::Article(Some(field)) => Value::String(format!("$article:{}", field)),
+            FieldSpec::Images => Value::String(IMAGES_KEY.to_string()),
+            FieldSpec::Outline => Value::String(OUTLINE_KEY.to_string()),
+            FieldSpec::Breadcrumbs => Value::String(BREADCRUMBS_KEY.to_string()),
+            FieldSpec::Pagination => Value::String(PAGINATION_KEY.to_string()),
+            FieldSpec::Form(None) => Value::String(FORM_KEY.to_string()),
+            FieldSpec::Index => Value::String(INDEX_KEY.to_string()),
+            FieldSpec::Recurse(within, depth) => {
+                let mut map = serde_json::Map::new();
+                if let Some(selector) = within {
+                    map.insert(
+                        "$".to_string(),
+                        Value::String(selector.as_str().to_string()),
+                    );
+                }
+                map.insert(RECURSE_KEY.to_string(), Value::from(*depth));
+                Value::Object(map)
+            }
+            FieldSpec::Rank => Value::String(RANK_KEY.to_string()),
+            FieldSpec::Now(format) => serde_json::json!({ NOW_KEY: format.as_str() }),
+            FieldSpec::Env(name) => serde_json::json!({ ENV_KEY: name }),
+            FieldSpec::Context(key) => serde_json::json!({ CONTEXT_KEY: key }),
+            FieldSpec::Form(Some(selector)) => {
+                Value::String(format!("{}:{}", FORM_KEY, selector.as_str()))
+            }
+            FieldSpec::Links(None) => Value::String(LINKS_KEY.to_string()),
+            FieldSpec::Links(Some(filter)) => {
+                Value::String(format!("{}:{}", LINKS_KEY, filter.as_str()))
+            }
+        }
+    }
+
+    fn alternatives_to_string(selectors: &[(SelectorRef, Vec<PipeCommand>)]) -> String {
+        selectors
+            .iter()
+            .map(|(selector, pipes)| Self::selector_to_string(selector, pipes))
+            .collect::<Vec<_>>()
+            .join(" || ")
+    }
+
+    fn selector_to_string(selector: &SelectorRef, pipes: &[PipeCommand]) -> String {
+        std::iter::once(selector.as_str().to_string())
+            .chain(pipes.iter().map(|p| p.to_string()))
+            .collect::<Vec<_>>()
+            .join(" | ")
+    }
+
+    pub(crate) fn from_json(value: &Value) -> Result<Self, anyhow::Error> {
         match value {
             Value::String(s) => {
                 if let Some(literal) = Self::parse_literal_string(s) {
@@ -190,18 +1190,117 @@ impl FieldSpec {
             }
             Value::Bool(b) => Ok(FieldSpec::Literal(LiteralValue::Boolean(*b))),
             Value::Null => Ok(FieldSpec::Literal(LiteralValue::Null)),
+            Value::Array(arr) if arr.first().is_some_and(Value::is_string) => {
+                Self::from_json(&arr[0])?.into_all()
+            }
             Value::Array(arr) if !arr.is_empty() => {
-                let item_spec = Spec::parse_object_spec(&arr[0])?;
-                Ok(FieldSpec::NestedArray(ArraySpec { item_spec }))
+                let spec = Spec::parse_array_spec(&arr[0])?;
+                Self::check_no_consts(&spec.item_spec)?;
+                Ok(FieldSpec::NestedArray(spec))
+            }
+            Value::Object(obj) if obj.contains_key(RECURSE_KEY) => Self::parse_recurse(obj),
+            Value::Object(obj)
+                if [NOW_KEY, ENV_KEY, CONTEXT_KEY]
+                    .iter()
+                    .any(|key| obj.contains_key(*key)) =>
+            {
+                Self::parse_generator(obj)
             }
             Value::Object(_) => {
                 let spec = Spec::parse_object_spec(value)?;
+                Self::check_no_consts(&spec)?;
                 Ok(FieldSpec::Nested(spec))
             }
             Value::Array(_) => Ok(FieldSpec::Literal(LiteralValue::Null)),
         }
     }
 
+    /// Constants are shared by the whole spec, so only the top level has them
+    pub(crate) fn check_no_consts(spec: &ObjectSpec) -> Result<(), anyhow::Error> {
+        if spec.consts.is_empty() {
+            return Ok(());
+        }
+        Err(anyhow::anyhow!(
+            "\"{}\" belongs at the top level of the spec",
+            CONSTS_KEY
+        ))
+    }
+
+    /// Parse `{"$recurse": DEPTH}`, optionally with a `"$"` to descend into
+    fn parse_recurse(obj: &serde_json::Map<String, Value>) -> Result<Self, anyhow::Error> {
+        let depth = obj[RECURSE_KEY]
+            .as_u64()
+            .filter(|n| *n > 0)
+            .and_then(|n| usize::try_from(n).ok())
+            .ok_or_else(|| {
+                anyhow::anyhow!("\"{}\" must be a positive maximum depth", RECURSE_KEY)
+            })?;
+        let mut within = None;
+        for (key, val) in obj {
+            match (key.as_str(), val) {
+                (RECURSE_KEY, _) => {}
+                ("$", Value::String(selector)) => {
+                    within = Some(SelectorRef::parse(selector.trim())?)
+                }
+                _ => {
+                    return Err(anyhow::anyhow!(
+                        "A {} object takes only \"$\", not '{}'",
+                        RECURSE_KEY,
+                        key
+                    ));
+                }
+            }
+        }
+        Ok(FieldSpec::Recurse(within, depth))
+    }
+
+    /// Parse `{"$now": FORMAT}`, `{"$env": NAME}` or `{"$context": KEY}`
+    fn parse_generator(obj: &serde_json::Map<String, Value>) -> Result<Self, anyhow::Error> {
+        let mut entries = obj.iter();
+        let (Some((key, value)), None) = (entries.next(), entries.next()) else {
+            return Err(anyhow::anyhow!(
+                "{}, {} and {} objects take no other keys",
+                NOW_KEY,
+                ENV_KEY,
+                CONTEXT_KEY
+            ));
+        };
+        let value = value.as_str().map(str::trim);
+        match (key.as_str(), value) {
+            (NOW_KEY, Some("iso")) => Ok(FieldSpec::Now(NowFormat::Iso)),
+            (NOW_KEY, Some("date")) => Ok(FieldSpec::Now(NowFormat::Date)),
+            (NOW_KEY, Some("unix")) => Ok(FieldSpec::Now(NowFormat::Unix)),
+            (NOW_KEY, _) => Err(anyhow::anyhow!(
+                "Unknown {} format {} (expected \"iso\", \"date\" or \"unix\")",
+                NOW_KEY,
+                obj[NOW_KEY]
+            )),
+            (ENV_KEY, Some(name)) if !name.is_empty() => Ok(FieldSpec::Env(name.to_string())),
+            (ENV_KEY, _) => Err(anyhow::anyhow!(
+                "{} takes the name of an environment variable",
+                ENV_KEY
+            )),
+            (_, Some(key)) if !key.is_empty() => Ok(FieldSpec::Context(key.to_string())),
+            _ => Err(anyhow::anyhow!(
+                "{} takes the name of a context value, such as \"url\"",
+                CONTEXT_KEY
+            )),
+        }
+    }
+
+    /// Turn a selector field into one collecting every match
+    pub(crate) fn into_all(self) -> Result<Self, anyhow::Error> {
+        match self {
+            FieldSpec::Selector(selector, pipes) => Ok(FieldSpec::All(vec![(selector, pipes)])),
+            FieldSpec::FallbackSelector(selectors) => Ok(FieldSpec::All(selectors)),
+            FieldSpec::All(selectors) => Ok(FieldSpec::All(selectors)),
+            other => Err(anyhow::anyhow!(
+                "Every-match fields take a selector, not {}",
+                other.to_json()
+            )),
+        }
+    }
+
     /// Check if a string is a literal (single or double quoted)
     fn parse_literal_string(s: &str) -> Option<LiteralValue> {
         let trimmed = s.trim();
@@ -223,13 +1322,17 @@ impl FieldSpec {
     /// - "selector | pipe1 | pipe2" -> ("selector", [pipe1, pipe2])
     /// - "$ | pipe1" -> ("$", [pipe1])
     /// - "attr:name" -> ("$", [Attr("name")])  (implicit self-selector)
+    /// - "p | regex:'a|b'" -> ("p", [Regex("a|b")])  (quoted arguments)
     fn parse_selector_string(s: &str) -> Result<(String, Vec<PipeCommand>), anyhow::Error> {
         let trimmed = s.trim();
         if trimmed == "$" {
             return Ok(("$".to_string(), Vec::new()));
         }
 
-        let parts: Vec<&str> = trimmed.split('|').map(|p| p.trim()).collect();
+        let parts: Vec<&str> = split_top_level(trimmed, "|")
+            .into_iter()
+            .map(|p| p.trim())
+            .collect();
 
         let (selector, pipe_start) = if parts[0].starts_with("attr:") {
             ("$".to_string(), 0)
@@ -246,6 +1349,17 @@ impl FieldSpec {
                 pipes.push(Self::parse_pipe_command(part)?);
             }
         }
+        // It checks the element, which later pipes no longer have
+        if let Some(PipeCommand::HasAttr(name)) = pipes
+            .iter()
+            .skip(1)
+            .find(|pipe| matches!(pipe, PipeCommand::HasAttr(_)))
+        {
+            return Err(anyhow::anyhow!(
+                "hasAttr:{} reads the element, so it must be the first pipe",
+                name
+            ));
+        }
 
         Ok((selector, pipes))
     }
@@ -257,30 +1371,105 @@ impl FieldSpec {
             "lower" => return Ok(PipeCommand::Lower),
             "upper" => return Ok(PipeCommand::Upper),
             "void" => return Ok(PipeCommand::Void),
+            "markdown" => return Ok(PipeCommand::Markdown),
+            "rawText" => return Ok(PipeCommand::RawText),
+            "lines" => return Ok(PipeCommand::Lines),
+            "classes" => return Ok(PipeCommand::Classes),
+            "datetime" => return Ok(PipeCommand::DateTime),
+            "digits" => return Ok(PipeCommand::Digits),
+            #[cfg(feature = "url")]
+            "absoluteUrl" => return Ok(PipeCommand::AbsoluteUrl(None)),
             "parseAs:number" => return Ok(PipeCommand::ParseAsNumber),
             "parseAs:int" => return Ok(PipeCommand::ParseAsInt),
             "parseAs:float" => return Ok(PipeCommand::ParseAsFloat),
+            "parseAs:json" => return Ok(PipeCommand::ParseAsJson),
             _ => {}
         }
 
-        // Commands with arguments (using prefix-based dispatch)
+        // Commands with arguments (using prefix-based dispatch); arguments
+        // may be quoted to hold `|` or `:`
         if let Some(rest) = s.strip_prefix("attr:") {
-            return Ok(PipeCommand::Attr(rest.to_string()));
+            return Ok(PipeCommand::Attr(unquote(rest).into_owned()));
+        }
+
+        if let Some(rest) = s.strip_prefix("hasAttr:") {
+            return Ok(PipeCommand::HasAttr(unquote(rest).into_owned()));
+        }
+
+        if let Some(rest) = s.strip_prefix("hasClass:") {
+            return Ok(PipeCommand::HasClass(unquote(rest).into_owned()));
+        }
+
+        if let Some(rest) = s.strip_prefix("style:") {
+            let (property, url) = match split_top_level(rest, ":")[..] {
+                [property] => (property, false),
+                [property, "url"] => (property, true),
+                _ => return Err(anyhow::anyhow!("Invalid style:{}", rest)),
+            };
+            let property = unquote(property.trim());
+            if property.trim().is_empty() {
+                return Err(anyhow::anyhow!("Missing property in style:{}", rest));
+            }
+            return Ok(PipeCommand::Style(
+                property.trim().to_ascii_lowercase(),
+                url,
+            ));
+        }
+
+        if let Some(rest) = s.strip_prefix("prefix:") {
+            return Ok(PipeCommand::Prefix(PipeArg::parse(rest)?));
+        }
+
+        #[cfg(feature = "url")]
+        if let Some(rest) = s.strip_prefix("absoluteUrl:") {
+            let base = PipeArg::parse(rest)?;
+            if let PipeArg::Text(url) = &base {
+                url::Url::parse(url).map_err(|e| {
+                    anyhow::anyhow!("Invalid base URL in absoluteUrl:{}: {}", rest, e)
+                })?;
+            }
+            return Ok(PipeCommand::AbsoluteUrl(Some(base)));
+        }
+        #[cfg(not(feature = "url"))]
+        if s == "absoluteUrl" || s.starts_with("absoluteUrl:") {
+            return Err(crate::pipe::url_disabled(s));
         }
 
         if let Some(rest) = s.strip_prefix("substr:") {
             return Self::parse_substr_command(rest);
         }
 
-        if let Some(pattern) = s.strip_prefix("regex:") {
-            return Ok(PipeCommand::Regex(pattern.to_string()));
+        // `regex:PATTERN`, `regexi:PATTERN` or `regex/FLAGS:PATTERN`
+        let regex = match s.strip_prefix("regex") {
+            Some(rest) if rest.starts_with(':') => Some(("", &rest[1..])),
+            Some(rest) if rest.starts_with("i:") => Some(("i", &rest[2..])),
+            Some(rest) => rest.strip_prefix('/').and_then(|rest| rest.split_once(':')),
+            None => None,
+        };
+        if let Some((flags, pattern)) = regex {
+            #[cfg(feature = "regex")]
+            return Ok(PipeCommand::Regex(RegexPattern::with_flags(
+                &unquote(pattern),
+                flags,
+            )?));
+            #[cfg(not(feature = "regex"))]
+            {
+                let _ = (pattern, flags);
+                return Err(anyhow::anyhow!(
+                    "Regex support disabled: '{}' needs html2json's `regex` feature",
+                    s
+                ));
+            }
         }
 
         Err(anyhow::anyhow!("Unknown pipe command: {}", s))
     }
 
     fn parse_substr_command(rest: &str) -> Result<PipeCommand, anyhow::Error> {
-        let parts: Vec<&str> = rest.split(':').collect();
+        let parts: Vec<Cow<str>> = split_top_level(rest, ":")
+            .into_iter()
+            .map(|p| unquote(p.trim()))
+            .collect();
         let start: usize = parts[0]
             .parse()
             .map_err(|_| anyhow::anyhow!("Invalid substr start: {}", parts[0]))?;
@@ -298,25 +1487,127 @@ impl FieldSpec {
         Ok(PipeCommand::Substr(start, end))
     }
 
+    /// Parse `$jsonld[:TYPE]`, `$microdata[:TYPE]`, `$article[:FIELD]`,
+    /// `$images`, `$links[:FILTER]`, `$outline`, `$breadcrumbs`, `$pagination`,
+    /// `$form[:SELECTOR]`, `$index` and `$rank`
+    fn parse_structured_data(s: &str) -> Result<Option<Self>, anyhow::Error> {
+        if let Some(field) = Self::parse_url_construct(s)? {
+            #[cfg(not(feature = "url"))]
+            return Err(crate::pipe::url_disabled(
+                field.to_json().as_str().unwrap_or(s),
+            ));
+            #[cfg(feature = "url")]
+            return Ok(Some(field));
+        }
+        match s {
+            OUTLINE_KEY => return Ok(Some(FieldSpec::Outline)),
+            INDEX_KEY => return Ok(Some(FieldSpec::Index)),
+            RANK_KEY => return Ok(Some(FieldSpec::Rank)),
+            _ => {}
+        }
+        type Construct = fn(Option<String>) -> FieldSpec;
+        let constructs: [(&str, Construct); 3] = [
+            ("$jsonld", FieldSpec::JsonLd),
+            ("$microdata", FieldSpec::Microdata),
+            ("$article", FieldSpec::Article),
+        ];
+        for (name, construct) in constructs {
+            let Some(rest) = s.strip_prefix(name) else {
+                continue;
+            };
+            if name == "$article" && (rest.is_empty() || rest.starts_with(':')) {
+                Self::check_article_field(rest.strip_prefix(':'))?;
+            }
+            if rest.is_empty() {
+                return Ok(Some(construct(None)));
+            }
+            if let Some(ty) = rest.strip_prefix(':') {
+                if ty.trim().is_empty() {
+                    return Err(anyhow::anyhow!("Missing type after {}:", name));
+                }
+                return Ok(Some(construct(Some(ty.trim().to_string()))));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Parse the constructs that resolve URLs: `$images`, `$links[:FILTER]`,
+    /// `$breadcrumbs`, `$pagination` and `$form[:SELECTOR]`
+    fn parse_url_construct(s: &str) -> Result<Option<Self>, anyhow::Error> {
+        match s {
+            IMAGES_KEY => return Ok(Some(FieldSpec::Images)),
+            BREADCRUMBS_KEY => return Ok(Some(FieldSpec::Breadcrumbs)),
+            PAGINATION_KEY => return Ok(Some(FieldSpec::Pagination)),
+            FORM_KEY => return Ok(Some(FieldSpec::Form(None))),
+            _ => {}
+        }
+        if let Some(selector) = s.strip_prefix(FORM_KEY).and_then(|s| s.strip_prefix(':')) {
+            let selector = SelectorRef::parse(selector.trim())
+                .map_err(|e| e.context(format!("Invalid {} selector", FORM_KEY)))?;
+            return Ok(Some(FieldSpec::Form(Some(selector))));
+        }
+        if let Some(rest) = s.strip_prefix(LINKS_KEY) {
+            let filter = match rest.strip_prefix(':').map(str::trim) {
+                None if rest.is_empty() => None,
+                Some("internal") => Some(LinkFilter::Internal),
+                Some("external") => Some(LinkFilter::External),
+                Some(filter) => {
+                    return Err(anyhow::anyhow!(
+                        "Unknown {} filter '{}' (expected internal or external)",
+                        LINKS_KEY,
+                        filter
+                    ));
+                }
+                None => return Ok(None),
+            };
+            return Ok(Some(FieldSpec::Links(filter)));
+        }
+        Ok(None)
+    }
+
+    #[cfg(feature = "article")]
+    fn check_article_field(field: Option<&str>) -> Result<(), anyhow::Error> {
+        match field.map(str::trim) {
+            Some(field) if !field.is_empty() && !crate::article::FIELDS.contains(&field) => {
+                Err(anyhow::anyhow!(
+                    "Unknown $article field '{}' (expected one of: {})",
+                    field,
+                    crate::article::FIELDS.join(", ")
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    #[cfg(not(feature = "article"))]
+    fn check_article_field(_: Option<&str>) -> Result<(), anyhow::Error> {
+        Err(anyhow::anyhow!(
+            "$article needs html2json to be built with the `article` feature"
+        ))
+    }
+
     /// Parse a selector string, handling fallback selectors with ||
     ///
     /// - "selector" -> Selector
     /// - "selector || fallback" -> FallbackSelector with two options
     /// - "sel1 || sel2 || sel3" -> FallbackSelector with three options
-    fn parse_selector_or_fallback(s: &str) -> Result<Self, anyhow::Error> {
+    pub(crate) fn parse_selector_or_fallback(s: &str) -> Result<Self, anyhow::Error> {
         let trimmed = s.trim();
 
-        // Check for || operator (fallback)
-        if trimmed.contains("||") {
-            let parts: Vec<&str> = trimmed.split("||").map(|p| p.trim()).collect();
-            if parts.len() < 2 {
-                return Err(anyhow::anyhow!("Invalid fallback selector"));
-            }
+        if let Some(spec) = Self::parse_structured_data(trimmed)? {
+            return Ok(spec);
+        }
 
+        // Check for || operator (fallback)
+        let parts: Vec<&str> = split_top_level(trimmed, "||")
+            .into_iter()
+            .map(|p| p.trim())
+            .collect();
+        if parts.len() > 1 {
             let mut selectors = Vec::new();
             for part in parts {
                 let (selector, pipes) = Self::parse_selector_string(part)?;
-                selectors.push((SelectorRef(selector), pipes));
+                selectors.push((SelectorRef::parse(selector)?, pipes));
             }
 
             return Ok(FieldSpec::FallbackSelector(selectors));
@@ -324,6 +1615,6 @@ impl FieldSpec {
 
         // Single selector
         let (selector, pipes) = Self::parse_selector_string(trimmed)?;
-        Ok(FieldSpec::Selector(SelectorRef(selector), pipes))
+        Ok(FieldSpec::Selector(SelectorRef::parse(selector)?, pipes))
     }
 }
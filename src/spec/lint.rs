@@ -0,0 +1,249 @@
+//! Best-practice checks for specs
+//!
+//! A spec can be valid and still fragile: it may break on the next redesign,
+//! or do something other than what it seems to say. [`lint`] flags those
+//! patterns, each with the path of the field and a suggested fix.
+
+use super::{
+    ALL_KEY, ALL_MATCHES_SUFFIX, CONSTS_KEY, CONTEXT_KEY, ENV_KEY, FieldSpec, NOW_KEY, PipeCommand,
+    RECURSE_KEY, ROOT_KEY, SelectorRef, Spec, WITH_NEXT_KEY,
+};
+use serde_json::Value;
+use std::fmt;
+
+/// Positional pseudo-classes; more than one in a selector pins it to layout
+const POSITIONAL: [&str; 4] = [
+    ":nth-child(",
+    ":nth-of-type(",
+    ":nth-last-child(",
+    ":nth-last-of-type(",
+];
+
+/// Child combinators after a leading `html`/`body` at which a selector counts
+/// as an absolute path
+const ROOTED_CHAIN_DEPTH: usize = 3;
+
+/// The check that raised a [`LintWarning`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Lint {
+    /// Chains of `:nth-child` or child combinators from the document root
+    PositionalSelector,
+    /// Scopes matching any element (`*`)
+    UniversalScope,
+    /// `regex:` patterns without a capture group
+    RegexWithoutGroup,
+    /// `?` on a literal, which is never null
+    OptionalLiteral,
+    /// Fallback alternatives repeating an earlier one
+    UnreachableFallback,
+}
+
+impl Lint {
+    /// Kebab-case name of the check, e.g. `positional-selector`
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::PositionalSelector => "positional-selector",
+            Self::UniversalScope => "universal-scope",
+            Self::RegexWithoutGroup => "regex-without-group",
+            Self::OptionalLiteral => "optional-literal",
+            Self::UnreachableFallback => "unreachable-fallback",
+        }
+    }
+}
+
+/// A fragile pattern found by [`lint`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintWarning {
+    /// Dotted path of the field (`items.price`), or of a scope (`items.$`)
+    pub path: String,
+    /// Which check raised the warning
+    pub lint: Lint,
+    /// What is wrong
+    pub message: String,
+    /// How to fix it
+    pub suggestion: String,
+}
+
+impl fmt::Display for LintWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} [{}]", self.path, self.message, self.lint.name())
+    }
+}
+
+/// Check a spec's JSON form for fragile patterns
+///
+/// Fails if the spec doesn't parse. Warnings are sorted by path.
+///
+/// # Example
+///
+/// ```
+/// use html2json::spec::{lint, Lint};
+///
+/// let spec = serde_json::json!({"title": "h1", "version?": 2});
+/// let warnings = lint(&spec)?;
+/// assert_eq!(warnings[0].lint, Lint::OptionalLiteral);
+/// assert_eq!(warnings[0].path, "version");
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn lint(spec: &Value) -> Result<Vec<LintWarning>, anyhow::Error> {
+    Spec::from_json(spec)?;
+    let mut linter = Linter::default();
+    match spec {
+        Value::Array(items) if !items.is_empty() => linter.object(&items[0], "")?,
+        Value::Object(_) => linter.object(spec, "")?,
+        _ => {}
+    }
+    let mut warnings = linter.warnings;
+    warnings.sort_by(|a, b| (&a.path, a.lint).cmp(&(&b.path, b.lint)));
+    Ok(warnings)
+}
+
+#[derive(Default)]
+struct Linter {
+    warnings: Vec<LintWarning>,
+}
+
+impl Linter {
+    fn warn(&mut self, path: String, lint: Lint, message: String, suggestion: impl Into<String>) {
+        self.warnings.push(LintWarning {
+            path,
+            lint,
+            message,
+            suggestion: suggestion.into(),
+        });
+    }
+
+    fn object(&mut self, value: &Value, path: &str) -> Result<(), anyhow::Error> {
+        let Some(obj) = value.as_object() else {
+            return Ok(());
+        };
+        let scope_path = join(path, "$");
+        if let Some(scope) = obj.get("$").and_then(Value::as_str) {
+            if is_universal(scope) {
+                self.warn(
+                    scope_path.clone(),
+                    Lint::UniversalScope,
+                    format!("scope '{}' matches any element", scope),
+                    "Name the repeating element, e.g. \"$\": \"li.result\"",
+                );
+            }
+            self.positional(&scope_path, scope);
+        }
+
+        for (key, val) in obj {
+            if key == "$"
+                || key == ALL_KEY
+                || key == ROOT_KEY
+                || key == WITH_NEXT_KEY
+                || key == RECURSE_KEY
+                || key == CONSTS_KEY
+                || key == NOW_KEY
+                || key == ENV_KEY
+                || key == CONTEXT_KEY
+            {
+                continue;
+            }
+            let (name, optional) = match key.strip_suffix('?') {
+                Some(name) => (name, true),
+                None => (key.as_str(), false),
+            };
+            let name = name.strip_suffix(ALL_MATCHES_SUFFIX).unwrap_or(name);
+            let field_path = join(path, name);
+            match val {
+                Value::Array(items) if items.first().is_some_and(Value::is_object) => {
+                    self.object(&items[0], &field_path)?
+                }
+                Value::Object(_) => self.object(val, &field_path)?,
+                _ => self.field(&field_path, &FieldSpec::from_json(val)?, optional),
+            }
+        }
+        Ok(())
+    }
+
+    fn field(&mut self, path: &str, spec: &FieldSpec, optional: bool) {
+        match spec {
+            FieldSpec::Literal(_) if optional => self.warn(
+                path.to_string(),
+                Lint::OptionalLiteral,
+                "'?' on a literal has no effect, or always drops a null".to_string(),
+                "Remove the '?'",
+            ),
+            FieldSpec::Selector(selector, pipes) => self.selector(path, selector, pipes),
+            FieldSpec::All(alternatives) => {
+                for (selector, pipes) in alternatives {
+                    self.selector(path, selector, pipes);
+                }
+            }
+            FieldSpec::FallbackSelector(alternatives) => {
+                for (i, (selector, pipes)) in alternatives.iter().enumerate() {
+                    self.selector(path, selector, pipes);
+                    if alternatives[..i]
+                        .iter()
+                        .any(|(s, p)| s == selector && p == pipes)
+                    {
+                        let alternative = FieldSpec::selector_to_string(selector, pipes);
+                        self.warn(
+                            path.to_string(),
+                            Lint::UnreachableFallback,
+                            format!("fallback '{}' repeats an earlier alternative", alternative),
+                            "Remove the repeated alternative, or change its selector or pipes",
+                        );
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn selector(&mut self, path: &str, selector: &SelectorRef, pipes: &[PipeCommand]) {
+        self.positional(path, selector.as_str());
+        #[cfg(feature = "regex")]
+        for pipe in pipes {
+            if let PipeCommand::Regex(pattern) = pipe
+                && pattern.regex().captures_len() == 1
+            {
+                self.warn(
+                    path.to_string(),
+                    Lint::RegexWithoutGroup,
+                    format!(
+                        "regex '{}' has no capture group, so the whole match is kept",
+                        pattern.as_str()
+                    ),
+                    "Wrap the part to keep in parentheses, e.g. regex:Price: (\\d+)",
+                );
+            }
+        }
+        #[cfg(not(feature = "regex"))]
+        let _ = pipes;
+    }
+
+    fn positional(&mut self, path: &str, selector: &str) {
+        let positions: usize = POSITIONAL.iter().map(|p| selector.matches(p).count()).sum();
+        let first = selector.split_whitespace().next().unwrap_or_default();
+        let rooted = matches!(first, "html" | "body" | ":root")
+            && selector.matches('>').count() >= ROOTED_CHAIN_DEPTH;
+        if positions >= 2 || rooted {
+            self.warn(
+                path.to_string(),
+                Lint::PositionalSelector,
+                format!("selector '{}' depends on the page layout", selector),
+                "Select by a class, id or attribute of the element or a close ancestor",
+            );
+        }
+    }
+}
+
+/// Whether a scope selector's subject is `*`
+fn is_universal(selector: &str) -> bool {
+    selector
+        .split(',')
+        .any(|part| part.trim().rsplit([' ', '>', '+', '~']).next() == Some("*"))
+}
+
+fn join(path: &str, name: &str) -> String {
+    if path.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", path, name)
+    }
+}
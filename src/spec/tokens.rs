@@ -0,0 +1,104 @@
+//! Quote- and bracket-aware splitting of selector strings and pipe arguments
+//!
+//! A `'` or `"` opens a quoted section when it starts a token or follows
+//! punctuation or whitespace, so `regex:'a|b'` and `a[title="x|y"]` keep
+//! their `|`, while the apostrophe in `it's` stays literal. A quote that is
+//! never closed is literal too. Inside a quoted argument the quote character
+//! is escaped by doubling it (`'it''s'`).
+//!
+//! Separators inside `[...]` and `(...)` don't split either, which covers
+//! `[lang|=en]` and regex groups like `(kg|lb)`, and a backslash escapes the
+//! character after it (`regex:a\|b`). The backslash is kept, as both CSS and
+//! regex read `\|` as a literal `|`. If the brackets don't balance, they are
+//! ignored.
+
+use std::borrow::Cow;
+
+/// Whether the quote at byte `i` of `s` opens a quoted section
+fn opens_quote(s: &str, i: usize) -> bool {
+    s[..i]
+        .chars()
+        .next_back()
+        .is_none_or(|prev| !prev.is_alphanumeric())
+}
+
+/// Split `s` at each `sep` outside quoted sections, brackets and escapes
+pub(super) fn split_top_level<'a>(s: &'a str, sep: &str) -> Vec<&'a str> {
+    split(s, sep, true).unwrap_or_else(|| split(s, sep, false).expect("no nesting to unbalance"))
+}
+
+/// Split, tracking bracket depth if `nest` is set (`None` if unbalanced)
+fn split<'a>(s: &'a str, sep: &str, nest: bool) -> Option<Vec<&'a str>> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut depth = 0usize;
+    let mut i = 0;
+    while i < s.len() {
+        let c = s[i..].chars().next().expect("i is a char boundary");
+        match c {
+            '\\' => {
+                i += 1 + s[i + 1..].chars().next().map_or(0, char::len_utf8);
+                continue;
+            }
+            '\'' | '"' if opens_quote(s, i) => {
+                if let Some(len) = s[i + 1..].find(c) {
+                    i += len + 2;
+                    continue;
+                }
+            }
+            '[' | '(' if nest => depth += 1,
+            ']' | ')' if nest => depth = depth.checked_sub(1)?,
+            _ if depth == 0 && s[i..].starts_with(sep) => {
+                parts.push(&s[start..i]);
+                i += sep.len();
+                start = i;
+                continue;
+            }
+            _ => {}
+        }
+        i += c.len_utf8();
+    }
+    if depth > 0 {
+        return None;
+    }
+    parts.push(&s[start..]);
+    Some(parts)
+}
+
+/// Strip the quotes from a fully quoted argument, undoubling inner quotes
+pub(super) fn unquote(arg: &str) -> Cow<'_, str> {
+    let Some(quote) = arg.chars().next().filter(|c| *c == '\'' || *c == '"') else {
+        return Cow::Borrowed(arg);
+    };
+    let mut inner = String::new();
+    let mut chars = arg[1..].chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != quote {
+            inner.push(c);
+        } else if chars.peek() == Some(&quote) {
+            inner.push(chars.next().expect("peeked"));
+        } else if chars.peek().is_none() {
+            return Cow::Owned(inner);
+        } else {
+            break;
+        }
+    }
+    // Not a single quoted section, so the quotes are part of the argument
+    Cow::Borrowed(arg)
+}
+
+/// Quote an argument if it would not survive [`split_top_level`] on `|` and
+/// `seps`, then [`unquote`], as written
+pub(super) fn quote<'a>(arg: &'a str, seps: &[char]) -> Cow<'a, str> {
+    let needs_quotes = arg.contains('|')
+        || arg.contains(seps)
+        || arg != arg.trim()
+        || arg
+            .char_indices()
+            .any(|(i, c)| (c == '\'' || c == '"') && opens_quote(arg, i));
+    if needs_quotes {
+        Cow::Owned(format!("'{}'", arg.replace('\'', "''")))
+    } else {
+        Cow::Borrowed(arg)
+    }
+}
@@ -0,0 +1,217 @@
+//! Spec file formats and the `spec` subcommand
+//!
+//! Specs are JSON, but can also be written as JSON5, YAML, or a JavaScript
+//! module exporting a cheerio-json-mapper object, as found in the JS
+//! codebases html2json is ported from. The format is picked by extension.
+
+use anyhow::Result;
+use html2json::Spec;
+use serde_json::Value;
+
+/// Spec file format
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum SpecFormat {
+    Json,
+    Json5,
+    Yaml,
+    /// JavaScript module with a cheerio-json-mapper object (`export default {...}`)
+    Cheerio,
+}
+
+impl SpecFormat {
+    /// Format of a spec file, by extension
+    pub fn from_path(path: &str) -> Option<Self> {
+        let extension = std::path::Path::new(path).extension()?.to_str()?;
+        match extension.to_ascii_lowercase().as_str() {
+            "json" => Some(Self::Json),
+            "json5" => Some(Self::Json5),
+            "yaml" | "yml" => Some(Self::Yaml),
+            "js" | "mjs" | "cjs" | "ts" => Some(Self::Cheerio),
+            _ => None,
+        }
+    }
+
+    /// Parse spec file content into its JSON form
+    pub fn parse(self, content: &str) -> Result<Value> {
+        match self {
+            Self::Json => html2json::spec::parse_json(content)
+                .map_err(|e| anyhow::anyhow!("Failed to parse spec JSON: {}", e)),
+            Self::Json5 => parse_json5(content)
+                .map_err(|e| anyhow::anyhow!("Failed to parse spec JSON5: {}", e)),
+            Self::Yaml => {
+                html2json::spec::deserialize_unique(serde_yaml_ng::Deserializer::from_str(content))
+                    .map_err(|e| anyhow::anyhow!("Failed to parse spec YAML: {}", e))
+            }
+            Self::Cheerio => {
+                // The object literal, without `export default`/`module.exports =`
+                // before it or `;`/`as const` after it
+                let start = content.find(['{', '[']);
+                let end = content.rfind(['}', ']']);
+                let literal = match (start, end) {
+                    (Some(start), Some(end)) if start < end => &content[start..=end],
+                    _ => return Err(anyhow::anyhow!("No spec object in JavaScript module")),
+                };
+                parse_json5(literal)
+                    .map_err(|e| anyhow::anyhow!("Failed to parse spec object: {}", e))
+            }
+        }
+    }
+
+    /// Write a spec's JSON form in this format
+    pub fn render(self, spec: &Value) -> Result<String> {
+        Ok(match self {
+            Self::Json => serde_json::to_string_pretty(spec)? + "\n",
+            Self::Json5 => {
+                let mut out = String::new();
+                write_literal(spec, 0, &mut out);
+                out + "\n"
+            }
+            Self::Yaml => serde_yaml_ng::to_string(spec)?,
+            Self::Cheerio => {
+                let mut out = String::from("export default ");
+                write_literal(spec, 0, &mut out);
+                out + ";\n"
+            }
+        })
+    }
+}
+
+/// Subcommands of `spec`
+#[derive(clap::Subcommand, Debug)]
+pub enum SpecCommand {
+    /// Convert a spec between formats, canonicalizing it on the way
+    ///
+    /// Fields are sorted and pipes normalized (`text` becomes `trim`, pipes
+    /// are separated by " | "). Converting to the same format only
+    /// canonicalizes.
+    Convert {
+        /// Spec file (format from its extension unless --from is given)
+        #[arg(value_name = "SPEC")]
+        input: String,
+
+        /// Format of the input spec
+        #[arg(long, value_enum, value_name = "FORMAT")]
+        from: Option<SpecFormat>,
+
+        /// Format to write [default: from --output's extension, else json]
+        #[arg(long, value_enum, value_name = "FORMAT")]
+        to: Option<SpecFormat>,
+
+        /// Write to FILE instead of stdout
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<String>,
+    },
+}
+
+pub fn run(command: &SpecCommand) -> Result<()> {
+    match command {
+        SpecCommand::Convert {
+            input,
+            from,
+            to,
+            output,
+        } => {
+            let spec = match from {
+                Some(format) => format.parse(&crate::read_spec_file(input)?)?,
+                None => crate::load_spec(input)?,
+            };
+            let canonical = Spec::from_json(&spec)?.to_json();
+            let to = to
+                .or_else(|| output.as_deref().and_then(SpecFormat::from_path))
+                .unwrap_or(SpecFormat::Json);
+            let rendered = to.render(&canonical)?;
+            match output {
+                Some(path) => std::fs::write(path, rendered)
+                    .map_err(|e| anyhow::anyhow!("Failed to write '{}': {}", path, e))?,
+                None => print!("{}", rendered),
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Print the lint warnings for a spec file; true if there are none
+pub fn lint(input: &str) -> Result<bool> {
+    let warnings = html2json::spec::lint(&crate::load_spec(input)?)?;
+    for warning in &warnings {
+        println!("{}\n  fix: {}", warning, warning.suggestion);
+    }
+    Ok(warnings.is_empty())
+}
+
+/// Write `value` as a JavaScript/JSON5 literal, with identifier keys unquoted
+fn write_literal(value: &Value, indent: usize, out: &mut String) {
+    let pad = "  ".repeat(indent + 1);
+    match value {
+        Value::Object(fields) if !fields.is_empty() => {
+            out.push_str("{\n");
+            for (key, value) in fields {
+                out.push_str(&pad);
+                if is_identifier(key) {
+                    out.push_str(key);
+                } else {
+                    out.push_str(&Value::from(key.as_str()).to_string());
+                }
+                out.push_str(": ");
+                write_literal(value, indent + 1, out);
+                out.push_str(",\n");
+            }
+            out.push_str(&"  ".repeat(indent));
+            out.push('}');
+        }
+        Value::Array(items) if !items.is_empty() => {
+            out.push_str("[\n");
+            for item in items {
+                out.push_str(&pad);
+                write_literal(item, indent + 1, out);
+                out.push_str(",\n");
+            }
+            out.push_str(&"  ".repeat(indent));
+            out.push(']');
+        }
+        _ => out.push_str(&value.to_string()),
+    }
+}
+
+fn is_identifier(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+}
+
+/// JSON5 text into its JSON form, rejecting repeated object keys
+fn parse_json5(text: &str) -> json5::Result<Value> {
+    let mut deserializer = json5::Deserializer::from_str(text)?;
+    html2json::spec::deserialize_unique(&mut deserializer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeated_keys_are_rejected_in_every_format() {
+        let specs = [
+            (SpecFormat::Json, r#"{"title": "h1", "title": "h2"}"#),
+            (SpecFormat::Json5, "{title: 'h1', title: 'h2'}"),
+            (SpecFormat::Yaml, "title: h1\ntitle: h2\n"),
+            (
+                SpecFormat::Cheerio,
+                "export default {items: [{$: 'li', $: 'tr'}]} as const;",
+            ),
+        ];
+        for (format, text) in specs {
+            let err = format.parse(text).unwrap_err();
+            assert!(
+                err.to_string().contains("Duplicate key"),
+                "{format:?}: {err}"
+            );
+        }
+        let value = SpecFormat::Json5
+            .parse("{title: 'h1', items: [1, 2.5]}")
+            .unwrap();
+        assert_eq!(value, serde_json::json!({"title": "h1", "items": [1, 2.5]}));
+    }
+}
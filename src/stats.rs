@@ -0,0 +1,142 @@
+//! Extraction statistics
+
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
+
+/// Maximum length of [`FieldStats::first_match`]
+const FIRST_MATCH_LEN: usize = 200;
+
+/// Statistics gathered by [`Dom::extract_with_stats`](crate::Dom::extract_with_stats)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExtractStats {
+    /// Wall time of the whole extraction
+    pub elapsed: Duration,
+    /// Nodes matched by scope, collection and field selectors
+    pub nodes_matched: usize,
+    /// Per-field statistics, in the order fields were first evaluated
+    pub fields: Vec<FieldStats>,
+}
+
+/// Statistics for one field of a spec
+///
+/// Nested fields are keyed by their dotted path (`stories.title`). Collection
+/// items share a path, so a field inside a collection is reported once with
+/// the totals over all items.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FieldStats {
+    /// Dotted path of the field in the spec
+    pub path: String,
+    /// The field's spec, as written in JSON
+    pub selector: String,
+    /// Number of times the field was evaluated
+    pub calls: usize,
+    /// Nodes matched while evaluating the field (excluding nested fields)
+    pub matched: usize,
+    /// Total time spent evaluating the field, including nested fields
+    pub elapsed: Duration,
+    /// Start of the outer HTML of the first node the field matched
+    pub first_match: Option<String>,
+}
+
+impl ExtractStats {
+    /// Fields ordered from slowest to fastest
+    pub fn slowest_fields(&self) -> Vec<&FieldStats> {
+        let mut fields: Vec<_> = self.fields.iter().collect();
+        fields.sort_by_key(|field| std::cmp::Reverse(field.elapsed));
+        fields
+    }
+}
+
+impl fmt::Display for ExtractStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Extracted in {:.3?}, {} nodes matched",
+            self.elapsed, self.nodes_matched
+        )?;
+        let width = self.fields.iter().map(|s| s.path.len()).max().unwrap_or(0);
+        for field in self.slowest_fields() {
+            writeln!(
+                f,
+                "{:>10.3?} {:>7} calls {:>7} matched  {:<width$}  {}",
+                field.elapsed, field.calls, field.matched, field.path, field.selector
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Accumulates [`FieldStats`] while an extraction runs
+#[derive(Debug, Default)]
+pub(crate) struct StatsCollector {
+    /// Path of the field currently being evaluated
+    path: Vec<String>,
+    fields: Vec<FieldStats>,
+    by_path: HashMap<String, usize>,
+    nodes_matched: usize,
+}
+
+impl StatsCollector {
+    pub(crate) fn enter(&mut self, key: &str) {
+        self.path.push(key.to_string());
+    }
+
+    /// Leave the current field, adding one call of `elapsed` to its totals
+    pub(crate) fn exit(&mut self, selector: impl FnOnce() -> String, elapsed: Duration) {
+        let path = self.path.join(".");
+        self.path.pop();
+        let field = self.field(path, selector);
+        field.calls += 1;
+        field.elapsed += elapsed;
+    }
+
+    /// Attribute matched nodes to the current field
+    pub(crate) fn matched(&mut self, count: usize) {
+        self.nodes_matched += count;
+        if self.path.is_empty() {
+            return;
+        }
+        let path = self.path.join(".");
+        self.field(path, String::new).matched += count;
+    }
+
+    /// Record the first node matched by the current field
+    pub(crate) fn sample(&mut self, html: impl FnOnce(usize) -> String) {
+        if self.path.is_empty() {
+            return;
+        }
+        let path = self.path.join(".");
+        let field = self.field(path, String::new);
+        if field.first_match.is_none() {
+            field.first_match = Some(html(FIRST_MATCH_LEN));
+        }
+    }
+
+    pub(crate) fn finish(self, elapsed: Duration) -> ExtractStats {
+        ExtractStats {
+            elapsed,
+            nodes_matched: self.nodes_matched,
+            fields: self.fields,
+        }
+    }
+
+    fn field(&mut self, path: String, selector: impl FnOnce() -> String) -> &mut FieldStats {
+        let index = match self.by_path.get(&path) {
+            Some(&index) => index,
+            None => {
+                self.fields.push(FieldStats {
+                    path: path.clone(),
+                    ..FieldStats::default()
+                });
+                self.by_path.insert(path, self.fields.len() - 1);
+                self.fields.len() - 1
+            }
+        };
+        let field = &mut self.fields[index];
+        if field.selector.is_empty() {
+            field.selector = selector();
+        }
+        field
+    }
+}